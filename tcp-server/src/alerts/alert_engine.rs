@@ -0,0 +1,334 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpMethod, HttpPath, HttpResponse, HttpStatus};
+use crate::models::{
+    AlertDelivery, AlertEvent, AlertRule, AlertState, SessionSensorData, SessionStatus, SessionSummary,
+};
+
+use super::WebhookSender;
+
+type Result<T> = crate::Result<T>;
+
+#[derive(Default)]
+struct RuleCache {
+    rules: Vec<AlertRule>,
+    // sensor of every session sensor evaluated since the rules were loaded
+    sensors: HashMap<i64, i64>,
+    loaded_at: Option<Instant>,
+}
+
+//Where every rule stands, by rule, session and session sensor
+type RuleStates = HashMap<(i64, i64, i64), RuleState>;
+
+//Where a rule stands for one session sensor
+#[derive(Debug, Default, Clone, PartialEq)]
+struct RuleState {
+    // datetime of the first datapoint of the current breach, while the rule waits out its duration
+    breached_since: Option<String>,
+    triggered: bool,
+    // datetime of the last datapoint evaluated, datapoints recorded before it are skipped
+    last: String,
+}
+
+impl RuleState {
+    // The event a datapoint raises, if any
+    fn next(&mut self, rule: &AlertRule, value: f64, datetime: &str) -> Option<AlertState> {
+        if datetime < self.last.as_str() {
+            return None;
+        }
+        self.last = datetime.to_string();
+
+        if self.triggered {
+            if rule.clears(value) {
+                self.triggered = false;
+                return Some(AlertState::Resolved);
+            }
+            return None;
+        }
+        if !rule.breaches(value) {
+            self.breached_since = None;
+            return None;
+        }
+        let since = self.breached_since.get_or_insert_with(|| datetime.to_string());
+        // datetimes that do not parse never count towards the duration
+        let held = SessionSummary::seconds_between(since, datetime).unwrap_or_default();
+        if held >= *rule.get_duration_seconds() {
+            self.triggered = true;
+            self.breached_since = None;
+            return Some(AlertState::Triggered);
+        }
+        None
+    }
+}
+
+//Evaluates alert rules against datapoints once they are written, recording an event whenever a rule triggers or resolves
+//Rules are cached and reloaded every REFRESH_INTERVAL, so rules changed by another server are picked up
+//Where each rule stands is kept in memory, and restored from its last event so a restart does not repeat a triggered alert
+pub struct AlertEngine {
+    cache: RwLock<RuleCache>,
+    states: Mutex<RuleStates>,
+    webhooks: Option<WebhookSender>,
+}
+
+impl AlertEngine {
+    pub const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+    //Events are always recorded, without a webhook sender their delivery is marked skipped
+    pub fn new(webhooks: Option<WebhookSender>) -> Self {
+        AlertEngine {
+            cache: RwLock::new(RuleCache::default()),
+            states: Mutex::new(HashMap::new()),
+            webhooks,
+        }
+    }
+
+    //Stores a rule, it applies to the next datapoint evaluated
+    pub fn put(&self, database: &dyn Database, rule: AlertRule) -> Result<AlertRule> {
+        let rule = database.insert_alert_rule(&rule.validate()?)?;
+        self.cache_mut()?.rules.push(rule.clone());
+        Ok(rule)
+    }
+
+    pub fn remove(&self, database: &dyn Database, rule_id: i64) -> Result<()> {
+        database.delete_alert_rule(rule_id)?;
+        self.cache_mut()?.rules.retain(|rule| *rule.get_id() != rule_id);
+        self.lock_states()?.retain(|(id, _, _), _| *id != rule_id);
+        Ok(())
+    }
+
+    //Evaluates every rule against datapoints just written, in the order they were recorded
+    //Failures are only logged so alerts never hold up ingestion
+    pub fn evaluate(&self, database: &dyn Database, datapoints: &[SessionSensorData]) {
+        if let Err(e) = self.try_evaluate(database, datapoints) {
+            eprintln!("Failed to evaluate alert rules: {e}");
+        }
+    }
+
+    fn try_evaluate(&self, database: &dyn Database, datapoints: &[SessionSensorData]) -> Result<()> {
+        self.refresh(database)?;
+        let rules = self.cache()?.rules.clone();
+        if rules.is_empty() {
+            return Ok(());
+        }
+
+        let mut datapoints: Vec<&SessionSensorData> = datapoints.iter().collect();
+        datapoints.sort_by(|left, right| left.get_datetime().cmp(right.get_datetime()));
+        let mut states = self.lock_states()?;
+        for datapoint in datapoints {
+            let (Some(session_id), Some(session_sensor_id)) = (*datapoint.get_id(), *datapoint.get_session_sensor_id())
+            else {
+                continue;
+            };
+            // one datapoint of an unknown session sensor must not keep the rest of the batch from being evaluated
+            let sensor_id = match self.sensor_of(database, session_sensor_id) {
+                Ok(sensor_id) => sensor_id,
+                Err(e) => {
+                    eprintln!(
+                        "Skipping the datapoint of session sensor {session_sensor_id} at {} in alert evaluation: {e}",
+                        datapoint.get_datetime()
+                    );
+                    continue;
+                }
+            };
+            for rule in rules.iter().filter(|rule| rule.applies_to(session_id, sensor_id)) {
+                let Some(value) = rule.value_of(datapoint.get_blob()) else {
+                    continue;
+                };
+                let state = match states.entry((*rule.get_id(), session_id, session_sensor_id)) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(Self::restore(database, rule, session_id, session_sensor_id)?),
+                };
+                if let Some(state) = state.next(rule, value, datapoint.get_datetime()) {
+                    self.raise(
+                        database,
+                        rule,
+                        AlertEvent::new(
+                            0,
+                            *rule.get_id(),
+                            session_id,
+                            session_sensor_id,
+                            state,
+                            value,
+                            datapoint.get_datetime().to_string(),
+                        ),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Records the event and queues it for the webhooks
+    fn raise(&self, database: &dyn Database, rule: &AlertRule, event: AlertEvent) {
+        let delivery = match self.webhooks {
+            Some(_) => AlertDelivery::Pending,
+            None => AlertDelivery::Skipped,
+        };
+        match database.insert_alert_event(&event.with_delivery(delivery, 0)) {
+            Ok(event) => {
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.send(event, rule.clone());
+                }
+            }
+            Err(e) => eprintln!("Failed to record an alert event of rule {}: {e}", rule.get_id()),
+        }
+    }
+
+    // A rule whose last event for the session sensor triggered it is still triggered
+    fn restore(
+        database: &dyn Database,
+        rule: &AlertRule,
+        session_id: i64,
+        session_sensor_id: i64,
+    ) -> Result<RuleState> {
+        let triggered = database
+            .get_alert_events(session_id)?
+            .iter()
+            .rev()
+            .find(|event| event.get_rule_id() == rule.get_id() && *event.get_session_sensor_id() == session_sensor_id)
+            .is_some_and(|event| *event.get_state() == AlertState::Triggered);
+        Ok(RuleState {
+            triggered,
+            ..Default::default()
+        })
+    }
+
+    fn refresh(&self, database: &dyn Database) -> Result<()> {
+        if self
+            .cache()?
+            .loaded_at
+            .is_some_and(|loaded_at| loaded_at.elapsed() < Self::REFRESH_INTERVAL)
+        {
+            return Ok(());
+        }
+
+        let rules = database.get_alert_rules()?;
+        *self.cache_mut()? = RuleCache {
+            rules,
+            sensors: HashMap::new(),
+            loaded_at: Some(Instant::now()),
+        };
+        self.prune(database)
+    }
+
+    //Forgets where rules stand for deleted rules and for sessions that stopped, were archived, or were deleted
+    //Runs with every refresh so the states do not grow for as long as the server runs, a session that still
+    //receives datapoints has its states restored from its events
+    pub fn prune(&self, database: &dyn Database) -> Result<()> {
+        let sessions: HashSet<i64> = self
+            .lock_states()?
+            .keys()
+            .map(|(_, session_id, _)| *session_id)
+            .collect();
+        let mut finished = HashSet::new();
+        for session_id in sessions {
+            match database.get_session(session_id) {
+                Ok(session) if matches!(session.get_status(), SessionStatus::Stopped | SessionStatus::Archived) => {
+                    finished.insert(session_id);
+                }
+                Ok(_) => {}
+                Err(Error::NotFound(_)) => {
+                    finished.insert(session_id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let rules: HashSet<i64> = self.cache()?.rules.iter().map(|rule| *rule.get_id()).collect();
+        self.lock_states()?
+            .retain(|(rule_id, session_id, _), _| rules.contains(rule_id) && !finished.contains(session_id));
+        Ok(())
+    }
+
+    fn sensor_of(&self, database: &dyn Database, session_sensor_id: i64) -> Result<i64> {
+        if let Some(sensor_id) = self.cache()?.sensors.get(&session_sensor_id) {
+            return Ok(*sensor_id);
+        }
+        let sensor_id = *database.get_session_sensor(session_sensor_id)?.get_sensor_id();
+        self.cache_mut()?.sensors.insert(session_sensor_id, sensor_id);
+        Ok(sensor_id)
+    }
+
+    fn cache(&self) -> Result<RwLockReadGuard<'_, RuleCache>> {
+        self.cache.read().map_err(|_| Self::poisoned())
+    }
+
+    fn cache_mut(&self) -> Result<RwLockWriteGuard<'_, RuleCache>> {
+        self.cache.write().map_err(|_| Self::poisoned())
+    }
+
+    fn lock_states(&self) -> Result<MutexGuard<'_, RuleStates>> {
+        self.states.lock().map_err(|_| Self::poisoned())
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Alert rules were poisoned by a panic"))
+    }
+
+    //Handles /alerts, listing, adding and deleting rules at /alerts/rules and listing the events of a session at /alerts/session/{id}
+    pub fn alerts_model(
+        &self,
+        database: &dyn Database,
+        method: HttpMethod,
+        subpath: &str,
+        body: Option<Value>,
+    ) -> HttpResponse {
+        match (method, HttpPath::subsection(subpath, 0), HttpPath::subsection(subpath, 1)) {
+            (HttpMethod::Get, Some("rules"), None) => match database.get_alert_rules() {
+                Ok(rules) => HttpResponse::from_vec(
+                    json!({"rules": rules.iter().map(AlertRule::to_json).collect::<Vec<_>>()}).to_string(),
+                ),
+                Err(e) => HttpResponse::from_error(&e),
+            },
+            (HttpMethod::Post, Some("rules"), None) => match body.map(serde_json::from_value::<AlertRule>) {
+                Some(Ok(rule)) => match self.put(database, rule) {
+                    Ok(rule) => {
+                        HttpResponse::new(HttpStatus::Created, HttpHeader::default_json(), rule.to_json().to_string())
+                    }
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                Some(Err(e)) => HttpResponse::invalid_body(Some(&e.to_string())),
+                None => HttpResponse::missing_body(Some(
+                    " Requires values \"sensor_id\" or \"session_id\": i64, \"field\": string, \"comparator\": string, \"threshold\": f64",
+                )),
+            },
+            (HttpMethod::Delete, Some("rules"), Some(rule_id)) => match rule_id.parse::<i64>() {
+                Ok(rule_id) => match self.remove(database, rule_id) {
+                    Ok(()) => HttpResponse::no_content(),
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+            },
+            (HttpMethod::Get, Some("session"), Some(session_id)) => match session_id.parse::<i64>() {
+                Ok(session_id) => match database
+                    .get_session(session_id)
+                    .and_then(|_| database.get_alert_events(session_id))
+                {
+                    Ok(events) => HttpResponse::from_vec(
+                        json!({"events": events.iter().map(AlertEvent::to_json).collect::<Vec<_>>()}).to_string(),
+                    ),
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+            },
+            (HttpMethod::Options, _, _) => HttpResponse::options_response(),
+            (HttpMethod::Get | HttpMethod::Post | HttpMethod::Delete, _, _) => HttpResponse::json_404(subpath),
+            _ => HttpResponse::method_not_allowed("GET, POST, DELETE, OPTIONS"),
+        }
+    }
+}
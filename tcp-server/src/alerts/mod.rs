@@ -0,0 +1,8 @@
+pub mod alert_engine;
+pub mod webhook;
+
+pub use self::alert_engine::AlertEngine;
+pub use self::webhook::{AlertConfig, WebhookSender};
+
+#[cfg(test)]
+mod test_alerts;
@@ -0,0 +1,345 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use serde_json::{json, Value};
+
+    use crate::alerts::{AlertConfig, AlertEngine, WebhookSender};
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::error::Error;
+    use crate::models::{
+        AlertComparator, AlertDelivery, AlertEvent, AlertRule, AlertState, Sensor, Session, SessionSensor,
+        SessionSensorData, SessionStatus, User,
+    };
+
+    /* Helpers */
+
+    // Session 1 records a heart rate monitor (session sensor 1)
+    fn init_database() -> Arc<MemoryDatabase> {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        db.insert_sensor(&Sensor::new(0, "HeartRate".to_string())).unwrap();
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        Arc::new(db)
+    }
+
+    // Heart rate over 180 for 10 seconds, resolved once back under 175
+    fn heart_rate_rule() -> AlertRule {
+        AlertRule::new(
+            0,
+            Some(1),
+            None,
+            "tachycardia".to_string(),
+            "bpm".to_string(),
+            AlertComparator::Above,
+            180.0,
+        )
+        .with_timing(10.0, 5.0)
+    }
+
+    // A datapoint second seconds into the session
+    fn datapoint(second: u32, bpm: f64) -> SessionSensorData {
+        SessionSensorData::new(
+            Some(1),
+            Some(1),
+            format!("2025-01-01T12:{:02}:{:02}", second / 60, second % 60),
+            json!({ "bpm": bpm }),
+        )
+    }
+
+    fn states(events: &[AlertEvent]) -> Vec<(AlertState, String)> {
+        events
+            .iter()
+            .map(|event| (*event.get_state(), event.get_datetime().to_string()))
+            .collect()
+    }
+
+    // Answers each post with the next status, sending every request received to the returned receiver
+    fn stand_in_receiver(statuses: Vec<u16>) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/alerts?source=test", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (stream, status) in listener.incoming().zip(statuses) {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4_096];
+                // the request is complete once the body announced by content-length has arrived
+                loop {
+                    let n = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .and_then(|length| length.parse::<usize>().ok())
+                            .unwrap_or_default();
+                        if body.len() >= length {
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                stream
+                    .write_all(format!("HTTP/1.1 {status} Status\r\nContent-Length: 0\r\n\r\n").as_bytes())
+                    .unwrap();
+                sender.send(String::from_utf8_lossy(&request).to_string()).unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    // Polls until the first event of the session is no longer pending
+    fn wait_for_delivery(db: &MemoryDatabase) -> AlertEvent {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let event = db.get_alert_events(1).unwrap().remove(0);
+            if *event.get_delivery() != AlertDelivery::Pending || Instant::now() > deadline {
+                return event;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_alert_rule_duration_and_hysteresis() {
+        let db = init_database();
+        let engine = AlertEngine::new(None);
+        engine.put(db.as_ref(), heart_rate_rule()).unwrap();
+
+        // a breach shorter than the duration does not trigger, and resets once the rate drops
+        engine.evaluate(
+            db.as_ref(),
+            &[datapoint(0, 185.0), datapoint(5, 190.0), datapoint(8, 170.0)],
+        );
+        assert!(db.get_alert_events(1).unwrap().is_empty());
+
+        // datapoints of a batch are evaluated in the order they were recorded
+        engine.evaluate(
+            db.as_ref(),
+            &[datapoint(22, 184.0), datapoint(10, 181.0), datapoint(15, 200.0)],
+        );
+        // within the hysteresis the alert stays triggered, and late datapoints are skipped
+        engine.evaluate(
+            db.as_ref(),
+            &[datapoint(25, 178.0), datapoint(9, 100.0), datapoint(30, 176.0)],
+        );
+        engine.evaluate(db.as_ref(), &[datapoint(35, 174.0), datapoint(40, 190.0)]);
+
+        let events = db.get_alert_events(1).unwrap();
+        assert_eq!(
+            states(&events),
+            vec![
+                (AlertState::Triggered, String::from("2025-01-01T12:00:22")),
+                (AlertState::Resolved, String::from("2025-01-01T12:00:35")),
+            ]
+        );
+        assert_eq!(*events[0].get_value(), 184.0);
+        // without webhooks the events are only recorded
+        assert_eq!(*events[0].get_delivery(), AlertDelivery::Skipped);
+    }
+
+    #[test]
+    fn test_alert_rule_validation() {
+        let db = init_database();
+        let engine = AlertEngine::new(None);
+
+        let both: AlertRule = serde_json::from_value(
+            json!({ "sensor_id": 1, "session_id": 1, "field": "bpm", "comparator": ">", "threshold": 180 }),
+        )
+        .unwrap();
+        assert!(matches!(engine.put(db.as_ref(), both), Err(Error::Validation(_))));
+        let negative = heart_rate_rule().with_timing(-1.0, 0.0);
+        assert!(matches!(engine.put(db.as_ref(), negative), Err(Error::Validation(_))));
+        assert!(serde_json::from_value::<AlertRule>(
+            json!({ "session_id": 1, "field": "bpm", "comparator": "==", "threshold": 180 })
+        )
+        .is_err());
+
+        let unknown_sensor = AlertRule::new(
+            0,
+            Some(9),
+            None,
+            String::new(),
+            "bpm".to_string(),
+            AlertComparator::Below,
+            40.0,
+        );
+        assert!(matches!(
+            engine.put(db.as_ref(), unknown_sensor),
+            Err(Error::NotFound(_))
+        ));
+
+        // a session rule watches every sensor of the session, and is forgotten once removed
+        let rule = engine
+            .put(
+                db.as_ref(),
+                AlertRule::new(
+                    0,
+                    None,
+                    Some(1),
+                    String::new(),
+                    "bpm".to_string(),
+                    AlertComparator::AtMost,
+                    40.0,
+                ),
+            )
+            .unwrap();
+        engine.evaluate(db.as_ref(), &[datapoint(0, 40.0)]);
+        assert_eq!(db.get_alert_events(1).unwrap().len(), 1);
+        engine.remove(db.as_ref(), *rule.get_id()).unwrap();
+        assert!(db.get_alert_events(1).unwrap().is_empty());
+        engine.evaluate(db.as_ref(), &[datapoint(5, 30.0)]);
+        assert!(db.get_alert_events(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_alert_state_restored_from_events() {
+        let db = init_database();
+        AlertEngine::new(None).put(db.as_ref(), heart_rate_rule()).unwrap();
+        let engine = AlertEngine::new(None);
+        engine.evaluate(db.as_ref(), &[datapoint(0, 190.0), datapoint(10, 190.0)]);
+        assert_eq!(db.get_alert_events(1).unwrap().len(), 1);
+
+        // a new engine, as after a restart, knows the alert is already triggered
+        let restarted = AlertEngine::new(None);
+        restarted.evaluate(
+            db.as_ref(),
+            &[datapoint(20, 195.0), datapoint(30, 195.0), datapoint(40, 150.0)],
+        );
+        assert_eq!(
+            states(&db.get_alert_events(1).unwrap()),
+            vec![
+                (AlertState::Triggered, String::from("2025-01-01T12:00:10")),
+                (AlertState::Resolved, String::from("2025-01-01T12:00:40")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alert_evaluation_skips_unknown_session_sensors() {
+        let db = init_database();
+        let engine = AlertEngine::new(None);
+        engine.put(db.as_ref(), heart_rate_rule()).unwrap();
+
+        // the datapoint of a session sensor that does not exist is skipped, the rest of the batch is evaluated
+        let unknown =
+            SessionSensorData::new(Some(1), Some(99), String::from("2025-01-01T12:00:05"), json!({ "bpm": 190 }));
+        engine.evaluate(db.as_ref(), &[datapoint(0, 190.0), unknown, datapoint(10, 190.0)]);
+        assert_eq!(
+            states(&db.get_alert_events(1).unwrap()),
+            vec![(AlertState::Triggered, String::from("2025-01-01T12:00:10"))]
+        );
+    }
+
+    #[test]
+    fn test_alert_states_pruned_when_session_stops() {
+        let db = init_database();
+        let engine = AlertEngine::new(None);
+        engine.put(db.as_ref(), heart_rate_rule()).unwrap();
+        db.set_session_status(1, SessionStatus::Recording).unwrap();
+
+        // a breach held while recording is kept across evaluations
+        engine.evaluate(db.as_ref(), &[datapoint(0, 190.0)]);
+        engine.prune(db.as_ref()).unwrap();
+        engine.evaluate(db.as_ref(), &[datapoint(10, 190.0)]);
+        assert_eq!(db.get_alert_events(1).unwrap().len(), 1);
+        engine.evaluate(db.as_ref(), &[datapoint(20, 150.0), datapoint(30, 190.0)]);
+
+        // once the session stops its breach is forgotten, a datapoint flushed late starts a new one
+        db.set_session_status(1, SessionStatus::Stopped).unwrap();
+        engine.prune(db.as_ref()).unwrap();
+        engine.evaluate(db.as_ref(), &[datapoint(40, 190.0)]);
+        assert_eq!(
+            states(&db.get_alert_events(1).unwrap()),
+            vec![
+                (AlertState::Triggered, String::from("2025-01-01T12:00:10")),
+                (AlertState::Resolved, String::from("2025-01-01T12:00:20")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_webhook_delivery_retries() {
+        let db = init_database();
+        let (url, requests) = stand_in_receiver(vec![503, 200]);
+        let config = AlertConfig {
+            webhooks: vec![url],
+            secret: String::from("hook secret"),
+            retries: 2,
+            retry_ms: 10,
+            timeout_ms: 1_000,
+        };
+        let webhooks = WebhookSender::start(Arc::clone(&db) as Arc<dyn Database>, config).unwrap();
+        let engine = AlertEngine::new(webhooks);
+        engine
+            .put(db.as_ref(), heart_rate_rule().with_timing(0.0, 0.0))
+            .unwrap();
+        engine.evaluate(db.as_ref(), &[datapoint(0, 200.0)]);
+
+        let event = wait_for_delivery(&db);
+        assert_eq!(*event.get_delivery(), AlertDelivery::Delivered);
+        assert_eq!(*event.get_attempts(), 2);
+
+        let refused = requests.recv_timeout(Duration::from_secs(1)).unwrap();
+        let accepted = requests.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(refused, accepted);
+        assert!(accepted.starts_with("POST /hooks/alerts?source=test HTTP/1.1\r\n"));
+        assert!(accepted.contains(&format!("{}: ", WebhookSender::SIGNATURE_HEADER)));
+        let body: Value = serde_json::from_str(accepted.split_once("\r\n\r\n").unwrap().1).unwrap();
+        assert_eq!(body["event"]["state"], "triggered");
+        assert_eq!(body["event"]["value"], 200.0);
+        assert_eq!(body["rule"]["name"], "tachycardia");
+    }
+
+    #[test]
+    fn test_webhook_delivery_fails_after_retries() {
+        let db = init_database();
+        let (url, _requests) = stand_in_receiver(vec![500, 500, 500]);
+        let config = AlertConfig {
+            webhooks: vec![url],
+            retries: 2,
+            retry_ms: 10,
+            ..Default::default()
+        };
+        let webhooks = WebhookSender::start(Arc::clone(&db) as Arc<dyn Database>, config).unwrap();
+        let engine = AlertEngine::new(webhooks);
+        engine
+            .put(db.as_ref(), heart_rate_rule().with_timing(0.0, 0.0))
+            .unwrap();
+        engine.evaluate(db.as_ref(), &[datapoint(0, 200.0)]);
+
+        let event = wait_for_delivery(&db);
+        assert_eq!(*event.get_delivery(), AlertDelivery::Failed);
+        assert_eq!(*event.get_attempts(), 3);
+
+        let https = AlertConfig {
+            webhooks: vec![String::from("https://example.com/alerts")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            WebhookSender::start(Arc::clone(&db) as Arc<dyn Database>, https),
+            Err(Error::Validation(_))
+        ));
+    }
+}
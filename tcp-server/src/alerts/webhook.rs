@@ -0,0 +1,246 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use url::Url;
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::models::{AlertDelivery, AlertEvent, AlertRule};
+
+type Result<T> = crate::Result<T>;
+type HmacSha256 = Hmac<Sha256>;
+
+//Settings of alert delivery, the [alerts] table of config.toml
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertConfig {
+    // urls every alert event is posted to, events are only recorded when there are none
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    // key the posted bodies are signed with, they are not signed when empty
+    #[serde(default)]
+    pub secret: String,
+    // posts retried after the first one fails
+    #[serde(default = "AlertConfig::default_retries")]
+    pub retries: u32,
+    // wait before the first retry, doubled before each further one
+    #[serde(default = "AlertConfig::default_retry_ms")]
+    pub retry_ms: u64,
+    // longest time a post waits to connect or for the answer
+    #[serde(default = "AlertConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        AlertConfig {
+            webhooks: Vec::new(),
+            secret: String::new(),
+            retries: Self::default_retries(),
+            retry_ms: Self::default_retry_ms(),
+            timeout_ms: Self::default_timeout_ms(),
+        }
+    }
+}
+
+impl AlertConfig {
+    fn default_retries() -> u32 {
+        3
+    }
+
+    fn default_retry_ms() -> u64 {
+        1_000
+    }
+
+    fn default_timeout_ms() -> u64 {
+        5_000
+    }
+}
+
+//An http url alert events are posted to
+struct Webhook {
+    url: String,
+    // host and port connected to
+    address: String,
+    host: String,
+    // path and query posted to
+    target: String,
+}
+
+impl Webhook {
+    // Only plain http is supported, there is no tls client
+    fn parse(url: &str) -> Result<Self> {
+        let parsed = Url::parse(url).map_err(|e| Error::Validation(format!("Invalid webhook url '{url}': {e}")))?;
+        if parsed.scheme() != "http" {
+            return Err(Error::Validation(format!(
+                "Invalid webhook url '{url}', only http urls are supported"
+            )));
+        }
+        let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+            return Err(Error::Validation(format!(
+                "Invalid webhook url '{url}', it has no host"
+            )));
+        };
+        Ok(Webhook {
+            url: url.to_string(),
+            address: format!("{host}:{port}"),
+            host: match parsed.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            },
+            target: match parsed.query() {
+                Some(query) => format!("{}?{query}", parsed.path()),
+                None => parsed.path().to_string(),
+            },
+        })
+    }
+
+    // Posts the json body, returning the status code of the answer
+    fn post(&self, body: &str, signature: Option<&str>, timeout: Duration) -> Result<u16> {
+        let address = self
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::Io(format!("Failed to resolve webhook host {}", self.address)))?;
+        let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.target,
+            self.host,
+            body.len()
+        );
+        if let Some(signature) = signature {
+            request.push_str(&format!("{}: {signature}\r\n", WebhookSender::SIGNATURE_HEADER));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes())?;
+
+        // only the status line of the answer is needed
+        let mut answer = Vec::new();
+        let mut buffer = [0; 512];
+        while !answer.windows(2).any(|window| window == b"\r\n") {
+            match stream.read(&mut buffer)? {
+                0 => break,
+                n => answer.extend_from_slice(&buffer[..n]),
+            }
+        }
+        String::from_utf8_lossy(&answer)
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| Error::Io(format!("Webhook {} did not answer with an http status", self.url)))
+    }
+}
+
+//Posts alert events to every webhook in the order they were raised, on a thread of its own so ingestion never waits on them
+//Each post is retried with a doubling backoff, and the outcome is recorded on the event
+//Events still queued when the server stops stay pending
+pub struct WebhookSender {
+    queue: Sender<(AlertEvent, AlertRule)>,
+}
+
+impl WebhookSender {
+    // hex encoded HMAC-SHA256 of the body, sent when a secret is configured
+    pub const SIGNATURE_HEADER: &'static str = "X-Alert-Signature";
+
+    //Starts the delivery thread, none is started when no webhooks are configured
+    pub fn start(database: Arc<dyn Database>, config: AlertConfig) -> Result<Option<Self>> {
+        if config.webhooks.is_empty() {
+            return Ok(None);
+        }
+        let webhooks = config
+            .webhooks
+            .iter()
+            .map(|url| Webhook::parse(url))
+            .collect::<Result<Vec<_>>>()?;
+        HmacSha256::new_from_slice(config.secret.as_bytes())
+            .map_err(|e| Error::Validation(format!("Invalid alert secret: {e}")))?;
+
+        let (queue, events) = mpsc::channel();
+        std::thread::spawn(move || Self::run(database.as_ref(), &config, &webhooks, events));
+        Ok(Some(WebhookSender { queue }))
+    }
+
+    pub fn send(&self, event: AlertEvent, rule: AlertRule) {
+        if self.queue.send((event, rule)).is_err() {
+            eprintln!("Alert delivery thread stopped, the alert event was not sent");
+        }
+    }
+
+    fn run(
+        database: &dyn Database,
+        config: &AlertConfig,
+        webhooks: &[Webhook],
+        events: Receiver<(AlertEvent, AlertRule)>,
+    ) {
+        for (event, rule) in events {
+            let body = json!({ "event": event.to_json(), "rule": rule.to_json() }).to_string();
+            let signature = Self::sign(&config.secret, &body);
+            let mut delivered = true;
+            let mut attempts = 0;
+            for webhook in webhooks {
+                let (accepted, posts) = Self::deliver(webhook, &body, signature.as_deref(), config);
+                delivered &= accepted;
+                attempts += posts;
+            }
+            let delivery = match delivered {
+                true => AlertDelivery::Delivered,
+                false => AlertDelivery::Failed,
+            };
+            if let Err(e) = database.update_alert_event_delivery(*event.get_id(), delivery, attempts) {
+                eprintln!("Failed to record the delivery of alert event {}: {e}", event.get_id());
+            }
+        }
+    }
+
+    // Posts the body until the webhook accepts it or the retries run out, returning whether it was accepted and the posts made
+    fn deliver(webhook: &Webhook, body: &str, signature: Option<&str>, config: &AlertConfig) -> (bool, i64) {
+        let timeout = Duration::from_millis(config.timeout_ms.max(1));
+        let mut backoff = Duration::from_millis(config.retry_ms);
+        for attempt in 0..=config.retries {
+            if attempt > 0 {
+                std::thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2);
+            }
+            match webhook.post(body, signature, timeout) {
+                Ok(status) if (200..300).contains(&status) => return (true, attempt as i64 + 1),
+                Ok(status) => eprintln!("Webhook {} refused an alert event with status {status}", webhook.url),
+                Err(e) => eprintln!("Failed to post an alert event to webhook {}: {e}", webhook.url),
+            }
+        }
+        (false, config.retries as i64 + 1)
+    }
+
+    fn sign(secret: &str, body: &str) -> Option<String> {
+        if secret.is_empty() {
+            return None;
+        }
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).ok()?;
+        mac.update(body.as_bytes());
+        Some(
+            mac.finalize()
+                .into_bytes()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,191 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde_json::{json, Map, Number, Value};
+
+use crate::data::Database;
+use crate::derived::Deriver;
+use crate::error::Error;
+use crate::http::{HttpRequest, HttpResponse};
+use crate::models::{SensorCalibration, SessionSensorData};
+
+type Result<T> = crate::Result<T>;
+
+//The calibrations and channel units of the sensor behind a session sensor
+#[derive(Debug, Default)]
+struct CalibratedSensor {
+    calibrations: Vec<SensorCalibration>,
+    units: Map<String, Value>,
+}
+
+//Calibrates datapoints as they are read, storage always keeps the raw values
+//Every numeric field of a blob named like a channel of its sensor is replaced by the value of the calibration
+//of that channel valid when the datapoint was recorded, fields without one are left raw
+pub struct Calibrator<'a> {
+    database: &'a dyn Database,
+    // loaded once per session sensor
+    sensors: HashMap<i64, CalibratedSensor>,
+}
+
+impl<'a> Calibrator<'a> {
+    pub fn new(database: &'a dyn Database) -> Self {
+        Calibrator {
+            database,
+            sensors: HashMap::new(),
+        }
+    }
+
+    //Whether a read asks for calibrated values with ?calibrated=true, raw values are read otherwise
+    pub fn requested(request: &HttpRequest) -> Result<bool> {
+        match request.parameter("calibrated") {
+            Some(calibrated) => calibrated
+                .parse::<bool>()
+                .map_err(|_| Error::Validation(format!("Invalid calibrated '{calibrated}', expected true or false"))),
+            None => Ok(false),
+        }
+    }
+
+    pub fn calibrate(&mut self, datapoint: &SessionSensorData) -> Result<SessionSensorData> {
+        let (Some(session_sensor_id), Value::Object(blob)) = (datapoint.get_session_sensor_id(), datapoint.get_blob())
+        else {
+            return Ok(datapoint.clone());
+        };
+        // compared as strings with the normalized datetimes of the calibrations
+        let Ok(datetime) = NaiveDateTime::parse_from_str(datapoint.get_datetime(), SensorCalibration::DATETIME_FORMAT)
+            .map(|datetime| datetime.format(SensorCalibration::DATETIME_FORMAT).to_string())
+        else {
+            return Ok(datapoint.clone());
+        };
+        let sensor = self.sensor(*session_sensor_id)?;
+
+        let mut calibrated = blob.clone();
+        for (field, value) in calibrated.iter_mut() {
+            let Some(raw) = value.as_f64() else {
+                continue;
+            };
+            if let Some(number) = sensor
+                .calibrations
+                .iter()
+                .find(|calibration| calibration.get_channel() == field && calibration.is_valid_at(&datetime))
+                .and_then(|calibration| Number::from_f64(calibration.apply(raw)))
+            {
+                *value = Value::Number(number);
+            }
+        }
+        Ok(SessionSensorData::new(
+            *datapoint.get_id(),
+            Some(*session_sensor_id),
+            datapoint.get_datetime().to_string(),
+            Value::Object(calibrated),
+        ))
+    }
+
+    //The units of the channels of every session sensor calibrated so far, by session sensor id
+    pub fn units(&self) -> Value {
+        Value::Object(
+            self.sensors
+                .iter()
+                .map(|(session_sensor_id, sensor)| (session_sensor_id.to_string(), Value::Object(sensor.units.clone())))
+                .collect(),
+        )
+    }
+
+    //Answers a read of datapoints, calibrated along with the units of their channels when the request asks for it,
+    //and with the derived channels of their sensor types unless the request asks for none
+    pub fn list_response(
+        database: &dyn Database,
+        datapoints: &[SessionSensorData],
+        request: &HttpRequest,
+    ) -> HttpResponse {
+        match Self::read(database, datapoints, request) {
+            Ok((datapoints, None)) => HttpResponse::from_vec(json!({ "datapoints": datapoints }).to_string()),
+            Ok((datapoints, Some(units))) => HttpResponse::from_vec(
+                json!({
+                    "datapoints": datapoints,
+                    "calibrated": true,
+                    "units": units,
+                })
+                .to_string(),
+            ),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+
+    //Answers a read of a single datapoint like list_response
+    pub fn datapoint_response(
+        database: &dyn Database,
+        datapoint: &SessionSensorData,
+        request: &HttpRequest,
+    ) -> HttpResponse {
+        match Self::read(database, std::slice::from_ref(datapoint), request) {
+            Ok((mut datapoints, units)) => {
+                let mut body = datapoints.remove(0);
+                if let Some(units) = units {
+                    body["calibrated"] = json!(true);
+                    body["units"] = units;
+                }
+                HttpResponse::from_vec(body.to_string())
+            }
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+
+    // The json of datapoints as the request reads them, along with the units of their channels when they are calibrated
+    // Channels are derived from the calibrated values of a calibrated datapoint
+    fn read(
+        database: &dyn Database,
+        datapoints: &[SessionSensorData],
+        request: &HttpRequest,
+    ) -> Result<(Vec<Value>, Option<Value>)> {
+        let calibrated = Self::requested(request)?;
+        let derived = Deriver::requested(request)?;
+        let mut calibrator = Calibrator::new(database);
+        let mut deriver = Deriver::new(database);
+        let mut read = Vec::with_capacity(datapoints.len());
+        for datapoint in datapoints {
+            let datapoint = match calibrated {
+                true => Cow::Owned(calibrator.calibrate(datapoint)?),
+                false => Cow::Borrowed(datapoint),
+            };
+            let mut json = datapoint.to_json();
+            if derived {
+                if let Some(values) = deriver.derive(&datapoint)? {
+                    values.annotate(&mut json);
+                }
+            }
+            read.push(json);
+        }
+        Ok((read, calibrated.then(|| calibrator.units())))
+    }
+
+    fn sensor(&mut self, session_sensor_id: i64) -> Result<&CalibratedSensor> {
+        match self.sensors.entry(session_sensor_id) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let sensor = self
+                    .database
+                    .get_sensor(*self.database.get_session_sensor(session_sensor_id)?.get_sensor_id())?;
+                let units = sensor
+                    .get_channels()
+                    .iter()
+                    .filter(|channel| !channel.unit.is_empty())
+                    .map(|channel| (channel.name.clone(), json!(channel.unit)))
+                    .collect();
+                Ok(entry.insert(CalibratedSensor {
+                    calibrations: self.database.get_sensor_calibrations(*sensor.get_id())?,
+                    units,
+                }))
+            }
+        }
+    }
+}
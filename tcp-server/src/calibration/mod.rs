@@ -0,0 +1,6 @@
+pub mod calibrator;
+
+pub use self::calibrator::Calibrator;
+
+#[cfg(test)]
+mod test_calibration;
@@ -0,0 +1,210 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::calibration::Calibrator;
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::error::Error;
+    use crate::http::{HttpHeader, HttpPath, HttpRequest, HttpStatus};
+    use crate::models::{
+        CalibrationMethod, Sensor, SensorCalibration, Session, SessionSensor, SessionSensorData, User,
+    };
+
+    /* Helpers */
+
+    // Session 1 records a thermometer (session sensor 1) whose raw readings are calibrated from 2025-01-01
+    // with -40 + 0.125 * raw, then from 2025-01-01T12:00:00 with a lookup table
+    fn init_database() -> MemoryDatabase {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        let sensor: Sensor = serde_json::from_value(json!({
+            "type": "Thermometer",
+            "channels": [{ "name": "celsius", "unit": "°C" }, { "name": "battery", "unit": "V" }, { "name": "label" }],
+        }))
+        .unwrap();
+        db.insert_sensor(&sensor).unwrap();
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        db.insert_sensor_calibration(&calibration(
+            CalibrationMethod::Linear,
+            vec![-40.0, 0.125],
+            Vec::new(),
+            "2025-01-01T00:00:00",
+        ))
+        .unwrap();
+        db.insert_sensor_calibration(&calibration(
+            CalibrationMethod::Table,
+            Vec::new(),
+            vec![(0.0, -10.0), (100.0, 0.0), (300.0, 40.0)],
+            "2025-01-01T12:00:00",
+        ))
+        .unwrap();
+        db
+    }
+
+    fn calibration(
+        method: CalibrationMethod,
+        coefficients: Vec<f64>,
+        table: Vec<(f64, f64)>,
+        valid_from: &str,
+    ) -> SensorCalibration {
+        SensorCalibration::new(0, 1, "celsius".to_string(), coefficients, valid_from.to_string(), None)
+            .with_table(method, table)
+    }
+
+    fn datapoint(datetime: &str, blob: Value) -> SessionSensorData {
+        SessionSensorData::new(Some(1), Some(1), datetime.to_string(), blob)
+    }
+
+    fn request(calibrated: &str) -> HttpRequest {
+        HttpRequest::get(
+            HttpPath::SessionSensorData(String::from("/session/1")),
+            Some(vec![("calibrated".to_string(), calibrated.to_string())]),
+            HttpHeader::new(),
+            None,
+        )
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_calibration_methods() {
+        let sensor = Sensor::new(1, "Thermometer".to_string());
+        let polynomial = calibration(
+            CalibrationMethod::Polynomial,
+            vec![1.0, 2.0, 0.5],
+            Vec::new(),
+            "2025-01-01T00:00:00",
+        );
+        assert_eq!(polynomial.apply(2.0), 1.0 + 2.0 * 2.0 + 0.5 * 4.0);
+        let linear = calibration(
+            CalibrationMethod::Linear,
+            vec![-40.0, 0.125],
+            Vec::new(),
+            "2025-01-01T00:00:00",
+        );
+        assert_eq!(linear.apply(480.0), 20.0);
+
+        // Tables interpolate between their points and clamp beyond the first and last
+        let table = calibration(
+            CalibrationMethod::Table,
+            Vec::new(),
+            vec![(0.0, -10.0), (100.0, 0.0), (300.0, 40.0)],
+            "2025-01-01T00:00:00",
+        );
+        let values: Vec<f64> = [-5.0, 0.0, 50.0, 200.0, 300.0, 1000.0]
+            .iter()
+            .map(|raw| table.apply(*raw))
+            .collect();
+        assert_eq!(values, vec![-10.0, -10.0, -5.0, 20.0, 40.0, 40.0]);
+        assert!(table.clone().validate(&sensor).is_ok());
+
+        let invalid = [
+            calibration(
+                CalibrationMethod::Linear,
+                vec![-40.0, 0.125, 1.0],
+                Vec::new(),
+                "2025-01-01T00:00:00",
+            ),
+            calibration(
+                CalibrationMethod::Polynomial,
+                Vec::new(),
+                vec![(0.0, 0.0), (1.0, 1.0)],
+                "2025-01-01T00:00:00",
+            ),
+            calibration(
+                CalibrationMethod::Table,
+                Vec::new(),
+                vec![(0.0, 0.0)],
+                "2025-01-01T00:00:00",
+            ),
+            calibration(
+                CalibrationMethod::Table,
+                Vec::new(),
+                vec![(1.0, 0.0), (1.0, 1.0)],
+                "2025-01-01T00:00:00",
+            ),
+        ];
+        for calibration in invalid {
+            assert!(matches!(calibration.validate(&sensor), Err(Error::Validation(_))));
+        }
+    }
+
+    #[test]
+    fn test_calibrate_datapoints() {
+        let db = init_database();
+        let mut calibrator = Calibrator::new(&db);
+        let blob = json!({ "celsius": 480, "battery": 3.3, "label": "kitchen" });
+
+        // Before any calibration the raw value is kept
+        let raw = calibrator
+            .calibrate(&datapoint("2024-12-31T23:59:59", blob.clone()))
+            .unwrap();
+        assert_eq!(raw.get_blob(), &blob);
+
+        let linear = calibrator
+            .calibrate(&datapoint("2025-01-01T11:59:59.500000", blob.clone()))
+            .unwrap();
+        assert_eq!(
+            linear.get_blob(),
+            &json!({ "celsius": 20.0, "battery": 3.3, "label": "kitchen" })
+        );
+        assert_eq!(linear.get_datetime(), "2025-01-01T11:59:59.500000");
+
+        // The calibration ended where the next one starts
+        let table = calibrator
+            .calibrate(&datapoint("2025-01-01T12:00:00", json!({ "celsius": 200 })))
+            .unwrap();
+        assert_eq!(table.get_blob(), &json!({ "celsius": 20.0 }));
+
+        // Blobs other than objects and datapoints with unknown datetimes are left raw
+        let value = calibrator
+            .calibrate(&datapoint("2025-01-02T00:00:00", json!(480)))
+            .unwrap();
+        assert_eq!(value.get_blob(), &json!(480));
+        let unparsed = calibrator.calibrate(&datapoint("yesterday", blob.clone())).unwrap();
+        assert_eq!(unparsed.get_blob(), &blob);
+
+        assert_eq!(calibrator.units(), json!({ "1": { "celsius": "°C", "battery": "V" } }));
+    }
+
+    #[test]
+    fn test_calibrated_parameter() {
+        let db = init_database();
+        let datapoints = vec![datapoint("2025-01-01T06:00:00", json!({ "celsius": 480 }))];
+
+        let raw = Calibrator::list_response(&db, &datapoints, &request("false"));
+        assert_eq!(
+            serde_json::from_str::<Value>(&raw.body).unwrap(),
+            json!({ "datapoints": [datapoints[0].to_json()] })
+        );
+
+        let calibrated = Calibrator::list_response(&db, &datapoints, &request("true"));
+        let body: Value = serde_json::from_str(&calibrated.body).unwrap();
+        assert_eq!(body["datapoints"][0]["data_blob"], json!({ "celsius": 20.0 }));
+        assert_eq!(
+            (&body["calibrated"], &body["units"]["1"]["celsius"]),
+            (&json!(true), &json!("°C"))
+        );
+
+        let single = Calibrator::datapoint_response(&db, &datapoints[0], &request("true"));
+        let body: Value = serde_json::from_str(&single.body).unwrap();
+        assert_eq!(
+            (&body["data_blob"]["celsius"], &body["calibrated"]),
+            (&json!(20.0), &json!(true))
+        );
+
+        let invalid = Calibrator::list_response(&db, &datapoints, &request("yes"));
+        assert!(matches!(invalid.status, HttpStatus::UnprocessableEntity));
+    }
+}
@@ -0,0 +1,104 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+//A fixed set of open connections, each checked out by one caller at a time
+pub struct ConnectionPool<C> {
+    connections: Mutex<Vec<C>>,
+    returned: Condvar,
+    size: usize,
+    checkout_timeout: Duration,
+}
+
+impl<C> ConnectionPool<C> {
+    pub fn new(connections: Vec<C>, checkout_timeout: Duration) -> Self {
+        ConnectionPool {
+            size: connections.len(),
+            connections: Mutex::new(connections),
+            returned: Condvar::new(),
+            checkout_timeout,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    //Checks out a connection, waiting up to the checkout timeout when all of them are busy
+    pub fn get(&self) -> Result<PooledConnection<'_, C>> {
+        let deadline = Instant::now() + self.checkout_timeout;
+        let mut connections = self.connections.lock().map_err(|_| Self::poisoned())?;
+        loop {
+            if let Some(connection) = connections.pop() {
+                return Ok(PooledConnection {
+                    pool: self,
+                    connection: Some(connection),
+                });
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Unavailable(format!(
+                    "Timed out waiting for one of {} database connections",
+                    self.size
+                )));
+            }
+            connections = self
+                .returned
+                .wait_timeout(connections, remaining)
+                .map_err(|_| Self::poisoned())?
+                .0;
+        }
+    }
+
+    fn put(&self, connection: C) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.push(connection);
+            self.returned.notify_one();
+        }
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Database connections were poisoned by a panic"))
+    }
+}
+
+//A checked out connection, it goes back to the pool when dropped
+pub struct PooledConnection<'a, C> {
+    pool: &'a ConnectionPool<C>,
+    // only taken while dropping
+    connection: Option<C>,
+}
+
+impl<C> Deref for PooledConnection<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl<C> DerefMut for PooledConnection<'_, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        self.connection.as_mut().unwrap()
+    }
+}
+
+impl<C> Drop for PooledConnection<'_, C> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.put(connection);
+        }
+    }
+}
@@ -7,13 +7,17 @@ Unless required by applicable law or agreed to in writing, software distributed
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
 
-use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+use crate::models::{
+    AlertDelivery, AlertEvent, AlertRule, DerivedChannel, QuarantinedSensorData, Sensor, SensorCalibration, SensorSchema,
+    SensorSummary, Session, SessionSensor, SessionSensorData, SessionSensorRollup, SessionStatus, SessionSummary,
+    SummaryGap, User,
+};
 
 type Result<T> = crate::Result<T>;
 
 //TODO: add query parameters
-pub trait Database {
-    fn temp_session_id_solution(&self);
+//Implementations are shared between the http workers and the udp listener
+pub trait Database: Send + Sync {
     /* Authentication */
     fn get_session_user(&self, session_id: &str) -> Result<User>;
     fn is_admin(&self, user: &User) -> bool;
@@ -35,6 +39,13 @@ pub trait Database {
     fn update_sensor(&self, sensor_id: i64, updated_sensor: &Sensor) -> Result<Sensor>;
     fn delete_sensor(&self, sensor_id: i64) -> Result<()>;
 
+    /* Sensor Calibration */
+    //Adds a calibration, ending the open ended one of its channel it replaces, see SensorCalibration::superseded
+    fn insert_sensor_calibration(&self, calibration: &SensorCalibration) -> Result<SensorCalibration>;
+    //Calibrations of a sensor by channel, oldest first
+    fn get_sensor_calibrations(&self, sensor_id: i64) -> Result<Vec<SensorCalibration>>;
+    fn delete_sensor_calibration(&self, sensor_id: i64, calibration_id: i64) -> Result<()>;
+
     /* Session */
     fn insert_session(&self, session: &Session) -> Result<Session>;
     fn get_session(&self, session_id: i64) -> Result<Session>;
@@ -42,6 +53,8 @@ pub trait Database {
     fn get_all_sessions(&self) -> Result<Vec<Session>>;
     fn update_session(&self, session_id: i64, updated_session: &Session) -> Result<Session>;
     fn delete_session(&self, session_id: i64) -> Result<()>;
+    fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session>;
+    fn get_active_session(&self, sensor_id: i64) -> Result<Session>;
 
     /* Session Sensor */
     fn insert_session_sensor(&self, session_sensor: &SessionSensor) -> Result<SessionSensor>;
@@ -62,7 +75,13 @@ pub trait Database {
     ) -> Result<SessionSensorData>;
     fn batch_session_sensor_data(
         &self,
-        data_blobs: &Vec<SessionSensorData>,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>>;
+    //Inserts the datapoints in one transaction like batch_session_sensor_data, returning the ones inserted
+    //Datapoints already stored for the same session sensor and datetime, or repeated in the batch, are skipped instead of failing it
+    fn import_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
     ) -> Result<Vec<SessionSensorData>>;
     fn get_sessions_sensors_data(&self) -> Result<Vec<SessionSensorData>>;
     fn get_sessions_sensor_data(&self, session_id: i64) -> Result<Vec<SessionSensorData>>;
@@ -71,17 +90,85 @@ pub trait Database {
         session_id: i64,
         datetime: &str,
     ) -> Result<Vec<SessionSensorData>>;
+    //At most limit datapoints of a session recorded from `from` and before `to`, ordered by datetime then session sensor
+    //Each page continues after `after`, the last datapoint of the page before it
+    fn get_sessions_sensor_data_page(
+        &self,
+        session_id: i64,
+        from: Option<&str>,
+        to: Option<&str>,
+        after: Option<&SessionSensorData>,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>>;
     fn get_session_sensor_data(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorData>>;
     fn get_session_sensor_datapoint(
         &self,
-        session_id: i64,
+        session_sensor_id: i64,
         datetime: &str,
     ) -> Result<SessionSensorData>;
     fn update_session_sensor_datapoint(
         &self,
-        session_id: i64,
+        session_sensor_id: i64,
         datetime: &str,
         updated_session_sensor_datapoint: &SessionSensorData,
     ) -> Result<SessionSensorData>;
-    fn delete_session_sensor_datapoint(&self, session_id: i64, datetime: &str) -> Result<()>;
+    fn delete_session_sensor_datapoint(&self, session_sensor_id: i64, datetime: &str) -> Result<()>;
+
+    /* Retention */
+    fn count_session_sensor_data_before(&self, session_sensor_id: i64, datetime: &str) -> Result<usize>;
+    //The oldest datapoints of a session sensor recorded before datetime, at most limit of them
+    fn get_session_sensor_data_before(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>>;
+    //Deletes the datapoints at datetimes and stores their rollups, replacing those of the same interval, in one transaction
+    fn prune_session_sensor_data(
+        &self,
+        session_sensor_id: i64,
+        datetimes: &[String],
+        rollups: &[SessionSensorRollup],
+    ) -> Result<usize>;
+    fn get_session_sensor_rollups(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorRollup>>;
+
+    /* Session Summary */
+    //Count, first and last datetime, and numeric field statistics of the datapoints of every sensor of a session
+    //Ordered by session sensor, leaving gaps and sample rates to SessionSummary
+    fn get_session_sensor_summaries(&self, session_id: i64) -> Result<Vec<SensorSummary>>;
+    //Consecutive datapoints of a session sensor more than gap_seconds apart, by session sensor then datetime
+    fn get_session_sensor_gaps(&self, session_id: i64, gap_seconds: f64) -> Result<Vec<SummaryGap>>;
+    //The cached summary of a session, if one was cached with the same gap threshold since its datapoints last changed
+    fn get_cached_session_summary(&self, session_id: i64, gap_seconds: f64) -> Result<Option<SessionSummary>>;
+    //Caches the summary of a session, replacing the one it had, until any of its datapoints is written or deleted
+    fn cache_session_summary(&self, summary: &SessionSummary) -> Result<()>;
+
+    /* Sensor Schema */
+    //Stores the schema of a sensor type, replacing the one it had
+    fn upsert_sensor_schema(&self, schema: &SensorSchema) -> Result<SensorSchema>;
+    fn get_sensor_schemas(&self) -> Result<Vec<SensorSchema>>;
+    fn get_sensor_schema(&self, sensor_type: &str) -> Result<SensorSchema>;
+    fn delete_sensor_schema(&self, sensor_type: &str) -> Result<()>;
+    fn quarantine_session_sensor_data(&self, quarantined: &[QuarantinedSensorData]) -> Result<()>;
+    //Quarantined datapoints of a session, oldest first
+    fn get_quarantined_sensor_data(&self, session_id: i64) -> Result<Vec<QuarantinedSensorData>>;
+    fn delete_quarantined_sensor_data(&self, session_id: i64) -> Result<usize>;
+
+    /* Alerts */
+    //Stores a rule of a sensor or a session, failing when that sensor or session does not exist
+    fn insert_alert_rule(&self, rule: &AlertRule) -> Result<AlertRule>;
+    fn get_alert_rules(&self) -> Result<Vec<AlertRule>>;
+    //Deletes a rule along with its events
+    fn delete_alert_rule(&self, rule_id: i64) -> Result<()>;
+    fn insert_alert_event(&self, event: &AlertEvent) -> Result<AlertEvent>;
+    //Events of a session, oldest first
+    fn get_alert_events(&self, session_id: i64) -> Result<Vec<AlertEvent>>;
+    fn update_alert_event_delivery(&self, event_id: i64, delivery: AlertDelivery, attempts: i64) -> Result<()>;
+
+    /* Derived Channels */
+    //Stores a channel, failing with a conflict when its sensor type already has a channel of that name
+    fn insert_derived_channel(&self, channel: &DerivedChannel) -> Result<DerivedChannel>;
+    //Channels of every sensor type, ordered by sensor type and name
+    fn get_derived_channels(&self) -> Result<Vec<DerivedChannel>>;
+    fn delete_derived_channel(&self, sensor_type: &str, name: &str) -> Result<()>;
 }
@@ -0,0 +1,1100 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::{btree_map, BTreeMap};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use chrono::NaiveDateTime;
+
+use crate::error::Error;
+use crate::models::{
+    AlertDelivery, AlertEvent, AlertRule, BaseModel, DerivedChannel, FieldSummary, QuarantinedSensorData, Sensor,
+    SensorCalibration, SensorSchema, SensorSummary, Session, SessionSensor, SessionSensorData, SessionSensorRollup,
+    SessionStatus, SessionSummary, SummaryGap, User,
+};
+
+use super::Database;
+type Result<T> = crate::Result<T>;
+
+// Session, session sensor, and datetime of a datapoint, the primary key of Session_Sensor_Data
+type DatapointKey = (Option<i64>, Option<i64>, String);
+// Session sensor, interval, and bucket of a rollup, the primary key of Session_Sensor_Rollup
+type RollupKey = (i64, i64, String);
+
+//Every table of the schema, kept in order of primary key
+#[derive(Default)]
+struct Tables {
+    users: BTreeMap<String, User>,
+    sensors: BTreeMap<i64, Sensor>,
+    sensor_calibrations: BTreeMap<i64, SensorCalibration>,
+    sessions: BTreeMap<i64, Session>,
+    session_sensors: BTreeMap<i64, SessionSensor>,
+    datapoints: BTreeMap<DatapointKey, SessionSensorData>,
+    rollups: BTreeMap<RollupKey, SessionSensorRollup>,
+    sensor_schemas: BTreeMap<String, SensorSchema>,
+    // in the order they were quarantined
+    quarantined: Vec<QuarantinedSensorData>,
+    session_summaries: BTreeMap<i64, SessionSummary>,
+    alert_rules: BTreeMap<i64, AlertRule>,
+    alert_events: BTreeMap<i64, AlertEvent>,
+    derived_channels: BTreeMap<(String, String), DerivedChannel>,
+}
+
+impl Tables {
+    // Ids are assigned like SQLite's rowid, one past the largest in use
+    fn next_id<T>(table: &BTreeMap<i64, T>) -> i64 {
+        table.keys().next_back().map_or(1, |id| id + 1)
+    }
+
+    fn unique_violation(column: &str) -> Error {
+        Error::Conflict(format!("UNIQUE constraint failed: {column}"))
+    }
+
+    fn foreign_key_violation() -> Error {
+        Error::Conflict(String::from("FOREIGN KEY constraint failed"))
+    }
+
+    fn check_user(&self, username: &str) -> Result<()> {
+        match self.users.contains_key(username) {
+            true => Ok(()),
+            false => Err(Self::foreign_key_violation()),
+        }
+    }
+
+    fn check_session_sensor(&self, session_sensor: &SessionSensor, ignored_id: Option<i64>) -> Result<()> {
+        if !self.sessions.contains_key(session_sensor.get_session_id())
+            || !self.sensors.contains_key(session_sensor.get_sensor_id())
+        {
+            return Err(Self::foreign_key_violation());
+        }
+        let duplicate = self.session_sensors.values().any(|existing| {
+            Some(*existing.get_id()) != ignored_id
+                && existing.get_session_id() == session_sensor.get_session_id()
+                && existing.get_sensor_id() == session_sensor.get_sensor_id()
+        });
+        match duplicate {
+            true => Err(Self::unique_violation("Session_Sensor.sessionID, Session_Sensor.sensorID")),
+            false => Ok(()),
+        }
+    }
+
+    fn check_datapoint(&self, datapoint: &SessionSensorData) -> Result<DatapointKey> {
+        let session_exists = datapoint.get_id().is_none_or(|id| self.sessions.contains_key(&id));
+        let session_sensor_exists = datapoint
+            .get_session_sensor_id()
+            .is_none_or(|id| self.session_sensors.contains_key(&id));
+        if !session_exists || !session_sensor_exists {
+            return Err(Self::foreign_key_violation());
+        }
+        let key = Self::datapoint_key(datapoint);
+        match self.datapoints.contains_key(&key) {
+            true => Err(Self::unique_violation(
+                "Session_Sensor_Data.sessionID, Session_Sensor_Data.session_sensorID, Session_Sensor_Data.datetime",
+            )),
+            false => Ok(key),
+        }
+    }
+
+    fn datapoint_key(datapoint: &SessionSensorData) -> DatapointKey {
+        (
+            *datapoint.get_id(),
+            *datapoint.get_session_sensor_id(),
+            datapoint.get_datetime().to_string(),
+        )
+    }
+
+    fn find_datapoint(&self, session_sensor_id: i64, datetime: &str) -> Result<&SessionSensorData> {
+        self.datapoints
+            .values()
+            .find(|data| *data.get_session_sensor_id() == Some(session_sensor_id) && data.get_datetime() == datetime)
+            .ok_or_else(|| Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")))
+    }
+
+    /* Cascades, following the ON DELETE CASCADE foreign keys of the schema */
+    fn delete_user(&mut self, username: &str) -> bool {
+        let sessions: Vec<i64> = self
+            .sessions
+            .values()
+            .filter(|session| session.get_username() == username)
+            .map(|session| *session.get_id())
+            .collect();
+        sessions.into_iter().for_each(|session_id| {
+            self.delete_session(session_id);
+        });
+        self.users.remove(username).is_some()
+    }
+
+    fn delete_sensor(&mut self, sensor_id: i64) -> bool {
+        let session_sensors: Vec<i64> = self
+            .session_sensors
+            .values()
+            .filter(|session_sensor| *session_sensor.get_sensor_id() == sensor_id)
+            .map(|session_sensor| *session_sensor.get_id())
+            .collect();
+        session_sensors.into_iter().for_each(|session_sensor_id| {
+            self.delete_session_sensor(session_sensor_id);
+        });
+        self.sensor_calibrations
+            .retain(|_, calibration| *calibration.get_sensor_id() != sensor_id);
+        let rules: Vec<i64> = self
+            .alert_rules
+            .values()
+            .filter(|rule| rule.get_sensor_id() == Some(sensor_id))
+            .map(|rule| *rule.get_id())
+            .collect();
+        rules.into_iter().for_each(|rule_id| {
+            self.delete_alert_rule(rule_id);
+        });
+        self.sensors.remove(&sensor_id).is_some()
+    }
+
+    fn delete_session(&mut self, session_id: i64) -> bool {
+        let session_sensors: Vec<i64> = self
+            .session_sensors
+            .values()
+            .filter(|session_sensor| *session_sensor.get_session_id() == session_id)
+            .map(|session_sensor| *session_sensor.get_id())
+            .collect();
+        session_sensors.into_iter().for_each(|session_sensor_id| {
+            self.delete_session_sensor(session_sensor_id);
+        });
+        self.datapoints.retain(|(session, _, _), _| *session != Some(session_id));
+        self.quarantined.retain(|datapoint| *datapoint.get_session_id() != session_id);
+        self.session_summaries.remove(&session_id);
+        let rules: Vec<i64> = self
+            .alert_rules
+            .values()
+            .filter(|rule| rule.get_session_id() == Some(session_id))
+            .map(|rule| *rule.get_id())
+            .collect();
+        rules.into_iter().for_each(|rule_id| {
+            self.delete_alert_rule(rule_id);
+        });
+        self.alert_events.retain(|_, event| *event.get_session_id() != session_id);
+        self.sessions.remove(&session_id).is_some()
+    }
+
+    fn session_of(&self, session_sensor_id: i64) -> Option<i64> {
+        self.session_sensors
+            .get(&session_sensor_id)
+            .map(|session_sensor| *session_sensor.get_session_id())
+    }
+
+    // Drops the cached summary of a session whose datapoints are written or deleted, like the triggers of the SQL schemas
+    fn invalidate_summary(&mut self, session_id: Option<i64>) {
+        if let Some(session_id) = session_id {
+            self.session_summaries.remove(&session_id);
+        }
+    }
+
+    fn delete_session_sensor(&mut self, session_sensor_id: i64) -> bool {
+        self.invalidate_summary(self.session_of(session_sensor_id));
+        self.datapoints
+            .retain(|(_, session_sensor, _), _| *session_sensor != Some(session_sensor_id));
+        self.rollups
+            .retain(|(session_sensor, _, _), _| *session_sensor != session_sensor_id);
+        self.quarantined
+            .retain(|datapoint| *datapoint.get_session_sensor_id() != session_sensor_id);
+        self.alert_events
+            .retain(|_, event| *event.get_session_sensor_id() != session_sensor_id);
+        self.session_sensors.remove(&session_sensor_id).is_some()
+    }
+
+    fn delete_alert_rule(&mut self, rule_id: i64) -> bool {
+        self.alert_events.retain(|_, event| *event.get_rule_id() != rule_id);
+        self.alert_rules.remove(&rule_id).is_some()
+    }
+}
+
+//Database kept entirely in memory, for tests and demos where nothing needs to outlive the process
+//Follows the uniqueness and cascade rules of the SQLite schema so it behaves like a real backend
+#[allow(unused)]
+pub struct MemoryDatabase {
+    tables: RwLock<Tables>,
+    // Temp Mock database to replace the unimplemented functions for testing
+    mock_db: super::MockDatabase,
+}
+
+#[allow(unused)]
+impl MemoryDatabase {
+    pub fn new() -> MemoryDatabase {
+        MemoryDatabase {
+            tables: RwLock::new(Tables::default()),
+            mock_db: super::MockDatabase::new(),
+        }
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, Tables>> {
+        self.tables.read().map_err(|_| Self::poisoned())
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Tables>> {
+        self.tables.write().map_err(|_| Self::poisoned())
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("In memory database was poisoned by a panic"))
+    }
+}
+
+impl Database for MemoryDatabase {
+    /* Authentication */
+    // TODO: Implement
+    fn is_admin(&self, user: &User) -> bool {
+        self.mock_db.is_admin(user)
+    }
+
+    // TODO: Implement
+    fn login(&self, user: &User) -> Result<String> {
+        self.mock_db.login(user)
+    }
+
+    // TODO: Implement
+    fn logout(&self, session_id: &str) -> Result<()> {
+        self.mock_db.logout(session_id)
+    }
+
+    // TODO: Implement
+    fn renew_session(&self, old_session: &str) -> Result<String> {
+        self.mock_db.renew_session(old_session)
+    }
+
+    //TODO: Implement
+    fn get_session_user(&self, session_id: &str) -> Result<User> {
+        self.mock_db.get_session_user(session_id)
+    }
+
+    /* User */
+    fn insert_user(&self, user: &User) -> Result<User> {
+        let mut tables = self.write()?;
+        if tables.users.contains_key(user.get_username()) {
+            return Err(Tables::unique_violation("User.username"));
+        }
+        tables.users.insert(user.get_username().to_string(), user.clone());
+
+        Ok(user.clone())
+    }
+
+    fn get_users(&self) -> Result<Vec<User>> {
+        Ok(self.read()?.users.values().cloned().collect())
+    }
+
+    fn get_user(&self, username: &str) -> Result<User> {
+        self.read()?
+            .users
+            .get(username)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("User {username}")))
+    }
+
+    // The username is the primary key and cannot be changed
+    fn update_user(&self, username: &str, updated_user: &User) -> Result<User> {
+        let mut tables = self.write()?;
+        let user = tables
+            .users
+            .get_mut(username)
+            .ok_or_else(|| Error::not_found(format!("User {username}")))?;
+        *user = User::new(username.to_string(), updated_user.get_password_hash().to_string());
+
+        Ok(updated_user.clone())
+    }
+
+    fn delete_user(&self, username: &str) -> Result<()> {
+        match self.write()?.delete_user(username) {
+            true => Ok(()),
+            false => Err(Error::not_found(format!("User {username}"))),
+        }
+    }
+
+    /* Sensor */
+    fn insert_sensor(&self, sensor: &Sensor) -> Result<Sensor> {
+        let mut tables = self.write()?;
+        let id = Tables::next_id(&tables.sensors);
+        let sensor = Sensor::new(id, sensor.get_sensor_type().to_string())
+            .with_metadata(
+                sensor.get_manufacturer().to_string(),
+                sensor.get_model().to_string(),
+                sensor.get_serial_number().to_string(),
+                sensor.get_firmware().to_string(),
+            )
+            .with_channels(sensor.get_channels().clone(), sensor.get_sample_rate());
+        tables.sensors.insert(id, sensor.clone());
+
+        Ok(sensor)
+    }
+
+    fn get_sensors(&self) -> Result<Vec<Sensor>> {
+        Ok(self.read()?.sensors.values().cloned().collect())
+    }
+
+    fn get_sensor(&self, sensor_id: i64) -> Result<Sensor> {
+        self.read()?
+            .sensors
+            .get(&sensor_id)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Sensor {sensor_id}")))
+    }
+
+    fn update_sensor(&self, sensor_id: i64, updated_sensor: &Sensor) -> Result<Sensor> {
+        let mut tables = self.write()?;
+        let existing = tables
+            .sensors
+            .get_mut(&sensor_id)
+            .ok_or_else(|| Error::not_found(format!("Sensor {sensor_id}")))?;
+        let mut sensor = Sensor::new(sensor_id, updated_sensor.get_sensor_type().to_string())
+            .with_metadata(
+                updated_sensor.get_manufacturer().to_string(),
+                updated_sensor.get_model().to_string(),
+                updated_sensor.get_serial_number().to_string(),
+                updated_sensor.get_firmware().to_string(),
+            )
+            .with_channels(updated_sensor.get_channels().clone(), updated_sensor.get_sample_rate());
+        sensor.fill_from(existing);
+        *existing = sensor.clone();
+
+        Ok(sensor)
+    }
+
+    fn delete_sensor(&self, sensor_id: i64) -> Result<()> {
+        match self.write()?.delete_sensor(sensor_id) {
+            true => Ok(()),
+            false => Err(Error::not_found(format!("Sensor {sensor_id}"))),
+        }
+    }
+
+    /* Sensor Calibration */
+    fn insert_sensor_calibration(&self, calibration: &SensorCalibration) -> Result<SensorCalibration> {
+        let mut tables = self.write()?;
+        let sensor_id = *calibration.get_sensor_id();
+        if !tables.sensors.contains_key(&sensor_id) {
+            return Err(Error::not_found(format!("Sensor {sensor_id}")));
+        }
+        let existing: Vec<SensorCalibration> = tables.sensor_calibrations.values().cloned().collect();
+        for ended in calibration.superseded(&existing)? {
+            tables.sensor_calibrations.insert(*ended.get_id(), ended);
+        }
+        let calibration = calibration
+            .clone()
+            .with_ids(Tables::next_id(&tables.sensor_calibrations), sensor_id);
+        tables.sensor_calibrations.insert(*calibration.get_id(), calibration.clone());
+
+        Ok(calibration)
+    }
+
+    fn get_sensor_calibrations(&self, sensor_id: i64) -> Result<Vec<SensorCalibration>> {
+        let mut calibrations: Vec<SensorCalibration> = self
+            .read()?
+            .sensor_calibrations
+            .values()
+            .filter(|calibration| *calibration.get_sensor_id() == sensor_id)
+            .cloned()
+            .collect();
+        calibrations.sort_by(|left, right| {
+            (left.get_channel(), left.get_valid_from()).cmp(&(right.get_channel(), right.get_valid_from()))
+        });
+
+        Ok(calibrations)
+    }
+
+    fn delete_sensor_calibration(&self, sensor_id: i64, calibration_id: i64) -> Result<()> {
+        let mut tables = self.write()?;
+        match tables.sensor_calibrations.get(&calibration_id) {
+            Some(calibration) if *calibration.get_sensor_id() == sensor_id => {
+                tables.sensor_calibrations.remove(&calibration_id);
+                Ok(())
+            }
+            _ => Err(Error::not_found(format!("Calibration {calibration_id} of sensor {sensor_id}"))),
+        }
+    }
+
+    /* Session */
+    // New sessions start out pending, whatever lifecycle the passed session has
+    fn insert_session(&self, session: &Session) -> Result<Session> {
+        let mut tables = self.write()?;
+        tables.check_user(session.get_username())?;
+        let session = Session::new(Tables::next_id(&tables.sessions), session.get_username().to_string())
+            .with_metadata(
+                session.get_name().to_string(),
+                session.get_description().to_string(),
+                session.get_tags().clone(),
+            );
+        tables.sessions.insert(*session.get_id(), session.clone());
+
+        Ok(session)
+    }
+
+    fn get_session(&self, session_id: i64) -> Result<Session> {
+        self.read()?
+            .sessions
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Session {session_id}")))
+    }
+
+    fn get_user_sessions(&self, username: &str) -> Result<Vec<Session>> {
+        Ok(self
+            .read()?
+            .sessions
+            .values()
+            .filter(|session| session.get_username() == username)
+            .cloned()
+            .collect())
+    }
+
+    fn get_all_sessions(&self) -> Result<Vec<Session>> {
+        Ok(self.read()?.sessions.values().cloned().collect())
+    }
+
+    // Updates the metadata of a Session, values missing from updated_session are kept
+    fn update_session(&self, session_id: i64, updated_session: &Session) -> Result<Session> {
+        let mut tables = self.write()?;
+        let existing = tables
+            .sessions
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Session {session_id}")))?;
+        let mut session = Session::new(session_id, updated_session.get_username().to_string())
+            .with_metadata(
+                updated_session.get_name().to_string(),
+                updated_session.get_description().to_string(),
+                updated_session.get_tags().clone(),
+            );
+        session.fill_from(&existing);
+        tables.check_user(session.get_username())?;
+
+        // only the metadata changes, the lifecycle is moved by set_session_status
+        tables.sessions.insert(
+            session_id,
+            session.clone().with_lifecycle(
+                *existing.get_status(),
+                existing.get_start_time().map(str::to_string),
+                existing.get_end_time().map(str::to_string),
+            ),
+        );
+
+        Ok(session)
+    }
+
+    fn delete_session(&self, session_id: i64) -> Result<()> {
+        match self.write()?.delete_session(session_id) {
+            true => Ok(()),
+            false => Err(Error::not_found(format!("Session {session_id}"))),
+        }
+    }
+
+    // Moves a Session through its lifecycle, a sensor can only record to one session at a time
+    fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session> {
+        let mut tables = self.write()?;
+        let mut session = tables
+            .sessions
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Session {session_id}")))?;
+        session.transition(status)?;
+
+        if status == SessionStatus::Recording {
+            let sensors: Vec<i64> = tables
+                .session_sensors
+                .values()
+                .filter(|session_sensor| *session_sensor.get_session_id() == session_id)
+                .map(|session_sensor| *session_sensor.get_sensor_id())
+                .collect();
+            let conflict = tables.session_sensors.values().any(|session_sensor| {
+                *session_sensor.get_session_id() != session_id
+                    && sensors.contains(session_sensor.get_sensor_id())
+                    && tables
+                        .sessions
+                        .get(session_sensor.get_session_id())
+                        .is_some_and(|other| *other.get_status() == SessionStatus::Recording)
+            });
+            if conflict {
+                return Err(Error::Conflict(format!(
+                    "Session {session_id} shares sensors with a session that is already recording"
+                )));
+            }
+        }
+        tables.sessions.insert(session_id, session.clone());
+
+        Ok(session)
+    }
+
+    // Returns the recording Session the sensor is attached to
+    fn get_active_session(&self, sensor_id: i64) -> Result<Session> {
+        let tables = self.read()?;
+        tables
+            .session_sensors
+            .values()
+            .filter(|session_sensor| *session_sensor.get_sensor_id() == sensor_id)
+            .filter_map(|session_sensor| tables.sessions.get(session_sensor.get_session_id()))
+            .filter(|session| *session.get_status() == SessionStatus::Recording)
+            .max_by(|a, b| a.get_start_time().cmp(&b.get_start_time()))
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Active session for sensor {sensor_id}")))
+    }
+
+    /* Session Sensor */
+    fn insert_session_sensor(&self, session_sensor: &SessionSensor) -> Result<SessionSensor> {
+        let mut tables = self.write()?;
+        tables.check_session_sensor(session_sensor, None)?;
+        let session_sensor = SessionSensor::new(
+            Tables::next_id(&tables.session_sensors),
+            *session_sensor.get_session_id(),
+            *session_sensor.get_sensor_id(),
+        );
+        tables.session_sensors.insert(*session_sensor.get_id(), session_sensor.clone());
+
+        Ok(session_sensor)
+    }
+
+    fn get_sessions_sensors(&self) -> Result<Vec<SessionSensor>> {
+        Ok(self.read()?.session_sensors.values().cloned().collect())
+    }
+
+    fn get_session_sensors(&self, session_id: i64) -> Result<Vec<SessionSensor>> {
+        Ok(self
+            .read()?
+            .session_sensors
+            .values()
+            .filter(|session_sensor| *session_sensor.get_session_id() == session_id)
+            .cloned()
+            .collect())
+    }
+
+    fn get_session_sensor(&self, session_sensor_id: i64) -> Result<SessionSensor> {
+        self.read()?
+            .session_sensors
+            .get(&session_sensor_id)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Session sensor {session_sensor_id}")))
+    }
+
+    fn update_session_sensor(
+        &self,
+        session_sensor_id: i64,
+        updated_session_sensor: &SessionSensor,
+    ) -> Result<SessionSensor> {
+        let mut tables = self.write()?;
+        if !tables.session_sensors.contains_key(&session_sensor_id) {
+            return Err(Error::not_found(format!("Session sensor {session_sensor_id}")));
+        }
+        tables.check_session_sensor(updated_session_sensor, Some(session_sensor_id))?;
+        tables.session_sensors.insert(
+            session_sensor_id,
+            SessionSensor::new(
+                session_sensor_id,
+                *updated_session_sensor.get_session_id(),
+                *updated_session_sensor.get_sensor_id(),
+            ),
+        );
+
+        Ok(updated_session_sensor.clone())
+    }
+
+    fn delete_session_sensor(&self, session_sensor_id: i64) -> Result<()> {
+        match self.write()?.delete_session_sensor(session_sensor_id) {
+            true => Ok(()),
+            false => Err(Error::not_found(format!("Session sensor {session_sensor_id}"))),
+        }
+    }
+
+    /* Session Sensor Data */
+    fn insert_session_sensor_data(
+        &self,
+        session_sensor_data: &SessionSensorData,
+    ) -> Result<SessionSensorData> {
+        let mut tables = self.write()?;
+        let key = tables.check_datapoint(session_sensor_data)?;
+        tables.invalidate_summary(key.0);
+        tables.datapoints.insert(key, session_sensor_data.clone());
+
+        Ok(session_sensor_data.clone())
+    }
+
+    // Every datapoint is checked before any is stored, so a batch is written whole or not at all
+    fn batch_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>> {
+        let mut tables = self.write()?;
+        let mut batch = BTreeMap::new();
+        for data in data_blobs {
+            let key = tables.check_datapoint(data)?;
+            if batch.insert(key, data.clone()).is_some() {
+                return Err(Tables::unique_violation(
+                    "Session_Sensor_Data.sessionID, Session_Sensor_Data.session_sensorID, Session_Sensor_Data.datetime",
+                ));
+            }
+        }
+        for (session_id, _, _) in batch.keys() {
+            tables.invalidate_summary(*session_id);
+        }
+        tables.datapoints.append(&mut batch);
+
+        Ok(data_blobs.to_vec())
+    }
+
+    // Missing sessions and sensors still fail the whole batch, only repeated datapoints are skipped
+    fn import_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>> {
+        let mut tables = self.write()?;
+        let mut batch = BTreeMap::new();
+        let mut inserted = Vec::new();
+        for data in data_blobs {
+            let key = match tables.check_datapoint(data) {
+                Ok(key) => key,
+                Err(_) if tables.datapoints.contains_key(&Tables::datapoint_key(data)) => continue,
+                Err(e) => return Err(e),
+            };
+            if let btree_map::Entry::Vacant(entry) = batch.entry(key) {
+                entry.insert(data.clone());
+                inserted.push(data.clone());
+            }
+        }
+        for (session_id, _, _) in batch.keys() {
+            tables.invalidate_summary(*session_id);
+        }
+        tables.datapoints.append(&mut batch);
+
+        Ok(inserted)
+    }
+
+    fn get_sessions_sensors_data(&self) -> Result<Vec<SessionSensorData>> {
+        Ok(self.read()?.datapoints.values().cloned().collect())
+    }
+
+    fn get_sessions_sensor_data(&self, session_id: i64) -> Result<Vec<SessionSensorData>> {
+        Ok(self
+            .read()?
+            .datapoints
+            .values()
+            .filter(|data| *data.get_id() == Some(session_id))
+            .cloned()
+            .collect())
+    }
+
+    // Returns the datapoints of a session recorded after the passed datetime
+    fn get_sessions_sensor_data_after(
+        &self,
+        session_id: i64,
+        datetime: &str,
+    ) -> Result<Vec<SessionSensorData>> {
+        let filter_time = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f")?;
+        let mut session_sensor_data_vec = Vec::new();
+        for data in self.get_sessions_sensor_data(session_id)? {
+            if NaiveDateTime::parse_from_str(data.get_datetime(), "%Y-%m-%dT%H:%M:%S%.f")? > filter_time {
+                session_sensor_data_vec.push(data);
+            }
+        }
+
+        Ok(session_sensor_data_vec)
+    }
+
+    fn get_sessions_sensor_data_page(
+        &self,
+        session_id: i64,
+        from: Option<&str>,
+        to: Option<&str>,
+        after: Option<&SessionSensorData>,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        let parse = |datetime: &str| NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f");
+        let from = from.map(parse).transpose()?;
+        let to = to.map(parse).transpose()?;
+        let after = match after {
+            Some(data) => Some((parse(data.get_datetime())?, data.get_session_sensor_id().unwrap_or(0))),
+            None => None,
+        };
+
+        let mut page = Vec::new();
+        for data in self.get_sessions_sensor_data(session_id)? {
+            let key = (parse(data.get_datetime())?, data.get_session_sensor_id().unwrap_or(0));
+            if from.is_some_and(|from| key.0 < from) || to.is_some_and(|to| key.0 >= to) || after.is_some_and(|after| key <= after) {
+                continue;
+            }
+            page.push((key, data));
+        }
+        page.sort_by_key(|(key, _)| *key);
+
+        Ok(page.into_iter().take(limit).map(|(_, data)| data).collect())
+    }
+
+    fn get_session_sensor_data(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorData>> {
+        Ok(self
+            .read()?
+            .datapoints
+            .values()
+            .filter(|data| *data.get_session_sensor_id() == Some(session_sensor_id))
+            .cloned()
+            .collect())
+    }
+
+    fn get_session_sensor_datapoint(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+    ) -> Result<SessionSensorData> {
+        self.read()?.find_datapoint(session_sensor_id, datetime).cloned()
+    }
+
+    // Only the datetime and data blob change, the datapoint stays with its session sensor
+    fn update_session_sensor_datapoint(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+        updated_session_sensor_datapoint: &SessionSensorData,
+    ) -> Result<SessionSensorData> {
+        let mut tables = self.write()?;
+        let existing = tables.find_datapoint(session_sensor_id, datetime)?.clone();
+        let mut filled = updated_session_sensor_datapoint.clone();
+        filled.fill_from(&existing);
+
+        let updated = SessionSensorData::new(
+            *existing.get_id(),
+            *existing.get_session_sensor_id(),
+            filled.get_datetime().to_string(),
+            filled.get_blob().clone(),
+        );
+        let old_key = Tables::datapoint_key(&existing);
+        let new_key = Tables::datapoint_key(&updated);
+        if new_key != old_key && tables.datapoints.contains_key(&new_key) {
+            return Err(Tables::unique_violation(
+                "Session_Sensor_Data.sessionID, Session_Sensor_Data.session_sensorID, Session_Sensor_Data.datetime",
+            ));
+        }
+        tables.invalidate_summary(*existing.get_id());
+        tables.datapoints.remove(&old_key);
+        tables.datapoints.insert(new_key, updated.clone());
+
+        Ok(updated)
+    }
+
+    fn delete_session_sensor_datapoint(&self, session_sensor_id: i64, datetime: &str) -> Result<()> {
+        let mut tables = self.write()?;
+        let key = Tables::datapoint_key(tables.find_datapoint(session_sensor_id, datetime)?);
+        tables.invalidate_summary(key.0);
+        tables.datapoints.remove(&key);
+
+        Ok(())
+    }
+
+    /* Retention */
+    fn count_session_sensor_data_before(&self, session_sensor_id: i64, datetime: &str) -> Result<usize> {
+        Ok(self.get_session_sensor_data_before(session_sensor_id, datetime, usize::MAX)?.len())
+    }
+
+    fn get_session_sensor_data_before(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        let filter_time = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f")?;
+        let mut before = Vec::new();
+        for data in self.get_session_sensor_data(session_sensor_id)? {
+            let recorded = NaiveDateTime::parse_from_str(data.get_datetime(), "%Y-%m-%dT%H:%M:%S%.f")?;
+            if recorded < filter_time {
+                before.push((recorded, data));
+            }
+        }
+        before.sort_by_key(|(recorded, _)| *recorded);
+
+        Ok(before.into_iter().take(limit).map(|(_, data)| data).collect())
+    }
+
+    fn prune_session_sensor_data(
+        &self,
+        session_sensor_id: i64,
+        datetimes: &[String],
+        rollups: &[SessionSensorRollup],
+    ) -> Result<usize> {
+        let mut tables = self.write()?;
+        if rollups
+            .iter()
+            .any(|rollup| !tables.session_sensors.contains_key(rollup.get_session_sensor_id()))
+        {
+            return Err(Tables::foreign_key_violation());
+        }
+        for rollup in rollups {
+            tables.rollups.insert(
+                (
+                    *rollup.get_session_sensor_id(),
+                    *rollup.get_interval_seconds(),
+                    rollup.get_bucket().to_string(),
+                ),
+                rollup.clone(),
+            );
+        }
+        let before = tables.datapoints.len();
+        tables.datapoints.retain(|(_, session_sensor, datetime), _| {
+            *session_sensor != Some(session_sensor_id) || !datetimes.contains(datetime)
+        });
+        if tables.datapoints.len() != before {
+            let session_id = tables.session_of(session_sensor_id);
+            tables.invalidate_summary(session_id);
+        }
+
+        Ok(before - tables.datapoints.len())
+    }
+
+    fn get_session_sensor_rollups(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorRollup>> {
+        Ok(self
+            .read()?
+            .rollups
+            .values()
+            .filter(|rollup| *rollup.get_session_sensor_id() == session_sensor_id)
+            .cloned()
+            .collect())
+    }
+
+    /* Session Summary */
+    fn get_session_sensor_summaries(&self, session_id: i64) -> Result<Vec<SensorSummary>> {
+        let tables = self.read()?;
+        let mut summaries: Vec<SensorSummary> = Vec::new();
+        let mut values: BTreeMap<(Option<i64>, String), Vec<f64>> = BTreeMap::new();
+        // datapoints are kept in order of session sensor, then datetime
+        for ((_, session_sensor_id, datetime), data) in tables
+            .datapoints
+            .iter()
+            .filter(|((session, _, _), _)| *session == Some(session_id))
+        {
+            match summaries.last_mut() {
+                Some(summary) if summary.session_sensor_id == *session_sensor_id => {
+                    summary.count += 1;
+                    summary.last = datetime.clone();
+                }
+                _ => summaries.push(SensorSummary {
+                    session_sensor_id: *session_sensor_id,
+                    count: 1,
+                    first: datetime.clone(),
+                    last: datetime.clone(),
+                    ..Default::default()
+                }),
+            }
+            for (field, value) in data.get_blob().as_object().into_iter().flatten() {
+                if let Some(value) = value.as_f64() {
+                    values.entry((*session_sensor_id, field.clone())).or_default().push(value);
+                }
+            }
+        }
+
+        for ((session_sensor_id, field), values) in values {
+            let count = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / count;
+            let squares: f64 = values.iter().map(|value| (value - mean) * (value - mean)).sum();
+            let summary = FieldSummary {
+                count: values.len() as i64,
+                min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                mean,
+                stddev: if values.len() > 1 { (squares / (count - 1.0)).sqrt() } else { 0.0 },
+            };
+            if let Some(sensor) = summaries
+                .iter_mut()
+                .find(|sensor| sensor.session_sensor_id == session_sensor_id)
+            {
+                sensor.fields.insert(field, summary);
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    fn get_session_sensor_gaps(&self, session_id: i64, gap_seconds: f64) -> Result<Vec<SummaryGap>> {
+        let tables = self.read()?;
+        let keys: Vec<&DatapointKey> = tables
+            .datapoints
+            .keys()
+            .filter(|(session, _, _)| *session == Some(session_id))
+            .collect();
+        Ok(keys
+            .windows(2)
+            .filter(|pair| pair[0].1 == pair[1].1)
+            .filter_map(|pair| {
+                let seconds = SessionSummary::seconds_between(&pair[0].2, &pair[1].2)?;
+                (seconds > gap_seconds).then(|| SummaryGap {
+                    session_sensor_id: pair[0].1,
+                    from: pair[0].2.clone(),
+                    to: pair[1].2.clone(),
+                    seconds,
+                })
+            })
+            .collect())
+    }
+
+    fn get_cached_session_summary(&self, session_id: i64, gap_seconds: f64) -> Result<Option<SessionSummary>> {
+        Ok(self
+            .read()?
+            .session_summaries
+            .get(&session_id)
+            .filter(|summary| *summary.get_gap_seconds() == gap_seconds)
+            .cloned())
+    }
+
+    fn cache_session_summary(&self, summary: &SessionSummary) -> Result<()> {
+        let mut tables = self.write()?;
+        if !tables.sessions.contains_key(summary.get_session_id()) {
+            return Err(Tables::foreign_key_violation());
+        }
+        tables.session_summaries.insert(*summary.get_session_id(), summary.clone());
+
+        Ok(())
+    }
+
+    /* Sensor Schema */
+    fn upsert_sensor_schema(&self, schema: &SensorSchema) -> Result<SensorSchema> {
+        self.write()?
+            .sensor_schemas
+            .insert(schema.get_sensor_type().to_string(), schema.clone());
+
+        Ok(schema.clone())
+    }
+
+    fn get_sensor_schemas(&self) -> Result<Vec<SensorSchema>> {
+        Ok(self.read()?.sensor_schemas.values().cloned().collect())
+    }
+
+    fn get_sensor_schema(&self, sensor_type: &str) -> Result<SensorSchema> {
+        self.read()?
+            .sensor_schemas
+            .get(sensor_type)
+            .cloned()
+            .ok_or_else(|| Error::not_found(format!("Schema of sensor type {sensor_type}")))
+    }
+
+    fn delete_sensor_schema(&self, sensor_type: &str) -> Result<()> {
+        match self.write()?.sensor_schemas.remove(sensor_type) {
+            Some(_) => Ok(()),
+            None => Err(Error::not_found(format!("Schema of sensor type {sensor_type}"))),
+        }
+    }
+
+    fn quarantine_session_sensor_data(&self, quarantined: &[QuarantinedSensorData]) -> Result<()> {
+        let mut tables = self.write()?;
+        if quarantined.iter().any(|datapoint| {
+            !tables.sessions.contains_key(datapoint.get_session_id())
+                || !tables.session_sensors.contains_key(datapoint.get_session_sensor_id())
+        }) {
+            return Err(Tables::foreign_key_violation());
+        }
+        tables.quarantined.extend_from_slice(quarantined);
+
+        Ok(())
+    }
+
+    fn get_quarantined_sensor_data(&self, session_id: i64) -> Result<Vec<QuarantinedSensorData>> {
+        let mut quarantined: Vec<QuarantinedSensorData> = self
+            .read()?
+            .quarantined
+            .iter()
+            .filter(|datapoint| *datapoint.get_session_id() == session_id)
+            .cloned()
+            .collect();
+        // a stable sort keeps those quarantined in the same second in the order they were written
+        quarantined.sort_by(|left, right| left.get_quarantined_at().cmp(right.get_quarantined_at()));
+
+        Ok(quarantined)
+    }
+
+    fn delete_quarantined_sensor_data(&self, session_id: i64) -> Result<usize> {
+        let mut tables = self.write()?;
+        let before = tables.quarantined.len();
+        tables.quarantined.retain(|datapoint| *datapoint.get_session_id() != session_id);
+
+        Ok(before - tables.quarantined.len())
+    }
+
+    /* Alerts */
+    fn insert_alert_rule(&self, rule: &AlertRule) -> Result<AlertRule> {
+        let mut tables = self.write()?;
+        if let Some(sensor_id) = rule.get_sensor_id().filter(|sensor_id| !tables.sensors.contains_key(sensor_id)) {
+            return Err(Error::not_found(format!("Sensor {sensor_id}")));
+        }
+        if let Some(session_id) = rule.get_session_id().filter(|session_id| !tables.sessions.contains_key(session_id)) {
+            return Err(Error::not_found(format!("Session {session_id}")));
+        }
+        let rule = rule.clone().with_id(Tables::next_id(&tables.alert_rules));
+        tables.alert_rules.insert(*rule.get_id(), rule.clone());
+
+        Ok(rule)
+    }
+
+    fn get_alert_rules(&self) -> Result<Vec<AlertRule>> {
+        Ok(self.read()?.alert_rules.values().cloned().collect())
+    }
+
+    fn delete_alert_rule(&self, rule_id: i64) -> Result<()> {
+        match self.write()?.delete_alert_rule(rule_id) {
+            true => Ok(()),
+            false => Err(Error::not_found(format!("Alert rule {rule_id}"))),
+        }
+    }
+
+    fn insert_alert_event(&self, event: &AlertEvent) -> Result<AlertEvent> {
+        let mut tables = self.write()?;
+        if !tables.alert_rules.contains_key(event.get_rule_id())
+            || !tables.sessions.contains_key(event.get_session_id())
+            || !tables.session_sensors.contains_key(event.get_session_sensor_id())
+        {
+            return Err(Tables::foreign_key_violation());
+        }
+        let event = event.clone().with_id(Tables::next_id(&tables.alert_events));
+        tables.alert_events.insert(*event.get_id(), event.clone());
+
+        Ok(event)
+    }
+
+    fn get_alert_events(&self, session_id: i64) -> Result<Vec<AlertEvent>> {
+        Ok(self
+            .read()?
+            .alert_events
+            .values()
+            .filter(|event| *event.get_session_id() == session_id)
+            .cloned()
+            .collect())
+    }
+
+    fn update_alert_event_delivery(&self, event_id: i64, delivery: AlertDelivery, attempts: i64) -> Result<()> {
+        let mut tables = self.write()?;
+        match tables.alert_events.remove(&event_id) {
+            Some(event) => {
+                tables.alert_events.insert(event_id, event.with_delivery(delivery, attempts));
+                Ok(())
+            }
+            None => Err(Error::not_found(format!("Alert event {event_id}"))),
+        }
+    }
+
+    /* Derived Channels */
+    fn insert_derived_channel(&self, channel: &DerivedChannel) -> Result<DerivedChannel> {
+        let mut tables = self.write()?;
+        match tables
+            .derived_channels
+            .entry((channel.get_sensor_type().to_string(), channel.get_name().to_string()))
+        {
+            btree_map::Entry::Occupied(_) => Err(Tables::unique_violation("Derived_Channel.type, Derived_Channel.name")),
+            btree_map::Entry::Vacant(entry) => Ok(entry.insert(channel.clone()).clone()),
+        }
+    }
+
+    fn get_derived_channels(&self) -> Result<Vec<DerivedChannel>> {
+        Ok(self.read()?.derived_channels.values().cloned().collect())
+    }
+
+    fn delete_derived_channel(&self, sensor_type: &str, name: &str) -> Result<()> {
+        match self
+            .write()?
+            .derived_channels
+            .remove(&(sensor_type.to_string(), name.to_string()))
+        {
+            Some(_) => Ok(()),
+            None => Err(Error::not_found(format!("Derived channel {name} of sensor type {sensor_type}"))),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+//A single schema change, applied once and in order of version
+//Every backend keeps its own list in its own dialect, with the same versions and names
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+    // Query returning a non zero count when a database from before versioning already has this change
+    // Only needed by migrations released before versioning existed
+    pub detect: Option<&'static str>,
+}
+
+impl Migration {
+    //Hex encoded SHA-256 of the migration's sql, used to detect migrations edited after being applied
+    pub fn checksum(&self) -> String {
+        Sha256::digest(self.sql.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    // Applied, but the migration has been edited since
+    Modified,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+        }
+    }
+}
+
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<String>,
+}
+
+impl fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>4}  {:<32} {:<9} {}",
+            self.version,
+            self.name,
+            self.state.as_str(),
+            self.applied_at.as_deref().unwrap_or("")
+        )
+    }
+}
+
+//A row of the Schema_Migrations table
+pub struct AppliedMigration {
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+//Compares the known migrations with those recorded as applied
+pub fn migration_status(
+    migrations: &[Migration],
+    applied: &HashMap<i64, AppliedMigration>,
+) -> Result<Vec<MigrationStatus>> {
+    check_unknown(migrations, applied)?;
+    Ok(migrations
+        .iter()
+        .map(|migration| match applied.get(&migration.version) {
+            Some(applied_migration) => MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state: if applied_migration.checksum == migration.checksum() {
+                    MigrationState::Applied
+                } else {
+                    MigrationState::Modified
+                },
+                applied_at: Some(applied_migration.applied_at.clone()),
+            },
+            None => MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state: MigrationState::Pending,
+                applied_at: None,
+            },
+        })
+        .collect())
+}
+
+//Migrations that have not been applied yet, fails if an applied migration was edited
+pub fn pending_migrations<'a>(
+    migrations: &'a [Migration],
+    applied: &HashMap<i64, AppliedMigration>,
+) -> Result<Vec<&'a Migration>> {
+    let mut pending = Vec::new();
+    for status in migration_status(migrations, applied)? {
+        match status.state {
+            MigrationState::Applied => {}
+            MigrationState::Pending => pending.extend(
+                migrations
+                    .iter()
+                    .find(|migration| migration.version == status.version),
+            ),
+            MigrationState::Modified => {
+                return Err(Error::Conflict(format!(
+                    "Migration {} '{}' was edited after being applied, its checksum no longer matches",
+                    status.version, status.name
+                )))
+            }
+        }
+    }
+    Ok(pending)
+}
+
+//Time a migration is recorded as applied at
+pub fn applied_at_now() -> String {
+    Utc::now().naive_utc().format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+// A database newer than this build cannot be migrated safely
+fn check_unknown(migrations: &[Migration], applied: &HashMap<i64, AppliedMigration>) -> Result<()> {
+    match applied
+        .iter()
+        .find(|(version, _)| !migrations.iter().any(|migration| migration.version == **version))
+    {
+        Some((version, applied_migration)) => Err(Error::Conflict(format!(
+            "Database has migration {version} '{}' applied which this build does not know about",
+            applied_migration.name
+        ))),
+        None => Ok(()),
+    }
+}
@@ -8,8 +8,13 @@ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
 */
 use serde_json::{Map, Value};
 
+use crate::error::Error;
 //#![cfg(not(feature = "sql"))]
-use crate::models::{BaseModel, Sensor, Session, SessionSensor, SessionSensorData, User};
+use crate::models::{
+    AlertDelivery, AlertEvent, AlertRule, BaseModel, DerivedChannel, QuarantinedSensorData, Sensor, SensorCalibration,
+    SensorSchema, SensorSummary, Session, SessionSensor, SessionSensorData, SessionSensorRollup, SessionStatus,
+    SessionSummary, SummaryGap, User,
+};
 
 use super::Database;
 
@@ -49,8 +54,16 @@ impl MockDatabase {
 
     pub fn sessions() -> Vec<Session> {
         vec![
-            Session::new(1, String::from("username_1")),
-            Session::new(2, String::from("username_2")),
+            Session::new(1, String::from("username_1")).with_lifecycle(
+                SessionStatus::Recording,
+                Some(String::from("datetime_1")),
+                None,
+            ),
+            Session::new(2, String::from("username_2")).with_lifecycle(
+                SessionStatus::Stopped,
+                Some(String::from("datetime_1")),
+                Some(String::from("datetime_2")),
+            ),
             Session::new(3, String::from("username_3")),
             Session::new(4, String::from("username_4")),
         ]
@@ -67,17 +80,15 @@ impl MockDatabase {
 
     pub fn sessions_sensors_data() -> Vec<SessionSensorData> {
         vec![
-            SessionSensorData::new(Some(1), String::from("datetime_1"), Value::Object(Map::new())),
-            SessionSensorData::new(Some(2), String::from("datetime_2"), Value::Object(Map::new())),
-            SessionSensorData::new(Some(3), String::from("datetime_3"), Value::Object(Map::new())),
-            SessionSensorData::new(Some(4), String::from("datetime_4"), Value::Object(Map::new())),
+            SessionSensorData::new(Some(1), Some(1), String::from("datetime_1"), Value::Object(Map::new())),
+            SessionSensorData::new(Some(2), Some(2), String::from("datetime_2"), Value::Object(Map::new())),
+            SessionSensorData::new(Some(3), Some(3), String::from("datetime_3"), Value::Object(Map::new())),
+            SessionSensorData::new(Some(4), Some(4), String::from("datetime_4"), Value::Object(Map::new())),
         ]
     }
 }
 
 impl Database for MockDatabase {
-    fn temp_session_id_solution(&self) {}
-
     fn get_session_user(&self, session_id: &str) -> Result<User> {
         Ok(User::new(
             session_id.to_string(),
@@ -133,7 +144,14 @@ impl Database for MockDatabase {
         Ok(Sensor::new(
             MockDatabase::SENSOR_ID,
             sensor.get_sensor_type().to_string(),
-        ))
+        )
+        .with_metadata(
+            sensor.get_manufacturer().to_string(),
+            sensor.get_model().to_string(),
+            sensor.get_serial_number().to_string(),
+            sensor.get_firmware().to_string(),
+        )
+        .with_channels(sensor.get_channels().clone(), sensor.get_sample_rate()))
     }
 
     fn get_sensors(&self) -> Result<Vec<Sensor>> {
@@ -158,6 +176,19 @@ impl Database for MockDatabase {
         Ok(())
     }
 
+    /* Sensor Calibration */
+    fn insert_sensor_calibration(&self, calibration: &SensorCalibration) -> Result<SensorCalibration> {
+        Ok(calibration.clone().with_ids(1, *calibration.get_sensor_id()))
+    }
+
+    fn get_sensor_calibrations(&self, _sensor_id: i64) -> Result<Vec<SensorCalibration>> {
+        Ok(vec![])
+    }
+
+    fn delete_sensor_calibration(&self, _sensor_id: i64, _calibration_id: i64) -> Result<()> {
+        Ok(())
+    }
+
     /* Session */
     fn insert_session(&self, session: &Session) -> Result<Session> {
         Ok(Session::new(
@@ -176,7 +207,7 @@ impl Database for MockDatabase {
     fn get_user_sessions(&self, username: &str) -> Result<Vec<Session>> {
         Ok(MockDatabase::sessions()
             .iter()
-            .map(|session| Session::new(session.get_id().clone(), username.to_string()))
+            .map(|session| Session::new(*session.get_id(), username.to_string()))
             .collect::<Vec<_>>())
     }
 
@@ -198,12 +229,31 @@ impl Database for MockDatabase {
         Ok(())
     }
 
+    fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session> {
+        let mut session = match MockDatabase::sessions()
+            .into_iter()
+            .find(|session| *session.get_id() == session_id)
+        {
+            Some(session) => session,
+            None => Session::new(session_id, String::from(MockDatabase::USERNAME)),
+        };
+        session.transition(status)?;
+        Ok(session)
+    }
+
+    fn get_active_session(&self, _sensor_id: i64) -> Result<Session> {
+        MockDatabase::sessions()
+            .into_iter()
+            .find(|session| *session.get_status() == SessionStatus::Recording)
+            .ok_or(Error::not_found("Active session"))
+    }
+
     /* Session Sensor */
     fn insert_session_sensor(&self, session_sensor: &SessionSensor) -> Result<SessionSensor> {
         Ok(SessionSensor::new(
             1,
-            session_sensor.get_session_id().clone(),
-            session_sensor.get_sensor_id().clone(),
+            *session_sensor.get_session_id(),
+            *session_sensor.get_sensor_id(),
         ))
     }
 
@@ -216,9 +266,9 @@ impl Database for MockDatabase {
             .iter()
             .map(|session_sensor| {
                 SessionSensor::new(
-                    session_sensor.get_id().clone(),
+                    *session_sensor.get_id(),
                     session_id,
-                    session_sensor.get_sensor_id().clone(),
+                    *session_sensor.get_sensor_id(),
                 )
             })
             .collect::<Vec<_>>())
@@ -258,6 +308,7 @@ impl Database for MockDatabase {
     ) -> Result<SessionSensorData> {
         Ok(SessionSensorData::new(
             Some(0),
+            *session_sensor_data.get_session_sensor_id(),
             session_sensor_data.get_datetime().to_string(),
             session_sensor_data.get_blob().clone(),
         ))
@@ -265,13 +316,14 @@ impl Database for MockDatabase {
 
     fn batch_session_sensor_data(
         &self,
-        data_blobs: &Vec<SessionSensorData>,
+        data_blobs: &[SessionSensorData],
     ) -> Result<Vec<SessionSensorData>> {
         Ok(data_blobs
             .iter()
             .map(|blob| {
                 SessionSensorData::new(
                     Some(0),
+                    *blob.get_session_sensor_id(),
                     blob.get_datetime().to_string(),
                     blob.get_blob().clone(),
                 )
@@ -279,6 +331,13 @@ impl Database for MockDatabase {
             .collect::<Vec<_>>())
     }
 
+    fn import_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>> {
+        self.batch_session_sensor_data(data_blobs)
+    }
+
     fn get_sessions_sensors_data(&self) -> Result<Vec<SessionSensorData>> {
         Ok(MockDatabase::sessions_sensors_data())
     }
@@ -291,11 +350,27 @@ impl Database for MockDatabase {
         Ok(MockDatabase::sessions_sensors_data())
     }
 
+    // Everything fits on the first page
+    fn get_sessions_sensor_data_page(
+        &self,
+        _session_id: i64,
+        _from: Option<&str>,
+        _to: Option<&str>,
+        after: Option<&SessionSensorData>,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        match after {
+            Some(_) => Ok(vec![]),
+            None => Ok(MockDatabase::sessions_sensors_data().into_iter().take(limit).collect()),
+        }
+    }
+
     fn get_session_sensor_data(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorData>> {
         Ok(MockDatabase::sessions_sensors_data()
             .iter()
             .map(|blob| {
                 SessionSensorData::new(
+                    *blob.get_id(),
                     Some(session_sensor_id),
                     blob.get_datetime().to_string(),
                     blob.get_blob().clone(),
@@ -306,11 +381,12 @@ impl Database for MockDatabase {
 
     fn get_session_sensor_datapoint(
         &self,
-        session_id: i64,
+        session_sensor_id: i64,
         datetime: &str,
     ) -> Result<SessionSensorData> {
         Ok(SessionSensorData::new(
-            Some(session_id),
+            Some(MockDatabase::SESSION_ID),
+            Some(session_sensor_id),
             datetime.to_string(),
             Value::Object(Map::new()),
         ))
@@ -318,14 +394,15 @@ impl Database for MockDatabase {
 
     fn update_session_sensor_datapoint(
         &self,
-        session_id: i64,
+        session_sensor_id: i64,
         datetime: &str,
         updated_session_sensor_datapoint: &SessionSensorData,
     ) -> Result<SessionSensorData> {
         let mut session_sensor_datapoint = SessionSensorData::empty();
         session_sensor_datapoint.fill_from(updated_session_sensor_datapoint);
         session_sensor_datapoint.fill_from(&SessionSensorData::new(
-            Some(session_id),
+            Some(MockDatabase::SESSION_ID),
+            Some(session_sensor_id),
             datetime.to_string(),
             Value::Object(Map::new()),
         ));
@@ -334,9 +411,123 @@ impl Database for MockDatabase {
 
     fn delete_session_sensor_datapoint(
         &self,
-        _session_id: i64,
+        _session_sensor_id: i64,
         _datetime: &str,
     ) -> Result<()> {
         Ok(())
     }
+
+    /* Retention */
+    fn count_session_sensor_data_before(&self, _session_sensor_id: i64, _datetime: &str) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn get_session_sensor_data_before(
+        &self,
+        _session_sensor_id: i64,
+        _datetime: &str,
+        _limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        Ok(vec![])
+    }
+
+    fn prune_session_sensor_data(
+        &self,
+        _session_sensor_id: i64,
+        datetimes: &[String],
+        _rollups: &[SessionSensorRollup],
+    ) -> Result<usize> {
+        Ok(datetimes.len())
+    }
+
+    fn get_session_sensor_rollups(&self, _session_sensor_id: i64) -> Result<Vec<SessionSensorRollup>> {
+        Ok(vec![])
+    }
+
+    /* Session Summary */
+    fn get_session_sensor_summaries(&self, _session_id: i64) -> Result<Vec<SensorSummary>> {
+        Ok(vec![])
+    }
+
+    fn get_session_sensor_gaps(&self, _session_id: i64, _gap_seconds: f64) -> Result<Vec<SummaryGap>> {
+        Ok(vec![])
+    }
+
+    fn get_cached_session_summary(&self, _session_id: i64, _gap_seconds: f64) -> Result<Option<SessionSummary>> {
+        Ok(None)
+    }
+
+    fn cache_session_summary(&self, _summary: &SessionSummary) -> Result<()> {
+        Ok(())
+    }
+
+    /* Sensor Schema */
+    fn upsert_sensor_schema(&self, schema: &SensorSchema) -> Result<SensorSchema> {
+        Ok(schema.clone())
+    }
+
+    // No schemas, so every blob is accepted
+    fn get_sensor_schemas(&self) -> Result<Vec<SensorSchema>> {
+        Ok(vec![])
+    }
+
+    fn get_sensor_schema(&self, sensor_type: &str) -> Result<SensorSchema> {
+        Err(Error::not_found(format!("Schema of sensor type {sensor_type}")))
+    }
+
+    fn delete_sensor_schema(&self, _sensor_type: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn quarantine_session_sensor_data(&self, _quarantined: &[QuarantinedSensorData]) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_quarantined_sensor_data(&self, _session_id: i64) -> Result<Vec<QuarantinedSensorData>> {
+        Ok(vec![])
+    }
+
+    fn delete_quarantined_sensor_data(&self, _session_id: i64) -> Result<usize> {
+        Ok(0)
+    }
+
+    /* Alerts */
+    fn insert_alert_rule(&self, rule: &AlertRule) -> Result<AlertRule> {
+        Ok(rule.clone())
+    }
+
+    // No rules, so datapoints never raise alerts
+    fn get_alert_rules(&self) -> Result<Vec<AlertRule>> {
+        Ok(vec![])
+    }
+
+    fn delete_alert_rule(&self, _rule_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn insert_alert_event(&self, event: &AlertEvent) -> Result<AlertEvent> {
+        Ok(event.clone())
+    }
+
+    fn get_alert_events(&self, _session_id: i64) -> Result<Vec<AlertEvent>> {
+        Ok(vec![])
+    }
+
+    fn update_alert_event_delivery(&self, _event_id: i64, _delivery: AlertDelivery, _attempts: i64) -> Result<()> {
+        Ok(())
+    }
+
+    /* Derived Channels */
+    fn insert_derived_channel(&self, channel: &DerivedChannel) -> Result<DerivedChannel> {
+        Ok(channel.clone())
+    }
+
+    // No derived channels, so datapoints are read as they are
+    fn get_derived_channels(&self) -> Result<Vec<DerivedChannel>> {
+        Ok(Vec::new())
+    }
+
+    fn delete_derived_channel(&self, _sensor_type: &str, _name: &str) -> Result<()> {
+        Ok(())
+    }
 }
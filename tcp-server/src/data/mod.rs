@@ -1,13 +1,33 @@
+pub mod connection_pool;
 pub mod database;
+pub mod memory_database;
+pub mod migrations;
 //#[cfg(not(feature = "sql"))]
 pub mod mock_database;
+#[cfg(feature = "postgres")]
+pub mod postgres_database;
+#[cfg(feature = "postgres")]
+pub mod postgres_migrations;
+pub mod sqlite_backup;
 pub mod sqlite_database;
+pub mod sqlite_migrations;
+pub mod sqlite_pool;
 
 pub use self::database::Database;
+#[cfg(feature = "memory")]
+pub use self::memory_database::MemoryDatabase;
 //#[cfg(not(feature = "sql"))]
 pub use self::mock_database::MockDatabase;
+#[cfg(feature = "postgres")]
+pub use self::postgres_database::{PostgresConfig, PostgresDatabase};
+#[cfg(feature = "sql")]
+pub use self::sqlite_backup::BackupConfig;
 #[cfg(feature = "sql")]
 pub use self::sqlite_database::SqliteDatabase;
+#[cfg(feature = "sql")]
+pub use self::sqlite_pool::{SqlitePoolConfig, SqliteSynchronous};
 
+#[cfg(test)]
+mod test_database;
 #[cfg(test)]
 mod test_sqlite_db;
@@ -0,0 +1,1341 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime};
+use postgres::types::ToSql;
+use postgres::{Client, GenericClient, NoTls, Row};
+
+use crate::error::Error;
+use crate::models::{
+    AlertComparator, AlertDelivery, AlertEvent, AlertRule, AlertState, BaseModel, CalibrationMethod, DerivedChannel,
+    FieldSummary, QuarantinedSensorData, SchemaPolicy, Sensor, SensorCalibration, SensorSchema, SensorSummary, Session,
+    SessionSensor, SessionSensorData, SessionSensorRollup, SessionStatus, SessionSummary, SummaryGap, User,
+};
+
+use super::connection_pool::{ConnectionPool, PooledConnection};
+use super::migrations::MigrationStatus;
+use super::postgres_migrations::PostgresMigrator;
+use super::Database;
+type Result<T> = crate::Result<T>;
+
+type Params<'a> = &'a [&'a (dyn ToSql + Sync)];
+
+const ALERT_RULE_COLUMNS: &str =
+    "alertRuleID, sensorID, sessionID, name, field, comparator, threshold, duration_seconds, hysteresis";
+const ALERT_EVENT_COLUMNS: &str =
+    "alertEventID, alertRuleID, sessionID, session_sensorID, state, value, datetime, delivery, attempts";
+const SENSOR_COLUMNS: &str = "sensorID, type, manufacturer, model, serial_number, firmware, channels, sample_rate";
+const SENSOR_CALIBRATION_COLUMNS: &str = "calibrationID, sensorID, channel, coefficients, valid_from, valid_to, method, lookup_table";
+const SESSION_COLUMNS: &str = "sessionID, username, name, description, tags, status, start_time, end_time";
+const SESSION_SENSOR_DATA_COLUMNS: &str = "sessionID, session_sensorID, datetime, data_blob";
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+// Advisory lock held while a session starts recording, so two sessions sharing a sensor cannot both start
+const RECORDING_LOCK: i64 = 0x7064_6171_7265_6364;
+
+//Settings of the PostgreSQL connections
+#[derive(Debug, Clone)]
+pub struct PostgresConfig {
+    // open connections, every call checks one out for its duration
+    pub connections: usize,
+    // how long a call waits for a free connection before giving up
+    pub checkout_timeout: Duration,
+    // turn Session_Sensor_Data into a TimescaleDB hypertable partitioned on datetime
+    pub timescale: bool,
+}
+
+impl Default for PostgresConfig {
+    fn default() -> Self {
+        PostgresConfig {
+            connections: 4,
+            checkout_timeout: Duration::from_millis(5_000),
+            timescale: false,
+        }
+    }
+}
+
+pub struct PostgresDatabase {
+    connection_config: postgres::Config,
+    pool: ConnectionPool<Client>,
+    timescale: bool,
+    // Temp Mock database to replace the unimplemented functions for testing
+    mock_db: super::MockDatabase,
+}
+
+impl PostgresDatabase {
+    pub fn new(url: &str, config: &PostgresConfig) -> Result<PostgresDatabase> {
+        let connection_config = url
+            .parse::<postgres::Config>()
+            .map_err(|e| Error::Validation(format!("Invalid database url: {e}")))?;
+        Self::from_config(connection_config, config)
+    }
+
+    pub fn from_config(connection_config: postgres::Config, config: &PostgresConfig) -> Result<PostgresDatabase> {
+        let mut clients = Vec::with_capacity(config.connections.max(1));
+        for _ in 0..config.connections.max(1) {
+            clients.push(connection_config.connect(NoTls)?);
+        }
+
+        Ok(PostgresDatabase {
+            connection_config,
+            pool: ConnectionPool::new(clients, config.checkout_timeout),
+            timescale: config.timescale,
+            mock_db: super::MockDatabase::new(),
+        })
+    }
+
+    // Function to initialize the database schema, applying any pending migrations
+    pub fn init(&self) {
+        match self.migrate() {
+            Ok(applied) if !applied.is_empty() => println!("Applied database migrations {applied:?}"),
+            Ok(_) => {}
+            Err(e) => panic!("Failed to migrate database schema: {e}"),
+        }
+    }
+
+    pub fn migrate(&self) -> Result<Vec<i64>> {
+        let mut client = self.client()?;
+        let applied = PostgresMigrator::new(&mut client).migrate()?;
+        if self.timescale {
+            Self::create_hypertable(&mut client)?;
+        }
+        Ok(applied)
+    }
+
+    pub fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        PostgresMigrator::new(&mut *self.client()?).status()
+    }
+
+    // Applies pending migrations inside a transaction that is rolled back
+    pub fn dry_run_migrations(&self) -> Result<Vec<i64>> {
+        PostgresMigrator::new(&mut *self.client()?).dry_run()
+    }
+
+    // Partitions datapoints by time, left out of the migrations since it needs the TimescaleDB extension
+    fn create_hypertable(client: &mut Client) -> Result<()> {
+        client
+            .batch_execute(
+                "CREATE EXTENSION IF NOT EXISTS timescaledb; \
+                SELECT create_hypertable('session_sensor_data', 'datetime', if_not_exists => TRUE, migrate_data => TRUE);",
+            )
+            .map_err(|e| Error::Storage(format!("Failed to create TimescaleDB hypertable: {}", Error::from(e))))
+    }
+
+    // Checks out a connection, reconnecting it if the server closed it
+    fn client(&self) -> Result<PooledConnection<'_, Client>> {
+        let mut client = self.pool.get()?;
+        if client.is_closed() {
+            *client = self.connection_config.connect(NoTls)?;
+        }
+        Ok(client)
+    }
+
+    // Datetimes are stored as timestamps, accepting the same formats the rest of the server reads
+    fn parse_datetime(datetime: &str) -> Result<NaiveDateTime> {
+        match NaiveDateTime::parse_from_str(datetime, DATETIME_FORMAT) {
+            Ok(datetime) => Ok(datetime),
+            Err(e) => DateTime::parse_from_rfc3339(datetime)
+                .map(|datetime| datetime.naive_utc())
+                .map_err(|_| Error::from(e)),
+        }
+    }
+
+    fn format_datetime(datetime: &NaiveDateTime) -> String {
+        datetime.format(DATETIME_FORMAT).to_string()
+    }
+
+    fn sensor_schema_from_row(row: &Row) -> Result<SensorSchema> {
+        let policy: String = row.try_get(2)?;
+        Ok(SensorSchema::new(row.try_get(0)?, row.try_get(1)?, SchemaPolicy::from_name(&policy)?))
+    }
+
+    // Builds a Sensor from a row selected with SENSOR_COLUMNS
+    fn sensor_from_row(row: &Row) -> Result<Sensor> {
+        Ok(Sensor::new(row.try_get(0)?, row.try_get(1)?)
+            .with_metadata(row.try_get(2)?, row.try_get(3)?, row.try_get(4)?, row.try_get(5)?)
+            .with_channels(serde_json::from_value(row.try_get(6)?).unwrap_or_default(), row.try_get(7)?))
+    }
+
+    fn query_sensor(client: &mut impl GenericClient, condition: &str, sensor_id: i64) -> Result<Sensor> {
+        let row = client
+            .query_opt(&format!("SELECT {SENSOR_COLUMNS} FROM Sensor WHERE sensorID = $1 {condition}"), &[&sensor_id])?
+            .ok_or_else(|| Error::not_found(format!("Sensor {sensor_id}")))?;
+
+        Self::sensor_from_row(&row)
+    }
+
+    // Builds a SensorCalibration from a row selected with SENSOR_CALIBRATION_COLUMNS
+    fn sensor_calibration_from_row(row: &Row) -> Result<SensorCalibration> {
+        let valid_to: Option<NaiveDateTime> = row.try_get(5)?;
+        Ok(SensorCalibration::new(
+            row.try_get(0)?,
+            row.try_get(1)?,
+            row.try_get(2)?,
+            serde_json::from_value(row.try_get(3)?)?,
+            Self::format_datetime(&row.try_get(4)?),
+            valid_to.as_ref().map(Self::format_datetime),
+        )
+        .with_table(
+            CalibrationMethod::from_name(row.try_get(6)?)?,
+            serde_json::from_value(row.try_get(7)?)?,
+        ))
+    }
+
+    fn query_sensor_calibrations(client: &mut impl GenericClient, sensor_id: i64) -> Result<Vec<SensorCalibration>> {
+        client
+            .query(
+                &format!(
+                    "SELECT {SENSOR_CALIBRATION_COLUMNS} FROM Sensor_Calibration WHERE sensorID = $1 \
+                    ORDER BY channel, valid_from"
+                ),
+                &[&sensor_id],
+            )?
+            .iter()
+            .map(Self::sensor_calibration_from_row)
+            .collect()
+    }
+
+    // Builds an AlertRule from a row selected with ALERT_RULE_COLUMNS
+    fn alert_rule_from_row(row: &Row) -> Result<AlertRule> {
+        Ok(AlertRule::new(
+            row.try_get(0)?,
+            row.try_get(1)?,
+            row.try_get(2)?,
+            row.try_get(3)?,
+            row.try_get(4)?,
+            AlertComparator::from_name(row.try_get(5)?)?,
+            row.try_get(6)?,
+        )
+        .with_timing(row.try_get(7)?, row.try_get(8)?))
+    }
+
+    // Builds an AlertEvent from a row selected with ALERT_EVENT_COLUMNS
+    fn alert_event_from_row(row: &Row) -> Result<AlertEvent> {
+        Ok(AlertEvent::new(
+            row.try_get(0)?,
+            row.try_get(1)?,
+            row.try_get(2)?,
+            row.try_get(3)?,
+            AlertState::from_name(row.try_get(4)?)?,
+            row.try_get(5)?,
+            Self::format_datetime(&row.try_get(6)?),
+        )
+        .with_delivery(AlertDelivery::from_name(row.try_get(7)?)?, row.try_get(8)?))
+    }
+
+    // Builds a Session from a row selected with SESSION_COLUMNS
+    fn session_from_row(row: &Row) -> Result<Session> {
+        let tags: String = row.try_get(4)?;
+        let status: String = row.try_get(5)?;
+        Ok(Session::new(row.try_get(0)?, row.try_get(1)?)
+            .with_metadata(
+                row.try_get(2)?,
+                row.try_get(3)?,
+                serde_json::from_str(&tags).unwrap_or_default(),
+            )
+            .with_lifecycle(
+                SessionStatus::from_str(&status).unwrap_or_default(),
+                row.try_get(6)?,
+                row.try_get(7)?,
+            ))
+    }
+
+    fn query_sessions(client: &mut impl GenericClient, condition: &str, params: Params) -> Result<Vec<Session>> {
+        client
+            .query(&format!("SELECT {SESSION_COLUMNS} FROM Session {condition}"), params)?
+            .iter()
+            .map(Self::session_from_row)
+            .collect()
+    }
+
+    fn query_session(client: &mut impl GenericClient, session_id: i64) -> Result<Session> {
+        Self::query_sessions(client, "WHERE sessionID = $1", &[&session_id])?
+            .pop()
+            .ok_or_else(|| Error::not_found(format!("Session {session_id}")))
+    }
+
+    // Builds a SessionSensorData from a row selected with SESSION_SENSOR_DATA_COLUMNS
+    fn session_sensor_data_from_row(row: &Row) -> Result<SessionSensorData> {
+        Ok(SessionSensorData::new(
+            row.try_get(0)?,
+            row.try_get(1)?,
+            Self::format_datetime(&row.try_get(2)?),
+            row.try_get(3)?,
+        ))
+    }
+
+    fn query_session_sensor_data(
+        client: &mut impl GenericClient,
+        condition: &str,
+        params: Params,
+    ) -> Result<Vec<SessionSensorData>> {
+        client
+            .query(
+                &format!("SELECT {SESSION_SENSOR_DATA_COLUMNS} FROM Session_Sensor_Data {condition}"),
+                params,
+            )?
+            .iter()
+            .map(Self::session_sensor_data_from_row)
+            .collect()
+    }
+
+    fn query_datapoint(
+        client: &mut impl GenericClient,
+        session_sensor_id: i64,
+        datetime: &str,
+    ) -> Result<SessionSensorData> {
+        Self::query_session_sensor_data(
+            client,
+            "WHERE session_sensorID = $1 AND datetime = $2",
+            &[&session_sensor_id, &Self::parse_datetime(datetime)?],
+        )?
+        .pop()
+        .ok_or_else(|| Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")))
+    }
+
+    fn session_sensor_from_row(row: &Row) -> Result<SessionSensor> {
+        Ok(SessionSensor::new(row.try_get(0)?, row.try_get(1)?, row.try_get(2)?))
+    }
+}
+
+impl Database for PostgresDatabase {
+    /* Authentication */
+    // TODO: Implement
+    // No column for admin
+    fn is_admin(&self, user: &User) -> bool {
+        self.mock_db.is_admin(user)
+    }
+
+    // TODO: Implement
+    fn login(&self, user: &User) -> Result<String> {
+        self.mock_db.login(user)
+    }
+
+    // TODO: Implement
+    fn logout(&self, session_id: &str) -> Result<()> {
+        self.mock_db.logout(session_id)
+    }
+
+    // TODO: Implement
+    fn renew_session(&self, old_session: &str) -> Result<String> {
+        self.mock_db.renew_session(old_session)
+    }
+
+    //TODO: Implement
+    fn get_session_user(&self, session_id: &str) -> Result<User> {
+        self.mock_db.get_session_user(session_id)
+    }
+
+    /* User */
+    // Inserts a single User into User
+    fn insert_user(&self, user: &User) -> Result<User> {
+        self.client()?.execute(
+            r#"INSERT INTO "User" (username, password_hash) VALUES ($1, $2)"#,
+            &[&user.get_username(), &user.get_password_hash()],
+        )?;
+
+        // Returning the user for now
+        Ok(user.clone())
+    }
+
+    // Returns all rows from User
+    fn get_users(&self) -> Result<Vec<User>> {
+        self.client()?
+            .query(r#"SELECT username, password_hash FROM "User""#, &[])?
+            .iter()
+            .map(|row| Ok(User::new(row.try_get(0)?, row.try_get(1)?)))
+            .collect()
+    }
+
+    // Returns a row from User where username matches
+    fn get_user(&self, username: &str) -> Result<User> {
+        let row = self
+            .client()?
+            .query_opt(r#"SELECT username, password_hash FROM "User" WHERE username = $1"#, &[&username])?
+            .ok_or_else(|| Error::not_found(format!("User {username}")))?;
+
+        Ok(User::new(row.try_get(0)?, row.try_get(1)?))
+    }
+
+    // Updates a user's information (note: username is the primary key and cannot be changed)
+    fn update_user(&self, username: &str, updated_user: &User) -> Result<User> {
+        let rows_updated = self.client()?.execute(
+            r#"UPDATE "User" SET password_hash = $1 WHERE username = $2"#,
+            &[&updated_user.get_password_hash(), &username],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("User {username}")));
+        }
+
+        Ok(updated_user.clone())
+    }
+
+    fn delete_user(&self, username: &str) -> Result<()> {
+        let rows_updated = self
+            .client()?
+            .execute(r#"DELETE FROM "User" WHERE username = $1"#, &[&username])?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("User {username}")));
+        }
+
+        Ok(())
+    }
+
+    /* Sensor */
+    // Inserts a single Sensor into Sensor
+    fn insert_sensor(&self, sensor: &Sensor) -> Result<Sensor> {
+        let row = self.client()?.query_one(
+            &format!(
+                "INSERT INTO Sensor (type, manufacturer, model, serial_number, firmware, channels, sample_rate) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {SENSOR_COLUMNS}"
+            ),
+            &[
+                &sensor.get_sensor_type(),
+                &sensor.get_manufacturer(),
+                &sensor.get_model(),
+                &sensor.get_serial_number(),
+                &sensor.get_firmware(),
+                &serde_json::to_value(sensor.get_channels())?,
+                &sensor.get_sample_rate(),
+            ],
+        )?;
+
+        Self::sensor_from_row(&row)
+    }
+
+    // Returns all rows from Sensor
+    fn get_sensors(&self) -> Result<Vec<Sensor>> {
+        self.client()?
+            .query(&format!("SELECT {SENSOR_COLUMNS} FROM Sensor"), &[])?
+            .iter()
+            .map(Self::sensor_from_row)
+            .collect()
+    }
+
+    // Returns a single row from Sensor where sensorID matches
+    fn get_sensor(&self, sensor_id: i64) -> Result<Sensor> {
+        Self::query_sensor(&mut *self.client()?, "", sensor_id)
+    }
+
+    // Updates the catalog values of a Sensor, values missing from updated_sensor are kept
+    fn update_sensor(&self, sensor_id: i64, updated_sensor: &Sensor) -> Result<Sensor> {
+        let mut sensor = updated_sensor.clone();
+        // the row stays locked until commit so a concurrent update cannot land in between
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        sensor.fill_from(&Self::query_sensor(&mut transaction, "FOR UPDATE", sensor_id)?);
+
+        let row = transaction.query_one(
+            &format!(
+                "UPDATE Sensor SET type = $1, manufacturer = $2, model = $3, serial_number = $4, firmware = $5, \
+                channels = $6, sample_rate = $7 WHERE sensorID = $8 RETURNING {SENSOR_COLUMNS}"
+            ),
+            &[
+                &sensor.get_sensor_type(),
+                &sensor.get_manufacturer(),
+                &sensor.get_model(),
+                &sensor.get_serial_number(),
+                &sensor.get_firmware(),
+                &serde_json::to_value(sensor.get_channels())?,
+                &sensor.get_sample_rate(),
+                &sensor_id,
+            ],
+        )?;
+        transaction.commit()?;
+
+        Self::sensor_from_row(&row)
+    }
+
+    fn delete_sensor(&self, sensor_id: i64) -> Result<()> {
+        let rows_updated = self
+            .client()?
+            .execute("DELETE FROM Sensor WHERE sensorID = $1", &[&sensor_id])?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Sensor {sensor_id}")));
+        }
+
+        Ok(())
+    }
+
+    /* Sensor Calibration */
+    fn insert_sensor_calibration(&self, calibration: &SensorCalibration) -> Result<SensorCalibration> {
+        let sensor_id = *calibration.get_sensor_id();
+        // the sensor stays locked until commit so calibrations of it are added one at a time
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        Self::query_sensor(&mut transaction, "FOR UPDATE", sensor_id)?;
+        for ended in calibration.superseded(&Self::query_sensor_calibrations(&mut transaction, sensor_id)?)? {
+            transaction.execute(
+                "UPDATE Sensor_Calibration SET valid_to = $1 WHERE calibrationID = $2",
+                &[&ended.get_valid_to().map(Self::parse_datetime).transpose()?, ended.get_id()],
+            )?;
+        }
+        let row = transaction.query_one(
+            &format!(
+                "INSERT INTO Sensor_Calibration (sensorID, channel, coefficients, valid_from, valid_to, method, lookup_table) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {SENSOR_CALIBRATION_COLUMNS}"
+            ),
+            &[
+                &sensor_id,
+                &calibration.get_channel(),
+                &serde_json::to_value(calibration.get_coefficients())?,
+                &Self::parse_datetime(calibration.get_valid_from())?,
+                &calibration.get_valid_to().map(Self::parse_datetime).transpose()?,
+                &calibration.get_method().as_str(),
+                &serde_json::to_value(calibration.get_table())?,
+            ],
+        )?;
+        transaction.commit()?;
+
+        Self::sensor_calibration_from_row(&row)
+    }
+
+    fn get_sensor_calibrations(&self, sensor_id: i64) -> Result<Vec<SensorCalibration>> {
+        Self::query_sensor_calibrations(&mut *self.client()?, sensor_id)
+    }
+
+    fn delete_sensor_calibration(&self, sensor_id: i64, calibration_id: i64) -> Result<()> {
+        let rows_updated = self.client()?.execute(
+            "DELETE FROM Sensor_Calibration WHERE sensorID = $1 AND calibrationID = $2",
+            &[&sensor_id, &calibration_id],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Calibration {calibration_id} of sensor {sensor_id}")));
+        }
+
+        Ok(())
+    }
+
+    /* Session */
+    // Inserts a single Session into Session
+    fn insert_session(&self, session: &Session) -> Result<Session> {
+        let row = self.client()?.query_one(
+            &format!(
+                "INSERT INTO Session (username, name, description, tags) VALUES ($1, $2, $3, $4) \
+                RETURNING {SESSION_COLUMNS}"
+            ),
+            &[
+                &session.get_username(),
+                &session.get_name(),
+                &session.get_description(),
+                &serde_json::to_string(session.get_tags())?,
+            ],
+        )?;
+
+        Self::session_from_row(&row)
+    }
+
+    // Returns a single row from Session where sessionID matches
+    fn get_session(&self, session_id: i64) -> Result<Session> {
+        Self::query_session(&mut *self.client()?, session_id)
+    }
+
+    // Returns rows from Session where username matches
+    fn get_user_sessions(&self, username: &str) -> Result<Vec<Session>> {
+        Self::query_sessions(&mut *self.client()?, "WHERE username = $1", &[&username])
+    }
+
+    // Returns all rows from Session
+    fn get_all_sessions(&self) -> Result<Vec<Session>> {
+        Self::query_sessions(&mut *self.client()?, "", &[])
+    }
+
+    // Updates the metadata of a Session, values missing from updated_session are kept
+    fn update_session(&self, session_id: i64, updated_session: &Session) -> Result<Session> {
+        let mut session = Session::new(session_id, updated_session.get_username().to_string())
+            .with_metadata(
+                updated_session.get_name().to_string(),
+                updated_session.get_description().to_string(),
+                updated_session.get_tags().clone(),
+            );
+        // the row stays locked until commit so a concurrent update cannot land in between
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        session.fill_from(
+            &Self::query_sessions(&mut transaction, "WHERE sessionID = $1 FOR UPDATE", &[&session_id])?
+                .pop()
+                .ok_or_else(|| Error::not_found(format!("Session {session_id}")))?,
+        );
+
+        transaction.execute(
+            "UPDATE Session SET username = $1, name = $2, description = $3, tags = $4 WHERE sessionID = $5",
+            &[
+                &session.get_username(),
+                &session.get_name(),
+                &session.get_description(),
+                &serde_json::to_string(session.get_tags())?,
+                &session_id,
+            ],
+        )?;
+        transaction.commit()?;
+
+        Ok(session)
+    }
+
+    fn delete_session(&self, session_id: i64) -> Result<()> {
+        let rows_updated = self
+            .client()?
+            .execute("DELETE FROM Session WHERE sessionID = $1", &[&session_id])?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Session {session_id}")));
+        }
+
+        Ok(())
+    }
+
+    // Moves a Session through its lifecycle, a sensor can only record to one session at a time
+    fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session> {
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        if status == SessionStatus::Recording {
+            transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&RECORDING_LOCK])?;
+        }
+        let mut session = Self::query_sessions(&mut transaction, "WHERE sessionID = $1 FOR UPDATE", &[&session_id])?
+            .pop()
+            .ok_or_else(|| Error::not_found(format!("Session {session_id}")))?;
+        session.transition(status)?;
+
+        if status == SessionStatus::Recording {
+            let conflicts: i64 = transaction
+                .query_one(
+                    "SELECT COUNT(*) FROM Session_Sensor WHERE sessionID != $1 \
+                    AND sensorID IN (SELECT sensorID FROM Session_Sensor WHERE sessionID = $1) \
+                    AND sessionID IN (SELECT sessionID FROM Session WHERE status = 'recording')",
+                    &[&session_id],
+                )?
+                .try_get(0)?;
+            if conflicts > 0 {
+                return Err(Error::Conflict(format!(
+                    "Session {session_id} shares sensors with a session that is already recording"
+                )));
+            }
+        }
+
+        transaction.execute(
+            "UPDATE Session SET status = $1, start_time = $2, end_time = $3 WHERE sessionID = $4",
+            &[
+                &session.get_status().as_str(),
+                &session.get_start_time(),
+                &session.get_end_time(),
+                &session_id,
+            ],
+        )?;
+        transaction.commit()?;
+
+        Ok(session)
+    }
+
+    // Returns the recording Session the sensor is attached to
+    fn get_active_session(&self, sensor_id: i64) -> Result<Session> {
+        Self::query_sessions(
+            &mut *self.client()?,
+            "WHERE status = 'recording' AND sessionID IN (SELECT sessionID FROM Session_Sensor WHERE sensorID = $1) ORDER BY start_time DESC LIMIT 1",
+            &[&sensor_id],
+        )?
+        .pop()
+        .ok_or_else(|| Error::not_found(format!("Active session for sensor {sensor_id}")))
+    }
+
+    /* Session Sensor */
+    // Inserts a single SessionSensor into Session_Sensor
+    fn insert_session_sensor(&self, session_sensor: &SessionSensor) -> Result<SessionSensor> {
+        let row = self.client()?.query_one(
+            "INSERT INTO Session_Sensor (sessionID, sensorID) VALUES ($1, $2) RETURNING session_sensorID",
+            &[session_sensor.get_session_id(), session_sensor.get_sensor_id()],
+        )?;
+
+        Ok(SessionSensor::new(
+            row.try_get(0)?,
+            *session_sensor.get_session_id(),
+            *session_sensor.get_sensor_id(),
+        ))
+    }
+
+    // Returns all rows from Session_Sensor
+    fn get_sessions_sensors(&self) -> Result<Vec<SessionSensor>> {
+        self.client()?
+            .query("SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor", &[])?
+            .iter()
+            .map(Self::session_sensor_from_row)
+            .collect()
+    }
+
+    // Returns rows from Session_Sensor where sessionID matches
+    fn get_session_sensors(&self, session_id: i64) -> Result<Vec<SessionSensor>> {
+        self.client()?
+            .query(
+                "SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor WHERE sessionID = $1",
+                &[&session_id],
+            )?
+            .iter()
+            .map(Self::session_sensor_from_row)
+            .collect()
+    }
+
+    // Returns a single row from Session_Sensor where session_sensorID matches
+    fn get_session_sensor(&self, session_sensor_id: i64) -> Result<SessionSensor> {
+        let row = self
+            .client()?
+            .query_opt(
+                "SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor WHERE session_sensorID = $1",
+                &[&session_sensor_id],
+            )?
+            .ok_or_else(|| Error::not_found(format!("Session sensor {session_sensor_id}")))?;
+
+        Self::session_sensor_from_row(&row)
+    }
+
+    fn update_session_sensor(
+        &self,
+        session_sensor_id: i64,
+        updated_session_sensor: &SessionSensor,
+    ) -> Result<SessionSensor> {
+        let rows_updated = self.client()?.execute(
+            "UPDATE Session_Sensor SET sessionID = $1, sensorID = $2 WHERE session_sensorID = $3",
+            &[
+                updated_session_sensor.get_session_id(),
+                updated_session_sensor.get_sensor_id(),
+                &session_sensor_id,
+            ],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Session sensor {session_sensor_id}")));
+        }
+
+        Ok(updated_session_sensor.clone())
+    }
+
+    fn delete_session_sensor(&self, session_sensor_id: i64) -> Result<()> {
+        let rows_updated = self.client()?.execute(
+            "DELETE FROM Session_Sensor WHERE session_sensorID = $1",
+            &[&session_sensor_id],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Session sensor {session_sensor_id}")));
+        }
+
+        Ok(())
+    }
+
+    /* Session Sensor Data */
+    // Inserts a single SessionSensorData into Session_Sensor_Data
+    fn insert_session_sensor_data(
+        &self,
+        session_sensor_data: &SessionSensorData,
+    ) -> Result<SessionSensorData> {
+        self.client()?.execute(
+            "INSERT INTO Session_Sensor_Data (sessionID, session_sensorID, datetime, data_blob) VALUES ($1, $2, $3, $4)",
+            &[
+                session_sensor_data.get_id(),
+                session_sensor_data.get_session_sensor_id(),
+                &Self::parse_datetime(session_sensor_data.get_datetime())?,
+                session_sensor_data.get_blob(),
+            ],
+        )?;
+
+        Ok(session_sensor_data.clone())
+    }
+
+    // Batch inserts data points
+    fn batch_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>> {
+        if data_blobs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let datetimes = data_blobs
+            .iter()
+            .map(|data| Self::parse_datetime(data.get_datetime()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // one transaction so a batch commits once, each chunk stays well under the limit of 65535 parameters
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        for (chunk, datetimes) in data_blobs.chunks(500).zip(datetimes.chunks(500)) {
+            let sql = format!(
+                "INSERT INTO Session_Sensor_Data (sessionID, session_sensorID, datetime, data_blob) VALUES {}",
+                (0..chunk.len())
+                    .map(|i| format!("(${}, ${}, ${}, ${})", 4 * i + 1, 4 * i + 2, 4 * i + 3, 4 * i + 4))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            let params = chunk
+                .iter()
+                .zip(datetimes)
+                .flat_map(|(data, datetime)| {
+                    [
+                        data.get_id() as &(dyn ToSql + Sync),
+                        data.get_session_sensor_id(),
+                        datetime,
+                        data.get_blob(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+
+            transaction.execute(&sql, &params)?;
+        }
+        transaction.commit()?;
+
+        Ok(data_blobs.to_vec())
+    }
+
+    // Rows are inserted one at a time so the ones skipped on conflict are known, the statement is prepared once
+    fn import_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>> {
+        if data_blobs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let datetimes = data_blobs
+            .iter()
+            .map(|data| Self::parse_datetime(data.get_datetime()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        let statement = transaction.prepare(
+            "INSERT INTO Session_Sensor_Data (sessionID, session_sensorID, datetime, data_blob) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+        )?;
+        let mut inserted = Vec::new();
+        for (data, datetime) in data_blobs.iter().zip(&datetimes) {
+            let changed = transaction.execute(
+                &statement,
+                &[data.get_id(), data.get_session_sensor_id(), datetime, data.get_blob()],
+            )?;
+            if changed > 0 {
+                inserted.push(data.clone());
+            }
+        }
+        transaction.commit()?;
+
+        Ok(inserted)
+    }
+
+    // Returns all rows from Session_Sensor_Data
+    fn get_sessions_sensors_data(&self) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(&mut *self.client()?, "", &[])
+    }
+
+    // Returns all rows from Session_Sensor_Data where sessionID matches
+    fn get_sessions_sensor_data(&self, session_id: i64) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(&mut *self.client()?, "WHERE sessionID = $1", &[&session_id])
+    }
+
+    // Returns all rows from Session_Sensor_Data where sessionID matches and datetime is after the passed value
+    fn get_sessions_sensor_data_after(
+        &self,
+        session_id: i64,
+        datetime: &str,
+    ) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &mut *self.client()?,
+            "WHERE sessionID = $1 AND datetime > $2",
+            &[&session_id, &Self::parse_datetime(datetime)?],
+        )
+    }
+
+    fn get_sessions_sensor_data_page(
+        &self,
+        session_id: i64,
+        from: Option<&str>,
+        to: Option<&str>,
+        after: Option<&SessionSensorData>,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &mut *self.client()?,
+            "WHERE sessionID = $1 AND ($2::timestamp IS NULL OR datetime >= $2) AND ($3::timestamp IS NULL OR datetime < $3)
+                AND ($4::timestamp IS NULL OR datetime > $4 OR (datetime = $4 AND COALESCE(session_sensorID, 0) > $5))
+                ORDER BY datetime, COALESCE(session_sensorID, 0) LIMIT $6",
+            &[
+                &session_id,
+                &from.map(Self::parse_datetime).transpose()?,
+                &to.map(Self::parse_datetime).transpose()?,
+                &after.map(|data| Self::parse_datetime(data.get_datetime())).transpose()?,
+                &after.and_then(|data| *data.get_session_sensor_id()).unwrap_or(0),
+                &(limit as i64),
+            ],
+        )
+    }
+
+    // Returns all rows from Session_Sensor_Data recorded by a single sensor of a session
+    fn get_session_sensor_data(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &mut *self.client()?,
+            "WHERE session_sensorID = $1",
+            &[&session_sensor_id],
+        )
+    }
+
+    // Returns a single datapoint that matches a session_sensor_id and datetime
+    fn get_session_sensor_datapoint(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+    ) -> Result<SessionSensorData> {
+        Self::query_datapoint(&mut *self.client()?, session_sensor_id, datetime)
+    }
+
+    fn update_session_sensor_datapoint(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+        updated_session_sensor_datapoint: &SessionSensorData,
+    ) -> Result<SessionSensorData> {
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        let mut session_sensor_datapoint = updated_session_sensor_datapoint.clone();
+        session_sensor_datapoint.fill_from(&Self::query_datapoint(&mut transaction, session_sensor_id, datetime)?);
+
+        let rows_updated = transaction.execute(
+            "UPDATE Session_Sensor_Data SET datetime = $1, data_blob = $2 WHERE session_sensorID = $3 AND datetime = $4",
+            &[
+                &Self::parse_datetime(session_sensor_datapoint.get_datetime())?,
+                session_sensor_datapoint.get_blob(),
+                &session_sensor_id,
+                &Self::parse_datetime(datetime)?,
+            ],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")));
+        }
+
+        let updated = Self::query_datapoint(&mut transaction, session_sensor_id, session_sensor_datapoint.get_datetime())?;
+        transaction.commit()?;
+        Ok(updated)
+    }
+
+    fn delete_session_sensor_datapoint(&self, session_sensor_id: i64, datetime: &str) -> Result<()> {
+        let rows_updated = self.client()?.execute(
+            "DELETE FROM Session_Sensor_Data WHERE session_sensorID = $1 AND datetime = $2",
+            &[&session_sensor_id, &Self::parse_datetime(datetime)?],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")));
+        }
+
+        Ok(())
+    }
+
+    /* Retention */
+    fn count_session_sensor_data_before(&self, session_sensor_id: i64, datetime: &str) -> Result<usize> {
+        let count: i64 = self
+            .client()?
+            .query_one(
+                "SELECT COUNT(*) FROM Session_Sensor_Data WHERE session_sensorID = $1 AND datetime < $2",
+                &[&session_sensor_id, &Self::parse_datetime(datetime)?],
+            )?
+            .try_get(0)?;
+
+        Ok(count as usize)
+    }
+
+    fn get_session_sensor_data_before(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &mut *self.client()?,
+            "WHERE session_sensorID = $1 AND datetime < $2 ORDER BY datetime LIMIT $3",
+            &[&session_sensor_id, &Self::parse_datetime(datetime)?, &(limit as i64)],
+        )
+    }
+
+    fn prune_session_sensor_data(
+        &self,
+        session_sensor_id: i64,
+        datetimes: &[String],
+        rollups: &[SessionSensorRollup],
+    ) -> Result<usize> {
+        let datetimes = datetimes
+            .iter()
+            .map(|datetime| Self::parse_datetime(datetime))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        for rollup in rollups {
+            transaction.execute(
+                "INSERT INTO Session_Sensor_Rollup (session_sensorID, interval_seconds, bucket, count, data_blob) VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (session_sensorID, interval_seconds, bucket)
+                    DO UPDATE SET count = EXCLUDED.count, data_blob = EXCLUDED.data_blob",
+                &[
+                    rollup.get_session_sensor_id(),
+                    rollup.get_interval_seconds(),
+                    &Self::parse_datetime(rollup.get_bucket())?,
+                    rollup.get_count(),
+                    rollup.get_blob(),
+                ],
+            )?;
+        }
+        let rows_deleted = transaction.execute(
+            "DELETE FROM Session_Sensor_Data WHERE session_sensorID = $1 AND datetime = ANY($2)",
+            &[&session_sensor_id, &datetimes],
+        )?;
+        transaction.commit()?;
+
+        Ok(rows_deleted as usize)
+    }
+
+    fn get_session_sensor_rollups(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorRollup>> {
+        self.client()?
+            .query(
+                "SELECT session_sensorID, bucket, interval_seconds, count, data_blob FROM Session_Sensor_Rollup
+                    WHERE session_sensorID = $1 ORDER BY interval_seconds, bucket",
+                &[&session_sensor_id],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(SessionSensorRollup::new(
+                    row.try_get(0)?,
+                    Self::format_datetime(&row.try_get(1)?),
+                    row.try_get(2)?,
+                    row.try_get(3)?,
+                    row.try_get(4)?,
+                ))
+            })
+            .collect()
+    }
+
+    /* Session Summary */
+    // Aggregated by PostgreSQL, so the blobs of a session are never read into the server
+    fn get_session_sensor_summaries(&self, session_id: i64) -> Result<Vec<SensorSummary>> {
+        let mut client = self.client()?;
+        let mut summaries = client
+            .query(
+                "SELECT session_sensorID, COUNT(*), MIN(datetime), MAX(datetime) FROM Session_Sensor_Data
+                    WHERE sessionID = $1 GROUP BY session_sensorID ORDER BY session_sensorID NULLS FIRST",
+                &[&session_id],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(SensorSummary {
+                    session_sensor_id: row.try_get(0)?,
+                    count: row.try_get(1)?,
+                    first: Self::format_datetime(&row.try_get(2)?),
+                    last: Self::format_datetime(&row.try_get(3)?),
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // jsonb_each fails on blobs that are not objects, so those are read as empty ones
+        let fields = client.query(
+            "SELECT session_sensorID, name, COUNT(*), MIN(value), MAX(value), AVG(value), COALESCE(STDDEV_SAMP(value), 0)
+                FROM (
+                    SELECT data.session_sensorID, member.key AS name, (member.value #>> '{}')::DOUBLE PRECISION AS value
+                        FROM Session_Sensor_Data AS data,
+                            jsonb_each(CASE jsonb_typeof(data.data_blob) WHEN 'object' THEN data.data_blob ELSE '{}' END) AS member
+                        WHERE data.sessionID = $1 AND jsonb_typeof(member.value) = 'number'
+                ) AS field
+                GROUP BY session_sensorID, name",
+            &[&session_id],
+        )?;
+        for row in fields {
+            let session_sensor_id: Option<i64> = row.try_get(0)?;
+            if let Some(summary) = summaries
+                .iter_mut()
+                .find(|summary| summary.session_sensor_id == session_sensor_id)
+            {
+                summary.fields.insert(
+                    row.try_get(1)?,
+                    FieldSummary {
+                        count: row.try_get(2)?,
+                        min: row.try_get(3)?,
+                        max: row.try_get(4)?,
+                        mean: row.try_get(5)?,
+                        stddev: row.try_get(6)?,
+                    },
+                );
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    fn get_session_sensor_gaps(&self, session_id: i64, gap_seconds: f64) -> Result<Vec<SummaryGap>> {
+        self.client()?
+            .query(
+                "SELECT session_sensorID, previous, datetime FROM (
+                    SELECT session_sensorID, datetime, LAG(datetime) OVER sensor AS previous
+                        FROM Session_Sensor_Data WHERE sessionID = $1
+                        WINDOW sensor AS (PARTITION BY session_sensorID ORDER BY datetime)
+                ) AS consecutive
+                WHERE EXTRACT(EPOCH FROM datetime - previous)::DOUBLE PRECISION > $2
+                ORDER BY session_sensorID NULLS FIRST, datetime",
+                &[&session_id, &gap_seconds],
+            )?
+            .iter()
+            .map(|row| {
+                let from = Self::format_datetime(&row.try_get(1)?);
+                let to = Self::format_datetime(&row.try_get(2)?);
+                Ok(SummaryGap {
+                    session_sensor_id: row.try_get(0)?,
+                    seconds: SessionSummary::seconds_between(&from, &to).unwrap_or_default(),
+                    from,
+                    to,
+                })
+            })
+            .collect()
+    }
+
+    fn get_cached_session_summary(&self, session_id: i64, gap_seconds: f64) -> Result<Option<SessionSummary>> {
+        self.client()?
+            .query_opt(
+                "SELECT summary FROM Session_Summary WHERE sessionID = $1 AND gap_seconds = $2",
+                &[&session_id, &gap_seconds],
+            )?
+            .map(|row| Ok(serde_json::from_value(row.try_get(0)?)?))
+            .transpose()
+    }
+
+    fn cache_session_summary(&self, summary: &SessionSummary) -> Result<()> {
+        self.client()?.execute(
+            "INSERT INTO Session_Summary (sessionID, gap_seconds, summary) VALUES ($1, $2, $3)
+                ON CONFLICT (sessionID) DO UPDATE SET gap_seconds = EXCLUDED.gap_seconds, summary = EXCLUDED.summary",
+            &[summary.get_session_id(), summary.get_gap_seconds(), &serde_json::to_value(summary)?],
+        )?;
+
+        Ok(())
+    }
+
+    /* Sensor Schema */
+    fn upsert_sensor_schema(&self, schema: &SensorSchema) -> Result<SensorSchema> {
+        self.client()?.execute(
+            "INSERT INTO Sensor_Schema (type, data_schema, policy) VALUES ($1, $2, $3)
+                ON CONFLICT (type) DO UPDATE SET data_schema = EXCLUDED.data_schema, policy = EXCLUDED.policy",
+            &[&schema.get_sensor_type(), schema.get_schema(), &schema.get_policy().as_str()],
+        )?;
+
+        Ok(schema.clone())
+    }
+
+    fn get_sensor_schemas(&self) -> Result<Vec<SensorSchema>> {
+        self.client()?
+            .query("SELECT type, data_schema, policy FROM Sensor_Schema ORDER BY type", &[])?
+            .iter()
+            .map(Self::sensor_schema_from_row)
+            .collect()
+    }
+
+    fn get_sensor_schema(&self, sensor_type: &str) -> Result<SensorSchema> {
+        let row = self
+            .client()?
+            .query_opt("SELECT type, data_schema, policy FROM Sensor_Schema WHERE type = $1", &[&sensor_type])?
+            .ok_or_else(|| Error::not_found(format!("Schema of sensor type {sensor_type}")))?;
+
+        Self::sensor_schema_from_row(&row)
+    }
+
+    fn delete_sensor_schema(&self, sensor_type: &str) -> Result<()> {
+        let rows_deleted = self
+            .client()?
+            .execute("DELETE FROM Sensor_Schema WHERE type = $1", &[&sensor_type])?;
+
+        if rows_deleted == 0 {
+            return Err(Error::not_found(format!("Schema of sensor type {sensor_type}")));
+        }
+
+        Ok(())
+    }
+
+    fn quarantine_session_sensor_data(&self, quarantined: &[QuarantinedSensorData]) -> Result<()> {
+        let mut client = self.client()?;
+        let mut transaction = client.transaction()?;
+        let statement = transaction.prepare(
+            "INSERT INTO Quarantined_Sensor_Data (sessionID, session_sensorID, datetime, data_blob, errors, quarantined_at)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+        )?;
+        for datapoint in quarantined {
+            transaction.execute(
+                &statement,
+                &[
+                    datapoint.get_session_id(),
+                    datapoint.get_session_sensor_id(),
+                    &Self::parse_datetime(datapoint.get_datetime())?,
+                    datapoint.get_blob(),
+                    &serde_json::to_value(datapoint.get_errors())?,
+                    &Self::parse_datetime(datapoint.get_quarantined_at())?,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn get_quarantined_sensor_data(&self, session_id: i64) -> Result<Vec<QuarantinedSensorData>> {
+        self.client()?
+            .query(
+                "SELECT sessionID, session_sensorID, datetime, data_blob, errors, quarantined_at FROM Quarantined_Sensor_Data
+                    WHERE sessionID = $1 ORDER BY quarantined_at, ctid",
+                &[&session_id],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(QuarantinedSensorData::new(
+                    row.try_get(0)?,
+                    row.try_get(1)?,
+                    Self::format_datetime(&row.try_get(2)?),
+                    row.try_get(3)?,
+                    serde_json::from_value(row.try_get(4)?)?,
+                    Self::format_datetime(&row.try_get(5)?),
+                ))
+            })
+            .collect()
+    }
+
+    fn delete_quarantined_sensor_data(&self, session_id: i64) -> Result<usize> {
+        Ok(self
+            .client()?
+            .execute("DELETE FROM Quarantined_Sensor_Data WHERE sessionID = $1", &[&session_id])? as usize)
+    }
+
+    /* Alerts */
+    fn insert_alert_rule(&self, rule: &AlertRule) -> Result<AlertRule> {
+        let mut client = self.client()?;
+        if let Some(sensor_id) = rule.get_sensor_id() {
+            Self::query_sensor(&mut *client, "", sensor_id)?;
+        }
+        if let Some(session_id) = rule.get_session_id() {
+            Self::query_session(&mut *client, session_id)?;
+        }
+        let row = client.query_one(
+            &format!(
+                "INSERT INTO Alert_Rule (sensorID, sessionID, name, field, comparator, threshold, duration_seconds, hysteresis) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {ALERT_RULE_COLUMNS}"
+            ),
+            &[
+                &rule.get_sensor_id(),
+                &rule.get_session_id(),
+                &rule.get_name(),
+                &rule.get_field(),
+                &rule.get_comparator().as_str(),
+                rule.get_threshold(),
+                rule.get_duration_seconds(),
+                rule.get_hysteresis(),
+            ],
+        )?;
+
+        Self::alert_rule_from_row(&row)
+    }
+
+    fn get_alert_rules(&self) -> Result<Vec<AlertRule>> {
+        self.client()?
+            .query(&format!("SELECT {ALERT_RULE_COLUMNS} FROM Alert_Rule ORDER BY alertRuleID"), &[])?
+            .iter()
+            .map(Self::alert_rule_from_row)
+            .collect()
+    }
+
+    fn delete_alert_rule(&self, rule_id: i64) -> Result<()> {
+        let rows_deleted = self
+            .client()?
+            .execute("DELETE FROM Alert_Rule WHERE alertRuleID = $1", &[&rule_id])?;
+
+        if rows_deleted == 0 {
+            return Err(Error::not_found(format!("Alert rule {rule_id}")));
+        }
+
+        Ok(())
+    }
+
+    fn insert_alert_event(&self, event: &AlertEvent) -> Result<AlertEvent> {
+        let row = self.client()?.query_one(
+            &format!(
+                "INSERT INTO Alert_Event (alertRuleID, sessionID, session_sensorID, state, value, datetime, delivery, attempts) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {ALERT_EVENT_COLUMNS}"
+            ),
+            &[
+                event.get_rule_id(),
+                event.get_session_id(),
+                event.get_session_sensor_id(),
+                &event.get_state().as_str(),
+                event.get_value(),
+                &Self::parse_datetime(event.get_datetime())?,
+                &event.get_delivery().as_str(),
+                event.get_attempts(),
+            ],
+        )?;
+
+        Self::alert_event_from_row(&row)
+    }
+
+    fn get_alert_events(&self, session_id: i64) -> Result<Vec<AlertEvent>> {
+        self.client()?
+            .query(
+                &format!("SELECT {ALERT_EVENT_COLUMNS} FROM Alert_Event WHERE sessionID = $1 ORDER BY alertEventID"),
+                &[&session_id],
+            )?
+            .iter()
+            .map(Self::alert_event_from_row)
+            .collect()
+    }
+
+    fn update_alert_event_delivery(&self, event_id: i64, delivery: AlertDelivery, attempts: i64) -> Result<()> {
+        let rows_updated = self.client()?.execute(
+            "UPDATE Alert_Event SET delivery = $1, attempts = $2 WHERE alertEventID = $3",
+            &[&delivery.as_str(), &attempts, &event_id],
+        )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Alert event {event_id}")));
+        }
+
+        Ok(())
+    }
+
+    /* Derived Channels */
+    fn insert_derived_channel(&self, channel: &DerivedChannel) -> Result<DerivedChannel> {
+        self.client()?.execute(
+            "INSERT INTO Derived_Channel (type, name, expression, unit) VALUES ($1, $2, $3, $4)",
+            &[&channel.get_sensor_type(), &channel.get_name(), &channel.get_expression(), &channel.get_unit()],
+        )?;
+
+        Ok(channel.clone())
+    }
+
+    fn get_derived_channels(&self) -> Result<Vec<DerivedChannel>> {
+        self.client()?
+            .query("SELECT type, name, expression, unit FROM Derived_Channel ORDER BY type, name", &[])?
+            .iter()
+            .map(|row| Ok(DerivedChannel::new(row.try_get(0)?, row.try_get(1)?, row.try_get(2)?, row.try_get(3)?)))
+            .collect()
+    }
+
+    fn delete_derived_channel(&self, sensor_type: &str, name: &str) -> Result<()> {
+        let rows_deleted = self.client()?.execute(
+            "DELETE FROM Derived_Channel WHERE type = $1 AND name = $2",
+            &[&sensor_type, &name],
+        )?;
+
+        if rows_deleted == 0 {
+            return Err(Error::not_found(format!("Derived channel {name} of sensor type {sensor_type}")));
+        }
+
+        Ok(())
+    }
+}
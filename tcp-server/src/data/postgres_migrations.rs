@@ -0,0 +1,366 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+
+use postgres::{Client, Transaction};
+
+use super::migrations::{
+    applied_at_now, migration_status, pending_migrations, AppliedMigration, Migration, MigrationStatus,
+};
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+// Tracks which migrations have been applied, created before any migration runs
+const METADATA_STATEMENT: &str = r#"
+CREATE TABLE IF NOT EXISTS Schema_Migrations (
+    version BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    applied_at TEXT NOT NULL
+);
+"#;
+
+// Advisory lock held while migrating, so servers started together take turns
+const MIGRATION_LOCK: i64 = 0x7064_6171_6d69_6772;
+
+// The same versions as the SQLite MIGRATIONS, written for PostgreSQL
+// Never edit a migration once released, add a new one instead
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS "User" (
+    username TEXT PRIMARY KEY,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS Session (
+    sessionID BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    username TEXT NOT NULL REFERENCES "User"(username) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS Sensor (
+    sensorID BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    type TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS Session_Sensor (
+    session_sensorID BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    sessionID BIGINT NOT NULL REFERENCES Session(sessionID) ON DELETE CASCADE,
+    sensorID BIGINT NOT NULL REFERENCES Sensor(sensorID) ON DELETE CASCADE,
+    UNIQUE (sessionID, sensorID)
+);
+
+CREATE TABLE IF NOT EXISTS Session_Sensor_Data (
+    datetime TIMESTAMP NOT NULL,
+    sessionID BIGINT NOT NULL REFERENCES Session(sessionID) ON DELETE CASCADE,
+    data_blob JSONB NOT NULL,
+    PRIMARY KEY (datetime, sessionID)
+);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 2,
+        name: "session_lifecycle",
+        sql: r#"
+ALTER TABLE Session ADD COLUMN name TEXT NOT NULL DEFAULT '';
+ALTER TABLE Session ADD COLUMN description TEXT NOT NULL DEFAULT '';
+ALTER TABLE Session ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE Session ADD COLUMN status TEXT NOT NULL DEFAULT 'pending';
+ALTER TABLE Session ADD COLUMN start_time TEXT;
+ALTER TABLE Session ADD COLUMN end_time TEXT;
+UPDATE Session SET status = 'stopped' WHERE sessionID IN (SELECT DISTINCT sessionID FROM Session_Sensor_Data);
+"#,
+        detect: None,
+    },
+    // Rows are given their session's only sensor, rows of sessions with several sensors are left without one
+    // A unique constraint replaces the primary key since those rows have no session sensor, it keeps datetime so the
+    // table can become a hypertable
+    Migration {
+        version: 3,
+        name: "session_sensor_data_per_sensor",
+        sql: r#"
+ALTER TABLE Session_Sensor_Data
+    ADD COLUMN session_sensorID BIGINT REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE;
+UPDATE Session_Sensor_Data SET session_sensorID =
+    (SELECT CASE WHEN COUNT(*) = 1 THEN MAX(session_sensorID) END FROM Session_Sensor
+        WHERE Session_Sensor.sessionID = Session_Sensor_Data.sessionID);
+ALTER TABLE Session_Sensor_Data DROP CONSTRAINT session_sensor_data_pkey;
+ALTER TABLE Session_Sensor_Data
+    ADD CONSTRAINT session_sensor_data_key UNIQUE (sessionID, session_sensorID, datetime);
+CREATE INDEX IF NOT EXISTS Session_Sensor_Data_session_sensor
+    ON Session_Sensor_Data (session_sensorID, datetime);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 4,
+        name: "session_sensor_rollups",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Session_Sensor_Rollup (
+    session_sensorID BIGINT NOT NULL REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE,
+    interval_seconds BIGINT NOT NULL,
+    bucket TIMESTAMP NOT NULL,
+    count BIGINT NOT NULL,
+    data_blob JSONB NOT NULL,
+    PRIMARY KEY (session_sensorID, interval_seconds, bucket)
+);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 5,
+        name: "sensor_schemas",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Sensor_Schema (
+    type TEXT PRIMARY KEY,
+    data_schema JSONB NOT NULL,
+    policy TEXT NOT NULL DEFAULT 'reject'
+);
+
+CREATE TABLE IF NOT EXISTS Quarantined_Sensor_Data (
+    sessionID BIGINT NOT NULL REFERENCES Session(sessionID) ON DELETE CASCADE,
+    session_sensorID BIGINT NOT NULL REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE,
+    datetime TIMESTAMP NOT NULL,
+    data_blob JSONB NOT NULL,
+    errors JSONB NOT NULL,
+    quarantined_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS Quarantined_Sensor_Data_session
+    ON Quarantined_Sensor_Data (sessionID, quarantined_at);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 6,
+        name: "sensor_catalog",
+        sql: r#"
+ALTER TABLE Sensor ADD COLUMN manufacturer TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN model TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN serial_number TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN firmware TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN channels JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE Sensor ADD COLUMN sample_rate DOUBLE PRECISION;
+
+CREATE TABLE IF NOT EXISTS Sensor_Calibration (
+    calibrationID BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    sensorID BIGINT NOT NULL REFERENCES Sensor(sensorID) ON DELETE CASCADE,
+    channel TEXT NOT NULL,
+    coefficients JSONB NOT NULL,
+    valid_from TIMESTAMP NOT NULL,
+    valid_to TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS Sensor_Calibration_sensor
+    ON Sensor_Calibration (sensorID, channel, valid_from);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 7,
+        name: "calibration_methods",
+        sql: r#"
+ALTER TABLE Sensor_Calibration ADD COLUMN method TEXT NOT NULL DEFAULT 'polynomial';
+ALTER TABLE Sensor_Calibration ADD COLUMN lookup_table JSONB NOT NULL DEFAULT '[]';
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 8,
+        name: "session_summaries",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Session_Summary (
+    sessionID BIGINT PRIMARY KEY REFERENCES Session(sessionID) ON DELETE CASCADE,
+    gap_seconds DOUBLE PRECISION NOT NULL,
+    summary JSONB NOT NULL
+);
+
+CREATE OR REPLACE FUNCTION session_summary_invalidate() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        DELETE FROM Session_Summary WHERE sessionID = OLD.sessionID;
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        DELETE FROM Session_Summary WHERE sessionID = NEW.sessionID;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER session_summary_invalidate AFTER INSERT OR UPDATE OR DELETE ON Session_Sensor_Data
+    FOR EACH ROW EXECUTE FUNCTION session_summary_invalidate();
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 9,
+        name: "alerts",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Alert_Rule (
+    alertRuleID BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    sensorID BIGINT REFERENCES Sensor(sensorID) ON DELETE CASCADE,
+    sessionID BIGINT REFERENCES Session(sessionID) ON DELETE CASCADE,
+    name TEXT NOT NULL DEFAULT '',
+    field TEXT NOT NULL,
+    comparator TEXT NOT NULL,
+    threshold DOUBLE PRECISION NOT NULL,
+    duration_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    hysteresis DOUBLE PRECISION NOT NULL DEFAULT 0,
+    CHECK ((sensorID IS NULL) <> (sessionID IS NULL))
+);
+
+CREATE TABLE IF NOT EXISTS Alert_Event (
+    alertEventID BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    alertRuleID BIGINT NOT NULL REFERENCES Alert_Rule(alertRuleID) ON DELETE CASCADE,
+    sessionID BIGINT NOT NULL REFERENCES Session(sessionID) ON DELETE CASCADE,
+    session_sensorID BIGINT NOT NULL REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE,
+    state TEXT NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    datetime TIMESTAMP NOT NULL,
+    delivery TEXT NOT NULL DEFAULT 'pending',
+    attempts BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS Alert_Event_session ON Alert_Event (sessionID, alertEventID);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 10,
+        name: "derived_channels",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Derived_Channel (
+    type TEXT NOT NULL,
+    name TEXT NOT NULL,
+    expression TEXT NOT NULL,
+    unit TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (type, name)
+);
+"#,
+        detect: None,
+    },
+    // The unique constraint does not keep datapoints left without a session sensor unique, since NULLs are distinct
+    // Repeats among them are dropped, keeping the first stored
+    Migration {
+        version: 11,
+        name: "unassigned_session_sensor_data_unique",
+        sql: r#"
+DELETE FROM Session_Sensor_Data AS repeat USING Session_Sensor_Data AS first
+    WHERE repeat.session_sensorID IS NULL AND first.session_sensorID IS NULL
+        AND repeat.sessionID = first.sessionID AND repeat.datetime = first.datetime AND repeat.ctid > first.ctid;
+CREATE UNIQUE INDEX IF NOT EXISTS Session_Sensor_Data_unassigned
+    ON Session_Sensor_Data (sessionID, datetime) WHERE session_sensorID IS NULL;
+"#,
+        detect: None,
+    },
+];
+
+//Applies POSTGRES_MIGRATIONS to a client, every migration runs in its own transaction
+pub struct PostgresMigrator<'a> {
+    client: &'a mut Client,
+    migrations: &'a [Migration],
+}
+
+impl<'a> PostgresMigrator<'a> {
+    pub fn new(client: &'a mut Client) -> Self {
+        Self::with_migrations(client, POSTGRES_MIGRATIONS)
+    }
+
+    pub fn with_migrations(client: &'a mut Client, migrations: &'a [Migration]) -> Self {
+        PostgresMigrator { client, migrations }
+    }
+
+    //Reports every known migration without changing the database
+    pub fn status(&mut self) -> Result<Vec<MigrationStatus>> {
+        let mut transaction = self.client.transaction()?;
+        let applied = Self::lock(&mut transaction)?;
+        transaction.rollback()?;
+        migration_status(self.migrations, &applied)
+    }
+
+    //Applies every pending migration and returns the versions that were applied
+    pub fn migrate(&mut self) -> Result<Vec<i64>> {
+        let mut versions = Vec::new();
+        loop {
+            let mut transaction = self.client.transaction()?;
+            let applied = Self::lock(&mut transaction)?;
+            // another server may have applied migrations while this one waited for the lock
+            let Some(migration) = pending_migrations(self.migrations, &applied)?.first().copied() else {
+                break;
+            };
+            Self::apply(&mut transaction, migration)?;
+            transaction.commit().map_err(|e| {
+                Error::Storage(format!(
+                    "Failed to commit migration {}: {e}",
+                    migration.version
+                ))
+            })?;
+            versions.push(migration.version);
+        }
+        Ok(versions)
+    }
+
+    //Runs every pending migration and rolls them back, returning the versions that would be applied
+    pub fn dry_run(&mut self) -> Result<Vec<i64>> {
+        let mut transaction = self.client.transaction()?;
+        let applied = Self::lock(&mut transaction)?;
+        let pending = pending_migrations(self.migrations, &applied)?;
+        for migration in &pending {
+            Self::apply(&mut transaction, migration)?;
+        }
+        transaction.rollback()?;
+        Ok(pending.iter().map(|migration| migration.version).collect())
+    }
+
+    //Takes the migration lock for the rest of the transaction and reads the applied migrations
+    fn lock(transaction: &mut Transaction) -> Result<HashMap<i64, AppliedMigration>> {
+        transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])?;
+        transaction.batch_execute(METADATA_STATEMENT)?;
+        let applied = transaction
+            .query("SELECT version, name, checksum, applied_at FROM Schema_Migrations", &[])?
+            .iter()
+            .map(|row| {
+                Ok((
+                    row.try_get(0)?,
+                    AppliedMigration {
+                        name: row.try_get(1)?,
+                        checksum: row.try_get(2)?,
+                        applied_at: row.try_get(3)?,
+                    },
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(applied)
+    }
+
+    fn apply(transaction: &mut Transaction, migration: &Migration) -> Result<()> {
+        transaction.batch_execute(migration.sql).map_err(|e| {
+            Error::Storage(format!(
+                "Migration {} '{}' failed: {}",
+                migration.version,
+                migration.name,
+                Error::from(e)
+            ))
+        })?;
+        transaction.execute(
+            "INSERT INTO Schema_Migrations (version, name, checksum, applied_at) VALUES ($1, $2, $3, $4)",
+            &[
+                &migration.version,
+                &migration.name,
+                &migration.checksum(),
+                &applied_at_now(),
+            ],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::Error;
+
+use super::migrations::MigrationState;
+use super::sqlite_migrations::Migrator;
+
+#[allow(unused)]
+type Result<T> = crate::Result<T>;
+
+//Settings of online backups, the [backup] table of config.toml
+#[allow(unused)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct BackupConfig {
+    // relative paths are resolved against the directory of the database file
+    #[serde(default = "BackupConfig::default_directory")]
+    pub directory: String,
+    // newest backups kept, older ones are deleted after each backup
+    #[serde(default = "BackupConfig::default_keep")]
+    pub keep: usize,
+    // minutes between scheduled backups, none are scheduled when unset
+    #[serde(default)]
+    pub interval_minutes: Option<u64>,
+}
+
+#[allow(unused)]
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            directory: Self::default_directory(),
+            keep: Self::default_keep(),
+            interval_minutes: None,
+        }
+    }
+}
+
+#[allow(unused)]
+impl BackupConfig {
+    fn default_directory() -> String {
+        String::from("backups")
+    }
+
+    fn default_keep() -> usize {
+        7
+    }
+}
+
+//A backup file that passed its integrity check
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    // newest migration applied to the backup
+    pub schema_version: i64,
+    pub created_at: String,
+}
+
+#[allow(unused)]
+impl BackupInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "file": self.path.file_name().map(|name| name.to_string_lossy()),
+            "size_bytes": self.size_bytes,
+            "schema_version": self.schema_version,
+            "created_at": self.created_at,
+        })
+    }
+}
+
+//Copies the SQLite database file while the server keeps running, and restores those copies while it is stopped
+//Backups read through a connection of their own, so in WAL mode writers are never blocked
+#[allow(unused)]
+pub struct SqliteBackup {
+    database_path: PathBuf,
+    directory: PathBuf,
+    keep: usize,
+    // scheduled backups and those requested over http take turns
+    running: Mutex<()>,
+}
+
+#[allow(unused)]
+impl SqliteBackup {
+    const FILE_TIME_FORMAT: &'static str = "%Y%m%dT%H%M%S%3f";
+
+    pub fn new(database_path: impl Into<PathBuf>, config: &BackupConfig) -> Self {
+        let database_path = database_path.into();
+        let directory = match database_path.parent() {
+            Some(parent) if Path::new(&config.directory).is_relative() => parent.join(&config.directory),
+            _ => PathBuf::from(&config.directory),
+        };
+        SqliteBackup {
+            database_path,
+            directory,
+            keep: config.keep.max(1),
+            running: Mutex::new(()),
+        }
+    }
+
+    //Writes a backup named after the database and the current time, checks it, and rotates out the oldest
+    pub fn create(&self) -> Result<BackupInfo> {
+        let _running = self.running.lock().map_err(|_| Self::poisoned())?;
+        fs::create_dir_all(&self.directory)
+            .map_err(|e| Error::Io(format!("Failed to create backup directory {:?}: {e}", self.directory)))?;
+
+        let name = format!("{}-{}", self.stem(), Utc::now().format(Self::FILE_TIME_FORMAT));
+        let partial = self.directory.join(format!("{name}.partial"));
+        let path = self.directory.join(format!("{name}.db"));
+        let result = self.copy_database(&partial).and_then(|_| Self::check(&partial));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        // only checked backups ever carry the .db extension
+        fs::rename(&partial, &path).map_err(|e| Error::Io(format!("Failed to name backup {path:?}: {e}")))?;
+
+        self.rotate()?;
+        Self::info(&path)
+    }
+
+    //Backups in the directory, newest first
+    pub fn list(&self) -> Result<Vec<BackupInfo>> {
+        self.backup_paths()?.iter().rev().map(|path| Self::info(path)).collect()
+    }
+
+    //Replaces the database file with a backup, the server must be stopped
+    //The backup is checked first, and the replaced database is kept beside it with a .pre-restore extension
+    pub fn restore(&self, backup: &Path) -> Result<BackupInfo> {
+        let restoring = self.database_path.with_extension("restore");
+        fs::copy(backup, &restoring).map_err(|e| Error::Io(format!("Failed to read backup {backup:?}: {e}")))?;
+        if let Err(e) = Self::check(&restoring) {
+            let _ = fs::remove_file(&restoring);
+            return Err(e);
+        }
+
+        if self.database_path.exists() {
+            let replaced = self.database_path.with_extension("pre-restore");
+            let _ = fs::remove_file(&replaced);
+            self.copy_database(&replaced)?;
+        }
+        // a write-ahead log left beside the restored file would be replayed into it
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = self.database_path.clone().into_os_string();
+            sidecar.push(suffix);
+            match fs::remove_file(&sidecar) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(Error::Io(format!("Failed to remove {sidecar:?}: {e}")))
+                }
+                _ => {}
+            }
+        }
+        fs::rename(&restoring, &self.database_path)
+            .map_err(|e| Error::Io(format!("Failed to replace {:?}: {e}", self.database_path)))?;
+
+        Self::info(&self.database_path)
+    }
+
+    //Backs up every interval on its own thread
+    pub fn start(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            match self.create() {
+                Ok(backup) => println!("Backed up database to {:?}", backup.path),
+                Err(error) => eprintln!("Scheduled backup failed, it is retried next interval. Error: {error}"),
+            }
+        })
+    }
+
+    // Copies every page in a single step, so the copy is one consistent snapshot
+    fn copy_database(&self, destination: &Path) -> Result<()> {
+        if !self.database_path.exists() {
+            return Err(Error::NotFound(format!("No database at {:?} to back up", self.database_path)));
+        }
+        let source = Connection::open_with_flags(
+            &self.database_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        let mut copy = Connection::open(destination)?;
+        let step = Backup::new(&source, &mut copy)?.step(-1)?;
+        match step {
+            StepResult::Done => Ok(()),
+            _ => Err(Error::Unavailable(String::from(
+                "Database was busy while backing up, try again",
+            ))),
+        }
+    }
+
+    // Runs an integrity check and makes sure this build can migrate the copy
+    fn check(path: &Path) -> Result<()> {
+        let connection = Connection::open(path)?;
+        let integrity: String = connection.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        if integrity != "ok" {
+            return Err(Error::Storage(format!("Backup {path:?} failed its integrity check: {integrity}")));
+        }
+        // fails for migrations newer than this build
+        let status = Migrator::new(&connection).status()?;
+        if let Some(modified) = status.iter().find(|status| status.state == MigrationState::Modified) {
+            return Err(Error::Conflict(format!(
+                "Backup {path:?} has migration {} '{}' applied differently than this build",
+                modified.version, modified.name
+            )));
+        }
+        Ok(())
+    }
+
+    fn info(path: &Path) -> Result<BackupInfo> {
+        let metadata = fs::metadata(path).map_err(|e| Error::Io(format!("Failed to read backup {path:?}: {e}")))?;
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let schema_version: Option<i64> = connection
+            .query_row("SELECT MAX(version) FROM Schema_Migrations", [], |row| row.get(0))
+            .unwrap_or_default();
+        Ok(BackupInfo {
+            path: path.to_path_buf(),
+            size_bytes: metadata.len(),
+            schema_version: schema_version.unwrap_or(0),
+            created_at: metadata
+                .modified()
+                .map(|modified| DateTime::<Utc>::from(modified).naive_utc().format("%Y-%m-%dT%H:%M:%S").to_string())
+                .unwrap_or_default(),
+        })
+    }
+
+    // Backups of this database, oldest first since their names sort by time
+    fn backup_paths(&self) -> Result<Vec<PathBuf>> {
+        let prefix = format!("{}-", self.stem());
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Error::Io(format!("Failed to read backup directory {:?}: {e}", self.directory))),
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension().is_some_and(|extension| extension == "db")
+                    && path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+            })
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn rotate(&self) -> Result<()> {
+        let paths = self.backup_paths()?;
+        for path in paths.iter().take(paths.len().saturating_sub(self.keep)) {
+            fs::remove_file(path).map_err(|e| Error::Io(format!("Failed to remove old backup {path:?}: {e}")))?;
+        }
+        Ok(())
+    }
+
+    fn stem(&self) -> String {
+        self.database_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("database"))
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Backup lock was poisoned by a panic"))
+    }
+}
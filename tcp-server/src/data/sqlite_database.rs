@@ -6,63 +6,45 @@ You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2
 Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
-use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+use crate::models::{
+    AlertComparator, AlertDelivery, AlertEvent, AlertRule, AlertState, BaseModel, CalibrationMethod, DerivedChannel,
+    FieldSummary, QuarantinedSensorData, SchemaPolicy, Sensor, SensorCalibration, SensorSchema, SensorSummary, Session,
+    SessionSensor, SessionSensorData, SessionSensorRollup, SessionStatus, SessionSummary, SummaryGap, User,
+};
 use chrono::NaiveDateTime;
-use rusqlite::{params, params_from_iter, Connection};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
 
+use crate::error::Error;
+
+use super::migrations::MigrationStatus;
+use super::sqlite_migrations::Migrator;
+use super::sqlite_pool::{SqlitePool, SqlitePoolConfig};
 use super::Database;
 type Result<T> = crate::Result<T>;
 
-const SCHEMA_STATEMENT: &str = r#"
-CREATE TABLE IF NOT EXISTS User (
-    username TEXT PRIMARY KEY,
-    password_hash TEXT NOT NULL
-);
-
-CREATE TABLE IF NOT EXISTS Session (
-    sessionID INTEGER PRIMARY KEY,
-    username TEXT NOT NULL,
-    FOREIGN KEY (username) REFERENCES User(username) ON DELETE CASCADE
-);
-
-CREATE TABLE IF NOT EXISTS Sensor (
-    sensorID INTEGER PRIMARY KEY,
-    type TEXT NOT NULL
-);
-
-CREATE TABLE IF NOT EXISTS Session_Sensor (
-    session_sensorID INTEGER PRIMARY KEY,
-    sessionID INTEGER NOT NULL,
-    sensorID INTEGER NOT NULL,
-    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
-    FOREIGN KEY (sensorID) REFERENCES Sensor(sensorID) ON DELETE CASCADE,
-    UNIQUE (sessionID, sensorID)
-);
-
-CREATE TABLE IF NOT EXISTS Session_Sensor_Data (
-    datetime TEXT,
-    sessionID INTEGER,
-    data_blob TEXT NOT NULL,
-    PRIMARY KEY (datetime, sessionID),
-    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE
-);
-"#;
+const ALERT_RULE_COLUMNS: &str =
+    "alertRuleID, sensorID, sessionID, name, field, comparator, threshold, duration_seconds, hysteresis";
+const ALERT_EVENT_COLUMNS: &str =
+    "alertEventID, alertRuleID, sessionID, session_sensorID, state, value, datetime, delivery, attempts";
+const SENSOR_COLUMNS: &str = "sensorID, type, manufacturer, model, serial_number, firmware, channels, sample_rate";
+const SENSOR_CALIBRATION_COLUMNS: &str = "calibrationID, sensorID, channel, coefficients, valid_from, valid_to, method, lookup_table";
+const SESSION_COLUMNS: &str = "sessionID, username, name, description, tags, status, start_time, end_time";
+const SESSION_SENSOR_DATA_COLUMNS: &str = "sessionID, session_sensorID, datetime, data_blob";
 
 #[allow(unused)]
 pub struct SqliteDatabase {
     url: String,
-    connection: Connection,
+    pool: SqlitePool,
     // Temp Mock database to replace the unimplemented functions for testing
     mock_db: super::MockDatabase,
 }
 
 #[allow(unused)]
 impl SqliteDatabase {
-    pub fn new(url: &str) -> Result<SqliteDatabase> {
-        let connection = Connection::open(url).map_err(|e| e.to_string())?;
+    pub fn new(url: &str, pool_config: &SqlitePoolConfig) -> Result<SqliteDatabase> {
         Ok(SqliteDatabase {
             url: url.to_string(),
-            connection,
+            pool: SqlitePool::open(url, pool_config)?,
             mock_db: super::MockDatabase::new(),
         })
     }
@@ -71,30 +53,206 @@ impl SqliteDatabase {
     pub fn from_connection(connection: Connection) -> Result<SqliteDatabase> {
         Ok(SqliteDatabase {
             url: ":memory:".to_string(),
-            connection,
+            pool: SqlitePool::from_connection(connection),
             mock_db: super::MockDatabase::new(),
         })
     }
 
-    // Function to initialize the database schema
+    // Function to initialize the database schema, applying any pending migrations
     pub fn init(&self) {
-        if let Err(e) = self.connection.execute_batch(SCHEMA_STATEMENT) {
-            panic!("Failed to create database schema: {e}")
+        match self.migrate() {
+            Ok(applied) if !applied.is_empty() => println!("Applied database migrations {applied:?}"),
+            Ok(_) => {}
+            Err(e) => panic!("Failed to migrate database schema: {e}"),
         }
     }
-}
 
-//TODO: impl Database for SqliteDatabase {}
-impl Database for SqliteDatabase {
-    fn temp_session_id_solution(&self) {
-        if let Err(e) = self
-            .connection
-            .execute_batch("UPDATE Session_Sensor_Data SET sessionID = 2 WHERE sessionID = 1")
-        {
-            eprintln!("Failed to update all sessionID `1` to `2`: {e}")
+    pub fn migrate(&self) -> Result<Vec<i64>> {
+        Migrator::new(&*self.pool.writer()?).migrate()
+    }
+
+    pub fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        Migrator::new(&*self.pool.writer()?).status()
+    }
+
+    // Applies pending migrations inside a transaction that is rolled back
+    pub fn dry_run_migrations(&self) -> Result<Vec<i64>> {
+        Migrator::new(&*self.pool.writer()?).dry_run()
+    }
+
+    // Builds a Sensor from a row selected with SENSOR_COLUMNS
+    fn sensor_from_row(row: &Row) -> rusqlite::Result<Sensor> {
+        let channels: String = row.get(6)?;
+        Ok(Sensor::new(row.get(0)?, row.get(1)?)
+            .with_metadata(row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)
+            .with_channels(serde_json::from_str(&channels).unwrap_or_default(), row.get(7)?))
+    }
+
+    fn query_sensor(connection: &Connection, sensor_id: i64) -> Result<Sensor> {
+        connection
+            .query_row(
+                &format!("SELECT {SENSOR_COLUMNS} FROM Sensor WHERE sensorID = ?1"),
+                params![sensor_id],
+                Self::sensor_from_row,
+            )
+            .optional()?
+            .ok_or_else(|| Error::not_found(format!("Sensor {sensor_id}")))
+    }
+
+    // Builds a SensorCalibration from a row selected with SENSOR_CALIBRATION_COLUMNS
+    fn sensor_calibration_from_row(row: &Row) -> rusqlite::Result<SensorCalibration> {
+        let coefficients: String = row.get(3)?;
+        let method: String = row.get(6)?;
+        let table: String = row.get(7)?;
+        Ok(SensorCalibration::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            serde_json::from_str(&coefficients).unwrap_or_default(),
+            row.get(4)?,
+            row.get(5)?,
+        )
+        .with_table(
+            CalibrationMethod::from_name(&method).unwrap_or_default(),
+            serde_json::from_str(&table).unwrap_or_default(),
+        ))
+    }
+
+    fn query_sensor_calibrations(connection: &Connection, sensor_id: i64) -> Result<Vec<SensorCalibration>> {
+        let mut statement = connection.prepare(&format!(
+            "SELECT {SENSOR_CALIBRATION_COLUMNS} FROM Sensor_Calibration WHERE sensorID = ?1 ORDER BY channel, valid_from"
+        ))?;
+        let calibrations = statement
+            .query_map(params![sensor_id], Self::sensor_calibration_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(calibrations)
+    }
+
+    // Builds an AlertRule from a row selected with ALERT_RULE_COLUMNS
+    fn alert_rule_from_row(row: &Row) -> rusqlite::Result<AlertRule> {
+        let comparator: String = row.get(5)?;
+        Ok(AlertRule::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            AlertComparator::from_name(&comparator).unwrap_or(AlertComparator::Above),
+            row.get(6)?,
+        )
+        .with_timing(row.get(7)?, row.get(8)?))
+    }
+
+    // Builds an AlertEvent from a row selected with ALERT_EVENT_COLUMNS
+    fn alert_event_from_row(row: &Row) -> rusqlite::Result<AlertEvent> {
+        let state: String = row.get(4)?;
+        let delivery: String = row.get(7)?;
+        Ok(AlertEvent::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            AlertState::from_name(&state).unwrap_or(AlertState::Triggered),
+            row.get(5)?,
+            row.get(6)?,
+        )
+        .with_delivery(AlertDelivery::from_name(&delivery).unwrap_or_default(), row.get(8)?))
+    }
+
+    // Builds a Session from a row selected with SESSION_COLUMNS
+    fn session_from_row(row: &Row) -> rusqlite::Result<Session> {
+        let tags: String = row.get(4)?;
+        let status: String = row.get(5)?;
+        Ok(Session::new(row.get(0)?, row.get(1)?)
+            .with_metadata(
+                row.get(2)?,
+                row.get(3)?,
+                serde_json::from_str(&tags).unwrap_or_default(),
+            )
+            .with_lifecycle(
+                SessionStatus::from_str(&status).unwrap_or_default(),
+                row.get(6)?,
+                row.get(7)?,
+            ))
+    }
+
+    fn query_sessions(
+        connection: &Connection,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Session>> {
+        let mut statement = connection
+            .prepare(&format!("SELECT {SESSION_COLUMNS} FROM Session {condition}"))?;
+
+        let session_itr = statement
+            .query_map(params, Self::session_from_row)?;
+
+        let mut session_vec = Vec::new();
+
+        for session in session_itr {
+            session_vec.push(session?);
+        }
+
+        Ok(session_vec)
+    }
+
+    fn query_session(connection: &Connection, session_id: i64) -> Result<Session> {
+        Self::query_sessions(connection, "WHERE sessionID = ?1", params![session_id])?
+            .pop()
+            .ok_or_else(|| Error::not_found(format!("Session {session_id}")))
+    }
+
+    // Builds a SessionSensorData from a row selected with SESSION_SENSOR_DATA_COLUMNS
+    fn session_sensor_data_from_row(row: &Row) -> rusqlite::Result<SessionSensorData> {
+        let data_blob: String = row.get(3)?;
+        Ok(SessionSensorData::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            serde_json::from_str(&data_blob).unwrap_or_default(),
+        ))
+    }
+
+    fn query_session_sensor_data(
+        connection: &Connection,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SessionSensorData>> {
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {SESSION_SENSOR_DATA_COLUMNS} FROM Session_Sensor_Data {condition}"
+            ))?;
+
+        let session_sensor_data_itr = statement
+            .query_map(params, Self::session_sensor_data_from_row)?;
+
+        let mut session_sensor_data_vec = Vec::new();
+        for session_sensor_data in session_sensor_data_itr {
+            session_sensor_data_vec.push(session_sensor_data?);
         }
+
+        Ok(session_sensor_data_vec)
+    }
+
+    // Builds a SensorSchema from the columns of Sensor_Schema, the schema is stored as JSON text
+    fn sensor_schema_from_columns(sensor_type: String, schema: &str, policy: &str) -> Result<SensorSchema> {
+        Ok(SensorSchema::new(sensor_type, serde_json::from_str(schema)?, SchemaPolicy::from_name(policy)?))
+    }
+
+    fn query_datapoint(connection: &Connection, session_sensor_id: i64, datetime: &str) -> Result<SessionSensorData> {
+        Self::query_session_sensor_data(
+            connection,
+            "WHERE session_sensorID = ?1 AND datetime = ?2",
+            params![session_sensor_id, datetime],
+        )?
+        .pop()
+        .ok_or_else(|| Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")))
     }
+}
 
+//TODO: impl Database for SqliteDatabase {}
+impl Database for SqliteDatabase {
     /* Authentication */
     // TODO: Implement
     // No column for admin
@@ -125,12 +283,12 @@ impl Database for SqliteDatabase {
     /* User */
     // Inserts a single User into User
     fn insert_user(&self, user: &User) -> Result<User> {
-        self.connection
+        self.pool
+            .writer()?
             .execute(
                 "INSERT INTO User (username, password_hash) VALUES (?1, ?2);",
                 params![user.get_username(), user.get_password_hash()],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         // Returning the user for now
         Ok(user.clone())
@@ -138,19 +296,17 @@ impl Database for SqliteDatabase {
 
     // Returns all rows from User
     fn get_users(&self) -> Result<Vec<User>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT username, password_hash FROM User")
-            .map_err(|e| e.to_string())?;
+        let connection = self.pool.reader()?;
+        let mut statement = connection
+            .prepare("SELECT username, password_hash FROM User")?;
 
         let user_itr = statement
-            .query_map([], |row| Ok(User::new(row.get(0)?, row.get(1)?)))
-            .map_err(|e| e.to_string())?;
+            .query_map([], |row| Ok(User::new(row.get(0)?, row.get(1)?)))?;
 
         let mut user_vec = Vec::new();
 
         for user in user_itr {
-            user_vec.push(user.map_err(|e| e.to_string())?);
+            user_vec.push(user?);
         }
 
         Ok(user_vec)
@@ -158,16 +314,16 @@ impl Database for SqliteDatabase {
 
     // Returns a row from User where username matches
     fn get_user(&self, username: &str) -> Result<User> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT username, password_hash FROM User WHERE username = ?1")
-            .map_err(|e| e.to_string())?;
+        let connection = self.pool.reader()?;
+        let mut statement = connection
+            .prepare("SELECT username, password_hash FROM User WHERE username = ?1")?;
 
         let user = statement
             .query_row(params![username], |row| {
                 Ok(User::new(row.get(0)?, row.get(1)?))
             })
-            .map_err(|e| e.to_string())?;
+            .optional()?
+            .ok_or_else(|| Error::not_found(format!("User {username}")))?;
 
         Ok(user)
     }
@@ -175,15 +331,15 @@ impl Database for SqliteDatabase {
     // Updates a user's information (note: username is the primary key and cannot be changed)
     fn update_user(&self, username: &str, updated_user: &User) -> Result<User> {
         let rows_updated = self
-            .connection
+            .pool
+            .writer()?
             .execute(
                 "UPDATE User SET password_hash = ?1 WHERE username = ?2",
                 params![updated_user.get_password_hash(), username],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to update User".into());
+            return Err(Error::not_found(format!("User {username}")));
         }
 
         Ok(updated_user.clone())
@@ -191,12 +347,12 @@ impl Database for SqliteDatabase {
 
     fn delete_user(&self, username: &str) -> Result<()> {
         let rows_updated = self
-            .connection
-            .execute("DELETE FROM User WHERE username = ?1", params![username])
-            .map_err(|e| e.to_string())?;
+            .pool
+            .writer()?
+            .execute("DELETE FROM User WHERE username = ?1", params![username])?;
 
         if rows_updated == 0 {
-            return Err("Failed to delete from User".into());
+            return Err(Error::not_found(format!("User {username}")));
         }
 
         Ok(())
@@ -205,33 +361,38 @@ impl Database for SqliteDatabase {
     /* Sensor */
     // Inserts a single Sensor into Sensor
     fn insert_sensor(&self, sensor: &Sensor) -> Result<Sensor> {
-        self.connection
+        let connection = self.pool.writer()?;
+        connection
             .execute(
-                "INSERT INTO Sensor (type) VALUES (?1)",
-                params![sensor.get_sensor_type()],
-            )
-            .map_err(|e| e.to_string())?;
-        Ok(Sensor::new(
-            self.connection.last_insert_rowid(),
-            sensor.get_sensor_type().to_string(),
-        ))
+                "INSERT INTO Sensor (type, manufacturer, model, serial_number, firmware, channels, sample_rate) \
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    sensor.get_sensor_type(),
+                    sensor.get_manufacturer(),
+                    sensor.get_model(),
+                    sensor.get_serial_number(),
+                    sensor.get_firmware(),
+                    serde_json::to_string(sensor.get_channels())?,
+                    sensor.get_sample_rate()
+                ],
+            )?;
+
+        Self::query_sensor(&connection, connection.last_insert_rowid())
     }
 
     // Returns all rows from Sensor
     fn get_sensors(&self) -> Result<Vec<Sensor>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT sensorID, type FROM Sensor")
-            .map_err(|e| e.to_string())?;
+        let connection = self.pool.reader()?;
+        let mut statement = connection
+            .prepare(&format!("SELECT {SENSOR_COLUMNS} FROM Sensor"))?;
 
         let sensor_itr = statement
-            .query_map([], |row| Ok(Sensor::new(row.get(0)?, row.get(1)?)))
-            .map_err(|e| e.to_string())?;
+            .query_map([], Self::sensor_from_row)?;
 
         let mut sensor_vec = Vec::new();
 
         for sensor in sensor_itr {
-            sensor_vec.push(sensor.map_err(|e| e.to_string())?);
+            sensor_vec.push(sensor?);
         }
 
         Ok(sensor_vec)
@@ -239,44 +400,96 @@ impl Database for SqliteDatabase {
 
     // Returns a single row from Sensor where sensorID matches
     fn get_sensor(&self, sensor_id: i64) -> Result<Sensor> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT sensorID, type FROM Sensor WHERE sensorID = ?1")
-            .map_err(|e| e.to_string())?;
-
-        let sensor = statement
-            .query_row(params![sensor_id], |row| {
-                Ok(Sensor::new(row.get(0)?, row.get(1)?))
-            })
-            .map_err(|e| e.to_string())?;
-
-        Ok(sensor)
+        Self::query_sensor(&*self.pool.reader()?, sensor_id)
     }
 
+    // Updates the catalog values of a Sensor, values missing from updated_sensor are kept
     fn update_sensor(&self, sensor_id: i64, updated_sensor: &Sensor) -> Result<Sensor> {
-        let rows_updated = self
-            .connection
+        let mut sensor = updated_sensor.clone();
+        // read and written under the writer so a concurrent update cannot land in between
+        let connection = self.pool.writer()?;
+        sensor.fill_from(&Self::query_sensor(&connection, sensor_id)?);
+
+        connection
             .execute(
-                "UPDATE Sensor SET type = ?1 WHERE sensorID = ?2",
-                params![updated_sensor.get_sensor_type(), sensor_id],
-            )
-            .map_err(|e| e.to_string())?;
+                "UPDATE Sensor SET type = ?1, manufacturer = ?2, model = ?3, serial_number = ?4, firmware = ?5, \
+                channels = ?6, sample_rate = ?7 WHERE sensorID = ?8",
+                params![
+                    sensor.get_sensor_type(),
+                    sensor.get_manufacturer(),
+                    sensor.get_model(),
+                    sensor.get_serial_number(),
+                    sensor.get_firmware(),
+                    serde_json::to_string(sensor.get_channels())?,
+                    sensor.get_sample_rate(),
+                    sensor_id
+                ],
+            )?;
+
+        Self::query_sensor(&connection, sensor_id)
+    }
+
+    fn delete_sensor(&self, sensor_id: i64) -> Result<()> {
+        let rows_updated = self
+            .pool
+            .writer()?
+            .execute("DELETE FROM Sensor WHERE sensorID = ?1", params![sensor_id])?;
 
         if rows_updated == 0 {
-            return Err("Failed to update Sensor".into());
+            return Err(Error::not_found(format!("Sensor {sensor_id}")));
         }
 
-        Ok(updated_sensor.clone())
+        Ok(())
     }
 
-    fn delete_sensor(&self, sensor_id: i64) -> Result<()> {
+    /* Sensor Calibration */
+    fn insert_sensor_calibration(&self, calibration: &SensorCalibration) -> Result<SensorCalibration> {
+        let connection = self.pool.writer()?;
+        let sensor_id = *calibration.get_sensor_id();
+        Self::query_sensor(&connection, sensor_id)?;
+        let superseded = calibration.superseded(&Self::query_sensor_calibrations(&connection, sensor_id)?)?;
+
+        let transaction = connection.unchecked_transaction()?;
+        for ended in &superseded {
+            transaction.execute(
+                "UPDATE Sensor_Calibration SET valid_to = ?1 WHERE calibrationID = ?2",
+                params![ended.get_valid_to(), ended.get_id()],
+            )?;
+        }
+        transaction.execute(
+            "INSERT INTO Sensor_Calibration (sensorID, channel, coefficients, valid_from, valid_to, method, lookup_table) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                sensor_id,
+                calibration.get_channel(),
+                serde_json::to_string(calibration.get_coefficients())?,
+                calibration.get_valid_from(),
+                calibration.get_valid_to(),
+                calibration.get_method().as_str(),
+                serde_json::to_string(calibration.get_table())?
+            ],
+        )?;
+        let calibration = calibration.clone().with_ids(transaction.last_insert_rowid(), sensor_id);
+        transaction.commit()?;
+
+        Ok(calibration)
+    }
+
+    fn get_sensor_calibrations(&self, sensor_id: i64) -> Result<Vec<SensorCalibration>> {
+        Self::query_sensor_calibrations(&*self.pool.reader()?, sensor_id)
+    }
+
+    fn delete_sensor_calibration(&self, sensor_id: i64, calibration_id: i64) -> Result<()> {
         let rows_updated = self
-            .connection
-            .execute("DELETE FROM Sensor WHERE sensorID = ?1", params![sensor_id])
-            .map_err(|e| e.to_string())?;
+            .pool
+            .writer()?
+            .execute(
+                "DELETE FROM Sensor_Calibration WHERE sensorID = ?1 AND calibrationID = ?2",
+                params![sensor_id, calibration_id],
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to delete from Sensor".into());
+            return Err(Error::not_found(format!("Calibration {calibration_id} of sensor {sensor_id}")));
         }
 
         Ok(())
@@ -285,124 +498,145 @@ impl Database for SqliteDatabase {
     /* Session */
     // Inserts a single Session into Session
     fn insert_session(&self, session: &Session) -> Result<Session> {
-        self.connection
+        let connection = self.pool.writer()?;
+        connection
             .execute(
-                "INSERT INTO Session (username) VALUES (?1)",
-                params![session.get_username()],
-            )
-            .map_err(|e| e.to_string())?;
+                "INSERT INTO Session (username, name, description, tags) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    session.get_username(),
+                    session.get_name(),
+                    session.get_description(),
+                    serde_json::to_string(session.get_tags())?
+                ],
+            )?;
 
-        Ok(Session::new(
-            self.connection.last_insert_rowid(),
-            session.get_username().to_string(),
-        ))
+        Self::query_session(&connection, connection.last_insert_rowid())
     }
 
     // Returns a single row from Session where sessionID matches
     fn get_session(&self, session_id: i64) -> Result<Session> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT sessionID, username FROM Session WHERE sessionID = ?1")
-            .map_err(|e| e.to_string())?;
-
-        let session = statement
-            .query_row(params![session_id], |row| {
-                Ok(Session::new(row.get(0)?, row.get(1)?))
-            })
-            .map_err(|e| e.to_string())?;
-
-        Ok(session)
+        Self::query_session(&*self.pool.reader()?, session_id)
     }
 
     // Returns rows from Session where username matches
     fn get_user_sessions(&self, username: &str) -> Result<Vec<Session>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT sessionID, username FROM Session WHERE username = ?1")
-            .map_err(|e| e.to_string())?;
-
-        let session_itr = statement
-            .query_map(params![username], |row| {
-                Ok(Session::new(row.get(0)?, row.get(1)?))
-            })
-            .map_err(|e| e.to_string())?;
-
-        let mut session_vec = Vec::new();
-
-        for session in session_itr {
-            session_vec.push(session.map_err(|e| e.to_string())?);
-        }
-
-        Ok(session_vec)
+        Self::query_sessions(&*self.pool.reader()?, "WHERE username = ?1", params![username])
     }
 
     // Returns all rows from Session
     fn get_all_sessions(&self) -> Result<Vec<Session>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT sessionID, username FROM Session")
-            .map_err(|e| e.to_string())?;
-
-        let session_itr = statement
-            .query_map([], |row| Ok(Session::new(row.get(0)?, row.get(1)?)))
-            .map_err(|e| e.to_string())?;
-
-        let mut session_vec = Vec::new();
-
-        for session in session_itr {
-            session_vec.push(session.map_err(|e| e.to_string())?);
-        }
-
-        Ok(session_vec)
+        Self::query_sessions(&*self.pool.reader()?, "", [])
     }
 
+    // Updates the metadata of a Session, values missing from updated_session are kept
     fn update_session(&self, session_id: i64, updated_session: &Session) -> Result<Session> {
-        let rows_updated = self
-            .connection
+        let mut session = Session::new(session_id, updated_session.get_username().to_string())
+            .with_metadata(
+                updated_session.get_name().to_string(),
+                updated_session.get_description().to_string(),
+                updated_session.get_tags().clone(),
+            );
+        // read and written under the writer so a concurrent update cannot land in between
+        let connection = self.pool.writer()?;
+        session.fill_from(&Self::query_session(&connection, session_id)?);
+
+        let rows_updated = connection
             .execute(
-                "UPDATE Session SET username = ?1 WHERE sessionID = ?2",
-                params![updated_session.get_username(), session_id],
-            )
-            .map_err(|e| e.to_string())?;
+                "UPDATE Session SET username = ?1, name = ?2, description = ?3, tags = ?4 WHERE sessionID = ?5",
+                params![
+                    session.get_username(),
+                    session.get_name(),
+                    session.get_description(),
+                    serde_json::to_string(session.get_tags())?,
+                    session_id
+                ],
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to update Session".into());
+            return Err(Error::not_found(format!("Session {session_id}")));
         }
 
-        Ok(updated_session.clone())
+        Ok(session)
     }
 
     fn delete_session(&self, session_id: i64) -> Result<()> {
         let rows_updated = self
-            .connection
+            .pool
+            .writer()?
             .execute(
                 "DELETE FROM Session WHERE sessionID = ?1",
                 params![session_id],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to delete from Session".into());
+            return Err(Error::not_found(format!("Session {session_id}")));
         }
 
         Ok(())
     }
 
+    // Moves a Session through its lifecycle, a sensor can only record to one session at a time
+    fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session> {
+        let connection = self.pool.writer()?;
+        let mut session = Self::query_session(&connection, session_id)?;
+        session.transition(status)?;
+
+        if status == SessionStatus::Recording {
+            let conflicts: i64 = connection
+                .query_row(
+                    "SELECT COUNT(*) FROM Session_Sensor WHERE sessionID != ?1 \
+                    AND sensorID IN (SELECT sensorID FROM Session_Sensor WHERE sessionID = ?1) \
+                    AND sessionID IN (SELECT sessionID FROM Session WHERE status = 'recording')",
+                    params![session_id],
+                    |row| row.get(0),
+                )?;
+            if conflicts > 0 {
+                return Err(Error::Conflict(format!(
+                    "Session {session_id} shares sensors with a session that is already recording"
+                )));
+            }
+        }
+
+        connection
+            .execute(
+                "UPDATE Session SET status = ?1, start_time = ?2, end_time = ?3 WHERE sessionID = ?4",
+                params![
+                    session.get_status().as_str(),
+                    session.get_start_time(),
+                    session.get_end_time(),
+                    session_id
+                ],
+            )?;
+
+        Ok(session)
+    }
+
+    // Returns the recording Session the sensor is attached to
+    fn get_active_session(&self, sensor_id: i64) -> Result<Session> {
+        Self::query_sessions(
+            &*self.pool.reader()?,
+            "WHERE status = 'recording' AND sessionID IN (SELECT sessionID FROM Session_Sensor WHERE sensorID = ?1) ORDER BY start_time DESC LIMIT 1",
+            params![sensor_id],
+        )?
+        .pop()
+        .ok_or_else(|| Error::not_found(format!("Active session for sensor {sensor_id}")))
+    }
+
     /* Session Sensor */
     // Inserts a single SessionSensor into Session_Sensor
     fn insert_session_sensor(&self, session_sensor: &SessionSensor) -> Result<SessionSensor> {
-        self.connection
+        let connection = self.pool.writer()?;
+        connection
             .execute(
                 "INSERT INTO Session_Sensor (sessionID, sensorID) VALUES (?1, ?2)",
                 params![
                     session_sensor.get_session_id(),
                     session_sensor.get_sensor_id()
                 ],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         Ok(SessionSensor::new(
-            self.connection.last_insert_rowid(),
+            connection.last_insert_rowid(),
             *session_sensor.get_session_id(),
             *session_sensor.get_sensor_id(),
         ))
@@ -410,21 +644,19 @@ impl Database for SqliteDatabase {
 
     // Returns all rows from Session_Sensor
     fn get_sessions_sensors(&self) -> Result<Vec<SessionSensor>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor")
-            .map_err(|e| e.to_string())?;
+        let connection = self.pool.reader()?;
+        let mut statement = connection
+            .prepare("SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor")?;
 
         let session_sensor_itr = statement
             .query_map([], |row| {
                 Ok(SessionSensor::new(row.get(0)?, row.get(1)?, row.get(2)?))
-            })
-            .map_err(|e| e.to_string())?;
+            })?;
 
         let mut session_sensor_vec = Vec::new();
 
         for session_sensor in session_sensor_itr {
-            session_sensor_vec.push(session_sensor.map_err(|e| e.to_string())?);
+            session_sensor_vec.push(session_sensor?);
         }
 
         Ok(session_sensor_vec)
@@ -432,22 +664,21 @@ impl Database for SqliteDatabase {
 
     // Returns rows from Session_Sensor where sessionID matches
     fn get_session_sensors(&self, session_id: i64) -> Result<Vec<SessionSensor>> {
-        let mut statement = self
-            .connection
+        let connection = self.pool.reader()?;
+        let mut statement = connection
             .prepare(
                 "SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor WHERE sessionID = ?1"
-            ).map_err(|e| e.to_string())?;
+            )?;
 
         let session_sensor_itr = statement
             .query_map(params![session_id], |row| {
                 Ok(SessionSensor::new(row.get(0)?, row.get(1)?, row.get(2)?))
-            })
-            .map_err(|e| e.to_string())?;
+            })?;
 
         let mut session_sensor_vec = Vec::new();
 
         for session_sensor in session_sensor_itr {
-            session_sensor_vec.push(session_sensor.map_err(|e| e.to_string())?);
+            session_sensor_vec.push(session_sensor?);
         }
 
         Ok(session_sensor_vec)
@@ -455,17 +686,18 @@ impl Database for SqliteDatabase {
 
     // Returns a single row from Session_Sensor where session_sensorID matches
     fn get_session_sensor(&self, session_sensor_id: i64) -> Result<SessionSensor> {
-        let mut statement = self
-            .connection
+        let connection = self.pool.reader()?;
+        let mut statement = connection
             .prepare(
                 "SELECT session_sensorID, sessionID, sensorID FROM Session_Sensor WHERE session_sensorID = ?1"
-            ).map_err(|e| e.to_string())?;
+            )?;
 
         let session_sensor = statement
             .query_row(params![session_sensor_id], |row| {
                 Ok(SessionSensor::new(row.get(0)?, row.get(1)?, row.get(2)?))
             })
-            .map_err(|e| e.to_string())?;
+            .optional()?
+            .ok_or_else(|| Error::not_found(format!("Session sensor {session_sensor_id}")))?;
 
         Ok(session_sensor)
     }
@@ -475,17 +707,18 @@ impl Database for SqliteDatabase {
         session_sensor_id: i64,
         updated_session_sensor: &SessionSensor,
     ) -> Result<SessionSensor> {
-        let rows_updated = self.connection
+        let rows_updated = self
+            .pool
+            .writer()?
             .execute(
                 "UPDATE Session_Sensor SET sessionID = ?1, sensorID = ?2 WHERE session_sensorID = ?3",
                 params![updated_session_sensor.get_session_id(),
                     updated_session_sensor.get_sensor_id(),
                     session_sensor_id],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to update Session_Sensor".into());
+            return Err(Error::not_found(format!("Session sensor {session_sensor_id}")));
         }
 
         Ok(updated_session_sensor.clone())
@@ -493,15 +726,15 @@ impl Database for SqliteDatabase {
 
     fn delete_session_sensor(&self, session_sensor_id: i64) -> Result<()> {
         let rows_updated = self
-            .connection
+            .pool
+            .writer()?
             .execute(
                 "DELETE FROM Session_Sensor WHERE session_sensorID = ?1",
                 params![session_sensor_id],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to delete from Session_Sensor".into());
+            return Err(Error::not_found(format!("Session sensor {session_sensor_id}")));
         }
 
         Ok(())
@@ -513,11 +746,12 @@ impl Database for SqliteDatabase {
         &self,
         session_sensor_data: &SessionSensorData,
     ) -> Result<SessionSensorData> {
-        self.connection
+        self.pool
+            .writer()?
             .execute(
-                "INSERT INTO Session_Sensor_Data (sessionID, datetime, data_blob) VALUES (?1, ?2, ?3)",
-                params![session_sensor_data.get_id(), session_sensor_data.get_datetime(), session_sensor_data.get_blob().to_string()]
-            ).map_err(|e| e.to_string())?;
+                "INSERT INTO Session_Sensor_Data (sessionID, session_sensorID, datetime, data_blob) VALUES (?1, ?2, ?3, ?4)",
+                params![session_sensor_data.get_id(), session_sensor_data.get_session_sensor_id(), session_sensor_data.get_datetime(), session_sensor_data.get_blob().to_string()]
+            )?;
 
         Ok(session_sensor_data.clone())
     }
@@ -525,19 +759,22 @@ impl Database for SqliteDatabase {
     // Batch inserts data points
     fn batch_session_sensor_data(
         &self,
-        data_blobs: &Vec<SessionSensorData>,
+        data_blobs: &[SessionSensorData],
     ) -> Result<Vec<SessionSensorData>> {
         if data_blobs.is_empty() {
             return Ok(vec![]);
         }
 
+        // one transaction so a batch commits, and syncs, once
+        let connection = self.pool.writer()?;
+        let transaction = connection.unchecked_transaction()?;
         for chunk in data_blobs.chunks(500) {
             let sql = format!(
-                "INSERT INTO Session_Sensor_Data (sessionID, datetime, data_blob) VALUES {}",
+                "INSERT INTO Session_Sensor_Data (sessionID, session_sensorID, datetime, data_blob) VALUES {}",
                 chunk
                     .iter()
                     .enumerate()
-                    .map(|(i, _)| format!("(?{}, ?{}, ?{})", 3 * i + 1, 3 * i + 2, 3 * i + 3))
+                    .map(|(i, _)| format!("(?{}, ?{}, ?{}, ?{})", 4 * i + 1, 4 * i + 2, 4 * i + 3, 4 * i + 4))
                     .collect::<Vec<_>>()
                     .join(", ")
             );
@@ -547,76 +784,61 @@ impl Database for SqliteDatabase {
                 .flat_map(|data| {
                     [
                         Box::new(data.get_id().unwrap_or(-1)) as Box<dyn rusqlite::ToSql>,
+                        Box::new(*data.get_session_sensor_id()),
                         Box::new(data.get_datetime()),
                         Box::new(data.get_blob().to_string()),
                     ]
                 })
                 .collect::<Vec<Box<dyn rusqlite::ToSql>>>();
 
-            self.connection
-                .execute(&sql, params_from_iter(params))
-                .map_err(|e| e.to_string())?;
+            transaction.execute(&sql, params_from_iter(params))?;
         }
+        transaction.commit()?;
 
-        Ok(data_blobs.clone())
+        Ok(data_blobs.to_vec())
     }
 
-    // Returns all rows from Session_Sensor_Data
-    fn get_sessions_sensors_data(&self) -> Result<Vec<SessionSensorData>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT sessionID, datetime, data_blob FROM Session_Sensor_Data")
-            .map_err(|e| e.to_string())?;
-
-        let session_sensor_data_itr = statement
-            .query_map([], |row| {
-                let id: i64 = row.get(0)?;
-                let datetime: String = row.get(1)?;
-                let data_blob: String = row.get(2)?;
-                Ok(SessionSensorData::new(
-                    Some(id),
-                    datetime,
-                    serde_json::from_str(&data_blob).unwrap_or_default(),
-                ))
-            })
-            .map_err(|e| e.to_string())?;
+    // Rows are inserted one at a time so the ones skipped by OR IGNORE are known, the statement is prepared once
+    fn import_session_sensor_data(
+        &self,
+        data_blobs: &[SessionSensorData],
+    ) -> Result<Vec<SessionSensorData>> {
+        if data_blobs.is_empty() {
+            return Ok(vec![]);
+        }
 
-        let mut session_sensor_data_vec = Vec::new();
-        for session_sensor_data in session_sensor_data_itr {
-            session_sensor_data_vec.push(session_sensor_data.map_err(|e| e.to_string())?);
+        let connection = self.pool.writer()?;
+        let transaction = connection.unchecked_transaction()?;
+        let mut inserted = Vec::new();
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT OR IGNORE INTO Session_Sensor_Data (sessionID, session_sensorID, datetime, data_blob) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for data in data_blobs {
+                let changed = statement.execute(params![
+                    data.get_id(),
+                    data.get_session_sensor_id(),
+                    data.get_datetime(),
+                    data.get_blob().to_string()
+                ])?;
+                if changed > 0 {
+                    inserted.push(data.clone());
+                }
+            }
         }
+        transaction.commit()?;
 
-        Ok(session_sensor_data_vec)
+        Ok(inserted)
+    }
+
+    // Returns all rows from Session_Sensor_Data
+    fn get_sessions_sensors_data(&self) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(&*self.pool.reader()?, "", [])
     }
 
     // Returns all rows from Session_Sensor_Data where sessionID matches
     fn get_sessions_sensor_data(&self, session_id: i64) -> Result<Vec<SessionSensorData>> {
-        let mut statement = self
-            .connection
-            .prepare(
-                "SELECT sessionID, datetime, data_blob FROM Session_Sensor_Data WHERE sessionID = ?1",
-            )
-            .map_err(|e| e.to_string())?;
-
-        let session_sensor_data_itr = statement
-            .query_map(params![session_id], |row| {
-                let id: i64 = row.get(0)?;
-                let datetime: String = row.get(1)?;
-                let data_blob: String = row.get(2)?;
-                Ok(SessionSensorData::new(
-                    Some(id),
-                    datetime,
-                    serde_json::from_str(&data_blob).unwrap_or_default(),
-                ))
-            })
-            .map_err(|e| e.to_string())?;
-
-        let mut session_sensor_data_vec = Vec::new();
-        for session_sensor_data in session_sensor_data_itr {
-            session_sensor_data_vec.push(session_sensor_data.map_err(|e| e.to_string())?);
-        }
-
-        Ok(session_sensor_data_vec)
+        Self::query_session_sensor_data(&*self.pool.reader()?, "WHERE sessionID = ?1", params![session_id])
     }
 
     // Returns all rows from Session_Sensor_Data where sessionID matches and datetime is after the passed value
@@ -625,33 +847,10 @@ impl Database for SqliteDatabase {
         session_id: i64,
         datetime: &str,
     ) -> Result<Vec<SessionSensorData>> {
-        let mut statement = self
-            .connection
-            .prepare(
-                "SELECT sessionID, datetime, data_blob FROM Session_Sensor_Data WHERE sessionID = ?1",
-            )
-            .map_err(|e| e.to_string())?;
-
-        let session_sensor_data_itr = statement
-            .query_map(params![session_id], |row| {
-                let id: i64 = row.get(0)?;
-                let datetime: String = row.get(1)?;
-                let data_blob: String = row.get(2)?;
-                Ok(SessionSensorData::new(
-                    Some(id),
-                    datetime,
-                    serde_json::from_str(&data_blob).unwrap_or_default(),
-                ))
-            })
-            .map_err(|e| e.to_string())?;
-
-        let filter_time = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f")
-            .map_err(|e| e.to_string())?;
+        let filter_time = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f")?;
         let mut session_sensor_data_vec = Vec::new();
-        for session_sensor_data in session_sensor_data_itr {
-            let data = session_sensor_data.map_err(|e| e.to_string())?;
-            if NaiveDateTime::parse_from_str(data.get_datetime(), "%Y-%m-%dT%H:%M:%S%.f")
-                .map_err(|e| e.to_string())?
+        for data in self.get_sessions_sensor_data(session_id)? {
+            if NaiveDateTime::parse_from_str(data.get_datetime(), "%Y-%m-%dT%H:%M:%S%.f")?
                 > filter_time
             {
                 session_sensor_data_vec.push(data);
@@ -661,99 +860,505 @@ impl Database for SqliteDatabase {
         Ok(session_sensor_data_vec)
     }
 
-    // Returns all rows from Session_Sensor_Data where session_id from Session_Sensor matches session_sensorID
-    fn get_session_sensor_data(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorData>> {
-        let mut statement = self
-            .connection
-            .prepare(
-                "SELECT sessionID, datetime, data_blob FROM Session_Sensor_Data WHERE sessionID = (SELECT sessionID FROM Session_Sensor WHERE session_sensorID = ?1)",
-            )
-            .map_err(|e| e.to_string())?;
-
-        let session_sensor_data_itr = statement
-            .query_map(params![session_sensor_id], |row| {
-                let id: i64 = row.get(0)?;
-                let datetime: String = row.get(1)?;
-                let data_blob: String = row.get(2)?;
-                Ok(SessionSensorData::new(
-                    Some(id),
-                    datetime,
-                    serde_json::from_str(&data_blob).unwrap_or_default(),
-                ))
-            })
-            .map_err(|e| e.to_string())?;
-
-        let mut session_sensor_data_vec = Vec::new();
-        for session_sensor_data in session_sensor_data_itr {
-            session_sensor_data_vec.push(session_sensor_data.map_err(|e| e.to_string())?);
-        }
+    // Datetimes are compared as text, the same instant written with a different number of fractional digits is not equal
+    fn get_sessions_sensor_data_page(
+        &self,
+        session_id: i64,
+        from: Option<&str>,
+        to: Option<&str>,
+        after: Option<&SessionSensorData>,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &*self.pool.reader()?,
+            "WHERE sessionID = ?1 AND (?2 IS NULL OR datetime >= ?2) AND (?3 IS NULL OR datetime < ?3)
+                AND (?4 IS NULL OR datetime > ?4 OR (datetime = ?4 AND COALESCE(session_sensorID, 0) > ?5))
+                ORDER BY datetime, COALESCE(session_sensorID, 0) LIMIT ?6",
+            params![
+                session_id,
+                from,
+                to,
+                after.map(|data| data.get_datetime()),
+                after.and_then(|data| *data.get_session_sensor_id()).unwrap_or(0),
+                limit as i64
+            ],
+        )
+    }
 
-        Ok(session_sensor_data_vec)
+    // Returns all rows from Session_Sensor_Data recorded by a single sensor of a session
+    fn get_session_sensor_data(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &*self.pool.reader()?,
+            "WHERE session_sensorID = ?1",
+            params![session_sensor_id],
+        )
     }
 
-    // Returns a single datapoint that matches a session_id and datetime
+    // Returns a single datapoint that matches a session_sensor_id and datetime
     fn get_session_sensor_datapoint(
         &self,
-        session_id: i64,
+        session_sensor_id: i64,
         datetime: &str,
     ) -> Result<SessionSensorData> {
-        let mut statement = self
-            .connection
-            .prepare(
-                "SELECT sessionID, datetime, data_blob FROM Session_Sensor_Data 
-             WHERE sessionID = ?1
-             AND datetime = ?2",
-            )
-            .map_err(|e| e.to_string())?;
-
-        let session_sensor_datapoint = statement
-            .query_row(params![session_id, datetime], |row| {
-                let id: i64 = row.get(0)?;
-                let datetime: String = row.get(1)?;
-                let data_blob: String = row.get(2)?;
-
-                Ok(SessionSensorData::new(
-                    Some(id),
-                    datetime,
-                    serde_json::from_str(&data_blob).unwrap_or_default(),
-                ))
-            })
-            .map_err(|e| e.to_string())?;
-
-        Ok(session_sensor_datapoint)
+        Self::query_datapoint(&*self.pool.reader()?, session_sensor_id, datetime)
     }
 
     fn update_session_sensor_datapoint(
         &self,
-        session_id: i64,
+        session_sensor_id: i64,
         datetime: &str,
         updated_session_sensor_datapoint: &SessionSensorData,
     ) -> Result<SessionSensorData> {
-        let rows_updated = self.connection
+        let connection = self.pool.writer()?;
+        let mut session_sensor_datapoint = updated_session_sensor_datapoint.clone();
+        session_sensor_datapoint.fill_from(&Self::query_datapoint(&connection, session_sensor_id, datetime)?);
+
+        let rows_updated = connection
             .execute(
-                "UPDATE Session_Sensor_Data SET sessionID = ?1, datetime = ?2, data_blob = ?3 WHERE datetime = ?4 AND sessionID = ?5",
-                params![updated_session_sensor_datapoint.get_id().unwrap_or_default(), updated_session_sensor_datapoint.get_datetime(), updated_session_sensor_datapoint.get_blob().to_string(), datetime, session_id],
-            )
-            .map_err(|e| e.to_string())?;
+                "UPDATE Session_Sensor_Data SET datetime = ?1, data_blob = ?2 WHERE session_sensorID = ?3 AND datetime = ?4",
+                params![session_sensor_datapoint.get_datetime(), session_sensor_datapoint.get_blob().to_string(), session_sensor_id, datetime],
+            )?;
 
         if rows_updated == 0 {
-            return Err("Failed to update Session_Sensor_Data".into());
+            return Err(Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")));
         }
 
-        Ok(updated_session_sensor_datapoint.clone())
+        Self::query_datapoint(&connection, session_sensor_id, session_sensor_datapoint.get_datetime())
     }
 
-    fn delete_session_sensor_datapoint(&self, session_id: i64, datetime: &str) -> Result<()> {
+    fn delete_session_sensor_datapoint(&self, session_sensor_id: i64, datetime: &str) -> Result<()> {
         let rows_updated = self
-            .connection
+            .pool
+            .writer()?
             .execute(
-                "DELETE FROM Session_Sensor_Data WHERE sessionID = ?1 AND datetime = ?2",
-                params![session_id, datetime],
+                "DELETE FROM Session_Sensor_Data WHERE session_sensorID = ?1 AND datetime = ?2",
+                params![session_sensor_id, datetime],
+            )?;
+
+        if rows_updated == 0 {
+            return Err(Error::not_found(format!("Datapoint of session sensor {session_sensor_id} at {datetime}")));
+        }
+
+        Ok(())
+    }
+
+    /* Retention */
+    // Datetimes are stored as ISO 8601 text, so they compare in order as text
+    fn count_session_sensor_data_before(&self, session_sensor_id: i64, datetime: &str) -> Result<usize> {
+        let count: i64 = self.pool.reader()?.query_row(
+            "SELECT COUNT(*) FROM Session_Sensor_Data WHERE session_sensorID = ?1 AND datetime < ?2",
+            params![session_sensor_id, datetime],
+            |row| row.get(0),
+        )?;
+
+        Ok(count as usize)
+    }
+
+    fn get_session_sensor_data_before(
+        &self,
+        session_sensor_id: i64,
+        datetime: &str,
+        limit: usize,
+    ) -> Result<Vec<SessionSensorData>> {
+        Self::query_session_sensor_data(
+            &*self.pool.reader()?,
+            "WHERE session_sensorID = ?1 AND datetime < ?2 ORDER BY datetime LIMIT ?3",
+            params![session_sensor_id, datetime, limit as i64],
+        )
+    }
+
+    fn prune_session_sensor_data(
+        &self,
+        session_sensor_id: i64,
+        datetimes: &[String],
+        rollups: &[SessionSensorRollup],
+    ) -> Result<usize> {
+        let connection = self.pool.writer()?;
+        let transaction = connection.unchecked_transaction()?;
+        for rollup in rollups {
+            transaction.execute(
+                "INSERT OR REPLACE INTO Session_Sensor_Rollup (session_sensorID, interval_seconds, bucket, count, data_blob) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![rollup.get_session_sensor_id(), rollup.get_interval_seconds(), rollup.get_bucket(), rollup.get_count(), rollup.get_blob().to_string()],
+            )?;
+        }
+        let mut rows_deleted = 0;
+        for datetime in datetimes {
+            rows_deleted += transaction.execute(
+                "DELETE FROM Session_Sensor_Data WHERE session_sensorID = ?1 AND datetime = ?2",
+                params![session_sensor_id, datetime],
+            )?;
+        }
+        transaction.commit()?;
+
+        Ok(rows_deleted)
+    }
+
+    fn get_session_sensor_rollups(&self, session_sensor_id: i64) -> Result<Vec<SessionSensorRollup>> {
+        let connection = self.pool.reader()?;
+        let mut statement = connection.prepare(
+            "SELECT session_sensorID, bucket, interval_seconds, count, data_blob FROM Session_Sensor_Rollup WHERE session_sensorID = ?1 ORDER BY interval_seconds, bucket",
+        )?;
+        let rollups = statement
+            .query_map(params![session_sensor_id], |row| {
+                let data_blob: String = row.get(4)?;
+                Ok(SessionSensorRollup::new(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    serde_json::from_str(&data_blob).unwrap_or_default(),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rollups)
+    }
+
+    /* Session Summary */
+    // Aggregated by SQLite, so the blobs of a session are never read into the server
+    fn get_session_sensor_summaries(&self, session_id: i64) -> Result<Vec<SensorSummary>> {
+        let connection = self.pool.reader()?;
+        let mut statement = connection.prepare(
+            "SELECT session_sensorID, COUNT(*), MIN(datetime), MAX(datetime) FROM Session_Sensor_Data
+                WHERE sessionID = ?1 GROUP BY session_sensorID ORDER BY session_sensorID NULLS FIRST",
+        )?;
+        let mut summaries = statement
+            .query_map(params![session_id], |row| {
+                Ok(SensorSummary {
+                    session_sensor_id: row.get(0)?,
+                    count: row.get(1)?,
+                    first: row.get(2)?,
+                    last: row.get(3)?,
+                    ..Default::default()
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // SQLite has no standard deviation, so the squared distances from the mean are summed in a second pass
+        let mut statement = connection.prepare(
+            "WITH field AS (
+                SELECT data.session_sensorID AS session_sensorID, member.key AS name, member.value AS value
+                    FROM Session_Sensor_Data AS data, json_each(data.data_blob) AS member
+                    WHERE data.sessionID = ?1 AND json_type(data.data_blob) = 'object' AND member.type IN ('integer', 'real')
+            ), mean AS (
+                SELECT session_sensorID, name, AVG(value) AS mean FROM field GROUP BY session_sensorID, name
+            )
+            SELECT field.session_sensorID, field.name, COUNT(*), MIN(field.value), MAX(field.value), mean.mean,
+                SUM((field.value - mean.mean) * (field.value - mean.mean))
+                FROM field JOIN mean ON field.session_sensorID IS mean.session_sensorID AND field.name = mean.name
+                GROUP BY field.session_sensorID, field.name",
+        )?;
+        let fields = statement
+            .query_map(params![session_id], |row| {
+                let count: i64 = row.get(2)?;
+                let squares: f64 = row.get(6)?;
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, String>(1)?,
+                    FieldSummary {
+                        count,
+                        min: row.get(3)?,
+                        max: row.get(4)?,
+                        mean: row.get(5)?,
+                        stddev: if count > 1 { (squares / (count - 1) as f64).sqrt() } else { 0.0 },
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (session_sensor_id, name, field) in fields {
+            if let Some(summary) = summaries
+                .iter_mut()
+                .find(|summary| summary.session_sensor_id == session_sensor_id)
+            {
+                summary.fields.insert(name, field);
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    fn get_session_sensor_gaps(&self, session_id: i64, gap_seconds: f64) -> Result<Vec<SummaryGap>> {
+        let connection = self.pool.reader()?;
+        let mut statement = connection.prepare(
+            "SELECT session_sensorID, previous, datetime, seconds FROM (
+                SELECT session_sensorID, datetime, LAG(datetime) OVER sensor AS previous,
+                    (julianday(datetime) - julianday(LAG(datetime) OVER sensor)) * 86400.0 AS seconds
+                    FROM Session_Sensor_Data WHERE sessionID = ?1
+                    WINDOW sensor AS (PARTITION BY session_sensorID ORDER BY datetime)
+            ) WHERE seconds > ?2 ORDER BY session_sensorID NULLS FIRST, datetime",
+        )?;
+        let gaps = statement
+            .query_map(params![session_id, gap_seconds], |row| {
+                let from: String = row.get(1)?;
+                let to: String = row.get(2)?;
+                // julianday keeps about a millisecond, the datetimes themselves are exact
+                let seconds = SessionSummary::seconds_between(&from, &to).unwrap_or(row.get(3)?);
+                Ok(SummaryGap {
+                    session_sensor_id: row.get(0)?,
+                    from,
+                    to,
+                    seconds,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(gaps)
+    }
+
+    fn get_cached_session_summary(&self, session_id: i64, gap_seconds: f64) -> Result<Option<SessionSummary>> {
+        let summary: Option<String> = self
+            .pool
+            .reader()?
+            .query_row(
+                "SELECT summary FROM Session_Summary WHERE sessionID = ?1 AND gap_seconds = ?2",
+                params![session_id, gap_seconds],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(summary.map(|summary| serde_json::from_str(&summary)).transpose()?)
+    }
+
+    fn cache_session_summary(&self, summary: &SessionSummary) -> Result<()> {
+        self.pool.writer()?.execute(
+            "INSERT INTO Session_Summary (sessionID, gap_seconds, summary) VALUES (?1, ?2, ?3)
+                ON CONFLICT (sessionID) DO UPDATE SET gap_seconds = excluded.gap_seconds, summary = excluded.summary",
+            params![summary.get_session_id(), summary.get_gap_seconds(), serde_json::to_string(summary)?],
+        )?;
+
+        Ok(())
+    }
+
+    /* Sensor Schema */
+    fn upsert_sensor_schema(&self, schema: &SensorSchema) -> Result<SensorSchema> {
+        self.pool.writer()?.execute(
+            "INSERT INTO Sensor_Schema (type, data_schema, policy) VALUES (?1, ?2, ?3)
+                ON CONFLICT (type) DO UPDATE SET data_schema = excluded.data_schema, policy = excluded.policy",
+            params![schema.get_sensor_type(), schema.get_schema().to_string(), schema.get_policy().as_str()],
+        )?;
+
+        Ok(schema.clone())
+    }
+
+    fn get_sensor_schemas(&self) -> Result<Vec<SensorSchema>> {
+        let connection = self.pool.reader()?;
+        let mut statement = connection.prepare("SELECT type, data_schema, policy FROM Sensor_Schema ORDER BY type")?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<(String, String, String)>>>()?;
+
+        rows.into_iter()
+            .map(|(sensor_type, schema, policy)| Self::sensor_schema_from_columns(sensor_type, &schema, &policy))
+            .collect()
+    }
+
+    fn get_sensor_schema(&self, sensor_type: &str) -> Result<SensorSchema> {
+        let (sensor_type, schema, policy): (String, String, String) = self
+            .pool
+            .reader()?
+            .query_row(
+                "SELECT type, data_schema, policy FROM Sensor_Schema WHERE type = ?1",
+                params![sensor_type],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
             )
-            .map_err(|e| e.to_string())?;
+            .optional()?
+            .ok_or_else(|| Error::not_found(format!("Schema of sensor type {sensor_type}")))?;
+
+        Self::sensor_schema_from_columns(sensor_type, &schema, &policy)
+    }
+
+    fn delete_sensor_schema(&self, sensor_type: &str) -> Result<()> {
+        let rows_deleted = self
+            .pool
+            .writer()?
+            .execute("DELETE FROM Sensor_Schema WHERE type = ?1", params![sensor_type])?;
+
+        if rows_deleted == 0 {
+            return Err(Error::not_found(format!("Schema of sensor type {sensor_type}")));
+        }
+
+        Ok(())
+    }
+
+    fn quarantine_session_sensor_data(&self, quarantined: &[QuarantinedSensorData]) -> Result<()> {
+        let connection = self.pool.writer()?;
+        let transaction = connection.unchecked_transaction()?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT INTO Quarantined_Sensor_Data (sessionID, session_sensorID, datetime, data_blob, errors, quarantined_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for datapoint in quarantined {
+                statement.execute(params![
+                    datapoint.get_session_id(),
+                    datapoint.get_session_sensor_id(),
+                    datapoint.get_datetime(),
+                    datapoint.get_blob().to_string(),
+                    serde_json::to_string(datapoint.get_errors())?,
+                    datapoint.get_quarantined_at(),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn get_quarantined_sensor_data(&self, session_id: i64) -> Result<Vec<QuarantinedSensorData>> {
+        let connection = self.pool.reader()?;
+        let mut statement = connection.prepare(
+            "SELECT sessionID, session_sensorID, datetime, data_blob, errors, quarantined_at FROM Quarantined_Sensor_Data
+                WHERE sessionID = ?1 ORDER BY quarantined_at, rowid",
+        )?;
+        let quarantined = statement
+            .query_map(params![session_id], |row| {
+                let data_blob: String = row.get(3)?;
+                let errors: String = row.get(4)?;
+                Ok(QuarantinedSensorData::new(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    serde_json::from_str(&data_blob).unwrap_or_default(),
+                    serde_json::from_str(&errors).unwrap_or_default(),
+                    row.get(5)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(quarantined)
+    }
+
+    fn delete_quarantined_sensor_data(&self, session_id: i64) -> Result<usize> {
+        Ok(self
+            .pool
+            .writer()?
+            .execute("DELETE FROM Quarantined_Sensor_Data WHERE sessionID = ?1", params![session_id])?)
+    }
+
+    /* Alerts */
+    fn insert_alert_rule(&self, rule: &AlertRule) -> Result<AlertRule> {
+        let connection = self.pool.writer()?;
+        if let Some(sensor_id) = rule.get_sensor_id() {
+            Self::query_sensor(&connection, sensor_id)?;
+        }
+        if let Some(session_id) = rule.get_session_id() {
+            Self::query_session(&connection, session_id)?;
+        }
+        connection.execute(
+            "INSERT INTO Alert_Rule (sensorID, sessionID, name, field, comparator, threshold, duration_seconds, hysteresis) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                rule.get_sensor_id(),
+                rule.get_session_id(),
+                rule.get_name(),
+                rule.get_field(),
+                rule.get_comparator().as_str(),
+                rule.get_threshold(),
+                rule.get_duration_seconds(),
+                rule.get_hysteresis()
+            ],
+        )?;
+
+        Ok(rule.clone().with_id(connection.last_insert_rowid()))
+    }
+
+    fn get_alert_rules(&self) -> Result<Vec<AlertRule>> {
+        let connection = self.pool.reader()?;
+        let mut statement =
+            connection.prepare(&format!("SELECT {ALERT_RULE_COLUMNS} FROM Alert_Rule ORDER BY alertRuleID"))?;
+        let rules = statement
+            .query_map([], Self::alert_rule_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rules)
+    }
+
+    fn delete_alert_rule(&self, rule_id: i64) -> Result<()> {
+        let rows_deleted = self
+            .pool
+            .writer()?
+            .execute("DELETE FROM Alert_Rule WHERE alertRuleID = ?1", params![rule_id])?;
+
+        if rows_deleted == 0 {
+            return Err(Error::not_found(format!("Alert rule {rule_id}")));
+        }
+
+        Ok(())
+    }
+
+    fn insert_alert_event(&self, event: &AlertEvent) -> Result<AlertEvent> {
+        let connection = self.pool.writer()?;
+        connection.execute(
+            "INSERT INTO Alert_Event (alertRuleID, sessionID, session_sensorID, state, value, datetime, delivery, attempts) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                event.get_rule_id(),
+                event.get_session_id(),
+                event.get_session_sensor_id(),
+                event.get_state().as_str(),
+                event.get_value(),
+                event.get_datetime(),
+                event.get_delivery().as_str(),
+                event.get_attempts()
+            ],
+        )?;
+
+        Ok(event.clone().with_id(connection.last_insert_rowid()))
+    }
+
+    fn get_alert_events(&self, session_id: i64) -> Result<Vec<AlertEvent>> {
+        let connection = self.pool.reader()?;
+        let mut statement = connection.prepare(&format!(
+            "SELECT {ALERT_EVENT_COLUMNS} FROM Alert_Event WHERE sessionID = ?1 ORDER BY alertEventID"
+        ))?;
+        let events = statement
+            .query_map(params![session_id], Self::alert_event_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(events)
+    }
+
+    fn update_alert_event_delivery(&self, event_id: i64, delivery: AlertDelivery, attempts: i64) -> Result<()> {
+        let rows_updated = self.pool.writer()?.execute(
+            "UPDATE Alert_Event SET delivery = ?1, attempts = ?2 WHERE alertEventID = ?3",
+            params![delivery.as_str(), attempts, event_id],
+        )?;
 
         if rows_updated == 0 {
-            return Err("Failed to delete from Session_Sensor_Data".into());
+            return Err(Error::not_found(format!("Alert event {event_id}")));
+        }
+
+        Ok(())
+    }
+
+    /* Derived Channels */
+    fn insert_derived_channel(&self, channel: &DerivedChannel) -> Result<DerivedChannel> {
+        self.pool.writer()?.execute(
+            "INSERT INTO Derived_Channel (type, name, expression, unit) VALUES (?1, ?2, ?3, ?4)",
+            params![channel.get_sensor_type(), channel.get_name(), channel.get_expression(), channel.get_unit()],
+        )?;
+
+        Ok(channel.clone())
+    }
+
+    fn get_derived_channels(&self) -> Result<Vec<DerivedChannel>> {
+        let connection = self.pool.reader()?;
+        let mut statement =
+            connection.prepare("SELECT type, name, expression, unit FROM Derived_Channel ORDER BY type, name")?;
+        let channels = statement
+            .query_map([], |row| Ok(DerivedChannel::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(channels)
+    }
+
+    fn delete_derived_channel(&self, sensor_type: &str, name: &str) -> Result<()> {
+        let rows_deleted = self.pool.writer()?.execute(
+            "DELETE FROM Derived_Channel WHERE type = ?1 AND name = ?2",
+            params![sensor_type, name],
+        )?;
+
+        if rows_deleted == 0 {
+            return Err(Error::not_found(format!("Derived channel {name} of sensor type {sensor_type}")));
         }
 
         Ok(())
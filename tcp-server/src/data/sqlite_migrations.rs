@@ -0,0 +1,435 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::migrations::{
+    applied_at_now, migration_status, pending_migrations, AppliedMigration, Migration, MigrationStatus,
+};
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+// Tracks which migrations have been applied, created before any migration runs
+const METADATA_STATEMENT: &str = r#"
+CREATE TABLE IF NOT EXISTS Schema_Migrations (
+    version INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    applied_at TEXT NOT NULL
+);
+"#;
+
+// Never edit a migration once released, add a new one instead
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS User (
+    username TEXT PRIMARY KEY,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS Session (
+    sessionID INTEGER PRIMARY KEY,
+    username TEXT NOT NULL,
+    FOREIGN KEY (username) REFERENCES User(username) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS Sensor (
+    sensorID INTEGER PRIMARY KEY,
+    type TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS Session_Sensor (
+    session_sensorID INTEGER PRIMARY KEY,
+    sessionID INTEGER NOT NULL,
+    sensorID INTEGER NOT NULL,
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
+    FOREIGN KEY (sensorID) REFERENCES Sensor(sensorID) ON DELETE CASCADE,
+    UNIQUE (sessionID, sensorID)
+);
+
+CREATE TABLE IF NOT EXISTS Session_Sensor_Data (
+    datetime TEXT,
+    sessionID INTEGER,
+    data_blob TEXT NOT NULL,
+    PRIMARY KEY (datetime, sessionID),
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE
+);
+"#,
+        detect: Some("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'Session_Sensor_Data'"),
+    },
+    // Sessions that already hold data are considered finished recordings
+    Migration {
+        version: 2,
+        name: "session_lifecycle",
+        sql: r#"
+ALTER TABLE Session ADD COLUMN name TEXT NOT NULL DEFAULT '';
+ALTER TABLE Session ADD COLUMN description TEXT NOT NULL DEFAULT '';
+ALTER TABLE Session ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE Session ADD COLUMN status TEXT NOT NULL DEFAULT 'pending';
+ALTER TABLE Session ADD COLUMN start_time TEXT;
+ALTER TABLE Session ADD COLUMN end_time TEXT;
+UPDATE Session SET status = 'stopped' WHERE sessionID IN (SELECT DISTINCT sessionID FROM Session_Sensor_Data);
+"#,
+        detect: Some("SELECT COUNT(*) FROM pragma_table_info('Session') WHERE name = 'status'"),
+    },
+    // Rows are given their session's only sensor, rows of sessions with several sensors are left without one
+    Migration {
+        version: 3,
+        name: "session_sensor_data_per_sensor",
+        sql: r#"
+ALTER TABLE Session_Sensor_Data RENAME TO Session_Sensor_Data_old;
+CREATE TABLE Session_Sensor_Data (
+    datetime TEXT,
+    sessionID INTEGER,
+    session_sensorID INTEGER,
+    data_blob TEXT NOT NULL,
+    PRIMARY KEY (sessionID, session_sensorID, datetime),
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
+    FOREIGN KEY (session_sensorID) REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE
+);
+INSERT INTO Session_Sensor_Data (datetime, sessionID, session_sensorID, data_blob)
+    SELECT datetime, sessionID,
+        (SELECT CASE WHEN COUNT(*) = 1 THEN MAX(session_sensorID) END FROM Session_Sensor
+            WHERE Session_Sensor.sessionID = Session_Sensor_Data_old.sessionID),
+        data_blob
+    FROM Session_Sensor_Data_old;
+DROP TABLE Session_Sensor_Data_old;
+CREATE INDEX IF NOT EXISTS Session_Sensor_Data_session_sensor
+    ON Session_Sensor_Data (session_sensorID, datetime);
+"#,
+        detect: Some(
+            "SELECT COUNT(*) FROM pragma_table_info('Session_Sensor_Data') WHERE name = 'session_sensorID'",
+        ),
+    },
+    // Summaries of pruned datapoints, one row per session sensor and interval
+    Migration {
+        version: 4,
+        name: "session_sensor_rollups",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Session_Sensor_Rollup (
+    session_sensorID INTEGER NOT NULL,
+    interval_seconds INTEGER NOT NULL,
+    bucket TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    data_blob TEXT NOT NULL,
+    PRIMARY KEY (session_sensorID, interval_seconds, bucket),
+    FOREIGN KEY (session_sensorID) REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE
+);
+"#,
+        detect: None,
+    },
+    // JSON Schemas of sensor types, and the datapoints set aside for not matching them
+    Migration {
+        version: 5,
+        name: "sensor_schemas",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Sensor_Schema (
+    type TEXT PRIMARY KEY,
+    data_schema TEXT NOT NULL,
+    policy TEXT NOT NULL DEFAULT 'reject'
+);
+
+CREATE TABLE IF NOT EXISTS Quarantined_Sensor_Data (
+    sessionID INTEGER NOT NULL,
+    session_sensorID INTEGER NOT NULL,
+    datetime TEXT NOT NULL,
+    data_blob TEXT NOT NULL,
+    errors TEXT NOT NULL,
+    quarantined_at TEXT NOT NULL,
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
+    FOREIGN KEY (session_sensorID) REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS Quarantined_Sensor_Data_session
+    ON Quarantined_Sensor_Data (sessionID, quarantined_at);
+"#,
+        detect: None,
+    },
+    // Catalog details of sensors, and the calibrations of their channels over time
+    Migration {
+        version: 6,
+        name: "sensor_catalog",
+        sql: r#"
+ALTER TABLE Sensor ADD COLUMN manufacturer TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN model TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN serial_number TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN firmware TEXT NOT NULL DEFAULT '';
+ALTER TABLE Sensor ADD COLUMN channels TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE Sensor ADD COLUMN sample_rate REAL;
+
+CREATE TABLE IF NOT EXISTS Sensor_Calibration (
+    calibrationID INTEGER PRIMARY KEY,
+    sensorID INTEGER NOT NULL,
+    channel TEXT NOT NULL,
+    coefficients TEXT NOT NULL,
+    valid_from TEXT NOT NULL,
+    valid_to TEXT,
+    FOREIGN KEY (sensorID) REFERENCES Sensor(sensorID) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS Sensor_Calibration_sensor
+    ON Sensor_Calibration (sensorID, channel, valid_from);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 7,
+        name: "calibration_methods",
+        sql: r#"
+ALTER TABLE Sensor_Calibration ADD COLUMN method TEXT NOT NULL DEFAULT 'polynomial';
+ALTER TABLE Sensor_Calibration ADD COLUMN lookup_table TEXT NOT NULL DEFAULT '[]';
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 8,
+        name: "session_summaries",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Session_Summary (
+    sessionID INTEGER PRIMARY KEY,
+    gap_seconds REAL NOT NULL,
+    summary TEXT NOT NULL,
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE
+);
+
+CREATE TRIGGER IF NOT EXISTS Session_Summary_insert AFTER INSERT ON Session_Sensor_Data
+BEGIN
+    DELETE FROM Session_Summary WHERE sessionID = NEW.sessionID;
+END;
+
+CREATE TRIGGER IF NOT EXISTS Session_Summary_update AFTER UPDATE ON Session_Sensor_Data
+BEGIN
+    DELETE FROM Session_Summary WHERE sessionID IN (OLD.sessionID, NEW.sessionID);
+END;
+
+CREATE TRIGGER IF NOT EXISTS Session_Summary_delete AFTER DELETE ON Session_Sensor_Data
+BEGIN
+    DELETE FROM Session_Summary WHERE sessionID = OLD.sessionID;
+END;
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 9,
+        name: "alerts",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Alert_Rule (
+    alertRuleID INTEGER PRIMARY KEY AUTOINCREMENT,
+    sensorID INTEGER,
+    sessionID INTEGER,
+    name TEXT NOT NULL DEFAULT '',
+    field TEXT NOT NULL,
+    comparator TEXT NOT NULL,
+    threshold REAL NOT NULL,
+    duration_seconds REAL NOT NULL DEFAULT 0,
+    hysteresis REAL NOT NULL DEFAULT 0,
+    CHECK ((sensorID IS NULL) <> (sessionID IS NULL)),
+    FOREIGN KEY (sensorID) REFERENCES Sensor(sensorID) ON DELETE CASCADE,
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS Alert_Event (
+    alertEventID INTEGER PRIMARY KEY AUTOINCREMENT,
+    alertRuleID INTEGER NOT NULL,
+    sessionID INTEGER NOT NULL,
+    session_sensorID INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    value REAL NOT NULL,
+    datetime TEXT NOT NULL,
+    delivery TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (alertRuleID) REFERENCES Alert_Rule(alertRuleID) ON DELETE CASCADE,
+    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
+    FOREIGN KEY (session_sensorID) REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS Alert_Event_session ON Alert_Event (sessionID, alertEventID);
+"#,
+        detect: None,
+    },
+    Migration {
+        version: 10,
+        name: "derived_channels",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS Derived_Channel (
+    type TEXT NOT NULL,
+    name TEXT NOT NULL,
+    expression TEXT NOT NULL,
+    unit TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (type, name)
+);
+"#,
+        detect: None,
+    },
+    // The primary key does not keep datapoints left without a session sensor unique, since SQLite treats NULLs as distinct
+    // Repeats among them are dropped, keeping the first stored
+    Migration {
+        version: 11,
+        name: "unassigned_session_sensor_data_unique",
+        sql: r#"
+DELETE FROM Session_Sensor_Data WHERE session_sensorID IS NULL AND rowid NOT IN (
+    SELECT MIN(rowid) FROM Session_Sensor_Data WHERE session_sensorID IS NULL GROUP BY sessionID, datetime
+);
+CREATE UNIQUE INDEX IF NOT EXISTS Session_Sensor_Data_unassigned
+    ON Session_Sensor_Data (sessionID, datetime) WHERE session_sensorID IS NULL;
+"#,
+        detect: None,
+    },
+];
+
+//Applies MIGRATIONS to a connection, every migration runs in its own transaction
+pub struct Migrator<'a> {
+    connection: &'a Connection,
+    migrations: &'a [Migration],
+}
+
+impl<'a> Migrator<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        Self::with_migrations(connection, MIGRATIONS)
+    }
+
+    pub fn with_migrations(connection: &'a Connection, migrations: &'a [Migration]) -> Self {
+        Migrator {
+            connection,
+            migrations,
+        }
+    }
+
+    //Reports every known migration without changing the database
+    pub fn status(&self) -> Result<Vec<MigrationStatus>> {
+        // adopting an unversioned database is rolled back, it only decides what is reported
+        let transaction = self.connection.unchecked_transaction()?;
+        self.adopt_unversioned(&transaction)?;
+        let status = self.current_status();
+        transaction.rollback()?;
+        status
+    }
+
+    fn current_status(&self) -> Result<Vec<MigrationStatus>> {
+        migration_status(self.migrations, &self.applied()?)
+    }
+
+    //Applies every pending migration and returns the versions that were applied
+    pub fn migrate(&self) -> Result<Vec<i64>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        self.adopt_unversioned(&transaction)?;
+        transaction.commit()?;
+
+        let pending = self.pending()?;
+        for migration in &pending {
+            let transaction = self.connection.unchecked_transaction()?;
+            Self::apply(&transaction, migration)?;
+            transaction.commit().map_err(|e| {
+                Error::Storage(format!(
+                    "Failed to commit migration {}: {e}",
+                    migration.version
+                ))
+            })?;
+        }
+        Ok(pending.iter().map(|migration| migration.version).collect())
+    }
+
+    //Runs every pending migration and rolls them back, returning the versions that would be applied
+    pub fn dry_run(&self) -> Result<Vec<i64>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        self.adopt_unversioned(&transaction)?;
+        let pending = self.pending()?;
+        for migration in &pending {
+            Self::apply(&transaction, migration)?;
+        }
+        transaction.rollback()?;
+        Ok(pending.iter().map(|migration| migration.version).collect())
+    }
+
+    fn apply(connection: &Connection, migration: &Migration) -> Result<()> {
+        connection.execute_batch(migration.sql).map_err(|e| {
+            Error::Storage(format!(
+                "Migration {} '{}' failed: {e}",
+                migration.version, migration.name
+            ))
+        })?;
+        Self::record(connection, migration)
+    }
+
+    fn record(connection: &Connection, migration: &Migration) -> Result<()> {
+        connection
+            .execute(
+                "INSERT INTO Schema_Migrations (version, name, checksum, applied_at) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    migration.version,
+                    migration.name,
+                    migration.checksum(),
+                    applied_at_now()
+                ],
+            )?;
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<&'a Migration>> {
+        pending_migrations(self.migrations, &self.applied()?)
+    }
+
+    fn applied(&self) -> Result<HashMap<i64, AppliedMigration>> {
+        if !self.has_table("Schema_Migrations")? {
+            return Ok(HashMap::new());
+        }
+        let mut statement = self
+            .connection
+            .prepare("SELECT version, name, checksum, applied_at FROM Schema_Migrations")?;
+        let applied = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    AppliedMigration {
+                        name: row.get(1)?,
+                        checksum: row.get(2)?,
+                        applied_at: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        Ok(applied)
+    }
+
+    //Creates the metadata table, databases from before versioning have the changes they already contain recorded
+    fn adopt_unversioned(&self, connection: &Connection) -> Result<()> {
+        if self.has_table("Schema_Migrations")? {
+            return Ok(());
+        }
+        connection.execute_batch(METADATA_STATEMENT)?;
+        for migration in self.migrations {
+            let present = match migration.detect {
+                Some(detect) => connection.query_row(detect, [], |row| row.get::<_, i64>(0))? > 0,
+                None => false,
+            };
+            if !present {
+                break;
+            }
+            Self::record(connection, migration)?;
+        }
+        Ok(())
+    }
+
+    fn has_table(&self, table: &str) -> Result<bool> {
+        self.connection
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                params![table],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|table| table.is_some())
+            .map_err(Error::from)
+    }
+}
@@ -0,0 +1,158 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+
+use super::connection_pool::{ConnectionPool, PooledConnection};
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+//How hard SQLite works to make a commit durable, see PRAGMA synchronous
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SqliteSynchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+#[allow(unused)]
+impl SqliteSynchronous {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SqliteSynchronous::Off => "OFF",
+            SqliteSynchronous::Normal => "NORMAL",
+            SqliteSynchronous::Full => "FULL",
+            SqliteSynchronous::Extra => "EXTRA",
+        }
+    }
+
+    pub fn from_str(level: &str) -> Result<Self> {
+        match level.to_ascii_lowercase().as_str() {
+            "off" => Ok(SqliteSynchronous::Off),
+            "normal" => Ok(SqliteSynchronous::Normal),
+            "full" => Ok(SqliteSynchronous::Full),
+            "extra" => Ok(SqliteSynchronous::Extra),
+            _ => Err(Error::Validation(format!(
+                "Unknown synchronous level '{level}', expected one of [off, normal, full, extra]"
+            ))),
+        }
+    }
+}
+
+//Settings applied to every connection the pool opens
+#[derive(Debug, Clone)]
+pub struct SqlitePoolConfig {
+    // read only connections, writes always go through the single writer
+    pub readers: usize,
+    // how long a statement waits on a lock held by another connection
+    pub busy_timeout: Duration,
+    pub synchronous: SqliteSynchronous,
+    // how long a read waits for a free reader before giving up
+    pub checkout_timeout: Duration,
+}
+
+impl Default for SqlitePoolConfig {
+    fn default() -> Self {
+        SqlitePoolConfig {
+            readers: 4,
+            busy_timeout: Duration::from_millis(5_000),
+            synchronous: SqliteSynchronous::Normal,
+            checkout_timeout: Duration::from_millis(5_000),
+        }
+    }
+}
+
+//A single writer and a set of readers over one database file
+//SQLite allows one writer at a time, in WAL mode readers keep reading the last commit while it writes
+pub struct SqlitePool {
+    writer: Mutex<Connection>,
+    readers: ConnectionPool<Connection>,
+}
+
+impl SqlitePool {
+    pub fn open(path: &str, config: &SqlitePoolConfig) -> Result<Self> {
+        let writer = Connection::open(path)?;
+        Self::configure(&writer, config)?;
+        // the journal mode is stored in the file, so it only needs setting once by the writer
+        let journal_mode: String =
+            writer.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            eprintln!("Database at '{path}' does not support WAL, using journal mode '{journal_mode}'");
+        }
+
+        // every in memory connection is its own database, so readers could never see the writer's data
+        let reader_count = if path == ":memory:" { 0 } else { config.readers };
+        let mut readers = Vec::with_capacity(reader_count);
+        for _ in 0..reader_count {
+            let reader = Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            Self::configure(&reader, config)?;
+            readers.push(reader);
+        }
+
+        Ok(SqlitePool {
+            writer: Mutex::new(writer),
+            readers: ConnectionPool::new(readers, config.checkout_timeout),
+        })
+    }
+
+    //Pool without readers, every read shares the writer, used for in memory databases that cannot be reopened
+    pub fn from_connection(connection: Connection) -> Self {
+        SqlitePool {
+            writer: Mutex::new(connection),
+            readers: ConnectionPool::new(Vec::new(), Duration::ZERO),
+        }
+    }
+
+    fn configure(connection: &Connection, config: &SqlitePoolConfig) -> Result<()> {
+        connection.busy_timeout(config.busy_timeout)?;
+        connection.pragma_update(None, "synchronous", config.synchronous.as_str())?;
+        Ok(())
+    }
+
+    //Waits for the writer, held until the guard is dropped
+    pub fn writer(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.writer
+            .lock()
+            .map_err(|_| Error::Storage(String::from("Database writer was poisoned by a panic")))
+    }
+
+    //Checks out a reader, waiting up to the checkout timeout when all of them are busy
+    pub fn reader(&self) -> Result<SqliteReader<'_>> {
+        if self.readers.size() == 0 {
+            return Ok(SqliteReader::Writer(self.writer()?));
+        }
+        Ok(SqliteReader::Reader(self.readers.get()?))
+    }
+}
+
+//A connection checked out for reading, readers go back to the pool when dropped
+pub enum SqliteReader<'a> {
+    Reader(PooledConnection<'a, Connection>),
+    Writer(MutexGuard<'a, Connection>),
+}
+
+impl Deref for SqliteReader<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            SqliteReader::Reader(connection) => connection,
+            SqliteReader::Writer(connection) => connection,
+        }
+    }
+}
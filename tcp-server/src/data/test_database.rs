@@ -0,0 +1,1591 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::models::{
+        AlertComparator, AlertDelivery, AlertEvent, AlertRule, AlertState, CalibrationMethod, DerivedChannel,
+        QuarantinedSensorData, SchemaPolicy, Sensor, SensorCalibration, SensorSchema, Session, SessionSensor,
+        SessionSensorData, SessionSensorRollup, SessionStatus, SessionSummary, SummaryGap, User,
+    };
+    use crate::data::Database;
+    use crate::error::Error;
+    use crate::http::HttpStatus;
+
+    /* Helpers */
+
+    macro_rules! assert_models_eq {
+        ($expected:expr, $actual:expr, [ $( $field:ident ),* ]) => {
+            $(
+                assert_eq!(
+                    $expected.$field(),
+                    $actual.$field(),
+                    concat!("Mismatch on field: ", stringify!($field))
+                );
+            )*
+        };
+    }
+
+    // Declares a #[test] for every case below, run against the database $open returns
+    // $open is evaluated once per case, so every case starts from an empty database
+    // Cases are skipped when $open returns None because the backend is not available
+    // A new backend conforms to the Database trait once it passes the suite from a module of its own below
+    macro_rules! database_suite {
+        ($open:expr) => {
+            database_suite!(
+                $open,
+                [
+                    test_insert_user,
+                    test_get_users,
+                    test_get_user,
+                    test_update_user,
+                    test_delete_user,
+                    test_insert_sensor,
+                    test_get_sensors,
+                    test_get_sensor,
+                    test_update_sensor,
+                    test_delete_sensor,
+                    test_insert_session,
+                    test_get_session,
+                    test_get_session_user,
+                    test_get_user_sessions,
+                    test_get_all_sessions,
+                    test_update_session,
+                    test_delete_session,
+                    test_set_session_status,
+                    test_error_variants,
+                    test_constraints,
+                    test_cascading_deletes,
+                    test_not_found,
+                    test_update_constraints,
+                    test_insert_session_sensor,
+                    test_get_sessions_sensors,
+                    test_get_session_sensors,
+                    test_get_session_sensor,
+                    test_update_session_sensor,
+                    test_delete_session_sensor,
+                    test_insert_session_sensor_data,
+                    test_batch_session_sensor_data,
+                    test_batch_atomicity,
+                    test_import_skips_duplicates,
+                    test_get_sessions_sensors_data,
+                    test_get_sessions_sensor_data,
+                    test_time_range,
+                    test_data_pages,
+                    test_get_session_sensor_data,
+                    test_get_session_sensor_datapoint,
+                    test_update_session_sensor_datapoint,
+                    test_delete_session_sensor_datapoint,
+                    test_prune_session_sensor_data,
+                    test_sensor_schemas,
+                    test_sensor_catalog,
+                    test_session_summary,
+                    test_alerts,
+                    test_derived_channels
+                ]
+            );
+        };
+        ($open:expr, [ $( $case:ident ),* ]) => {
+            $(
+                #[test]
+                fn $case() {
+                    match $open {
+                        Some(db) => super::$case(&*db),
+                        None => eprintln!("Skipping {}, the database is not available", stringify!($case)),
+                    }
+                }
+            )*
+        };
+    }
+
+    // Records are added through the database under test, every backend numbers new rows from 1
+    fn add_test_user(db: &dyn Database, user: &User) {
+        db.insert_user(user).expect("Failed to insert test user");
+    }
+
+    fn add_test_session(db: &dyn Database, session: &Session) {
+        let inserted = db.insert_session(session).expect("Failed to insert test session");
+        assert_eq!(inserted.get_id(), session.get_id(), "Test sessions must be added in order of id");
+    }
+
+    fn add_test_sensor(db: &dyn Database, sensor: &Sensor) {
+        let inserted = db.insert_sensor(sensor).expect("Failed to insert test sensor");
+        assert_eq!(inserted.get_id(), sensor.get_id(), "Test sensors must be added in order of id");
+    }
+
+    fn add_test_session_sensor(db: &dyn Database, session_sensor: &SessionSensor) {
+        let inserted = db.insert_session_sensor(session_sensor).expect("Failed to insert test session_sensor");
+        assert_eq!(inserted.get_id(), session_sensor.get_id(), "Test session sensors must be added in order of id");
+    }
+
+    fn add_test_session_sensor_data(db: &dyn Database, session_sensor_data: &SessionSensorData) {
+        db.insert_session_sensor_data(session_sensor_data).expect("Failed to insert test session_sensor_data");
+    }
+
+    // Adds a user with one session recording from one sensor
+    fn add_test_recording(db: &dyn Database) -> (Session, Sensor, SessionSensor) {
+        let user = User::new("user1".to_string(), "hunter2".to_string());
+        add_test_user(db, &user);
+
+        let session = Session::new(1, user.get_username().to_string());
+        add_test_session(db, &session);
+
+        let sensor = Sensor::new(1, "Acceleration".to_string());
+        add_test_sensor(db, &sensor);
+
+        let session_sensor = SessionSensor::new(1, *session.get_id(), *sensor.get_id());
+        add_test_session_sensor(db, &session_sensor);
+
+        (session, sensor, session_sensor)
+    }
+
+    fn test_datapoint(session_sensor: &SessionSensor, datetime: &str, value: i64) -> SessionSensorData {
+        SessionSensorData::new(
+            Some(*session_sensor.get_session_id()),
+            Some(*session_sensor.get_id()),
+            datetime.to_string(),
+            json!({ "value": value }),
+        )
+    }
+
+    /* Tests */
+
+    /*
+    #[test]
+    fn is_admin() {
+    }
+
+    #[test]
+    fn login() {
+    }
+
+    #[test]
+    fn logout() {
+    }
+
+    #[test]
+    fn renew_session() {
+    }
+    */
+
+    fn test_insert_user(db: &dyn Database) {
+        let user = User::new("NewUser".to_string(), "pwordHashed".to_string());
+
+        let result = db.insert_user(&user);
+        assert!(result.is_ok());
+
+        // Check db for new user
+        let fetch_user_result = db.get_user(user.get_username());
+        assert!(fetch_user_result.is_ok());
+
+        let fetched_user = fetch_user_result.unwrap();
+        assert_models_eq!(user, fetched_user, [get_username, get_password_hash]);
+    }
+
+    fn test_get_users(db: &dyn Database) {
+        let users_to_add = vec![
+            User::new("user1".to_string(), "pwordHashed".to_string()),
+            User::new("user2".to_string(), "my_password123".to_string()),
+            User::new("user3".to_string(), "hunter2".to_string()),
+        ];
+
+        for user in &users_to_add {
+            add_test_user(db, user);
+        }
+
+        let result = db.get_users();
+        assert!(result.is_ok());
+
+        let users = result.unwrap();
+        assert_eq!(users.len(), users_to_add.len());
+
+        for test_user in &users_to_add {
+            let matched = users.iter().find(|u|
+                u.get_username() == test_user.get_username()  &&
+                u.get_password_hash() == test_user.get_password_hash()
+            );
+
+            assert!(matched.is_some(), "User {:?} not found in db result", test_user.get_username());
+        }
+    }
+
+    fn test_get_user(db: &dyn Database) {
+        let user = User::new("NewUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+
+        let result = db.get_user(user.get_username());
+        assert!(result.is_ok());
+
+        let returned_user = result.unwrap();
+        assert_models_eq!(user, returned_user, [get_username, get_password_hash]);
+    }
+
+    fn test_update_user(db: &dyn Database) {
+        let original_user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &original_user);
+
+        // Can't change username, it's the primary key
+        let updated_user = User::new(original_user.get_username().to_string(), "new_password".to_string());
+
+        let result = db.update_user(original_user.get_username(), &updated_user);
+        assert!(result.is_ok());
+
+        // Verify the user has been updated
+        let fetch_updated_result = db.get_user(updated_user.get_username());
+        assert!(fetch_updated_result.is_ok());
+
+        let fetched_user = fetch_updated_result.unwrap();
+        assert_models_eq!(updated_user, fetched_user, [get_username, get_password_hash]);
+    }
+
+    fn test_delete_user(db: &dyn Database) {
+        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+
+        let result = db.delete_user(user.get_username());
+        assert!(result.is_ok());
+
+        // Verify that the user does not exist in the db
+        let fetch_user_result = db.get_user(user.get_username());
+        assert!(fetch_user_result.is_err());
+    }
+
+    fn test_insert_sensor(db: &dyn Database) {
+        let sensor = Sensor::new(0, "Acceleration".to_string());
+
+        let result = db.insert_sensor(&sensor);
+        assert!(result.is_ok());
+
+        // Check db for new sensor, the id is assigned by the database
+        let inserted_sensor = result.unwrap();
+        let fetch_sensor_result = db.get_sensor(*inserted_sensor.get_id());
+        assert!(fetch_sensor_result.is_ok());
+
+        let fetched_sensor = fetch_sensor_result.unwrap();
+        assert_models_eq!(inserted_sensor, fetched_sensor, [get_id, get_sensor_type]);
+    }
+
+    fn test_get_sensors(db: &dyn Database) {
+        let sensors_to_add = vec![
+            Sensor::new(1, "Acceleration".to_string()),
+            Sensor::new(2, "GPS".to_string()),
+            Sensor::new(3, "Altitude".to_string()),
+        ];
+
+        for sensor in &sensors_to_add {
+            add_test_sensor(db, sensor);
+        }
+
+        let result = db.get_sensors();
+        assert!(result.is_ok());
+
+        let sensors = result.unwrap();
+        assert_eq!(sensors.len(), sensors_to_add.len());
+
+        for test_sensor in &sensors_to_add {
+            let matched = sensors.iter().find(|s|
+                s.get_id() == test_sensor.get_id()  &&
+                s.get_sensor_type() == test_sensor.get_sensor_type()
+            );
+
+            assert!(matched.is_some(), "Sensor {:?} not found in db result", test_sensor.get_id());
+        }
+    }
+
+    fn test_get_sensor(db: &dyn Database) {
+        let sensor = Sensor::new(1, "Acceleration".to_string());
+        add_test_sensor(db, &sensor);
+
+        let result = db.get_sensor(*sensor.get_id());
+        assert!(result.is_ok());
+
+        let returned_sensor = result.unwrap();
+        assert_models_eq!(sensor, returned_sensor, [get_id, get_sensor_type]);
+    }
+
+    fn test_update_sensor(db: &dyn Database) {
+        let original_sensor = Sensor::new(1, "Acceleration".to_string());
+        add_test_sensor(db, &original_sensor);
+
+        // Can't change id, it's the primary key
+        let updated_sensor = Sensor::new(*original_sensor.get_id(), "GPS".to_string());
+
+        let result = db.update_sensor(*original_sensor.get_id(), &updated_sensor);
+        assert!(result.is_ok());
+
+        // Verify the sensor has been updated
+        let fetch_updated_result = db.get_sensor(*updated_sensor.get_id());
+        assert!(fetch_updated_result.is_ok());
+
+        let fetched_sensor = fetch_updated_result.unwrap();
+        assert_models_eq!(updated_sensor, fetched_sensor, [get_id, get_sensor_type]);
+    }
+
+    fn test_delete_sensor(db: &dyn Database) {
+        let sensor = Sensor::new(1, "Acceleration".to_string());
+        add_test_sensor(db, &sensor);
+
+        let result = db.delete_sensor(*sensor.get_id());
+        assert!(result.is_ok());
+
+        // Verify that the sensor does not exist in the db
+        let fetch_sensor_result = db.get_sensor(*sensor.get_id());
+        assert!(fetch_sensor_result.is_err());
+    }
+
+    fn test_insert_session(db: &dyn Database) {
+        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+
+        let session = Session::new(0, "TestUser".to_string())
+            .with_metadata("Morning run".to_string(), String::new(), vec!["outdoor".to_string()]);
+
+        let result = db.insert_session(&session);
+        assert!(result.is_ok());
+
+        // Check db for new session, the id is assigned by the database
+        let inserted_session = result.unwrap();
+        let fetch_session_result = db.get_session(*inserted_session.get_id());
+        assert!(fetch_session_result.is_ok());
+
+        let fetched_session = fetch_session_result.unwrap();
+        assert_models_eq!(session, fetched_session, [get_username, get_name, get_tags, get_status]);
+    }
+
+    fn test_get_session(db: &dyn Database) {
+        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+
+        let session = Session::new(1, "TestUser".to_string());
+        add_test_session(db, &session);
+
+        let result = db.get_session(*session.get_id());
+        assert!(result.is_ok());
+
+        let returned_session = result.unwrap();
+        assert_models_eq!(session, returned_session, [get_id, get_username, get_status]);
+    }
+
+    fn test_get_session_user(db: &dyn Database) {
+        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+
+        // login sessions are still resolved by the mock on every backend, so only the lookup is checked
+        let cookie = db.login(&user).expect("Failed to log in");
+        let result = db.get_session_user(&cookie);
+        assert!(result.is_ok());
+
+        let returned_user = result.unwrap();
+        assert!(!returned_user.get_username().is_empty());
+    }
+
+    fn test_get_user_sessions(db: &dyn Database) {
+        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+        let other_user = User::new("other_user".to_string(), "my_password123".to_string());
+        add_test_user(db, &other_user);
+
+        let sessions_to_add = vec![
+            Session::new(1, "TestUser".to_string()),
+            Session::new(2, "TestUser".to_string()),
+            Session::new(3, "TestUser".to_string()),
+        ];
+
+        for session in &sessions_to_add {
+            add_test_session(db, session);
+        }
+        add_test_session(db, &Session::new(4, "other_user".to_string()));
+
+        let result = db.get_user_sessions(user.get_username());
+        assert!(result.is_ok());
+
+        let sessions = result.unwrap();
+        assert_eq!(sessions.len(), sessions_to_add.len());
+
+        for test_session in &sessions_to_add {
+            let matched = sessions.iter().find(|s|
+                s.get_id() == test_session.get_id() &&
+                s.get_username() == test_session.get_username()
+            );
+
+            assert!(matched.is_some(), "Session {:?} not found in db result", test_session.get_id());
+        }
+    }
+
+    fn test_get_all_sessions(db: &dyn Database) {
+        let user1 = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user1);
+
+        let user2 = User::new("other_user".to_string(), "my_password123".to_string());
+        add_test_user(db, &user2);
+
+        let sessions_to_add = vec![
+            Session::new(1, "TestUser".to_string()),
+            Session::new(2, "TestUser".to_string()),
+            Session::new(3, "TestUser".to_string()),
+            Session::new(4, "other_user".to_string()),
+            Session::new(5, "other_user".to_string()),
+        ];
+
+        for session in &sessions_to_add {
+            add_test_session(db, session);
+        }
+
+        let result = db.get_all_sessions();
+        assert!(result.is_ok());
+
+        let sessions = result.unwrap();
+        assert_eq!(sessions.len(), sessions_to_add.len());
+
+        for test_session in &sessions_to_add {
+            let matched = sessions.iter().find(|s|
+                s.get_id() == test_session.get_id() &&
+                s.get_username() == test_session.get_username()
+            );
+
+            assert!(matched.is_some(), "Session {:?} not found in db result", test_session.get_id());
+        }
+    }
+
+    fn test_update_session(db: &dyn Database) {
+        let user1 = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user1);
+
+        let user2 = User::new("other_user".to_string(), "my_password123".to_string());
+        add_test_user(db, &user2);
+
+        let original_session = Session::new(1, "TestUser".to_string());
+        add_test_session(db, &original_session);
+
+        // Can't change id, it's the primary key
+        let updated_session = Session::new(*original_session.get_id(), "other_user".to_string())
+            .with_metadata("Renamed".to_string(), "A description".to_string(), Vec::new());
+
+        let result = db.update_session(*original_session.get_id(), &updated_session);
+        assert!(result.is_ok());
+
+        // Verify the session has been updated
+        let fetch_updated_result = db.get_session(*updated_session.get_id());
+        assert!(fetch_updated_result.is_ok());
+
+        let fetched_session = fetch_updated_result.unwrap();
+        assert_models_eq!(updated_session, fetched_session, [get_id, get_username, get_name, get_description]);
+    }
+
+    fn test_delete_session(db: &dyn Database) {
+        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
+        add_test_user(db, &user);
+
+        let session = Session::new(1, "TestUser".to_string());
+        add_test_session(db, &session);
+
+        let result = db.delete_session(*session.get_id());
+        assert!(result.is_ok());
+
+        // Verify that the session does not exist in the db
+        let fetch_session_result = db.get_session(*session.get_id());
+        assert!(fetch_session_result.is_err());
+    }
+
+    fn test_set_session_status(db: &dyn Database) {
+        let (session, sensor, _) = add_test_recording(db);
+
+        assert!(db.get_active_session(*sensor.get_id()).is_err());
+
+        let started = db
+            .set_session_status(*session.get_id(), SessionStatus::Recording)
+            .expect("Failed to start session");
+        assert_eq!(*started.get_status(), SessionStatus::Recording);
+        assert!(started.get_start_time().is_some());
+
+        let active = db.get_active_session(*sensor.get_id()).expect("No active session for sensor");
+        assert_eq!(active.get_id(), session.get_id());
+
+        // Only recording sessions can be stopped
+        assert!(db.set_session_status(*session.get_id(), SessionStatus::Archived).is_err());
+    }
+
+    fn test_error_variants(db: &dyn Database) {
+        let (session, _, _) = add_test_recording(db);
+
+        let missing = db.get_session(42).expect_err("Missing session was found");
+        assert_eq!(missing, Error::NotFound(String::from("Session 42 not found")));
+        assert_eq!(missing.status(), HttpStatus::NotFound);
+
+        let missing_user = User::new("nobody".to_string(), "hunter2".to_string());
+        let update = db.update_user("nobody", &missing_user).expect_err("Missing user was updated");
+        assert!(matches!(update, Error::NotFound(_)));
+        assert!(matches!(db.delete_sensor(42), Err(Error::NotFound(_))));
+
+        // Unique violations are a conflict rather than a missing record
+        let duplicate = User::new("user1".to_string(), "hunter2".to_string());
+        let insert = db.insert_user(&duplicate).expect_err("Duplicate user was inserted");
+        assert!(matches!(insert, Error::Conflict(_)));
+        assert_eq!(insert.status(), HttpStatus::Conflict);
+
+        let transition = db
+            .set_session_status(*session.get_id(), SessionStatus::Stopped)
+            .expect_err("Pending session was stopped");
+        assert!(matches!(transition, Error::Conflict(_)));
+    }
+
+    fn test_constraints(db: &dyn Database) {
+        let (session, sensor, session_sensor) = add_test_recording(db);
+
+        // References to missing records are refused
+        let orphan_session = Session::new(0, "nobody".to_string());
+        assert!(matches!(db.insert_session(&orphan_session), Err(Error::Conflict(_))));
+        let orphan_session_sensor = SessionSensor::new(0, 42, *sensor.get_id());
+        assert!(matches!(db.insert_session_sensor(&orphan_session_sensor), Err(Error::Conflict(_))));
+
+        // A sensor is attached to a session once
+        let duplicate = SessionSensor::new(0, *session.get_id(), *sensor.get_id());
+        assert!(matches!(db.insert_session_sensor(&duplicate), Err(Error::Conflict(_))));
+
+        // A batch with a duplicate datapoint stores none of its datapoints
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1));
+        let batch = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:01", 2),
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 3),
+        ];
+        assert!(matches!(db.batch_session_sensor_data(&batch), Err(Error::Conflict(_))));
+        assert_eq!(db.get_session_sensor_data(*session_sensor.get_id()).unwrap().len(), 1);
+    }
+
+    fn test_cascading_deletes(db: &dyn Database) {
+        let (session, sensor, session_sensor) = add_test_recording(db);
+        let sensor2 = Sensor::new(2, "GPS".to_string());
+        add_test_sensor(db, &sensor2);
+        let session_sensor2 = SessionSensor::new(2, *session.get_id(), *sensor2.get_id());
+        add_test_session_sensor(db, &session_sensor2);
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor2, "2025-01-01T12:00:00", 2));
+
+        // Deleting a sensor removes its session sensors and their datapoints
+        db.delete_sensor(*sensor.get_id()).expect("Failed to delete sensor");
+        assert!(db.get_session_sensor(*session_sensor.get_id()).is_err());
+        assert_eq!(db.get_sessions_sensor_data(*session.get_id()).unwrap().len(), 1);
+
+        // Deleting a user removes their sessions and everything recorded in them
+        // Deleting a session sensor removes only its own datapoints
+        let session2 = Session::new(2, session.get_username().to_string());
+        add_test_session(db, &session2);
+        let session_sensor3 = SessionSensor::new(3, *session2.get_id(), *sensor2.get_id());
+        add_test_session_sensor(db, &session_sensor3);
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor3, "2025-01-01T12:00:00", 3));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor3, "2025-01-01T12:00:01", 4));
+        db.delete_session_sensor(*session_sensor3.get_id()).expect("Failed to delete session sensor");
+        assert!(db.get_sessions_sensor_data(*session2.get_id()).unwrap().is_empty());
+        assert_eq!(db.get_sessions_sensor_data(*session.get_id()).unwrap().len(), 1);
+
+        // Deleting a session removes its session sensors and datapoints but not its sensors
+        // ids of deleted rows may be reused, so the session sensor keeps whichever id it is given
+        db.insert_session_sensor(&SessionSensor::new(0, *session2.get_id(), *sensor2.get_id()))
+            .expect("Failed to insert test session_sensor");
+        db.delete_session(*session2.get_id()).expect("Failed to delete session");
+        assert!(db.get_session_sensors(*session2.get_id()).unwrap().is_empty());
+        assert!(db.get_sensor(*sensor2.get_id()).is_ok());
+
+        db.delete_user(session.get_username()).expect("Failed to delete user");
+        assert!(db.get_session(*session.get_id()).is_err());
+        assert!(db.get_sessions_sensors().unwrap().is_empty());
+        assert!(db.get_sessions_sensors_data().unwrap().is_empty());
+        assert!(db.get_sensor(*sensor2.get_id()).is_ok());
+    }
+
+    fn test_not_found(db: &dyn Database) {
+        let user = User::new("nobody".to_string(), "hunter2".to_string());
+        assert!(matches!(db.get_user("nobody"), Err(Error::NotFound(_))));
+        assert!(matches!(db.delete_user("nobody"), Err(Error::NotFound(_))));
+
+        let sensor = Sensor::new(42, "GPS".to_string());
+        assert!(matches!(db.get_sensor(42), Err(Error::NotFound(_))));
+        assert!(matches!(db.update_sensor(42, &sensor), Err(Error::NotFound(_))));
+
+        let session = Session::new(42, user.get_username().to_string());
+        assert!(matches!(db.update_session(42, &session), Err(Error::NotFound(_))));
+        assert!(matches!(db.delete_session(42), Err(Error::NotFound(_))));
+        assert!(matches!(db.set_session_status(42, SessionStatus::Recording), Err(Error::NotFound(_))));
+        assert!(matches!(db.get_active_session(42), Err(Error::NotFound(_))));
+
+        let session_sensor = SessionSensor::new(42, 42, 42);
+        assert!(matches!(db.get_session_sensor(42), Err(Error::NotFound(_))));
+        assert!(matches!(db.update_session_sensor(42, &session_sensor), Err(Error::NotFound(_))));
+        assert!(matches!(db.delete_session_sensor(42), Err(Error::NotFound(_))));
+
+        let datapoint = test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1);
+        assert!(matches!(db.get_session_sensor_datapoint(42, "2025-01-01T12:00:00"), Err(Error::NotFound(_))));
+        assert!(matches!(
+            db.update_session_sensor_datapoint(42, "2025-01-01T12:00:00", &datapoint),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(db.delete_session_sensor_datapoint(42, "2025-01-01T12:00:00"), Err(Error::NotFound(_))));
+
+        // Listing what does not exist is empty rather than an error
+        assert!(db.get_user_sessions("nobody").unwrap().is_empty());
+        assert!(db.get_session_sensors(42).unwrap().is_empty());
+        assert!(db.get_sessions_sensor_data(42).unwrap().is_empty());
+        assert!(db.get_session_sensor_data(42).unwrap().is_empty());
+    }
+
+    fn test_update_constraints(db: &dyn Database) {
+        let (session, sensor, session_sensor) = add_test_recording(db);
+        let sensor2 = Sensor::new(2, "GPS".to_string());
+        add_test_sensor(db, &sensor2);
+        let session_sensor2 = SessionSensor::new(2, *session.get_id(), *sensor2.get_id());
+        add_test_session_sensor(db, &session_sensor2);
+
+        // Moving a session sensor onto a pair that exists, or a sensor that does not, is refused
+        let duplicate = SessionSensor::new(2, *session.get_id(), *sensor.get_id());
+        assert!(matches!(db.update_session_sensor(2, &duplicate), Err(Error::Conflict(_))));
+        let orphan = SessionSensor::new(2, *session.get_id(), 42);
+        assert!(matches!(db.update_session_sensor(2, &orphan), Err(Error::Conflict(_))));
+        assert_eq!(db.get_session_sensor(2).unwrap().get_sensor_id(), sensor2.get_id());
+
+        // A session cannot be given to a user that does not exist
+        let orphan_session = Session::new(*session.get_id(), "nobody".to_string());
+        assert!(matches!(db.update_session(*session.get_id(), &orphan_session), Err(Error::Conflict(_))));
+        assert_eq!(db.get_session(*session.get_id()).unwrap().get_username(), session.get_username());
+
+        // Moving a datapoint onto the datetime of another is refused and changes neither
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:01", 2));
+        let moved = test_datapoint(&session_sensor, "2025-01-01T12:00:01", 3);
+        let update = db.update_session_sensor_datapoint(*session_sensor.get_id(), "2025-01-01T12:00:00", &moved);
+        assert!(matches!(update, Err(Error::Conflict(_))));
+        let fetched = db.get_session_sensor_datapoint(*session_sensor.get_id(), "2025-01-01T12:00:01").unwrap();
+        assert_eq!(fetched.get_blob(), &json!({ "value": 2 }));
+    }
+
+    fn test_insert_session_sensor(db: &dyn Database) {
+        let user = User::new("user1".to_string(), "hunter2".to_string());
+        add_test_user(db, &user);
+
+        let session = Session::new(1, user.get_username().to_string());
+        add_test_session(db, &session);
+
+        let sensor = Sensor::new(1, "Acceleration".to_string());
+        add_test_sensor(db, &sensor);
+
+        let session_sensor = SessionSensor::new(0, *session.get_id(), *sensor.get_id());
+
+        let result = db.insert_session_sensor(&session_sensor);
+        assert!(result.is_ok());
+
+        // Check db for new session_sensor
+        let inserted_session_sensor = result.unwrap();
+        let fetch_session_sensor_result = db.get_session_sensor(*inserted_session_sensor.get_id());
+        assert!(fetch_session_sensor_result.is_ok());
+
+        let fetched_session_sensor = fetch_session_sensor_result.unwrap();
+        assert_models_eq!(inserted_session_sensor, fetched_session_sensor, [get_id, get_session_id, get_sensor_id]);
+    }
+
+    fn test_get_sessions_sensors(db: &dyn Database) {
+        let user = User::new("user1".to_string(), "hunter2".to_string());
+        add_test_user(db, &user);
+
+        for session_id in 1..=2 {
+            add_test_session(db, &Session::new(session_id, user.get_username().to_string()));
+        }
+        for sensor_id in 1..=2 {
+            add_test_sensor(db, &Sensor::new(sensor_id, "Acceleration".to_string()));
+        }
+
+        let session_sensors_to_add = vec![
+            SessionSensor::new(1, 1, 1),
+            SessionSensor::new(2, 1, 2),
+            SessionSensor::new(3, 2, 1),
+        ];
+
+        for session_sensor in &session_sensors_to_add {
+            add_test_session_sensor(db, session_sensor);
+        }
+
+        let result = db.get_sessions_sensors();
+        assert!(result.is_ok());
+
+        let session_sensors = result.unwrap();
+        assert_eq!(session_sensors.len(), session_sensors_to_add.len());
+
+        for test_session_sensor in &session_sensors_to_add {
+            let matched = session_sensors.iter().find(|s|
+                s.get_id() == test_session_sensor.get_id() &&
+                s.get_session_id() == test_session_sensor.get_session_id() &&
+                s.get_sensor_id() == test_session_sensor.get_sensor_id()
+            );
+
+            assert!(matched.is_some(), "Session sensor {:?} not found in db result", test_session_sensor.get_id());
+        }
+    }
+
+    fn test_get_session_sensors(db: &dyn Database) {
+        let user = User::new("user1".to_string(), "hunter2".to_string());
+        add_test_user(db, &user);
+
+        for session_id in 1..=2 {
+            add_test_session(db, &Session::new(session_id, user.get_username().to_string()));
+        }
+        for sensor_id in 1..=2 {
+            add_test_sensor(db, &Sensor::new(sensor_id, "Acceleration".to_string()));
+        }
+
+        let session_sensors_to_add = vec![
+            SessionSensor::new(1, 1, 1),
+            SessionSensor::new(2, 1, 2),
+        ];
+
+        for session_sensor in &session_sensors_to_add {
+            add_test_session_sensor(db, session_sensor);
+        }
+        add_test_session_sensor(db, &SessionSensor::new(3, 2, 1));
+
+        let result = db.get_session_sensors(1);
+        assert!(result.is_ok());
+
+        let session_sensors = result.unwrap();
+        assert_eq!(session_sensors.len(), session_sensors_to_add.len());
+
+        for test_session_sensor in &session_sensors_to_add {
+            let matched = session_sensors.iter().find(|s|
+                s.get_id() == test_session_sensor.get_id() &&
+                s.get_sensor_id() == test_session_sensor.get_sensor_id()
+            );
+
+            assert!(matched.is_some(), "Session sensor {:?} not found in db result", test_session_sensor.get_id());
+        }
+    }
+
+    fn test_get_session_sensor(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+
+        let result = db.get_session_sensor(*session_sensor.get_id());
+        assert!(result.is_ok());
+
+        let returned_session_sensor = result.unwrap();
+        assert_models_eq!(session_sensor, returned_session_sensor, [get_id, get_session_id, get_sensor_id]);
+    }
+
+    fn test_update_session_sensor(db: &dyn Database) {
+        let (session, _, original_session_sensor) = add_test_recording(db);
+
+        let session2 = Session::new(2, session.get_username().to_string());
+        add_test_session(db, &session2);
+
+        let sensor2 = Sensor::new(2, "GPS".to_string());
+        add_test_sensor(db, &sensor2);
+
+        let updated_session_sensor = SessionSensor::new(
+            *original_session_sensor.get_id(),
+            *session2.get_id(),
+            *sensor2.get_id(),
+        );
+
+        let result = db.update_session_sensor(*original_session_sensor.get_id(), &updated_session_sensor);
+        assert!(result.is_ok());
+
+        // Verify the session_sensor has been updated
+        let fetched_session_sensor = db
+            .get_session_sensor(*updated_session_sensor.get_id())
+            .expect("Fetch failed after update");
+        assert_models_eq!(updated_session_sensor, fetched_session_sensor, [get_id, get_session_id, get_sensor_id]);
+    }
+
+    fn test_delete_session_sensor(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+
+        let result = db.delete_session_sensor(*session_sensor.get_id());
+        assert!(result.is_ok());
+
+        // Verify that the session_sensor does not exist in the db
+        let fetch_session_sensor_result = db.get_session_sensor(*session_sensor.get_id());
+        assert!(fetch_session_sensor_result.is_err());
+    }
+
+    fn test_insert_session_sensor_data(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+
+        let session_sensor_data = test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1);
+
+        let result = db.insert_session_sensor_data(&session_sensor_data);
+        assert!(result.is_ok());
+
+        // Check db for new datapoint
+        let fetch_session_sensor_datapoint_result = db.get_session_sensor_datapoint(
+            *session_sensor.get_id(),
+            session_sensor_data.get_datetime()
+        );
+        assert!(fetch_session_sensor_datapoint_result.is_ok());
+
+        let fetched_session_sensor_data = fetch_session_sensor_datapoint_result.unwrap();
+        assert_models_eq!(session_sensor_data, fetched_session_sensor_data, [get_id, get_session_sensor_id, get_datetime, get_blob]);
+    }
+
+    fn test_batch_session_sensor_data(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+
+        let session_sensor_data = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1),
+            test_datapoint(&session_sensor, "2025-01-01T12:00:01", 2),
+            test_datapoint(&session_sensor, "2025-01-01T12:00:02", 3),
+        ];
+
+        let result = db.batch_session_sensor_data(&session_sensor_data);
+        assert!(result.is_ok());
+
+        let fetched = db
+            .get_sessions_sensor_data(*session.get_id())
+            .expect("Fetch failed after batch insert");
+        assert_eq!(fetched.len(), session_sensor_data.len());
+
+        for datapoint in &session_sensor_data {
+            let matched = fetched.iter().find(|d|
+                d.get_datetime() == datapoint.get_datetime() &&
+                d.get_session_sensor_id() == datapoint.get_session_sensor_id() &&
+                d.get_blob() == datapoint.get_blob()
+            );
+
+            assert!(matched.is_some(), "Datapoint {:?} not found in db result", datapoint.get_datetime());
+        }
+    }
+
+    fn test_batch_atomicity(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+
+        // A datapoint referencing a missing session sensor fails the whole batch
+        let orphan = SessionSensor::new(42, *session.get_id(), 42);
+        let batch = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1),
+            test_datapoint(&orphan, "2025-01-01T12:00:01", 2),
+        ];
+        assert!(matches!(db.batch_session_sensor_data(&batch), Err(Error::Conflict(_))));
+
+        // So does a batch repeating one of its own datapoints
+        let batch = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1),
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 2),
+        ];
+        assert!(matches!(db.batch_session_sensor_data(&batch), Err(Error::Conflict(_))));
+        assert!(db.get_sessions_sensor_data(*session.get_id()).unwrap().is_empty());
+
+        // An empty batch stores nothing and succeeds
+        assert!(db.batch_session_sensor_data(&[]).unwrap().is_empty());
+    }
+
+    fn test_import_skips_duplicates(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1));
+
+        // Datapoints already stored and those repeated in the batch are skipped, the first of each is kept
+        let batch = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 2),
+            test_datapoint(&session_sensor, "2025-01-01T12:00:01", 3),
+            test_datapoint(&session_sensor, "2025-01-01T12:00:01", 4),
+        ];
+        let inserted = db.import_session_sensor_data(&batch).expect("Failed to import datapoints");
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted[0].get_blob(), &json!({ "value": 3 }));
+
+        let mut values: Vec<Value> = db
+            .get_sessions_sensor_data(*session.get_id())
+            .unwrap()
+            .iter()
+            .map(|data| data.get_blob()["value"].clone())
+            .collect();
+        values.sort_by_key(|value| value.as_i64());
+        assert_eq!(values, vec![json!(1), json!(3)]);
+
+        // Datapoints of a missing session sensor still fail the whole batch
+        let orphan = SessionSensor::new(42, *session.get_id(), 42);
+        let batch = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:02", 5),
+            test_datapoint(&orphan, "2025-01-01T12:00:03", 6),
+        ];
+        assert!(matches!(db.import_session_sensor_data(&batch), Err(Error::Conflict(_))));
+        assert_eq!(db.get_sessions_sensor_data(*session.get_id()).unwrap().len(), 2);
+
+        // Datapoints without a session sensor are unique by session and datetime
+        let unassigned = |value: i64| {
+            SessionSensorData::new(
+                Some(*session.get_id()),
+                None,
+                "2025-01-01T12:00:00".to_string(),
+                json!({ "value": value }),
+            )
+        };
+        add_test_session_sensor_data(db, &unassigned(7));
+        assert!(matches!(db.insert_session_sensor_data(&unassigned(8)), Err(Error::Conflict(_))));
+        assert!(db.import_session_sensor_data(&[unassigned(9)]).unwrap().is_empty());
+        assert_eq!(db.get_sessions_sensor_data(*session.get_id()).unwrap().len(), 3);
+    }
+
+    fn test_get_sessions_sensors_data(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+
+        let session2 = Session::new(2, session.get_username().to_string());
+        add_test_session(db, &session2);
+        let session_sensor2 = SessionSensor::new(2, *session2.get_id(), *session_sensor.get_sensor_id());
+        add_test_session_sensor(db, &session_sensor2);
+
+        let session_sensor_data = vec![
+            test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1),
+            test_datapoint(&session_sensor2, "2025-01-01T12:00:00", 2),
+        ];
+        for datapoint in &session_sensor_data {
+            add_test_session_sensor_data(db, datapoint);
+        }
+
+        let result = db.get_sessions_sensors_data();
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().len(), session_sensor_data.len());
+    }
+
+    fn test_get_sessions_sensor_data(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+
+        let session2 = Session::new(2, session.get_username().to_string());
+        add_test_session(db, &session2);
+        let session_sensor2 = SessionSensor::new(2, *session2.get_id(), *session_sensor.get_sensor_id());
+        add_test_session_sensor(db, &session_sensor2);
+
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:01", 2));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor2, "2025-01-01T12:00:00", 3));
+
+        let session_sensor_data = db
+            .get_sessions_sensor_data(*session.get_id())
+            .expect("Failed to fetch session data");
+        assert_eq!(session_sensor_data.len(), 2);
+        assert!(session_sensor_data.iter().all(|d| d.get_id() == &Some(*session.get_id())));
+
+        let after = db
+            .get_sessions_sensor_data_after(*session.get_id(), "2025-01-01T12:00:00")
+            .expect("Failed to fetch session data after datetime");
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].get_datetime(), "2025-01-01T12:00:01");
+    }
+
+    fn test_time_range(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+
+        // Fractions of a second of different lengths, compared as times rather than text
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00.25", 2));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T12:00:00.5", 3));
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-02T00:00:00", 4));
+
+        let values_after = |datetime: &str| {
+            let mut values: Vec<i64> = db
+                .get_sessions_sensor_data_after(*session.get_id(), datetime)
+                .expect("Failed to fetch session data after datetime")
+                .iter()
+                .map(|data| data.get_blob()["value"].as_i64().unwrap())
+                .collect();
+            values.sort();
+            values
+        };
+
+        // The range is exclusive of its start
+        assert_eq!(values_after("2025-01-01T12:00:00"), vec![2, 3, 4]);
+        assert_eq!(values_after("2025-01-01T12:00:00.3"), vec![3, 4]);
+        assert_eq!(values_after("2025-01-01T12:00:00.500"), vec![4]);
+        assert_eq!(values_after("2024-12-31T23:59:59"), vec![1, 2, 3, 4]);
+        assert!(values_after("2025-01-02T00:00:00").is_empty());
+
+        assert!(db.get_sessions_sensor_data_after(*session.get_id(), "yesterday").is_err());
+    }
+
+    fn test_data_pages(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+        let sensor2 = Sensor::new(2, "GPS".to_string());
+        add_test_sensor(db, &sensor2);
+        let session_sensor2 = db
+            .insert_session_sensor(&SessionSensor::new(0, *session.get_id(), *sensor2.get_id()))
+            .expect("Failed to insert test session sensor");
+
+        // Both sensors record every second from 12:00:00 to 12:00:04, inserted out of order
+        for second in (0..5).rev() {
+            let datetime = format!("2025-01-01T12:00:0{second}");
+            add_test_session_sensor_data(db, &test_datapoint(&session_sensor, &datetime, second));
+            add_test_session_sensor_data(db, &test_datapoint(&session_sensor2, &datetime, 10 + second));
+        }
+
+        let mut values = Vec::new();
+        let mut after: Option<SessionSensorData> = None;
+        loop {
+            let page = db
+                .get_sessions_sensor_data_page(
+                    *session.get_id(),
+                    Some("2025-01-01T12:00:01"),
+                    Some("2025-01-01T12:00:04"),
+                    after.as_ref(),
+                    4,
+                )
+                .expect("Failed to fetch page of session data");
+            assert!(page.len() <= 4);
+            values.extend(page.iter().map(|data| data.get_blob()["value"].as_i64().unwrap()));
+            match page.last() {
+                Some(last) => after = Some(last.clone()),
+                None => break,
+            }
+        }
+        // Ordered by datetime then session sensor, every datapoint in the range exactly once
+        assert_eq!(values, vec![1, 11, 2, 12, 3, 13]);
+    }
+
+    fn test_get_session_sensor_data(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+
+        let sensor2 = Sensor::new(2, "GPS".to_string());
+        add_test_sensor(db, &sensor2);
+        let session_sensor2 = SessionSensor::new(2, *session.get_id(), *sensor2.get_id());
+        add_test_session_sensor(db, &session_sensor2);
+
+        // Both sensors of the session record at the same time
+        let session_sensor_data = test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1);
+        add_test_session_sensor_data(db, &session_sensor_data);
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor2, "2025-01-01T12:00:00", 2));
+
+        let result = db.get_session_sensor_data(*session_sensor.get_id());
+        assert!(result.is_ok());
+
+        let fetched = result.unwrap();
+        assert_eq!(fetched.len(), 1);
+        assert_models_eq!(session_sensor_data, fetched[0], [get_id, get_session_sensor_id, get_datetime, get_blob]);
+    }
+
+    fn test_get_session_sensor_datapoint(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+
+        let session_sensor_data = test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1);
+        add_test_session_sensor_data(db, &session_sensor_data);
+
+        let result = db.get_session_sensor_datapoint(
+            *session_sensor.get_id(),
+            session_sensor_data.get_datetime()
+        );
+        assert!(result.is_ok());
+
+        let fetched = result.unwrap();
+        assert_models_eq!(session_sensor_data, fetched, [get_id, get_session_sensor_id, get_datetime, get_blob]);
+
+        assert!(db.get_session_sensor_datapoint(*session_sensor.get_id(), "2025-01-01T12:00:01").is_err());
+    }
+
+    fn test_update_session_sensor_datapoint(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+
+        let session_sensor_data = test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1);
+        add_test_session_sensor_data(db, &session_sensor_data);
+
+        let updated = test_datapoint(&session_sensor, session_sensor_data.get_datetime(), 2);
+
+        let result = db.update_session_sensor_datapoint(
+            *session_sensor.get_id(),
+            session_sensor_data.get_datetime(),
+            &updated,
+        );
+        assert!(result.is_ok());
+
+        // Fetch and confirm the update was applied
+        let fetched = db
+            .get_session_sensor_datapoint(*session_sensor.get_id(), updated.get_datetime())
+            .expect("Fetch failed after update");
+
+        assert_models_eq!(updated, fetched, [get_id, get_session_sensor_id, get_datetime, get_blob]);
+    }
+
+    fn test_delete_session_sensor_datapoint(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+
+        let session_sensor_data = test_datapoint(&session_sensor, "2025-01-01T12:00:00", 1);
+        add_test_session_sensor_data(db, &session_sensor_data);
+
+        let result = db.delete_session_sensor_datapoint(
+            *session_sensor.get_id(),
+            session_sensor_data.get_datetime(),
+        );
+        assert!(result.is_ok());
+
+        // Verify that the datapoint does not exist in the db
+        let post_delete = db.get_session_sensor_datapoint(
+            *session_sensor.get_id(),
+            session_sensor_data.get_datetime()
+        );
+        assert!(post_delete.is_err());
+    }
+
+    fn test_prune_session_sensor_data(db: &dyn Database) {
+        let (_, _, session_sensor) = add_test_recording(db);
+        let session_sensor_id = *session_sensor.get_id();
+        for (second, datetime) in ["2025-01-01T12:00:02", "2025-01-01T12:00:00", "2025-01-01T12:00:01.5", "2025-01-02T00:00:00"]
+            .iter()
+            .enumerate()
+        {
+            add_test_session_sensor_data(db, &test_datapoint(&session_sensor, datetime, second as i64));
+        }
+
+        // Oldest first, and only those before the datetime
+        assert_eq!(db.count_session_sensor_data_before(session_sensor_id, "2025-01-02T00:00:00").unwrap(), 3);
+        let before = db.get_session_sensor_data_before(session_sensor_id, "2025-01-02T00:00:00", 2).unwrap();
+        let values: Vec<i64> = before.iter().map(|data| data.get_blob()["value"].as_i64().unwrap()).collect();
+        assert_eq!(values, vec![1, 2]);
+        let datetimes: Vec<String> = before.iter().map(|data| data.get_datetime().to_string()).collect();
+
+        let rollups = vec![SessionSensorRollup::new(session_sensor_id, "2025-01-01T12:00:00".to_string(), 60, 2, json!({}))];
+        let pruned = db.prune_session_sensor_data(session_sensor_id, &datetimes, &rollups).unwrap();
+        assert_eq!(pruned, 2);
+        assert_eq!(db.get_session_sensor_data(session_sensor_id).unwrap().len(), 2);
+        assert_eq!(db.get_session_sensor_rollups(session_sensor_id).unwrap(), rollups);
+
+        // A rollup of the same interval is replaced rather than duplicated
+        let rollups = vec![SessionSensorRollup::new(session_sensor_id, "2025-01-01T12:00:00".to_string(), 60, 3, json!({}))];
+        let pruned = db
+            .prune_session_sensor_data(session_sensor_id, &["2025-01-01T12:00:02".to_string()], &rollups)
+            .unwrap();
+        assert_eq!(pruned, 1);
+        assert_eq!(db.get_session_sensor_rollups(session_sensor_id).unwrap(), rollups);
+
+        // Rollups go with their session sensor
+        db.delete_session_sensor(session_sensor_id).unwrap();
+        assert!(db.get_session_sensor_rollups(session_sensor_id).unwrap().is_empty());
+    }
+
+    fn test_sensor_schemas(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+        let schema = SensorSchema::new(
+            "Acceleration".to_string(),
+            json!({ "type": "object", "required": ["value"] }),
+            SchemaPolicy::Reject,
+        );
+        assert_eq!(db.upsert_sensor_schema(&schema).unwrap(), schema);
+
+        // A second schema of the type replaces the first
+        let schema = SensorSchema::new("Acceleration".to_string(), json!({ "type": "object" }), SchemaPolicy::Quarantine);
+        db.upsert_sensor_schema(&schema).unwrap();
+        assert_eq!(db.get_sensor_schemas().unwrap(), vec![schema.clone()]);
+        assert_eq!(db.get_sensor_schema("Acceleration").unwrap(), schema);
+        assert!(matches!(db.get_sensor_schema("GPS"), Err(Error::NotFound(_))));
+
+        let quarantined = vec![
+            QuarantinedSensorData::new(
+                *session.get_id(),
+                *session_sensor.get_id(),
+                "2025-01-01T12:00:00".to_string(),
+                json!([1]),
+                vec!["data_blob: [1] is not of type \"object\"".to_string()],
+                "2025-01-02T00:00:00".to_string(),
+            ),
+            QuarantinedSensorData::new(
+                *session.get_id(),
+                *session_sensor.get_id(),
+                "2025-01-01T12:00:00".to_string(),
+                json!("x"),
+                Vec::new(),
+                "2025-01-02T00:00:01".to_string(),
+            ),
+        ];
+        db.quarantine_session_sensor_data(&quarantined).unwrap();
+        assert_eq!(db.get_quarantined_sensor_data(*session.get_id()).unwrap(), quarantined);
+
+        // Quarantined datapoints need a session sensor, and go with it
+        let orphan = QuarantinedSensorData::new(
+            *session.get_id(),
+            99,
+            "2025-01-01T12:00:00".to_string(),
+            json!({}),
+            Vec::new(),
+            "2025-01-02T00:00:00".to_string(),
+        );
+        assert!(db.quarantine_session_sensor_data(&[orphan]).is_err());
+        db.delete_session_sensor(*session_sensor.get_id()).unwrap();
+        assert!(db.get_quarantined_sensor_data(*session.get_id()).unwrap().is_empty());
+
+        db.delete_sensor_schema("Acceleration").unwrap();
+        assert!(db.get_sensor_schemas().unwrap().is_empty());
+        assert!(matches!(db.delete_sensor_schema("Acceleration"), Err(Error::NotFound(_))));
+    }
+
+    fn test_sensor_catalog(db: &dyn Database) {
+        let channels = serde_json::from_value(json!([
+            { "name": "celsius", "unit": "degC", "data_type": "number", "min": -40.0, "max": 125.0 }
+        ]))
+        .unwrap();
+        let sensor = Sensor::new(1, "Thermometer".to_string())
+            .with_metadata("Acme".to_string(), "T-100".to_string(), "SN-42".to_string(), "1.2.0".to_string())
+            .with_channels(channels, Some(10.0));
+        assert_eq!(db.insert_sensor(&sensor).unwrap(), sensor);
+        assert_eq!(db.get_sensor(1).unwrap(), sensor);
+
+        // Values left out of an update are kept
+        let update = Sensor::new(0, "Thermometer".to_string())
+            .with_metadata(String::new(), String::new(), String::new(), "1.3.0".to_string());
+        let updated = db.update_sensor(1, &update).unwrap();
+        assert_eq!((updated.get_firmware(), updated.get_serial_number()), ("1.3.0", "SN-42"));
+        assert_eq!(db.get_sensor(1).unwrap(), updated);
+
+        let calibration = |valid_from: &str, valid_to: Option<&str>| {
+            SensorCalibration::new(
+                0,
+                1,
+                "celsius".to_string(),
+                vec![-40.0, 0.125],
+                valid_from.to_string(),
+                valid_to.map(str::to_string),
+            )
+        };
+        let first = db.insert_sensor_calibration(&calibration("2025-01-01T00:00:00", None)).unwrap();
+        assert_eq!(first, calibration("2025-01-01T00:00:00", None).with_ids(*first.get_id(), 1));
+
+        // A new calibration ends the open ended one before it
+        let second = db
+            .insert_sensor_calibration(&calibration("2025-02-01T00:00:00", Some("2025-03-01T00:00:00")))
+            .unwrap();
+        assert_eq!(
+            db.get_sensor_calibrations(1).unwrap(),
+            vec![
+                calibration("2025-01-01T00:00:00", Some("2025-02-01T00:00:00")).with_ids(*first.get_id(), 1),
+                second.clone()
+            ]
+        );
+        let overlapping = calibration("2025-02-15T00:00:00", None);
+        assert!(matches!(db.insert_sensor_calibration(&overlapping), Err(Error::Conflict(_))));
+        assert_eq!(db.get_sensor_calibrations(1).unwrap().len(), 2);
+        assert!(matches!(
+            db.insert_sensor_calibration(&calibration("2025-01-01T00:00:00", None).with_ids(0, 2)),
+            Err(Error::NotFound(_))
+        ));
+
+        // The method and lookup table of a calibration are stored with it
+        let table = SensorCalibration::new(
+            0,
+            1,
+            "humidity".to_string(),
+            Vec::new(),
+            "2025-01-01T00:00:00".to_string(),
+            None,
+        )
+        .with_table(CalibrationMethod::Table, vec![(0.0, 0.0), (512.0, 40.5), (1024.0, 100.0)]);
+        let inserted = db.insert_sensor_calibration(&table).unwrap();
+        assert_eq!(inserted, table.clone().with_ids(*inserted.get_id(), 1));
+        assert!(db.get_sensor_calibrations(1).unwrap().contains(&inserted));
+
+        db.delete_sensor_calibration(1, *second.get_id()).unwrap();
+        assert!(matches!(db.delete_sensor_calibration(1, *second.get_id()), Err(Error::NotFound(_))));
+        assert!(matches!(db.delete_sensor_calibration(2, *first.get_id()), Err(Error::NotFound(_))));
+
+        // Calibrations go with their sensor
+        db.delete_sensor(1).unwrap();
+        assert!(db.get_sensor_calibrations(1).unwrap().is_empty());
+    }
+
+    fn test_session_summary(db: &dyn Database) {
+        let (session, _, session_sensor) = add_test_recording(db);
+        let gps = Sensor::new(2, "GPS".to_string());
+        add_test_sensor(db, &gps);
+        let other = SessionSensor::new(2, *session.get_id(), *gps.get_id());
+        add_test_session_sensor(db, &other);
+        for (datetime, value) in [
+            ("2025-01-01T00:00:00", 20),
+            ("2025-01-01T00:00:10", 22),
+            ("2025-01-01T00:02:10", 24),
+            ("2025-01-01T00:02:20.500", 26),
+        ] {
+            add_test_session_sensor_data(db, &test_datapoint(&session_sensor, datetime, value));
+        }
+        let blob = |blob: Value| SessionSensorData::new(Some(1), Some(2), "2025-01-01T00:00:05".to_string(), blob);
+        add_test_session_sensor_data(db, &blob(json!({ "lat": 44.5, "fix": true, "name": "a" })));
+        add_test_session_sensor_data(
+            db,
+            &SessionSensorData::new(Some(1), Some(2), "2025-01-01T00:00:06".to_string(), json!([1, 2])),
+        );
+
+        // Only numeric fields of object blobs are summarized
+        let summaries = db.get_session_sensor_summaries(1).unwrap();
+        assert_eq!(summaries.len(), 2);
+        let (first, second) = (&summaries[0], &summaries[1]);
+        assert_eq!(
+            (first.session_sensor_id, first.count, first.first.as_str(), first.last.as_str()),
+            (Some(1), 4, "2025-01-01T00:00:00", "2025-01-01T00:02:20.500")
+        );
+        let value = &first.fields["value"];
+        assert_eq!((value.count, value.min, value.max, value.mean), (4, 20.0, 26.0, 23.0));
+        assert!((value.stddev - (20.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert_eq!((second.session_sensor_id, second.count), (Some(2), 2));
+        assert_eq!(second.fields.keys().collect::<Vec<_>>(), vec!["lat"]);
+        assert_eq!((second.fields["lat"].count, second.fields["lat"].stddev), (1, 0.0));
+        assert!(db.get_session_sensor_summaries(2).unwrap().is_empty());
+
+        let gaps = db.get_session_sensor_gaps(1, 60.0).unwrap();
+        assert_eq!(
+            gaps,
+            vec![SummaryGap {
+                session_sensor_id: Some(1),
+                from: "2025-01-01T00:00:10".to_string(),
+                to: "2025-01-01T00:02:10".to_string(),
+                seconds: 120.0,
+            }]
+        );
+        assert_eq!(db.get_session_sensor_gaps(1, 5.0).unwrap().len(), 3);
+
+        // A cached summary is kept for its gap threshold until a datapoint of its session changes
+        let summary = SessionSummary::new(1, 60.0, summaries, gaps);
+        assert_eq!(db.get_cached_session_summary(1, 60.0).unwrap(), None);
+        db.cache_session_summary(&summary).unwrap();
+        assert_eq!(db.get_cached_session_summary(1, 60.0).unwrap(), Some(summary.clone()));
+        assert_eq!(db.get_cached_session_summary(1, 30.0).unwrap(), None);
+
+        add_test_session_sensor_data(db, &test_datapoint(&session_sensor, "2025-01-01T00:03:00", 28));
+        assert_eq!(db.get_cached_session_summary(1, 60.0).unwrap(), None);
+        db.cache_session_summary(&summary).unwrap();
+        db.delete_session_sensor_datapoint(1, "2025-01-01T00:03:00").unwrap();
+        assert_eq!(db.get_cached_session_summary(1, 60.0).unwrap(), None);
+
+        db.cache_session_summary(&summary).unwrap();
+        db.delete_session(1).unwrap();
+        assert_eq!(db.get_cached_session_summary(1, 60.0).unwrap(), None);
+        assert!(db.cache_session_summary(&summary).is_err());
+    }
+
+    fn test_alerts(db: &dyn Database) {
+        let (session, sensor, session_sensor) = add_test_recording(db);
+        let rule = |sensor_id: Option<i64>, session_id: Option<i64>, comparator: AlertComparator| {
+            AlertRule::new(0, sensor_id, session_id, "limit".to_string(), "x".to_string(), comparator, -1.5)
+        };
+        let sensor_rule = rule(Some(*sensor.get_id()), None, AlertComparator::Above).with_timing(30.0, 0.5);
+        let session_rule = rule(None, Some(*session.get_id()), AlertComparator::AtMost);
+        let sensor_rule = db.insert_alert_rule(&sensor_rule).unwrap();
+        let session_rule = db.insert_alert_rule(&session_rule).unwrap();
+        assert_ne!(sensor_rule.get_id(), session_rule.get_id());
+        assert_eq!(db.get_alert_rules().unwrap(), vec![sensor_rule.clone(), session_rule.clone()]);
+
+        let unknown = rule(Some(99), None, AlertComparator::Below);
+        assert!(matches!(db.insert_alert_rule(&unknown), Err(Error::NotFound(_))));
+
+        let event = |rule: &AlertRule, state: AlertState, datetime: &str| {
+            let (session_id, session_sensor_id) = (*session.get_id(), *session_sensor.get_id());
+            AlertEvent::new(0, *rule.get_id(), session_id, session_sensor_id, state, 10.25, datetime.to_string())
+        };
+        let triggered = event(&sensor_rule, AlertState::Triggered, "2025-01-01T00:00:30");
+        let triggered = db.insert_alert_event(&triggered).unwrap();
+        let resolved = event(&sensor_rule, AlertState::Resolved, "2025-01-01T00:01:00");
+        let resolved = db.insert_alert_event(&resolved.with_delivery(AlertDelivery::Skipped, 0)).unwrap();
+        let session_event = event(&session_rule, AlertState::Triggered, "2025-01-01T00:00:45");
+        let session_event = db.insert_alert_event(&session_event).unwrap();
+        assert_eq!(
+            db.get_alert_events(*session.get_id()).unwrap(),
+            vec![triggered.clone(), resolved.clone(), session_event.clone()]
+        );
+        assert_eq!(*triggered.get_delivery(), AlertDelivery::Pending);
+        assert!(db.get_alert_events(99).unwrap().is_empty());
+
+        db.update_alert_event_delivery(*triggered.get_id(), AlertDelivery::Delivered, 2).unwrap();
+        assert_eq!(
+            db.get_alert_events(*session.get_id()).unwrap()[0],
+            triggered.clone().with_delivery(AlertDelivery::Delivered, 2)
+        );
+        assert!(matches!(db.update_alert_event_delivery(999, AlertDelivery::Failed, 1), Err(Error::NotFound(_))));
+
+        // Deleting a rule deletes its events, deleting the session deletes its rules
+        db.delete_alert_rule(*session_rule.get_id()).unwrap();
+        assert!(matches!(db.delete_alert_rule(*session_rule.get_id()), Err(Error::NotFound(_))));
+        assert_eq!(db.get_alert_events(*session.get_id()).unwrap().len(), 2);
+        db.insert_alert_rule(&session_rule).unwrap();
+        db.delete_session(*session.get_id()).unwrap();
+        assert_eq!(db.get_alert_rules().unwrap(), vec![sensor_rule]);
+        assert!(db.get_alert_events(*session.get_id()).unwrap().is_empty());
+    }
+
+    fn test_derived_channels(db: &dyn Database) {
+        let channel = |sensor_type: &str, name: &str, expression: &str| {
+            DerivedChannel::new(sensor_type.to_string(), name.to_string(), expression.to_string(), String::new())
+        };
+        let magnitude = channel("Acceleration", "magnitude", "hypot(x, y, z)");
+        assert_eq!(db.insert_derived_channel(&magnitude).unwrap(), magnitude);
+        let speed = channel("GPS", "speed_kmh", "convert(speed, \"m/s\", \"km/h\")");
+        db.insert_derived_channel(&speed).unwrap();
+        let jerk = channel("Acceleration", "jerk", "x * 2");
+        db.insert_derived_channel(&jerk).unwrap();
+
+        // Names are unique per sensor type, and channels are listed by sensor type then name
+        assert!(matches!(
+            db.insert_derived_channel(&channel("Acceleration", "magnitude", "x")),
+            Err(Error::Conflict(_))
+        ));
+        db.insert_derived_channel(&channel("GPS", "magnitude", "speed")).unwrap();
+        let names: Vec<(String, String)> = db
+            .get_derived_channels()
+            .unwrap()
+            .iter()
+            .map(|channel| (channel.get_sensor_type().to_string(), channel.get_name().to_string()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Acceleration".to_string(), "jerk".to_string()),
+                ("Acceleration".to_string(), "magnitude".to_string()),
+                ("GPS".to_string(), "magnitude".to_string()),
+                ("GPS".to_string(), "speed_kmh".to_string()),
+            ]
+        );
+
+        db.delete_derived_channel("GPS", "magnitude").unwrap();
+        assert!(matches!(db.delete_derived_channel("GPS", "magnitude"), Err(Error::NotFound(_))));
+        assert_eq!(db.get_derived_channels().unwrap(), vec![jerk, magnitude, speed]);
+    }
+
+    mod memory {
+        use crate::data::memory_database::MemoryDatabase;
+        use crate::data::Database;
+
+        fn open() -> Option<Box<dyn Database>> {
+            Some(Box::new(MemoryDatabase::new()))
+        }
+
+        database_suite!(open());
+    }
+
+    #[cfg(feature = "sql")]
+    mod sqlite {
+        use rusqlite::Connection;
+
+        use crate::data::{Database, SqliteDatabase};
+
+        fn open() -> Option<Box<dyn Database>> {
+            let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+            let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
+            db.migrate().expect("Failed to initialize schema");
+            Some(Box::new(db))
+        }
+
+        database_suite!(open());
+    }
+
+    #[cfg(feature = "postgres")]
+    mod postgres {
+        use std::ops::Deref;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use postgres::{Client, NoTls};
+
+        use crate::data::migrations::MigrationState;
+        use crate::data::postgres_migrations::POSTGRES_MIGRATIONS;
+        use crate::data::{PostgresConfig, PostgresDatabase};
+
+        // Server the tests run against, such as postgresql://postgres@localhost:5432/postgres
+        const TEST_URL_VARIABLE: &str = "POSTGRES_TEST_URL";
+
+        static SCHEMAS: AtomicUsize = AtomicUsize::new(0);
+
+        // A schema of its own for every test, dropped together with its tables when the test ends
+        struct TestSchema {
+            name: String,
+            admin: Client,
+            database: PostgresDatabase,
+        }
+
+        impl TestSchema {
+            // None when no test server is configured
+            fn create() -> Option<Self> {
+                let url = match std::env::var(TEST_URL_VARIABLE) {
+                    Ok(url) => url,
+                    Err(_) => {
+                        eprintln!("{TEST_URL_VARIABLE} is not set, skipping PostgreSQL tests");
+                        return None;
+                    }
+                };
+                let name = format!(
+                    "tcp_server_test_{}_{}",
+                    std::process::id(),
+                    SCHEMAS.fetch_add(1, Ordering::SeqCst)
+                );
+
+                let mut admin = Client::connect(&url, NoTls).expect("Failed to connect to the test server");
+                admin
+                    .batch_execute(&format!("DROP SCHEMA IF EXISTS {name} CASCADE; CREATE SCHEMA {name}"))
+                    .expect("Failed to create test schema");
+
+                let mut config = url.parse::<postgres::Config>().expect("Invalid test server url");
+                config.options(&format!("-c search_path={name}"));
+                let database = PostgresDatabase::from_config(
+                    config,
+                    &PostgresConfig { connections: 2, ..Default::default() },
+                )
+                .expect("Failed to create test db");
+                Some(TestSchema { name, admin, database })
+            }
+
+            fn migrated() -> Option<Self> {
+                let schema = Self::create()?;
+                schema.database.migrate().expect("Failed to initialize schema");
+                Some(schema)
+            }
+        }
+
+        impl Deref for TestSchema {
+            type Target = PostgresDatabase;
+
+            fn deref(&self) -> &PostgresDatabase {
+                &self.database
+            }
+        }
+
+        impl Drop for TestSchema {
+            fn drop(&mut self) {
+                let _ = self.admin.batch_execute(&format!("DROP SCHEMA IF EXISTS {} CASCADE", self.name));
+            }
+        }
+
+        database_suite!(TestSchema::migrated());
+
+        #[test]
+        fn test_migrate_new_database() {
+            let Some(db) = TestSchema::create() else {
+                return;
+            };
+            let versions = POSTGRES_MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>();
+
+            // A dry run leaves every migration pending
+            assert_eq!(db.dry_run_migrations().expect("Dry run failed"), versions);
+            assert!(db
+                .migration_status()
+                .expect("Failed to read migration status")
+                .iter()
+                .all(|status| status.state == MigrationState::Pending));
+
+            assert_eq!(db.migrate().expect("Failed to migrate new database"), versions);
+            assert!(db
+                .migration_status()
+                .expect("Failed to read migration status")
+                .iter()
+                .all(|status| status.state == MigrationState::Applied));
+
+            // Running again has nothing left to apply
+            assert!(db.migrate().expect("Failed to migrate twice").is_empty());
+        }
+
+        #[test]
+        fn test_concurrent_migrations() {
+            let Some(db) = TestSchema::create() else {
+                return;
+            };
+
+            // Servers started together each apply some of the migrations, never the same one twice
+            let database: &PostgresDatabase = &db;
+            let mut applied = std::thread::scope(|scope| {
+                let runs = (0..3)
+                    .map(|_| scope.spawn(|| database.migrate().expect("Concurrent migration failed")))
+                    .collect::<Vec<_>>();
+                runs.into_iter().flat_map(|run| run.join().unwrap()).collect::<Vec<_>>()
+            });
+            applied.sort();
+            assert_eq!(applied, POSTGRES_MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>());
+        }
+    }
+}
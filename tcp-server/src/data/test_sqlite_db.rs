@@ -10,1111 +10,464 @@ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
 #[cfg(test)]
 mod tests {
     use rusqlite::{Connection, params};
-    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+    use serde_json::json;
+    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, SessionStatus, User};
     use crate::data::{Database, SqliteDatabase};
+    use crate::data::migrations::{Migration, MigrationState};
+    use crate::data::sqlite_migrations::{Migrator, MIGRATIONS};
+    use crate::data::sqlite_backup::{BackupConfig, SqliteBackup};
+    use crate::data::sqlite_pool::{SqlitePool, SqlitePoolConfig};
+    use crate::error::Error;
 
     /* Helpers */
 
-    macro_rules! assert_models_eq {
-        ($expected:expr, $actual:expr, [ $( $field:ident ),* ]) => {
-            $(
-                assert_eq!(
-                    $expected.$field(),
-                    $actual.$field(),
-                    concat!("Mismatch on field: ", stringify!($field))
-                );
-            )*
-        };
-    }
-
-
-    fn init_schema() -> Connection {
-        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
-
-        conn
-            .execute_batch(
-                r#"
-                CREATE TABLE IF NOT EXISTS User (
-                    username TEXT PRIMARY KEY,
-                    password_hash TEXT NOT NULL
-                );
-
-                CREATE TABLE IF NOT EXISTS Session (
-                    sessionID TEXT PRIMARY KEY,
-                    username TEXT NOT NULL,
-                    FOREIGN KEY (username) REFERENCES User(username) ON DELETE CASCADE
-                );
-
-                CREATE TABLE IF NOT EXISTS Sensor (
-                    sensorID TEXT PRIMARY KEY,
-                    type TEXT NOT NULL
-                );
-
-                CREATE TABLE IF NOT EXISTS Session_Sensor (
-                    session_sensorID TEXT PRIMARY KEY,
-                    sessionID TEXT NOT NULL,
-                    sensorID TEXT NOT NULL,
-                    FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
-                    FOREIGN KEY (sensorID) REFERENCES Sensor(sensorID) ON DELETE CASCADE
-                );
-
-                CREATE TABLE IF NOT EXISTS Session_Sensor_Data (
-                    datetime TEXT,
-                    session_sensorID TEXT,
-                    data_blob BLOB NOT NULL,
-                    PRIMARY KEY (datetime, session_sensorID),
-                    FOREIGN KEY (session_sensorID) REFERENCES Session_Sensor(session_sensorID) ON DELETE CASCADE
-                );
-            "#,
-            )
-            .expect("Failed to initialize schema");
-
-            conn
-    }
-
-    fn add_test_user(conn: &Connection, user: &User) {
-        conn
-            .execute(
-                "INSERT INTO User (username, password_hash) VALUES (?1, ?2)",
-                params![user.get_username(), user.get_password_hash()],
-            ).expect("Failed to insert test user");
-    }
-
-    fn add_test_session(conn: &Connection, session: &Session) {
-        conn
-            .execute(
-                "INSERT INTO Session (sessionID, username) VALUES (?1, ?2)",
-                params![session.get_id(), session.get_username()],
-            ).expect("Failed to insert test session");
-    }
-
-    fn add_test_sensor(conn: &Connection, sensor: &Sensor) {
-        conn
-            .execute(
-                "INSERT INTO Sensor (sensorID, type) VALUES (?1, ?2)",
-                params![sensor.get_id(), sensor.get_sensor_type()],
-            ).expect("Failed to insert test sensor");
-    }
-    
-    fn add_test_session_sensor(conn: &Connection, session_sensor: &SessionSensor) {
-        conn
-            .execute(
-                "INSERT INTO Session_Sensor (session_sensorID, sessionID, sensorID) VALUES (?1, ?2, ?3)",
-                params![session_sensor.get_id(), session_sensor.get_session_id(), session_sensor.get_sensor_id()]
-            ).expect("Failed to insert test session_sensor");
-    }
-
-    fn add_test_session_sensor_data(conn: &Connection, session_sensor_data: &SessionSensorData) {
-        conn
-            .execute(
-                "INSERT INTO Session_Sensor_Data (session_sensorID, datetime, data_blob) VALUES (?1, ?2, ?3)",
-                params![session_sensor_data.get_id(), session_sensor_data.get_datetime(), session_sensor_data.get_blob()]
-            ).expect("Failed to insert test session_sensor_data");
-    }
-
-    /* Tests */
-
- 
-    /*
-    #[test]
-    fn is_admin() {
-    }
-
-    #[test]
-    fn login() {
-    }
-
-    #[test]
-    fn logout() {
-    }
-
-    #[test]
-    fn renew_session() {
-    }
-    */
-
-    #[test]
-    fn test_insert_user() {
-        let conn = init_schema();
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let user = User::new("NewUser".to_string(), "pwordHashed".to_string());
-
-        let result = db.insert_user(&user);
-        assert!(result.is_ok());
+    // Schema as it was before migrations were versioned
+    const BASELINE_SCHEMA: &str = r#"
+        CREATE TABLE IF NOT EXISTS User (
+            username TEXT PRIMARY KEY,
+            password_hash TEXT NOT NULL
+        );
 
-        // Check db for new user
-        let fetch_user_result = db.get_user(user.get_username());
-        assert!(fetch_user_result.is_ok());
+        CREATE TABLE IF NOT EXISTS Session (
+            sessionID INTEGER PRIMARY KEY,
+            username TEXT NOT NULL,
+            FOREIGN KEY (username) REFERENCES User(username) ON DELETE CASCADE
+        );
 
-        let fetched_user = fetch_user_result.unwrap();
-        assert_models_eq!(user, fetched_user, [get_username, get_password_hash]);
-    }
+        CREATE TABLE IF NOT EXISTS Sensor (
+            sensorID INTEGER PRIMARY KEY,
+            type TEXT NOT NULL
+        );
 
-    #[test]
-    fn test_get_users() {
-        let conn = init_schema();
-        
-        let users_to_add = vec![
-            User::new("user1".to_string(), "pwordHashed".to_string()),
-            User::new("user2".to_string(), "my_password123".to_string()),
-            User::new("user3".to_string(), "hunter2".to_string()),
-        ];
+        CREATE TABLE IF NOT EXISTS Session_Sensor (
+            session_sensorID INTEGER PRIMARY KEY,
+            sessionID INTEGER NOT NULL,
+            sensorID INTEGER NOT NULL,
+            FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE,
+            FOREIGN KEY (sensorID) REFERENCES Sensor(sensorID) ON DELETE CASCADE,
+            UNIQUE (sessionID, sensorID)
+        );
 
-        for user in &users_to_add {
-            add_test_user(&conn, user);
+        CREATE TABLE IF NOT EXISTS Session_Sensor_Data (
+            datetime TEXT,
+            sessionID INTEGER,
+            data_blob TEXT NOT NULL,
+            PRIMARY KEY (datetime, sessionID),
+            FOREIGN KEY (sessionID) REFERENCES Session(sessionID) ON DELETE CASCADE
+        );
+    "#;
+
+    fn table_columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut statement = conn
+            .prepare("SELECT name FROM pragma_table_info(?1)")
+            .expect("Failed to prepare column query");
+        let columns = statement
+            .query_map(params![table], |row| row.get(0))
+            .expect("Failed to query columns")
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .expect("Failed to read columns");
+        columns
+    }
+
+    fn test_datapoint(session_sensor: &SessionSensor, datetime: &str, value: i64) -> SessionSensorData {
+        SessionSensorData::new(
+            Some(*session_sensor.get_session_id()),
+            Some(*session_sensor.get_id()),
+            datetime.to_string(),
+            json!({ "value": value }),
+        )
+    }
+
+    // Database file removed together with its WAL files when dropped
+    struct TempDatabase {
+        path: String,
+    }
+
+    impl TempDatabase {
+        fn new(name: &str) -> Self {
+            let mut path = std::env::temp_dir();
+            path.push(format!("tcp_server_{name}_{}.db", std::process::id()));
+            let temp = TempDatabase { path: path.to_string_lossy().to_string() };
+            temp.remove();
+            temp
         }
 
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_users();
-        assert!(result.is_ok());
-
-        let users = result.unwrap();
-        assert_eq!(users.len(), users_to_add.len());
-
-        for test_user in &users_to_add {
-            let matched = users.iter().find(|u|
-                u.get_username() == test_user.get_username()  &&
-                u.get_password_hash() == test_user.get_password_hash()
-            );
-
-            assert!(matched.is_some(), "User {:?} not found in db result", test_user.get_username());
+        fn remove(&self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{suffix}", self.path));
+            }
         }
     }
 
-    #[test]
-    fn test_get_user() {
-        let conn = init_schema();
-        
-        let user = User::new("NewUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_user(user.get_username());
-        assert!(result.is_ok());
-
-        let returned_user = result.unwrap();
-        assert_models_eq!(user, returned_user, [get_username, get_password_hash]);
-    }
-
-    #[test]
-    fn test_update_user() {
-        let conn = init_schema();
-        
-        let original_user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &original_user);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-        
-        // Can't change username, it's the primary key
-        let updated_user = User::new(original_user.get_username().to_string(), "new_password".to_string());
-
-        let result = db.update_user(original_user.get_username(), &updated_user);
-        assert!(result.is_ok());
-
-        // Verify the user has been updated
-        let fetch_updated_result = db.get_user(updated_user.get_username());
-        assert!(fetch_updated_result.is_ok());
-
-        let fetched_user = fetch_updated_result.unwrap();
-        assert_models_eq!(updated_user, fetched_user, [get_username, get_password_hash]);
-    }
-
-    #[test]
-    fn test_delete_user() {
-        let conn = init_schema();
-
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.delete_user(user.get_username());
-        assert!(result.is_ok());
-
-        // Verify that the user does not exist in the db
-        let fetch_user_result = db.get_user(user.get_username());
-        assert!(fetch_user_result.is_err());
-    }
-
-    #[test]
-    fn test_insert_sensor() {
-        let conn = init_schema();
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let sensor = Sensor::new("NewSensor".to_string(), "Acceleration".to_string());
-
-        let result = db.insert_sensor(&sensor);
-        assert!(result.is_ok());
-
-        // Check db for new sensor
-        let fetch_sensor_result = db.get_sensor(sensor.get_id());
-        assert!(fetch_sensor_result.is_ok());
-
-        let fetched_sensor = fetch_sensor_result.unwrap();
-        assert_models_eq!(sensor, fetched_sensor, [get_id, get_sensor_type]);
-    }
-
-    #[test]
-    fn test_get_sensors() {
-        let conn = init_schema();
-        
-        let sensors_to_add = vec![
-            Sensor::new("sensor1".to_string(), "Acceleration".to_string()),
-            Sensor::new("sensor2".to_string(), "GPS".to_string()),
-            Sensor::new("sensor3".to_string(), "Altitude".to_string()),
-        ];
-
-        for sensor in &sensors_to_add { 
-            add_test_sensor(&conn, sensor);
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            self.remove();
         }
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_sensors();
-        assert!(result.is_ok());
-
-        let sensors = result.unwrap();
-        assert_eq!(sensors.len(), sensors_to_add.len());
-
-        for test_sensor in &sensors_to_add {
-            let matched = sensors.iter().find(|s|
-                s.get_id() == test_sensor.get_id()  &&
-                s.get_sensor_type() == test_sensor.get_sensor_type()
-            );
-
-            assert!(matched.is_some(), "Sensor {:?} not found in db result", test_sensor.get_id());
-        }
-    }
-
-    #[test]
-    fn test_get_sensor() {
-        let conn = init_schema();
-
-        let sensor = Sensor::new("NewSensor".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_sensor(sensor.get_id());
-        assert!(result.is_ok());
-
-        let returned_sensor = result.unwrap();
-        assert_models_eq!(sensor, returned_sensor, [get_id, get_sensor_type]);
-    }
-
-    #[test]
-    fn test_update_sensor() {
-        let conn = init_schema();
-        
-        let original_sensor = Sensor::new("TestSensor".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &original_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-        
-        // Can't change id, it's the primary key
-        let updated_sensor = Sensor::new(original_sensor.get_id().to_string(), "GPS".to_string());
-
-        let result = db.update_sensor(original_sensor.get_id(), &updated_sensor);
-        assert!(result.is_ok());
-
-        // Verify the user has been updated
-        let fetch_updated_result = db.get_sensor(updated_sensor.get_id());
-        assert!(fetch_updated_result.is_ok());
-
-        let fetched_sensor = fetch_updated_result.unwrap();
-        assert_models_eq!(updated_sensor, fetched_sensor, [get_id, get_sensor_type]);
-    }
-
-    #[test]
-    fn test_delete_sensor() {
-        let conn = init_schema();
-
-        let sensor = Sensor::new("TestSensor".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.delete_sensor(sensor.get_id());
-        assert!(result.is_ok());
-
-        // Verify that the user does not exist in the db
-        let fetch_sensor_result = db.get_sensor(sensor.get_id());
-        assert!(fetch_sensor_result.is_err());
-    }
-
-    #[test]
-    fn test_insert_session() {
-        let conn = init_schema();
-
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-        
-        let session = Session::new("TestSession".to_string(), "TestUser".to_string());
-
-        let result = db.insert_session(&session);
-        assert!(result.is_ok());
-
-        // Check db for new session 
-        let fetch_session_result = db.get_session(session.get_id());
-        assert!(fetch_session_result.is_ok());
-
-        let fetched_session = fetch_session_result.unwrap();
-        assert_models_eq!(session, fetched_session, [get_id, get_username]);
     }
-    
-    #[test]
-    fn test_get_session() {
-        let conn = init_schema();
-        
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("TestSession".to_string(), "TestUser".to_string());
-        add_test_session(&conn, &session);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_session(session.get_id());
-        assert!(result.is_ok());
-
-        let returned_session = result.unwrap();
-        assert_models_eq!(session, returned_session, [get_id, get_username]);
-    }
-
-    #[test]
-    fn test_get_session_user() {
-        let conn = init_schema();
-
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
 
-        let session = Session::new("session123".to_string(), "TestUser".to_string());
-        add_test_session(&conn, &session);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_session_user(session.get_id());
-        assert!(result.is_ok());
-
-        let returned_user = result.unwrap();
-        assert_models_eq!(user, returned_user, [get_username, get_password_hash]);
-    }
-
-
-    #[test]
-    fn test_get_user_sessions() {
-        let conn = init_schema();
-
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let sessions_to_add = vec![
-            Session::new("session1".to_string(), "TestUser".to_string()),
-            Session::new("session2".to_string(), "TestUser".to_string()),
-            Session::new("session3".to_string(), "TestUser".to_string()),
-        ];
-
-        for session in &sessions_to_add { 
-            add_test_session(&conn, session);
-        }
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_user_sessions(user.get_username());
-        assert!(result.is_ok());
-
-        let sessions = result.unwrap();
-        assert_eq!(sessions.len(), sessions_to_add.len());
-
-        for test_session in &sessions_to_add {
-            let matched = sessions.iter().find(|s|
-                s.get_id() == test_session.get_id()  &&
-                s.get_username() == test_session.get_username()
-            );
-
-            assert!(matched.is_some(), "Session {:?} not found in db result", test_session.get_id());
+    // File database with the schema and one recording session sensor
+    fn init_file_database(temp: &TempDatabase, config: &SqlitePoolConfig) -> (SqliteDatabase, SessionSensor) {
+        let db = SqliteDatabase::new(&temp.path, config).expect("Failed to open file database");
+        db.migrate().expect("Failed to initialize schema");
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .expect("Failed to insert test user");
+        let session = db
+            .insert_session(&Session::new(0, "user1".to_string()))
+            .expect("Failed to insert test session");
+        let sensor = db
+            .insert_sensor(&Sensor::new(0, "Acceleration".to_string()))
+            .expect("Failed to insert test sensor");
+        let session_sensor = db
+            .insert_session_sensor(&SessionSensor::new(0, *session.get_id(), *sensor.get_id()))
+            .expect("Failed to insert test session sensor");
+        (db, session_sensor)
+    }
+
+    fn datapoint_batch(session_sensor: &SessionSensor, batch: usize, size: usize) -> Vec<SessionSensorData> {
+        (0..size)
+            .map(|i| {
+                let n = batch * size + i;
+                let datetime = format!("2025-01-01T12:{:02}:{:02}.{:03}", n / 60_000, n / 1_000 % 60, n % 1_000);
+                test_datapoint(session_sensor, &datetime, n as i64)
+            })
+            .collect()
+    }
+
+    // Backup directory of a test, removed with everything in it when dropped
+    struct TempBackups {
+        config: BackupConfig,
+    }
+
+    impl TempBackups {
+        fn new(name: &str, keep: usize) -> Self {
+            let mut path = std::env::temp_dir();
+            path.push(format!("tcp_server_{name}_backups_{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            TempBackups {
+                config: BackupConfig {
+                    directory: path.to_string_lossy().to_string(),
+                    keep,
+                    interval_minutes: None,
+                },
+            }
         }
     }
 
-    #[test]
-    fn test_get_all_sessions() {
-        let conn = init_schema();
-
-        let user1 = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user1);
-
-        let user2 = User::new("other_user".to_string(), "my_password123".to_string());
-        add_test_user(&conn, &user2);
-
-        let sessions_to_add = vec![
-            Session::new("session1".to_string(), "TestUser".to_string()),
-            Session::new("session2".to_string(), "TestUser".to_string()),
-            Session::new("session3".to_string(), "TestUser".to_string()),
-            Session::new("session4".to_string(), "other_user".to_string()),
-            Session::new("session5".to_string(), "other_user".to_string()),
-        ];
-
-        for session in &sessions_to_add { 
-            add_test_session(&conn, session);
+    impl Drop for TempBackups {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.config.directory);
         }
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_all_sessions();
-        assert!(result.is_ok());
-
-        let sessions = result.unwrap();
-        assert_eq!(sessions.len(), sessions_to_add.len());
-
-        for test_session in &sessions_to_add {
-            let matched = sessions.iter().find(|s|
-                s.get_id() == test_session.get_id()  &&
-                s.get_username() == test_session.get_username()
-            );
-
-            assert!(matched.is_some(), "Session {:?} not found in db result", test_session.get_id());
-        }
-    }
-    
-    #[test]
-    fn test_update_session() {
-        let conn = init_schema();
-
-        let user1 = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user1);
-
-        let user2 = User::new("other_user".to_string(), "my_password123".to_string());
-        add_test_user(&conn, &user2);
-        
-        let original_session = Session::new("session1".to_string(), "TestUser".to_string());
-        add_test_session(&conn, &original_session);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-        
-        // Can't change id, it's the primary key
-        let updated_session = Session::new(original_session.get_id().to_string(), "other_user".to_string());
-
-        let result = db.update_session(original_session.get_id(), &updated_session);
-        assert!(result.is_ok());
-
-        // Verify the user has been updated
-        let fetch_updated_result = db.get_session(updated_session.get_id());
-        assert!(fetch_updated_result.is_ok());
-
-        let fetched_session = fetch_updated_result.unwrap();
-        assert_models_eq!(updated_session, fetched_session, [get_id, get_username]);
     }
 
-    #[test]
-    fn test_delete_session() {
-        let conn = init_schema();
-
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), "TestUser".to_string());
-        add_test_session(&conn, &session);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.delete_session(session.get_id());
-        assert!(result.is_ok());
-
-        // Verify that the user does not exist in the db
-        let fetch_session_result = db.get_session(session.get_id());
-        assert!(fetch_session_result.is_err());
-    }
-
-    #[test]
-    fn test_insert_session_sensor() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let session_sensor = SessionSensor::new("session_sensor1".to_string(), 
-            session.get_id().to_string(), sensor.get_id().to_string());
-
-        let result = db.insert_session_sensor(&session_sensor);
-        assert!(result.is_ok());
-
-        // Check db for new session_sensor
-        let fetch_session_sensor_result = db.get_session_sensor(session_sensor.get_id());
-        assert!(fetch_session_sensor_result.is_ok());
-
-        let fetched_session_sensor = fetch_session_sensor_result.unwrap();
-        assert_models_eq!(session_sensor, fetched_session_sensor, [get_id, get_session_id, get_sensor_id]);
-    }
-
-    #[test]
-    fn test_get_sessions_sensors() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let sessions_sensors_to_add = vec![
-            SessionSensor::new("session_sensor1".to_string(), 
-                session.get_id().to_string(), sensor.get_id().to_string()),
-
-            SessionSensor::new("session_sensor2".to_string(), 
-                session.get_id().to_string(), sensor.get_id().to_string()),
-
-            SessionSensor::new("session_sensor3".to_string(), 
-                session.get_id().to_string(), sensor.get_id().to_string()),
-        ];
-
-        for session_sensor in &sessions_sensors_to_add { 
-            add_test_session_sensor(&conn, session_sensor);
-        }
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_sessions_sensors();
-        assert!(result.is_ok());
-
-        let sessions_sensors = result.unwrap();
-        assert_eq!(sessions_sensors.len(), sessions_sensors_to_add.len());
-
-        for test_session_sensor in &sessions_sensors_to_add {
-            let matched = sessions_sensors.iter().find(|s|
-                s.get_id() == test_session_sensor.get_id()  &&
-                s.get_session_id() == test_session_sensor.get_session_id()  &&
-                s.get_sensor_id() == test_session_sensor.get_sensor_id()
-            );
-
-            assert!(matched.is_some(), "SessionSensor {:?} not found in db result", test_session_sensor.get_id());
-        }
-    }
-
-    #[test]
-    fn test_get_session_sensors() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let sessions_sensors_to_add = vec![
-            SessionSensor::new("session_sensor1".to_string(), 
-                session.get_id().to_string(), sensor.get_id().to_string()),
-
-            SessionSensor::new("session_sensor2".to_string(), 
-                session.get_id().to_string(), sensor.get_id().to_string()),
-
-            SessionSensor::new("session_sensor3".to_string(), 
-                session.get_id().to_string(), sensor.get_id().to_string()),
-        ];
-
-        for session_sensor in &sessions_sensors_to_add { 
-            add_test_session_sensor(&conn, session_sensor);
-        }
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_session_sensors(session.get_id());
-        assert!(result.is_ok());
-
-        let sessions_sensors = result.unwrap();
-        assert_eq!(sessions_sensors.len(), sessions_sensors_to_add.len());
-
-        for test_session_sensor in &sessions_sensors_to_add {
-            let matched = sessions_sensors.iter().find(|s|
-                s.get_id() == test_session_sensor.get_id()  &&
-                s.get_session_id() == test_session_sensor.get_session_id()  &&
-                s.get_sensor_id() == test_session_sensor.get_sensor_id()
-            );
-
-            assert!(matched.is_some(), "SessionSensor {:?} not found in db result", test_session_sensor.get_id());
-        }
-    }
-
-    #[test]
-    fn test_get_session_sensor() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new("session_sensor1".to_string(), 
-            session.get_id().to_string(), sensor.get_id().to_string());
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.get_session_sensor(session_sensor.get_id());
-        assert!(result.is_ok());
+    /* Tests */
 
-        let returned_session_sensor = result.unwrap();
-        assert_models_eq!(session_sensor, returned_session_sensor, [get_id, get_session_id, get_sensor_id]);
-    }
+    /* Migrations */
 
     #[test]
-    fn test_update_session_sensor() {
-        let conn = init_schema();
-        
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session1 = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session1);
-
-        let session2 = Session::new("session2".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session2);
-
-        let sensor1 = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor1);
-
-        let sensor2 = Sensor::new("sensor2".to_string(), "GPS".to_string());
-        add_test_sensor(&conn, &sensor2);
-
-        let original_session_sensor = SessionSensor::new("session_sensor1".to_string(), 
-            session1.get_id().to_string(), sensor1.get_id().to_string());
-        add_test_session_sensor(&conn, &original_session_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let updated_session_sensor = SessionSensor::new(original_session_sensor.get_id().to_string(), 
-            session2.get_id().to_string(), sensor2.get_id().to_string());
+    fn test_migrate_new_database() {
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        let migrator = Migrator::new(&conn);
 
-        let result = db.update_session_sensor(original_session_sensor.get_id(), &updated_session_sensor);
-        assert!(result.is_ok());
+        let applied = migrator.migrate().expect("Failed to migrate new database");
+        assert_eq!(applied, MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>());
 
-        // Verify the session_sensor has been updated
-        let fetch_updated_result = db.get_session_sensor(updated_session_sensor.get_id());
-        assert!(fetch_updated_result.is_ok());
+        assert!(migrator
+            .status()
+            .expect("Failed to read migration status")
+            .iter()
+            .all(|status| status.state == MigrationState::Applied));
 
-        let fetched_session_sensor = fetch_updated_result.unwrap();
-        assert_models_eq!(updated_session_sensor, fetched_session_sensor, [get_id, get_session_id, get_sensor_id]);
+        // Running again has nothing left to apply
+        assert!(migrator.migrate().expect("Failed to migrate twice").is_empty());
     }
 
     #[test]
-    fn test_delete_session_sensor() {
-        let conn = init_schema();
-
-        let user = User::new("TestUser".to_string(), "pwordHashed".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), "TestUser".to_string());
-        add_test_session(&conn, &session);
+    fn test_upgrade_from_baseline_schema() {
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        conn.execute_batch(BASELINE_SCHEMA).expect("Failed to create baseline schema");
+        conn.execute_batch(
+            r#"
+            INSERT INTO User VALUES ('user1', 'hunter2');
+            INSERT INTO Session (sessionID, username) VALUES (1, 'user1'), (2, 'user1'), (3, 'user1');
+            INSERT INTO Sensor VALUES (1, 'Acceleration'), (2, 'GPS');
+            INSERT INTO Session_Sensor VALUES (10, 1, 1), (20, 2, 1), (21, 2, 2);
+            INSERT INTO Session_Sensor_Data VALUES
+                ('2025-01-01T12:00:00', 1, '{"value":1}'),
+                ('2025-01-01T12:00:01', 2, '{"value":2}');
+            "#,
+        ).expect("Failed to add baseline data");
 
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
+        let status = Migrator::new(&conn).status().expect("Failed to read migration status");
+        assert_eq!(status[0].state, MigrationState::Applied, "Baseline schema should be recognized");
+        assert!(status[1..].iter().all(|status| status.state == MigrationState::Pending));
 
-        let session_sensor = SessionSensor::new("session_sensor1".to_string(), 
-            session.get_id().to_string(), sensor.get_id().to_string());
-        add_test_session_sensor(&conn, &session_sensor);
+        let applied = Migrator::new(&conn).migrate().expect("Failed to upgrade baseline database");
+        assert_eq!(applied, MIGRATIONS[1..].iter().map(|m| m.version).collect::<Vec<_>>());
+        assert!(table_columns(&conn, "Session_Sensor_Data").contains(&"session_sensorID".to_string()));
 
         let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
 
-        let result = db.delete_session_sensor(session_sensor.get_id());
-        assert!(result.is_ok());
+        // Sessions that held data are finished recordings, the rest have not started
+        assert_eq!(*db.get_session(1).unwrap().get_status(), SessionStatus::Stopped);
+        assert_eq!(*db.get_session(2).unwrap().get_status(), SessionStatus::Stopped);
+        assert_eq!(*db.get_session(3).unwrap().get_status(), SessionStatus::Pending);
 
-        // Verify that the user does not exist in the db
-        let fetch_session_sensor_result = db.get_session(session_sensor.get_id());
-        assert!(fetch_session_sensor_result.is_err());
+        // Datapoints only gain a sensor when their session has exactly one
+        let datapoints = db.get_sessions_sensors_data().expect("Failed to fetch migrated data");
+        assert_eq!(datapoints.len(), 2);
+        let session_sensor_of = |session_id: i64| {
+            *datapoints.iter().find(|d| d.get_id() == &Some(session_id)).unwrap().get_session_sensor_id()
+        };
+        assert_eq!(session_sensor_of(1), Some(10));
+        assert_eq!(session_sensor_of(2), None);
+        assert_eq!(db.get_session_sensor_data(10).unwrap()[0].get_blob(), &json!({ "value": 1 }));
     }
 
     #[test]
-    fn test_insert_session_sensor_data() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new("session_sensor1".to_string(), 
-            session.get_id().to_string(), sensor.get_id().to_string());
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let session_sensor_data = SessionSensorData::new(
-            session_sensor.get_id().to_string(),
-            "01/01/2025U12:00:00".to_string(), 
-            "content".as_bytes().to_vec()
-        );
-
-        let result = db.insert_session_sensor_data(&session_sensor_data);
-        assert!(result.is_ok());
-
-        // Check db for new session_sensor
-        let fetch_session_sensor_datapoint_result = db.get_session_sensor_datapoint(
-            session_sensor_data.get_id(), 
-            session_sensor_data.get_datetime()
-        );
-        assert!(fetch_session_sensor_datapoint_result.is_ok());
+    fn test_adopt_unversioned_database() {
+        // Databases migrated before versioning existed have the changes but no record of them
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        Migrator::with_migrations(&conn, &MIGRATIONS[..3]).migrate().expect("Failed to initialize schema");
+        conn.execute_batch("DROP TABLE Schema_Migrations").expect("Failed to drop migration table");
 
-        let fetched_session_sensor_data = fetch_session_sensor_datapoint_result.unwrap();
-        assert_models_eq!(session_sensor_data, fetched_session_sensor_data, [get_id, get_datetime, get_blob]);
+        // only the migrations released since versioning existed are applied
+        let applied = Migrator::new(&conn).migrate().expect("Failed to adopt unversioned database");
+        assert_eq!(applied, MIGRATIONS[3..].iter().map(|m| m.version).collect::<Vec<_>>());
+        assert!(Migrator::new(&conn)
+            .status()
+            .expect("Failed to read migration status")
+            .iter()
+            .all(|status| status.state == MigrationState::Applied));
     }
 
-
     #[test]
-    fn test_batch_session_sensor_data() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let data_blobs = vec![
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:00:00".to_string(),
-                "data1".as_bytes().to_vec(),
-            ),
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:01:00".to_string(),
-                "data2".as_bytes().to_vec(),
-            ),
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:02:00".to_string(),
-                "data3".as_bytes().to_vec(),
-            ),
-        ];
-
-        // Insert them using batch function
-        let result = db.batch_session_sensor_data(&data_blobs);
-        assert!(result.is_ok());
+    fn test_dry_run_rolls_back() {
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        conn.execute_batch(BASELINE_SCHEMA).expect("Failed to create baseline schema");
 
-        // Fetch and verify each inserted datapoint
-        for original in data_blobs.iter() {
-            let fetched = db
-                .get_session_sensor_datapoint(original.get_id(), original.get_datetime())
-                .expect("Failed to fetch inserted session sensor data");
+        let pending = Migrator::new(&conn).dry_run().expect("Dry run failed");
+        assert_eq!(pending, MIGRATIONS[1..].iter().map(|m| m.version).collect::<Vec<_>>());
 
-            assert_models_eq!(original, fetched, [get_id, get_datetime, get_blob]);
-        }
+        // Nothing was changed, not even the metadata table
+        assert!(!table_columns(&conn, "Session").contains(&"status".to_string()));
+        assert!(table_columns(&conn, "Schema_Migrations").is_empty());
     }
 
     #[test]
-    fn test_get_sessions_sensors_data() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let expected_data = vec![
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:00:00".to_string(),
-                "data1".as_bytes().to_vec(),
-            ),
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:01:00".to_string(),
-                "data2".as_bytes().to_vec(),
-            ),
+    fn test_failed_migration_rolls_back() {
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        let migrations = [
+            Migration { version: 1, name: "create", sql: "CREATE TABLE Example (id INTEGER);", detect: None },
+            Migration { version: 2, name: "broken", sql: "ALTER TABLE Example ADD COLUMN a TEXT; SELECT * FROM Missing;", detect: None },
         ];
 
-        db.batch_session_sensor_data(&expected_data).expect("Batch insert failed");
+        assert!(Migrator::with_migrations(&conn, &migrations).migrate().is_err());
 
-        let fetched_result = db.get_sessions_sensors_data();
-        assert!(fetched_result.is_ok());
-
-        let mut fetched_data = fetched_result.unwrap();
-        let mut expected_data = expected_data;
-
-        // Sort by datetime for deterministic comparison
-        fetched_data.sort_by(|a, b| a.get_datetime().cmp(b.get_datetime()));
-        expected_data.sort_by(|a, b| a.get_datetime().cmp(b.get_datetime()));
-
-        assert_eq!(fetched_data.len(), expected_data.len());
-
-        for (expected, actual) in expected_data.iter().zip(fetched_data.iter()) {
-            assert_models_eq!(expected, actual, [get_id, get_datetime, get_blob]);
-        }
+        // The first migration stays applied, the broken one left no trace
+        assert_eq!(table_columns(&conn, "Example"), vec!["id".to_string()]);
+        let status = Migrator::with_migrations(&conn, &migrations).status().unwrap();
+        assert_eq!(status[0].state, MigrationState::Applied);
+        assert_eq!(status[1].state, MigrationState::Pending);
     }
 
     #[test]
-    fn test_get_sessions_sensor_data() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let expected_data = vec![
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:00:00".to_string(),
-                "dataA".as_bytes().to_vec(),
-            ),
-            SessionSensorData::new(
-                session_sensor.get_id().to_string(),
-                "01/01/2025U12:01:00".to_string(),
-                "dataB".as_bytes().to_vec(),
-            ),
-        ];
-
-        db.batch_session_sensor_data(&expected_data).expect("Method failed");
-
-        let fetched_result = db.get_sessions_sensor_data(session_sensor.get_id());
-        assert!(fetched_result.is_ok());
+    fn test_modified_migration_detected() {
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        let original = [Migration { version: 1, name: "create", sql: "CREATE TABLE Example (id INTEGER);", detect: None }];
+        Migrator::with_migrations(&conn, &original).migrate().expect("Failed to migrate");
 
-        let fetched_data = fetched_result.unwrap();
-        assert_eq!(fetched_data.len(), expected_data.len());
+        let edited = [Migration { version: 1, name: "create", sql: "CREATE TABLE Example (id TEXT);", detect: None }];
+        let status = Migrator::with_migrations(&conn, &edited).status().unwrap();
+        assert_eq!(status[0].state, MigrationState::Modified);
+        assert!(Migrator::with_migrations(&conn, &edited).migrate().is_err());
 
-        for (expected, actual) in expected_data.iter().zip(fetched_data.iter()) {
-            assert_models_eq!(expected, actual, [get_id, get_datetime, get_blob]);
-        }
+        // A database migrated by a newer build is refused
+        assert!(Migrator::with_migrations(&conn, &[]).migrate().is_err());
     }
 
-    #[test]
-    fn test_get_session_sensor_data() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let session_sensor_data = SessionSensorData::new(
-            session_sensor.get_id().to_string(),
-            "01/01/2025U12:00:00".to_string(),
-            "content".as_bytes().to_vec(),
-        );
-        add_test_session_sensor_data(&conn, &session_sensor_data);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        db.insert_session_sensor_data(&session_sensor_data);
-
-        let fetched = db.get_session_sensor_datapoint(
-            session_sensor_data.get_id(),
-            session_sensor_data.get_datetime(),
-        ).expect("Method failed");
-
-        assert_models_eq!(session_sensor_data, fetched, [get_id, get_datetime, get_blob]);
-    }
+    /* Concurrency */
 
     #[test]
-    fn test_get_session_sensor_datapoint() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
+    fn test_pool_pragmas() {
+        let temp = TempDatabase::new("pool_pragmas");
+        let pool = SqlitePool::open(&temp.path, &SqlitePoolConfig::default()).expect("Failed to open pool");
 
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let session_sensor_data = SessionSensorData::new(
-            session_sensor.get_id().to_string(),
-            "01/01/2025U12:00:00".to_string(),
-            "content".as_bytes().to_vec(),
-        );
-        add_test_session_sensor_data(&conn, &session_sensor_data);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let fetched = db
-            .get_session_sensor_datapoint(
-                session_sensor_data.get_id(),
-                session_sensor_data.get_datetime(),
-            )
-            .expect("Method failed");
-
-        assert_models_eq!(
-            session_sensor_data,
-            fetched,
-            [get_id, get_datetime, get_blob]
-        );
+        let journal_mode: String = pool.reader().unwrap().query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(journal_mode, "wal");
+        // NORMAL is reported as 1
+        let synchronous: i64 = pool.writer().unwrap().query_row("PRAGMA synchronous", [], |row| row.get(0)).unwrap();
+        assert_eq!(synchronous, 1);
     }
 
     #[test]
-    fn test_update_session_sensor_datapoint() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
+    fn test_reads_not_blocked_by_writer() {
+        let temp = TempDatabase::new("reads_not_blocked");
+        let (db, session_sensor) = init_file_database(&temp, &SqlitePoolConfig::default());
+        db.batch_session_sensor_data(&datapoint_batch(&session_sensor, 0, 10)).unwrap();
 
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
+        // An open write transaction holds the database lock until it commits
+        let pool = SqlitePool::open(&temp.path, &SqlitePoolConfig::default()).unwrap();
+        let writer = pool.writer().unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+        writer.execute("DELETE FROM Session_Sensor_Data", []).unwrap();
 
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
+        let start = std::time::Instant::now();
+        let fetched = db.get_session_sensor_data(*session_sensor.get_id()).expect("Read failed during write");
+        assert_eq!(fetched.len(), 10);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1), "Read waited on the writer");
 
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let session_sensor_data = SessionSensorData::new(
-            session_sensor.get_id().to_string(),
-            "01/01/2025U12:00:00".to_string(),
-            "content".as_bytes().to_vec(),
-        );
-        add_test_session_sensor_data(&conn, &session_sensor_data);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let updated = SessionSensorData::new(
-            session_sensor_data.get_id().to_string(),
-            session_sensor_data.get_datetime().to_string(),
-            "updated".as_bytes().to_vec(),
-        );
-
-        let result = db.update_session_sensor_datapoint(
-            updated.get_id(),
-            updated.get_datetime(),
-            &updated,
-        );
-        assert!(result.is_ok());
-
-        // Fetch and confirm the update was applied
-        let fetched = db
-            .get_session_sensor_datapoint(updated.get_id(), updated.get_datetime())
-            .expect("Fetch failed after update");
-
-        assert_models_eq!(updated, fetched, [get_id, get_datetime, get_blob]);
+        writer.execute_batch("ROLLBACK").unwrap();
     }
 
     #[test]
-    fn test_delete_session_sensor_datapoint() {
-        let conn = init_schema();
-
-        let user = User::new("user1".to_string(), "hunter2".to_string());
-        add_test_user(&conn, &user);
-
-        let session = Session::new("session1".to_string(), user.get_username().to_string());
-        add_test_session(&conn, &session);
-
-        let sensor = Sensor::new("sensor1".to_string(), "Acceleration".to_string());
-        add_test_sensor(&conn, &sensor);
-
-        let session_sensor = SessionSensor::new(
-            "session_sensor1".to_string(),
-            session.get_id().to_string(),
-            sensor.get_id().to_string(),
-        );
-        add_test_session_sensor(&conn, &session_sensor);
-
-        let session_sensor_data = SessionSensorData::new(
-            session_sensor.get_id().to_string(),
-            "01/01/2025U12:00:00".to_string(),
-            "content".as_bytes().to_vec(),
-        );
-        add_test_session_sensor_data(&conn, &session_sensor_data);
-
-        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
-
-        let result = db.delete_session_sensor_datapoint(
-            session_sensor_data.get_id(),
-            session_sensor_data.get_datetime(),
-        );
-        assert!(result.is_ok());
-
-        // Verify that the datapoint does not exist in the db
-        let post_delete = db.get_session_sensor_datapoint(
-            session_sensor_data.get_id(), 
-            session_sensor_data.get_datetime()
+    fn test_reader_checkout_timeout() {
+        let temp = TempDatabase::new("checkout_timeout");
+        let config = SqlitePoolConfig {
+            readers: 1,
+            checkout_timeout: std::time::Duration::from_millis(50),
+            ..Default::default()
+        };
+        let pool = SqlitePool::open(&temp.path, &config).expect("Failed to open pool");
+
+        let reader = pool.reader().expect("Failed to check out reader");
+        assert!(matches!(pool.reader(), Err(Error::Unavailable(_))));
+        drop(reader);
+        assert!(pool.reader().is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_readers_and_writer() {
+        const BATCHES: usize = 20;
+        const BATCH_SIZE: usize = 50;
+        let temp = TempDatabase::new("concurrent");
+        let config = SqlitePoolConfig { readers: 2, ..Default::default() };
+        let (db, session_sensor) = init_file_database(&temp, &config);
+        let writing = std::sync::atomic::AtomicBool::new(true);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for batch in 0..BATCHES {
+                    db.batch_session_sensor_data(&datapoint_batch(&session_sensor, batch, BATCH_SIZE))
+                        .expect("Batch insert failed while reading");
+                }
+                writing.store(false, std::sync::atomic::Ordering::SeqCst);
+            });
+            // more readers than pooled connections, so some wait for a checkout
+            for _ in 0..3 {
+                scope.spawn(|| {
+                    let mut seen = 0;
+                    while writing.load(std::sync::atomic::Ordering::SeqCst) {
+                        let count = db
+                            .get_session_sensor_data(*session_sensor.get_id())
+                            .expect("Read failed while writing")
+                            .len();
+                        // batches commit whole, readers never see part of one or go back in time
+                        assert_eq!(count % BATCH_SIZE, 0);
+                        assert!(count >= seen);
+                        seen = count;
+                    }
+                });
+            }
+        });
+
+        let fetched = db.get_session_sensor_data(*session_sensor.get_id()).unwrap();
+        assert_eq!(fetched.len(), BATCHES * BATCH_SIZE);
+    }
+
+    /* Backups */
+
+    #[test]
+    fn test_backup_rotates_and_restores() {
+        let temp = TempDatabase::new("backup_restore");
+        let backup_dir = TempBackups::new("backup_restore", 2);
+        let (db, session_sensor) = init_file_database(&temp, &SqlitePoolConfig::default());
+        db.batch_session_sensor_data(&datapoint_batch(&session_sensor, 0, 10)).unwrap();
+
+        // Taken while the database is open, only the newest two are kept
+        let backups = SqliteBackup::new(&temp.path, &backup_dir.config);
+        for _ in 0..3 {
+            backups.create().expect("Failed to back up database");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let listed = backups.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed[0].path > listed[1].path, "Backups should be listed newest first");
+        assert_eq!(listed[0].schema_version, MIGRATIONS.last().unwrap().version);
+
+        db.delete_session_sensor(*session_sensor.get_id()).unwrap();
+        drop(db);
+
+        let restored = backups.restore(&listed[0].path).expect("Failed to restore backup");
+        assert_eq!(restored.schema_version, listed[0].schema_version);
+        assert!(std::path::Path::new(&temp.path).with_extension("pre-restore").exists());
+        let db = SqliteDatabase::new(&temp.path, &SqlitePoolConfig::default()).unwrap();
+        assert_eq!(db.get_session_sensor_data(*session_sensor.get_id()).unwrap().len(), 10);
+        let _ = std::fs::remove_file(std::path::Path::new(&temp.path).with_extension("pre-restore"));
+    }
+
+    #[test]
+    fn test_restore_rejects_unknown_schema_version() {
+        let temp = TempDatabase::new("restore_newer");
+        let newer = TempDatabase::new("restore_newer_backup");
+        let (db, session_sensor) = init_file_database(&temp, &SqlitePoolConfig::default());
+        db.batch_session_sensor_data(&datapoint_batch(&session_sensor, 0, 10)).unwrap();
+        drop(db);
+
+        // Backup of a build with a migration this one does not know about
+        let conn = Connection::open(&newer.path).unwrap();
+        Migrator::new(&conn).migrate().unwrap();
+        conn.execute(
+            "INSERT INTO Schema_Migrations (version, name, checksum, applied_at) VALUES (99, 'future', '', '')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let backup_dir = TempBackups::new("restore_newer", 2);
+        let result = SqliteBackup::new(&temp.path, &backup_dir.config).restore(std::path::Path::new(&newer.path));
+        assert!(matches!(result, Err(Error::Conflict(_))), "Expected a conflict, got {result:?}");
+
+        // The database is left as it was
+        let db = SqliteDatabase::new(&temp.path, &SqlitePoolConfig::default()).unwrap();
+        assert_eq!(db.get_session_sensor_data(*session_sensor.get_id()).unwrap().len(), 10);
+        assert!(!std::path::Path::new(&temp.path).with_extension("restore").exists());
+    }
+
+    // Throughput of ingestion while the ui reads, run with `cargo test --features sql -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_ingest_under_read_load() {
+        const BATCHES: usize = 200;
+        const BATCH_SIZE: usize = 500;
+        let temp = TempDatabase::new("bench");
+        let (db, session_sensor) = init_file_database(&temp, &SqlitePoolConfig::default());
+        let writing = std::sync::atomic::AtomicBool::new(true);
+        let reads = std::sync::atomic::AtomicUsize::new(0);
+
+        let start = std::time::Instant::now();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for batch in 0..BATCHES {
+                    db.batch_session_sensor_data(&datapoint_batch(&session_sensor, batch, BATCH_SIZE)).unwrap();
+                }
+                writing.store(false, std::sync::atomic::Ordering::SeqCst);
+            });
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while writing.load(std::sync::atomic::Ordering::SeqCst) {
+                        db.get_session_sensor_data(*session_sensor.get_id()).unwrap();
+                        reads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+        let elapsed = start.elapsed();
+
+        println!(
+            "Inserted {} datapoints in {elapsed:?} ({:.0}/s) alongside {} full reads",
+            BATCHES * BATCH_SIZE,
+            (BATCHES * BATCH_SIZE) as f64 / elapsed.as_secs_f64(),
+            reads.load(std::sync::atomic::Ordering::SeqCst)
         );
-        assert!(post_delete.is_err());
     }
 }
@@ -0,0 +1,134 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::HttpRequest;
+use crate::models::{DerivedChannel, SessionSensorData};
+
+use super::Expression;
+
+type Result<T> = crate::Result<T>;
+
+//A derived channel along with its parsed expression, or why the expression no longer parses
+struct CompiledChannel {
+    channel: DerivedChannel,
+    expression: std::result::Result<Expression, String>,
+}
+
+//The derived channels of one datapoint by name, a channel whose expression failed is null and its error is kept
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DerivedValues {
+    pub values: Map<String, Value>,
+    pub errors: Map<String, Value>,
+}
+
+impl DerivedValues {
+    //Adds the values to the json of a datapoint as "derived", along with "derived_errors" when any failed
+    pub fn annotate(self, json: &mut Value) {
+        json["derived"] = Value::Object(self.values);
+        if !self.errors.is_empty() {
+            json["derived_errors"] = Value::Object(self.errors);
+        }
+    }
+}
+
+//Computes the derived channels of datapoints as they are read, from the channels defined for their sensor type
+//Channels are loaded and parsed once per Deriver, so one is made for every read
+pub struct Deriver<'a> {
+    database: &'a dyn Database,
+    // by sensor type, loaded on first use
+    channels: Option<HashMap<String, Vec<CompiledChannel>>>,
+    // sensor type of every session sensor derived so far
+    sensor_types: HashMap<i64, String>,
+}
+
+impl<'a> Deriver<'a> {
+    pub fn new(database: &'a dyn Database) -> Self {
+        Deriver {
+            database,
+            channels: None,
+            sensor_types: HashMap::new(),
+        }
+    }
+
+    //Whether a read includes derived channels, which it does unless it asks for ?derived=false
+    pub fn requested(request: &HttpRequest) -> Result<bool> {
+        match request.parameter("derived") {
+            Some(derived) => derived
+                .parse::<bool>()
+                .map_err(|_| Error::Validation(format!("Invalid derived '{derived}', expected true or false"))),
+            None => Ok(true),
+        }
+    }
+
+    //The derived channels of a datapoint, none when its sensor type has none
+    pub fn derive(&mut self, datapoint: &SessionSensorData) -> Result<Option<DerivedValues>> {
+        let Some(session_sensor_id) = datapoint.get_session_sensor_id() else {
+            return Ok(None);
+        };
+        if self.channels()?.is_empty() {
+            return Ok(None);
+        }
+        if !self.sensor_types.contains_key(session_sensor_id) {
+            let sensor_id = *self.database.get_session_sensor(*session_sensor_id)?.get_sensor_id();
+            let sensor_type = self.database.get_sensor(sensor_id)?.get_sensor_type().to_string();
+            self.sensor_types.insert(*session_sensor_id, sensor_type);
+        }
+        let sensor_type = self.sensor_types[session_sensor_id].clone();
+        self.derive_blob(&sensor_type, datapoint.get_blob())
+    }
+
+    //The derived channels of a blob recorded by a sensor of the type, none when the type has none
+    pub fn derive_blob(&mut self, sensor_type: &str, blob: &Value) -> Result<Option<DerivedValues>> {
+        let Some(channels) = self.channels()?.get(sensor_type) else {
+            return Ok(None);
+        };
+        let mut derived = DerivedValues::default();
+        for compiled in channels {
+            let name = compiled.channel.get_name().to_string();
+            let value = match &compiled.expression {
+                Ok(expression) => expression.evaluate(blob).map_err(|e| e.to_string()),
+                Err(e) => Err(e.clone()),
+            };
+            match value {
+                Ok(value) => {
+                    derived
+                        .values
+                        .insert(name, Number::from_f64(value).map_or(Value::Null, Value::Number));
+                }
+                Err(e) => {
+                    derived.values.insert(name.clone(), Value::Null);
+                    derived.errors.insert(name, Value::String(e));
+                }
+            }
+        }
+        Ok(Some(derived))
+    }
+
+    fn channels(&mut self) -> Result<&HashMap<String, Vec<CompiledChannel>>> {
+        if self.channels.is_none() {
+            let mut channels: HashMap<String, Vec<CompiledChannel>> = HashMap::new();
+            for channel in self.database.get_derived_channels()? {
+                channels
+                    .entry(channel.get_sensor_type().to_string())
+                    .or_default()
+                    .push(CompiledChannel {
+                        expression: channel.compile().map_err(|e| e.to_string()),
+                        channel,
+                    });
+            }
+            self.channels = Some(channels);
+        }
+        Ok(self.channels.get_or_insert_default())
+    }
+}
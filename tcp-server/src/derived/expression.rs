@@ -0,0 +1,547 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::f64::consts::{E, PI};
+
+use serde_json::Value;
+
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+//A unit convert() understands, values are converted through the SI unit of its dimension as `value * factor + offset`
+#[derive(Debug)]
+struct Unit {
+    name: &'static str,
+    dimension: &'static str,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(name: &'static str, dimension: &'static str, factor: f64) -> Unit {
+    Unit {
+        name,
+        dimension,
+        factor,
+        offset: 0.0,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    unit("m", "length", 1.0),
+    unit("km", "length", 1_000.0),
+    unit("cm", "length", 0.01),
+    unit("mm", "length", 0.001),
+    unit("mi", "length", 1_609.344),
+    unit("yd", "length", 0.9144),
+    unit("ft", "length", 0.3048),
+    unit("in", "length", 0.0254),
+    unit("nmi", "length", 1_852.0),
+    unit("m/s", "speed", 1.0),
+    unit("km/h", "speed", 1.0 / 3.6),
+    unit("mph", "speed", 0.44704),
+    unit("kn", "speed", 1_852.0 / 3_600.0),
+    unit("ft/s", "speed", 0.3048),
+    unit("m/s^2", "acceleration", 1.0),
+    unit("ft/s^2", "acceleration", 0.3048),
+    unit("gn", "acceleration", 9.80665),
+    unit("s", "time", 1.0),
+    unit("ms", "time", 0.001),
+    unit("min", "time", 60.0),
+    unit("h", "time", 3_600.0),
+    unit("K", "temperature", 1.0),
+    Unit {
+        name: "degC",
+        dimension: "temperature",
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        name: "degF",
+        dimension: "temperature",
+        factor: 5.0 / 9.0,
+        offset: 273.15 - 32.0 * 5.0 / 9.0,
+    },
+    unit("kg", "mass", 1.0),
+    unit("g", "mass", 0.001),
+    unit("lb", "mass", 0.453_592_37),
+    unit("oz", "mass", 0.028_349_523_125),
+    unit("Pa", "pressure", 1.0),
+    unit("hPa", "pressure", 100.0),
+    unit("kPa", "pressure", 1_000.0),
+    unit("bar", "pressure", 100_000.0),
+    unit("atm", "pressure", 101_325.0),
+    unit("psi", "pressure", 6_894.757_293_168),
+    unit("mmHg", "pressure", 133.322_387_415),
+    unit("rad", "angle", 1.0),
+    unit("deg", "angle", PI / 180.0),
+];
+
+// Functions by name with the fewest and most arguments they take
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("abs", 1, 1),
+    ("sqrt", 1, 1),
+    ("cbrt", 1, 1),
+    ("exp", 1, 1),
+    ("ln", 1, 1),
+    ("log10", 1, 1),
+    ("log2", 1, 1),
+    ("sin", 1, 1),
+    ("cos", 1, 1),
+    ("tan", 1, 1),
+    ("asin", 1, 1),
+    ("acos", 1, 1),
+    ("atan", 1, 1),
+    ("atan2", 2, 2),
+    ("floor", 1, 1),
+    ("ceil", 1, 1),
+    ("round", 1, 1),
+    ("trunc", 1, 1),
+    ("sign", 1, 1),
+    ("pow", 2, 2),
+    ("hypot", 1, usize::MAX),
+    ("min", 1, usize::MAX),
+    ("max", 1, usize::MAX),
+    ("clamp", 3, 3),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Text(String),
+    Symbol(char),
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+}
+
+#[derive(Debug)]
+enum Node {
+    Number(f64),
+    // path of the field in the blob, nested objects and arrays separated by dots
+    Field(Vec<String>),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(&'static str, Vec<Node>),
+    Convert(Box<Node>, &'static Unit, &'static Unit),
+}
+
+//A parsed arithmetic expression over the fields of a data blob, such as `sqrt(x^2 + y^2 + z^2)`
+//There are no loops, assignments, or side effects, so evaluating one always ends and only ever reads its blob
+#[derive(Debug)]
+pub struct Expression {
+    root: Node,
+}
+
+impl Expression {
+    pub const MAX_LENGTH: usize = 1_000;
+    // deepest nesting of parentheses, calls, and operators, so parsing cannot overflow the stack
+    pub const MAX_DEPTH: usize = 64;
+
+    //Parses an expression, the error names the position of the first character it could not make sense of
+    pub fn parse(source: &str) -> Result<Self> {
+        if source.chars().count() > Self::MAX_LENGTH {
+            return Err(Error::Validation(format!(
+                "Invalid expression, it is longer than {} characters",
+                Self::MAX_LENGTH
+            )));
+        }
+        let mut parser = Parser {
+            tokens: tokenize(source).map_err(|(message, position)| invalid(source, &message, position))?,
+            index: 0,
+            depth: 0,
+        };
+        let root = parser
+            .parse()
+            .map_err(|(message, position)| invalid(source, &message, position))?;
+        Ok(Expression { root })
+    }
+
+    //Evaluates the expression against a blob, failing on missing or non-numeric fields and on results that are not finite
+    pub fn evaluate(&self, blob: &Value) -> Result<f64> {
+        evaluate(&self.root, blob)
+    }
+}
+
+fn invalid(source: &str, message: &str, position: usize) -> Error {
+    Error::Validation(format!(
+        "Invalid expression '{source}': {message} at position {position}"
+    ))
+}
+
+// Splits the source into tokens along with the position of their first character, counted from 1
+fn tokenize(source: &str) -> std::result::Result<Vec<(Token, usize)>, (String, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let start = index;
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+        let token = if c.is_ascii_digit() || (c == '.' && chars.get(index + 1).is_some_and(char::is_ascii_digit)) {
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+            // an exponent only when digits follow it
+            if matches!(chars.get(index), Some('e' | 'E')) {
+                let digits = match chars.get(index + 1) {
+                    Some('+' | '-') => index + 2,
+                    _ => index + 1,
+                };
+                if chars.get(digits).is_some_and(char::is_ascii_digit) {
+                    index = digits;
+                    while index < chars.len() && chars[index].is_ascii_digit() {
+                        index += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..index].iter().collect();
+            Token::Number(
+                text.parse::<f64>()
+                    .map_err(|_| (format!("'{text}' is not a number"), start + 1))?,
+            )
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while index < chars.len() && (chars[index].is_ascii_alphanumeric() || matches!(chars[index], '_' | '.')) {
+                index += 1;
+            }
+            Token::Name(chars[start..index].iter().collect())
+        } else if c == '"' {
+            index += 1;
+            while index < chars.len() && chars[index] != '"' {
+                index += 1;
+            }
+            if index == chars.len() {
+                return Err((String::from("unterminated string"), start + 1));
+            }
+            index += 1;
+            Token::Text(chars[start + 1..index - 1].iter().collect())
+        } else if "+-*/%^(),".contains(c) {
+            index += 1;
+            Token::Symbol(c)
+        } else {
+            return Err((format!("unexpected character '{c}'"), start + 1));
+        };
+        tokens.push((token, start + 1));
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+//Recursive descent parser, from the loosest binding operators to the tightest:
+//`+ -`, then `* / %`, then unary `-`, then `^` which is right associative
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    depth: usize,
+}
+
+type Parsed<T> = std::result::Result<T, (String, usize)>;
+
+impl Parser {
+    fn parse(&mut self) -> Parsed<Node> {
+        let node = self.additive()?;
+        match self.peek() {
+            (Token::End, _) => Ok(node),
+            (token, position) => Err((format!("unexpected {}", describe(token)), *position)),
+        }
+    }
+
+    fn peek(&self) -> &(Token, usize) {
+        &self.tokens[self.index.min(self.tokens.len() - 1)]
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let token = self.peek().clone();
+        self.index += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek().0 == Token::Symbol(symbol) {
+            self.index += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: char) -> Parsed<()> {
+        match self.next() {
+            (Token::Symbol(found), _) if found == symbol => Ok(()),
+            (token, position) => Err((format!("expected '{symbol}' but found {}", describe(&token)), position)),
+        }
+    }
+
+    // Guards every level that can recurse
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Parsed<T>) -> Parsed<T> {
+        if self.depth >= Expression::MAX_DEPTH {
+            return Err((String::from("expression is nested too deeply"), self.peek().1));
+        }
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    fn additive(&mut self) -> Parsed<Node> {
+        let mut node = self.multiplicative()?;
+        loop {
+            let operator = match self.peek().0 {
+                Token::Symbol('+') => Operator::Add,
+                Token::Symbol('-') => Operator::Subtract,
+                _ => return Ok(node),
+            };
+            self.index += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Parsed<Node> {
+        let mut node = self.unary()?;
+        loop {
+            let operator = match self.peek().0 {
+                Token::Symbol('*') => Operator::Multiply,
+                Token::Symbol('/') => Operator::Divide,
+                Token::Symbol('%') => Operator::Remainder,
+                _ => return Ok(node),
+            };
+            self.index += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    // -x^2 reads as -(x^2)
+    fn unary(&mut self) -> Parsed<Node> {
+        self.nested(|parser| {
+            if parser.eat('-') {
+                return Ok(Node::Negate(Box::new(parser.unary()?)));
+            }
+            if parser.eat('+') {
+                return parser.unary();
+            }
+            let base = parser.primary()?;
+            if parser.eat('^') {
+                return Ok(Node::Binary(Operator::Power, Box::new(base), Box::new(parser.unary()?)));
+            }
+            Ok(base)
+        })
+    }
+
+    fn primary(&mut self) -> Parsed<Node> {
+        match self.next() {
+            (Token::Number(number), _) => Ok(Node::Number(number)),
+            (Token::Symbol('('), _) => {
+                let node = self.nested(Self::additive)?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            (Token::Name(name), position) => {
+                if self.peek().0 == Token::Symbol('(') {
+                    self.index += 1;
+                    return self.call(&name, position);
+                }
+                match name.as_str() {
+                    "pi" => Ok(Node::Number(PI)),
+                    "e" => Ok(Node::Number(E)),
+                    _ => field(&name, position),
+                }
+            }
+            (token, position) => Err((format!("expected a value but found {}", describe(&token)), position)),
+        }
+    }
+
+    // Parses the arguments of a call, the opening parenthesis is already read
+    fn call(&mut self, name: &str, position: usize) -> Parsed<Node> {
+        match name {
+            // field("heart rate") reads fields whose names are not identifiers, or are named like a constant
+            "field" => {
+                let node = match self.next() {
+                    (Token::Text(name), position) => field(&name, position)?,
+                    (token, position) => {
+                        return Err((
+                            format!("field takes a quoted name but found {}", describe(&token)),
+                            position,
+                        ))
+                    }
+                };
+                self.expect(')')?;
+                Ok(node)
+            }
+            "convert" => {
+                let value = self.nested(Self::additive)?;
+                self.expect(',')?;
+                let from = self.unit()?;
+                self.expect(',')?;
+                let to = self.unit()?;
+                self.expect(')')?;
+                if from.dimension != to.dimension {
+                    return Err((
+                        format!(
+                            "cannot convert {} '{}' to {} '{}'",
+                            from.dimension, from.name, to.dimension, to.name
+                        ),
+                        position,
+                    ));
+                }
+                Ok(Node::Convert(Box::new(value), from, to))
+            }
+            _ => {
+                let Some(&(name, fewest, most)) = FUNCTIONS.iter().find(|(function, _, _)| *function == name) else {
+                    return Err((format!("unknown function '{name}'"), position));
+                };
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.nested(Self::additive)?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                if args.len() < fewest || args.len() > most {
+                    let expected = match (fewest, most) {
+                        (fewest, most) if fewest == most => fewest.to_string(),
+                        (fewest, _) => format!("at least {fewest}"),
+                    };
+                    return Err((
+                        format!("{name} takes {expected} argument(s) but was given {}", args.len()),
+                        position,
+                    ));
+                }
+                Ok(Node::Call(name, args))
+            }
+        }
+    }
+
+    fn unit(&mut self) -> Parsed<&'static Unit> {
+        match self.next() {
+            (Token::Text(name), position) => UNITS
+                .iter()
+                .find(|unit| unit.name == name)
+                .ok_or_else(|| (format!("unknown unit '{name}'"), position)),
+            (token, position) => Err((
+                format!("expected a quoted unit but found {}", describe(&token)),
+                position,
+            )),
+        }
+    }
+}
+
+fn field(name: &str, position: usize) -> Parsed<Node> {
+    let path: Vec<String> = name.split('.').map(str::to_string).collect();
+    if path.iter().any(String::is_empty) {
+        return Err((format!("invalid field '{name}'"), position));
+    }
+    Ok(Node::Field(path))
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(number) => format!("number {number}"),
+        Token::Name(name) => format!("'{name}'"),
+        Token::Text(text) => format!("string \"{text}\""),
+        Token::Symbol(symbol) => format!("'{symbol}'"),
+        Token::End => String::from("the end of the expression"),
+    }
+}
+
+fn evaluate(node: &Node, blob: &Value) -> Result<f64> {
+    let value = match node {
+        Node::Number(number) => *number,
+        Node::Field(path) => {
+            let name = path.join(".");
+            let value = path
+                .iter()
+                .try_fold(blob, |value, key| match value {
+                    Value::Array(values) => key.parse::<usize>().ok().and_then(|index| values.get(index)),
+                    value => value.get(key),
+                })
+                .filter(|value| !value.is_null())
+                .ok_or_else(|| Error::Validation(format!("Field '{name}' is missing from the data blob")))?;
+            return value
+                .as_f64()
+                .ok_or_else(|| Error::Validation(format!("Field '{name}' is not a number")));
+        }
+        Node::Negate(node) => -evaluate(node, blob)?,
+        Node::Binary(operator, left, right) => {
+            let (left, right) = (evaluate(left, blob)?, evaluate(right, blob)?);
+            match operator {
+                Operator::Add => left + right,
+                Operator::Subtract => left - right,
+                Operator::Multiply => left * right,
+                Operator::Divide | Operator::Remainder if right == 0.0 => {
+                    return Err(Error::Validation(String::from("Division by zero")));
+                }
+                Operator::Divide => left / right,
+                Operator::Remainder => left % right,
+                Operator::Power => left.powf(right),
+            }
+        }
+        Node::Call(name, args) => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate(arg, blob))
+                .collect::<Result<Vec<f64>>>()?;
+            let value = call(name, &args);
+            if !value.is_finite() {
+                let args: Vec<String> = args.iter().map(f64::to_string).collect();
+                return Err(Error::Validation(format!("{name}({}) is undefined", args.join(", "))));
+            }
+            value
+        }
+        Node::Convert(value, from, to) => (evaluate(value, blob)? * from.factor + from.offset - to.offset) / to.factor,
+    };
+    if !value.is_finite() {
+        return Err(Error::Validation(String::from("The result is not a finite number")));
+    }
+    Ok(value)
+}
+
+// Arguments are already checked against the arity of the function
+fn call(name: &str, args: &[f64]) -> f64 {
+    match name {
+        "abs" => args[0].abs(),
+        "sqrt" => args[0].sqrt(),
+        "cbrt" => args[0].cbrt(),
+        "exp" => args[0].exp(),
+        "ln" => args[0].ln(),
+        "log10" => args[0].log10(),
+        "log2" => args[0].log2(),
+        "sin" => args[0].sin(),
+        "cos" => args[0].cos(),
+        "tan" => args[0].tan(),
+        "asin" => args[0].asin(),
+        "acos" => args[0].acos(),
+        "atan" => args[0].atan(),
+        "atan2" => args[0].atan2(args[1]),
+        "floor" => args[0].floor(),
+        "ceil" => args[0].ceil(),
+        "round" => args[0].round(),
+        "trunc" => args[0].trunc(),
+        "sign" if args[0] == 0.0 => 0.0,
+        "sign" => args[0].signum(),
+        "pow" => args[0].powf(args[1]),
+        "hypot" => args.iter().map(|arg| arg * arg).sum::<f64>().sqrt(),
+        "min" => args.iter().copied().fold(f64::INFINITY, f64::min),
+        "max" => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        "clamp" if args[1] <= args[2] => args[0].clamp(args[1], args[2]),
+        _ => f64::NAN,
+    }
+}
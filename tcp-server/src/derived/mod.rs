@@ -0,0 +1,8 @@
+pub mod deriver;
+pub mod expression;
+
+pub use self::deriver::Deriver;
+pub use self::expression::Expression;
+
+#[cfg(test)]
+mod test_derived;
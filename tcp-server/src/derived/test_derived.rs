@@ -0,0 +1,290 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::calibration::Calibrator;
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::derived::Expression;
+    use crate::error::Error;
+    use crate::export::csv_export::{CsvExport, CsvOptions};
+    use crate::export::ExportQuery;
+    use crate::http::{HttpHeader, HttpPath, HttpRequest, HttpStatus};
+    use crate::models::{DerivedChannel, Sensor, Session, SessionSensor, SessionSensorData, User};
+
+    /* Helpers */
+
+    // Session 1 records an accelerometer (session sensor 1) with a magnitude channel,
+    // and a pace channel that fails on blobs without a speed
+    fn init_database() -> MemoryDatabase {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        db.insert_sensor(&Sensor::new(0, "Acceleration".to_string())).unwrap();
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        for (name, expression, unit) in [
+            ("magnitude", "hypot(x, y, z)", "m/s^2"),
+            ("pace", "convert(1000 / speed, \"s\", \"min\")", "min/km"),
+        ] {
+            db.insert_derived_channel(&DerivedChannel::new(
+                "Acceleration".to_string(),
+                name.to_string(),
+                expression.to_string(),
+                unit.to_string(),
+            ))
+            .unwrap();
+        }
+        db
+    }
+
+    fn evaluate(expression: &str, blob: Value) -> crate::Result<f64> {
+        Expression::parse(expression)?.evaluate(&blob)
+    }
+
+    // The message of the validation error an expression fails with
+    fn error(expression: &str, blob: Value) -> String {
+        match evaluate(expression, blob) {
+            Err(Error::Validation(message)) => message,
+            result => panic!("'{expression}' did not fail validation: {result:?}"),
+        }
+    }
+
+    fn request(parameters: &[(&str, &str)]) -> HttpRequest {
+        HttpRequest::get(
+            HttpPath::SessionSensorData(String::from("/session/1")),
+            Some(
+                parameters
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            HttpHeader::new(),
+            None,
+        )
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_expression_precedence_and_functions() {
+        let blob =
+            json!({ "x": 3, "y": 4, "z": 12, "accel": { "x": -2.5 }, "values": [1, 7], "heart rate": 61, "e": 5 });
+        for (expression, expected) in [
+            ("1 + 2 * 3 ^ 2", 19.0),
+            ("(1 + 2) * 3 - 4 / 8", 8.5),
+            ("-2 ^ 2", -4.0),
+            ("2 ^ 3 ^ 2", 512.0),
+            ("2 ^ -1", 0.5),
+            ("10 % 4 + 1.5e3", 1_502.0),
+            ("hypot(x, y, z)", 13.0),
+            ("sqrt(x^2 + y^2)", 5.0),
+            ("abs(accel.x) + values.1", 9.5),
+            ("max(1, x, 2) - min(y, z)", -1.0),
+            ("clamp(z, 0, 10) + sign(-x) + round(2.5)", 12.0),
+            ("field(\"heart rate\") + field(\"e\")", 66.0),
+        ] {
+            assert_close(evaluate(expression, blob.clone()).unwrap(), expected);
+        }
+        assert_close(evaluate("atan2(1, 1) * 4", json!({})).unwrap(), std::f64::consts::PI);
+        assert_close(evaluate("ln(e) + cos(pi)", json!({})).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        let blob = json!({ "speed": 4, "celsius": 100 });
+        assert_close(
+            evaluate("convert(speed, \"m/s\", \"km/h\")", blob.clone()).unwrap(),
+            14.4,
+        );
+        assert_close(
+            evaluate("convert(celsius, \"degC\", \"degF\")", blob.clone()).unwrap(),
+            212.0,
+        );
+        assert_close(evaluate("convert(32, \"degF\", \"K\")", blob.clone()).unwrap(), 273.15);
+        assert_close(
+            evaluate("convert(1, \"gn\", \"m/s^2\")", blob.clone()).unwrap(),
+            9.80665,
+        );
+        // pace in minutes per kilometre
+        assert_close(
+            evaluate("convert(1000 / speed, \"s\", \"min\")", blob.clone()).unwrap(),
+            25.0 / 6.0,
+        );
+
+        assert_eq!(
+            error("convert(speed, \"m/s\", \"degC\")", blob.clone()),
+            "Invalid expression 'convert(speed, \"m/s\", \"degC\")': cannot convert speed 'm/s' to temperature 'degC' at position 1"
+        );
+        assert_eq!(
+            error("convert(speed, \"m/s\", \"kph\")", blob.clone()),
+            "Invalid expression 'convert(speed, \"m/s\", \"kph\")': unknown unit 'kph' at position 23"
+        );
+        assert!(error("convert(speed, m/s, \"km/h\")", blob)
+            .contains("expected a quoted unit but found 'm' at position 16"));
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        for (expression, message) in [
+            (
+                "1 +",
+                "expected a value but found the end of the expression at position 4",
+            ),
+            ("2 $ 3", "unexpected character '$' at position 3"),
+            (
+                "(1 + 2",
+                "expected ')' but found the end of the expression at position 7",
+            ),
+            ("1 2", "unexpected number 2 at position 3"),
+            ("sqr(2)", "unknown function 'sqr' at position 1"),
+            ("atan2(1)", "atan2 takes 2 argument(s) but was given 1 at position 1"),
+            (
+                "min()",
+                "min takes at least 1 argument(s) but was given 0 at position 1",
+            ),
+            ("\"x\" + 1", "expected a value but found string \"x\" at position 1"),
+            ("accel..x", "invalid field 'accel..x' at position 1"),
+        ] {
+            assert!(
+                error(expression, json!({})).ends_with(message),
+                "{expression}: {}",
+                error(expression, json!({}))
+            );
+        }
+        let nested = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert!(error(&nested, json!({})).contains("nested too deeply"));
+        assert!(error(&"1+".repeat(600), json!({})).contains("longer than 1000 characters"));
+
+        // evaluation fails on the blob rather than the expression
+        let blob = json!({ "speed": 0, "label": "walk", "none": null });
+        assert_eq!(
+            error("distance / 2", blob.clone()),
+            "Field 'distance' is missing from the data blob"
+        );
+        assert_eq!(
+            error("none + 1", blob.clone()),
+            "Field 'none' is missing from the data blob"
+        );
+        assert_eq!(error("label * 2", blob.clone()), "Field 'label' is not a number");
+        assert_eq!(error("1000 / speed", blob.clone()), "Division by zero");
+        assert_eq!(error("sqrt(speed - 1)", blob.clone()), "sqrt(-1) is undefined");
+        assert_eq!(error("10 ^ 400", blob), "The result is not a finite number");
+    }
+
+    #[test]
+    fn test_derived_channels_on_read() {
+        let db = init_database();
+        let datapoints = vec![
+            SessionSensorData::new(
+                Some(1),
+                Some(1),
+                "2025-01-01T12:00:00".to_string(),
+                json!({ "x": 3, "y": 4, "z": 0, "speed": 4 }),
+            ),
+            SessionSensorData::new(
+                Some(1),
+                Some(1),
+                "2025-01-01T12:00:01".to_string(),
+                json!({ "x": 0, "y": 0, "z": 2 }),
+            ),
+        ];
+
+        let response = Calibrator::list_response(&db, &datapoints, &request(&[]));
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["datapoints"][0]["data_blob"], datapoints[0].get_blob().clone());
+        assert_eq!(
+            body["datapoints"][0]["derived"],
+            json!({ "magnitude": 5.0, "pace": 25.0 / 6.0 })
+        );
+        assert!(body["datapoints"][0].get("derived_errors").is_none());
+        // a channel that fails is null, with the reason alongside
+        assert_eq!(
+            body["datapoints"][1]["derived"],
+            json!({ "magnitude": 2.0, "pace": null })
+        );
+        assert_eq!(
+            body["datapoints"][1]["derived_errors"],
+            json!({ "pace": "Field 'speed' is missing from the data blob" })
+        );
+
+        let single = Calibrator::datapoint_response(&db, &datapoints[1], &request(&[]));
+        let body: Value = serde_json::from_str(&single.body).unwrap();
+        assert_eq!(body["derived"]["magnitude"], json!(2.0));
+
+        let raw = Calibrator::list_response(&db, &datapoints, &request(&[("derived", "false")]));
+        assert_eq!(
+            serde_json::from_str::<Value>(&raw.body).unwrap(),
+            json!({ "datapoints": datapoints.iter().map(SessionSensorData::to_json).collect::<Vec<_>>() })
+        );
+        let invalid = Calibrator::list_response(&db, &datapoints, &request(&[("derived", "no")]));
+        assert!(matches!(invalid.status, HttpStatus::UnprocessableEntity));
+
+        let unnamed = DerivedChannel::new(
+            "Acceleration".to_string(),
+            "2fast".to_string(),
+            "x".to_string(),
+            String::new(),
+        );
+        assert!(matches!(unnamed.compile(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn test_derived_channels_exported() {
+        let db = init_database();
+        db.batch_session_sensor_data(&[
+            SessionSensorData::new(
+                Some(1),
+                Some(1),
+                "2025-01-01T12:00:00".to_string(),
+                json!({ "x": 3, "y": 4, "z": 0, "speed": 4 }),
+            ),
+            SessionSensorData::new(
+                Some(1),
+                Some(1),
+                "2025-01-01T12:00:01".to_string(),
+                json!({ "x": 0, "y": 0, "z": 2 }),
+            ),
+        ])
+        .unwrap();
+
+        let export = |query: &ExportQuery| {
+            let mut csv = Vec::new();
+            CsvExport::new(CsvOptions::default(), &db, query)
+                .and_then(|export| export.write(&db, query, &mut csv))
+                .unwrap();
+            String::from_utf8(csv).unwrap()
+        };
+        let query = ExportQuery::new(1, Vec::new(), None, None).unwrap();
+        let csv = export(&query);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "datetime,sensor_id,sensor_type,derived.magnitude,derived.pace,speed,x,y,z"
+        );
+        assert_eq!(
+            lines[1],
+            "2025-01-01T12:00:00+00:00,1,Acceleration,5.0,4.166666666666667,4,3,4,0"
+        );
+        // the failed channel is left empty
+        assert_eq!(lines[2], "2025-01-01T12:00:01+00:00,1,Acceleration,2.0,,,0,0,2");
+
+        let raw = export(&ExportQuery {
+            derived: false,
+            ..query
+        });
+        assert!(raw.starts_with("datetime,sensor_id,sensor_type,speed,x,y,z\n"));
+    }
+}
@@ -0,0 +1,227 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::fmt;
+use std::num::ParseIntError;
+
+use rusqlite::ErrorCode;
+
+use crate::http::HttpStatus;
+
+//Every failure the crate reports, the variant decides the response status
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    // the requested resource does not exist
+    NotFound(String),
+    // the request clashes with the current state, such as a duplicate key or an invalid transition
+    Conflict(String),
+    // the request was understood but its values are not acceptable
+    Validation(String),
+    Unauthorized(String),
+    Forbidden(String),
+    // the database failed for reasons the client cannot fix
+    Storage(String),
+    // the request or a value in it could not be parsed
+    Parse(String),
+    // reading from or writing to a connection failed
+    Io(String),
+    // the path exists but does not accept the request method
+    MethodNotAllowed(String),
+    // the request body is larger than the server accepts
+    PayloadTooLarge(String),
+    // the request body is not in a format the server accepts
+    UnsupportedMediaType(String),
+    // the server cannot handle the request right now, such as while the database is locked
+    Unavailable(String),
+}
+
+impl Error {
+    //Builds a NotFound error worded like the server's 404 responses
+    pub fn not_found(resource: impl fmt::Display) -> Self {
+        Error::NotFound(format!("{resource} not found"))
+    }
+
+    pub fn status(&self) -> HttpStatus {
+        match self {
+            Error::NotFound(_) => HttpStatus::NotFound,
+            Error::Conflict(_) => HttpStatus::Conflict,
+            Error::Validation(_) => HttpStatus::UnprocessableEntity,
+            Error::Parse(_) => HttpStatus::BadRequest,
+            Error::Unauthorized(_) => HttpStatus::Unauthorized,
+            Error::Forbidden(_) => HttpStatus::Forbidden,
+            Error::Storage(_) | Error::Io(_) => HttpStatus::InternalServerError,
+            Error::MethodNotAllowed(_) => HttpStatus::MethodNotAllowed,
+            Error::PayloadTooLarge(_) => HttpStatus::PayloadTooLarge,
+            Error::UnsupportedMediaType(_) => HttpStatus::UnsupportedMediaType,
+            Error::Unavailable(_) => HttpStatus::ServiceUnavailable,
+        }
+    }
+
+    //Stable identifier of the kind of error, used as the problem type clients match on
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::NotFound(_) => "not-found",
+            Error::Conflict(_) => "conflict",
+            Error::Validation(_) => "validation",
+            Error::Unauthorized(_) => "unauthorized",
+            Error::Forbidden(_) => "forbidden",
+            Error::Storage(_) => "storage",
+            Error::Parse(_) => "malformed-request",
+            Error::Io(_) => "io",
+            Error::MethodNotAllowed(_) => "method-not-allowed",
+            Error::PayloadTooLarge(_) => "payload-too-large",
+            Error::UnsupportedMediaType(_) => "unsupported-media-type",
+            Error::Unavailable(_) => "unavailable",
+        }
+    }
+
+    //Short summary of the kind of error, the message holds the specifics
+    pub fn title(&self) -> &'static str {
+        match self {
+            Error::NotFound(_) => "Resource not found",
+            Error::Conflict(_) => "Conflict with the current state",
+            Error::Validation(_) => "Validation failed",
+            Error::Unauthorized(_) => "Authentication required",
+            Error::Forbidden(_) => "Permission denied",
+            Error::Storage(_) => "Database error",
+            Error::Parse(_) => "Malformed request",
+            Error::Io(_) => "Connection error",
+            Error::MethodNotAllowed(_) => "Method not allowed",
+            Error::PayloadTooLarge(_) => "Payload too large",
+            Error::UnsupportedMediaType(_) => "Unsupported media type",
+            Error::Unavailable(_) => "Service unavailable",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::NotFound(msg)
+            | Error::Conflict(msg)
+            | Error::Validation(msg)
+            | Error::Unauthorized(msg)
+            | Error::Forbidden(msg)
+            | Error::Storage(msg)
+            | Error::Parse(msg)
+            | Error::Io(msg)
+            | Error::MethodNotAllowed(msg)
+            | Error::PayloadTooLarge(msg)
+            | Error::UnsupportedMediaType(msg)
+            | Error::Unavailable(msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        match &error {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound(String::from("No matching record found")),
+            rusqlite::Error::SqliteFailure(failure, _) if failure.code == ErrorCode::ConstraintViolation => {
+                match failure.extended_code {
+                    rusqlite::ffi::SQLITE_CONSTRAINT_NOTNULL | rusqlite::ffi::SQLITE_CONSTRAINT_CHECK => {
+                        Error::Validation(error.to_string())
+                    }
+                    // unique, primary key, and foreign key violations
+                    _ => Error::Conflict(error.to_string()),
+                }
+            }
+            rusqlite::Error::SqliteFailure(failure, _)
+                if matches!(failure.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) =>
+            {
+                Error::Unavailable(error.to_string())
+            }
+            _ => Error::Storage(error.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<postgres::Error> for Error {
+    fn from(error: postgres::Error) -> Self {
+        use postgres::error::SqlState;
+
+        let Some(db_error) = error.as_db_error() else {
+            // the connection was lost or never made
+            return match error.is_closed() {
+                true => Error::Unavailable(error.to_string()),
+                false => Error::Storage(error.to_string()),
+            };
+        };
+        let message = db_error.message().to_string();
+        match db_error.code() {
+            code if *code == SqlState::UNIQUE_VIOLATION || *code == SqlState::FOREIGN_KEY_VIOLATION => {
+                Error::Conflict(message)
+            }
+            code if *code == SqlState::NOT_NULL_VIOLATION || *code == SqlState::CHECK_VIOLATION => {
+                Error::Validation(message)
+            }
+            code if *code == SqlState::T_R_SERIALIZATION_FAILURE
+                || *code == SqlState::T_R_DEADLOCK_DETECTED
+                || *code == SqlState::LOCK_NOT_AVAILABLE
+                || *code == SqlState::TOO_MANY_CONNECTIONS =>
+            {
+                Error::Unavailable(message)
+            }
+            _ => Error::Storage(message),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for Error {
+    fn from(error: chrono::ParseError) -> Self {
+        Error::Parse(format!("Failed to parse datetime: {error}"))
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(error: ParseIntError) -> Self {
+        Error::Parse(format!("Failed to parse id to i64: {error}"))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error.to_string())
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(error: csv::Error) -> Self {
+        match error.kind() {
+            csv::ErrorKind::Io(_) => Error::Io(format!("Csv I/O failed: {error}")),
+            _ => Error::Validation(format!("Invalid csv: {error}")),
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        Error::Io(format!("Failed to write parquet: {error}"))
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(error: arrow_schema::ArrowError) -> Self {
+        Error::Io(format!("Failed to write arrow: {error}"))
+    }
+}
@@ -0,0 +1,301 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Write};
+use std::net::Shutdown;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow_ipc::writer::{IpcWriteOptions, StreamWriter};
+use arrow_ipc::CompressionType;
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression as ParquetCompression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpRequest, HttpResponse, HttpStatus};
+
+use super::export_query::{ExportQuery, ExportRow};
+
+type Result<T> = crate::Result<T>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnarFormat {
+    // a parquet file, written a row group at a time
+    Parquet,
+    // an arrow ipc stream, written a record batch per page
+    Arrow,
+}
+
+impl ColumnarFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ColumnarFormat::Parquet => "parquet",
+            ColumnarFormat::Arrow => "arrows",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ColumnarFormat::Parquet => "application/vnd.apache.parquet",
+            ColumnarFormat::Arrow => "application/vnd.apache.arrow.stream",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    // parquet only, arrow ipc supports lz4 and zstd
+    Snappy,
+    Zstd,
+}
+
+//How datapoints are written as parquet or arrow, read from the query string
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnarOptions {
+    pub format: ColumnarFormat,
+    pub compression: Compression,
+    // rows per parquet row group, ignored by arrow
+    pub row_group_size: usize,
+}
+
+impl ColumnarOptions {
+    pub const DEFAULT_ROW_GROUP_SIZE: usize = 65_536;
+
+    pub fn new(format: ColumnarFormat) -> Self {
+        ColumnarOptions {
+            format,
+            compression: Compression::Zstd,
+            row_group_size: Self::DEFAULT_ROW_GROUP_SIZE,
+        }
+    }
+
+    pub fn from_request(format: ColumnarFormat, request: &HttpRequest) -> Result<Self> {
+        let mut options = ColumnarOptions::new(format);
+        if let Some(compression) = request.parameter("compression") {
+            options.compression = match (compression, format) {
+                ("none", _) => Compression::None,
+                ("snappy", ColumnarFormat::Parquet) => Compression::Snappy,
+                ("zstd", _) => Compression::Zstd,
+                (compression, ColumnarFormat::Parquet) => {
+                    return Err(Error::Validation(format!(
+                        "Unknown compression '{compression}', expected one of [none, snappy, zstd]"
+                    )))
+                }
+                (compression, ColumnarFormat::Arrow) => {
+                    return Err(Error::Validation(format!(
+                        "Unknown compression '{compression}' for arrow, expected one of [none, zstd]"
+                    )))
+                }
+            };
+        }
+        if let Some(row_group_size) = request.parameter("row_group_size") {
+            options.row_group_size = match row_group_size.parse::<usize>() {
+                Ok(row_group_size) if row_group_size > 0 => row_group_size,
+                _ => {
+                    return Err(Error::Validation(format!(
+                        "Invalid row_group_size '{row_group_size}', expected a positive number of rows"
+                    )))
+                }
+            };
+        }
+        Ok(options)
+    }
+}
+
+//Type of a blob column, widened as more of its values are read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnType {
+    Boolean,
+    Int64,
+    Float64,
+    Utf8,
+}
+
+impl ColumnType {
+    //None for null, which says nothing about the type of a column
+    pub fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Bool(_) => Some(ColumnType::Boolean),
+            Value::Number(number) if number.is_i64() => Some(ColumnType::Int64),
+            Value::Number(_) => Some(ColumnType::Float64),
+            _ => Some(ColumnType::Utf8),
+        }
+    }
+
+    //Integers and floats make a float column, any other mix is written as text
+    pub fn merge(self, other: ColumnType) -> Self {
+        match (self, other) {
+            (left, right) if left == right => left,
+            (ColumnType::Int64, ColumnType::Float64) | (ColumnType::Float64, ColumnType::Int64) => ColumnType::Float64,
+            _ => ColumnType::Utf8,
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Utf8 => DataType::Utf8,
+        }
+    }
+}
+
+//Writes exported datapoints as parquet or arrow, one row per datapoint
+//Blob columns are flattened like the csv export and typed from their values, columns that are only ever null are left out
+pub struct ColumnarExport {
+    options: ColumnarOptions,
+    columns: Vec<(String, ColumnType)>,
+    schema: SchemaRef,
+}
+
+impl ColumnarExport {
+    const TIMEZONE: &'static str = "UTC";
+
+    //The schema is inferred by reading every page once
+    pub fn new(options: ColumnarOptions, database: &dyn Database, query: &ExportQuery) -> Result<Self> {
+        let mut types: BTreeMap<String, ColumnType> = BTreeMap::new();
+        for page in query.pages(database, ExportQuery::PAGE_SIZE)? {
+            for row in page? {
+                for (key, value) in row.fields() {
+                    if let Some(value_type) = ColumnType::of(&value) {
+                        types
+                            .entry(key)
+                            .and_modify(|column_type| *column_type = column_type.merge(value_type))
+                            .or_insert(value_type);
+                    }
+                }
+            }
+        }
+        let columns: Vec<(String, ColumnType)> = types.into_iter().collect();
+
+        let fields = [
+            Field::new(
+                "datetime",
+                DataType::Timestamp(TimeUnit::Microsecond, Some(Self::TIMEZONE.into())),
+                false,
+            ),
+            Field::new("sensor_id", DataType::Int64, false),
+            Field::new("sensor_type", DataType::Utf8, false),
+        ]
+        .into_iter()
+        .chain(columns.iter().map(|(name, column_type)| Field::new(name, column_type.data_type(), true)));
+        let schema = Arc::new(Schema::new(fields.collect::<Vec<Field>>()));
+        Ok(ColumnarExport { options, columns, schema })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    pub fn write<W: Write + Send>(&self, database: &dyn Database, query: &ExportQuery, mut writer: W) -> Result<()> {
+        let pages = query.pages(database, ExportQuery::PAGE_SIZE)?;
+        match self.options.format {
+            ColumnarFormat::Parquet => {
+                let compression = match self.options.compression {
+                    Compression::None => ParquetCompression::UNCOMPRESSED,
+                    Compression::Snappy => ParquetCompression::SNAPPY,
+                    Compression::Zstd => ParquetCompression::ZSTD(ZstdLevel::default()),
+                };
+                let properties = WriterProperties::builder()
+                    .set_compression(compression)
+                    .set_max_row_group_size(self.options.row_group_size)
+                    .build();
+                // buffers at most a row group before writing it out
+                let mut parquet_writer = ArrowWriter::try_new(&mut writer, self.schema(), Some(properties))?;
+                for page in pages {
+                    parquet_writer.write(&self.batch(&page?)?)?;
+                }
+                parquet_writer.close()?;
+            }
+            ColumnarFormat::Arrow => {
+                let compression = match self.options.compression {
+                    Compression::Zstd => Some(CompressionType::ZSTD),
+                    _ => None,
+                };
+                let options = IpcWriteOptions::default().try_with_compression(compression)?;
+                let mut arrow_writer = StreamWriter::try_new_with_options(&mut writer, &self.schema, options)?;
+                for page in pages {
+                    arrow_writer.write(&self.batch(&page?)?)?;
+                }
+                arrow_writer.finish()?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    //Streams the datapoints of a query as a parquet or arrow download, a page is read from the database at a time
+    pub fn response(database: Arc<dyn Database>, query: ExportQuery, options: ColumnarOptions) -> HttpResponse {
+        let format = options.format;
+        let export = match ColumnarExport::new(options, database.as_ref(), &query) {
+            Ok(export) => export,
+            Err(e) => return HttpResponse::from_error(&e),
+        };
+        HttpResponse::streamed(
+            HttpStatus::OK,
+            HttpHeader::default_attachment(
+                format.content_type(),
+                &format!("session-{}.{}", query.session_id, format.extension()),
+            ),
+            Box::new(move |stream| {
+                if let Err(e) = export.write(database.as_ref(), &query, BufWriter::new(&stream)) {
+                    eprintln!("Columnar export closed: {e}");
+                }
+                let _ = stream.shutdown(Shutdown::Both);
+            }),
+        )
+    }
+
+    fn batch(&self, rows: &[ExportRow]) -> Result<RecordBatch> {
+        let fields: Vec<BTreeMap<String, Value>> =
+            rows.iter().map(ExportRow::fields).collect();
+
+        let mut arrays: Vec<ArrayRef> = vec![
+            Arc::new(
+                TimestampMicrosecondArray::from_iter_values(
+                    rows.iter().map(|row| row.datetime.and_utc().timestamp_micros()),
+                )
+                .with_timezone(Self::TIMEZONE),
+            ),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|row| row.sensor_id))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.sensor_type.as_str()))),
+        ];
+        for (name, column_type) in &self.columns {
+            let values = fields.iter().map(|fields| fields.get(name));
+            // values that do not fit the column, when rows changed since the schema was inferred, are written as null
+            let array: ArrayRef = match column_type {
+                ColumnType::Boolean => Arc::new(values.map(|value| value.and_then(Value::as_bool)).collect::<BooleanArray>()),
+                ColumnType::Int64 => Arc::new(values.map(|value| value.and_then(Value::as_i64)).collect::<Int64Array>()),
+                ColumnType::Float64 => {
+                    Arc::new(values.map(|value| value.and_then(Value::as_f64)).collect::<Float64Array>())
+                }
+                ColumnType::Utf8 => Arc::new(
+                    values
+                        .map(|value| match value {
+                            None | Some(Value::Null) => None,
+                            Some(Value::String(text)) => Some(text.clone()),
+                            Some(value) => Some(value.to_string()),
+                        })
+                        .collect::<StringArray>(),
+                ),
+            };
+            arrays.push(array);
+        }
+        Ok(RecordBatch::try_new(self.schema(), arrays)?)
+    }
+}
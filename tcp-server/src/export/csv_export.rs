@@ -0,0 +1,203 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufWriter, Write};
+use std::net::Shutdown;
+use std::sync::Arc;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpRequest, HttpResponse, HttpStatus};
+
+use super::export_query::{ExportQuery, ExportRow};
+
+type Result<T> = crate::Result<T>;
+
+//How datapoints are written as csv, read from the query string
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub header: bool,
+    pub timezone: Tz,
+    pub datetime_format: String,
+    // inferred from the blobs when unset
+    pub columns: Option<Vec<String>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            header: true,
+            timezone: Tz::UTC,
+            datetime_format: String::from(Self::DEFAULT_DATETIME_FORMAT),
+            columns: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub const DEFAULT_DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.f%:z";
+
+    pub fn from_request(request: &HttpRequest) -> Result<Self> {
+        let mut options = CsvOptions::default();
+        if let Some(delimiter) = request.parameter("delimiter") {
+            options.delimiter = match delimiter {
+                "tab" | "\t" => b'\t',
+                delimiter if delimiter.len() == 1 && delimiter != "\"" => delimiter.as_bytes()[0],
+                delimiter => {
+                    return Err(Error::Validation(format!(
+                        "Invalid delimiter '{delimiter}', expected a single character other than '\"' or \"tab\""
+                    )))
+                }
+            };
+        }
+        if let Some(header) = request.parameter("header") {
+            options.header = header
+                .parse::<bool>()
+                .map_err(|_| Error::Validation(format!("Invalid header '{header}', expected true or false")))?;
+        }
+        if let Some(timezone) = request.parameter("timezone") {
+            options.timezone = timezone.parse::<Tz>().map_err(|_| {
+                Error::Validation(format!("Unknown timezone '{timezone}', expected a name such as UTC or Europe/Berlin"))
+            })?;
+        }
+        if let Some(datetime_format) = request.parameter("datetime_format") {
+            // formatting with an invalid specifier would panic halfway through the export
+            if StrftimeItems::new(datetime_format).any(|item| matches!(item, Item::Error)) {
+                return Err(Error::Validation(format!("Invalid datetime_format '{datetime_format}'")));
+            }
+            options.datetime_format = datetime_format.to_string();
+        }
+        if let Some(columns) = request.parameter("columns") {
+            options.columns = Some(columns.split(',').map(|column| column.trim().to_string()).collect());
+        }
+        Ok(options)
+    }
+}
+
+//Writes exported datapoints as csv, one row per datapoint
+//Nested blob keys become dotted column names, so {"accel": {"x": 1}} is written to the column accel.x
+pub struct CsvExport {
+    options: CsvOptions,
+    columns: Vec<String>,
+}
+
+impl CsvExport {
+    pub const FIXED_COLUMNS: [&'static str; 3] = ["datetime", "sensor_id", "sensor_type"];
+
+    //The blob columns are inferred by reading and deriving every page once when the options name none
+    //That doubles the reads of a long session, so clients that know their keys pass columns= instead
+    pub fn new(options: CsvOptions, database: &dyn Database, query: &ExportQuery) -> Result<Self> {
+        // the session and sensors are checked before anything is streamed
+        let pages = query.pages(database, ExportQuery::PAGE_SIZE)?;
+        let columns = match &options.columns {
+            Some(columns) => columns.clone(),
+            None => {
+                let mut columns = BTreeSet::new();
+                for page in pages {
+                    for row in page? {
+                        columns.extend(row.fields().into_keys());
+                    }
+                }
+                columns.into_iter().collect()
+            }
+        };
+        Ok(CsvExport { options, columns })
+    }
+
+    pub fn write(&self, database: &dyn Database, query: &ExportQuery, writer: impl Write) -> Result<()> {
+        let mut csv_writer = csv::WriterBuilder::new()
+            .delimiter(self.options.delimiter)
+            .from_writer(writer);
+        if self.options.header {
+            csv_writer.write_record(Self::FIXED_COLUMNS.iter().copied().chain(self.columns.iter().map(String::as_str)))?;
+        }
+        for page in query.pages(database, ExportQuery::PAGE_SIZE)? {
+            for row in page? {
+                csv_writer.write_record(self.record(&row))?;
+            }
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    //Streams the datapoints of a query as a csv download, a page is read from the database at a time
+    pub fn response(database: Arc<dyn Database>, query: ExportQuery, options: CsvOptions) -> HttpResponse {
+        let export = match CsvExport::new(options, database.as_ref(), &query) {
+            Ok(export) => export,
+            Err(e) => return HttpResponse::from_error(&e),
+        };
+        HttpResponse::streamed(
+            HttpStatus::OK,
+            HttpHeader::default_attachment("text/csv; charset=utf-8", &format!("session-{}.csv", query.session_id)),
+            Box::new(move |stream| {
+                if let Err(e) = export.write(database.as_ref(), &query, BufWriter::new(&stream)) {
+                    eprintln!("Csv export closed: {e}");
+                }
+                let _ = stream.shutdown(Shutdown::Both);
+            }),
+        )
+    }
+
+    fn record(&self, row: &ExportRow) -> Vec<String> {
+        let mut fields = row.fields();
+        let datetime = row
+            .datetime
+            .and_utc()
+            .with_timezone(&self.options.timezone)
+            .format(&self.options.datetime_format)
+            .to_string();
+        [datetime, row.sensor_id.to_string(), row.sensor_type.clone()]
+            .into_iter()
+            .chain(self.columns.iter().map(|column| fields.remove(column).map(text).unwrap_or_default()))
+            .collect()
+    }
+}
+
+// The csv field of a flattened value, null is left empty
+fn text(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text,
+        value => value.to_string(),
+    }
+}
+
+//Flattens a blob into dotted keys, array elements are keyed by their index and a blob that is not an object is keyed "value"
+pub fn flatten_values(blob: &Value) -> BTreeMap<String, Value> {
+    let mut fields = BTreeMap::new();
+    match blob {
+        Value::Object(_) | Value::Array(_) => flatten_into("", blob, &mut fields),
+        Value::Null => {}
+        value => flatten_into("value", value, &mut fields),
+    }
+    fields
+}
+
+fn flatten_into(prefix: &str, value: &Value, fields: &mut BTreeMap<String, Value>) {
+    let key = |name: &str| match prefix {
+        "" => name.to_string(),
+        prefix => format!("{prefix}.{name}"),
+    };
+    match value {
+        Value::Object(map) => map.iter().for_each(|(name, value)| flatten_into(&key(name), value, fields)),
+        Value::Array(values) => values
+            .iter()
+            .enumerate()
+            .for_each(|(index, value)| flatten_into(&key(&index.to_string()), value, fields)),
+        value => {
+            fields.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
@@ -0,0 +1,307 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use serde_json::{Map, Value};
+
+use crate::data::Database;
+use crate::derived::Deriver;
+use crate::error::Error;
+use crate::http::{HttpRequest, HttpResponse};
+use crate::models::SessionSensorData;
+
+#[cfg(feature = "parquet")]
+use super::columnar_export::{ColumnarExport, ColumnarFormat, ColumnarOptions};
+use super::csv_export::{flatten_values, CsvExport, CsvOptions};
+use super::track_export::{TrackExport, TrackFormat, TrackOptions};
+
+type Result<T> = crate::Result<T>;
+
+//A datapoint being exported along with the sensor that recorded it
+#[derive(Debug, Clone)]
+pub struct ExportRow {
+    pub sensor_id: i64,
+    pub sensor_type: String,
+    // parsed datetime of the datapoint, rows are ordered by it
+    pub datetime: NaiveDateTime,
+    pub datapoint: SessionSensorData,
+    // derived channels of the sensor type by name, null where the expression failed
+    pub derived: Map<String, Value>,
+}
+
+impl ExportRow {
+    //The flattened fields of the blob, followed by the derived channels keyed "derived.{name}"
+    pub fn fields(&self) -> BTreeMap<String, Value> {
+        let mut fields = flatten_values(self.datapoint.get_blob());
+        fields.extend(
+            self.derived
+                .iter()
+                .map(|(name, value)| (format!("derived.{name}"), value.clone())),
+        );
+        fields
+    }
+}
+
+//Which datapoints of a session are exported, read from the query string
+//`sensor` takes a comma separated list of sensor ids, `from` is inclusive and `to` exclusive
+//Derived channels are exported unless `derived` is false
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportQuery {
+    pub session_id: i64,
+    // every sensor of the session when empty
+    pub sensor_ids: Vec<i64>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub derived: bool,
+}
+
+impl ExportQuery {
+    pub const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.f";
+    // datapoints read from the database at a time
+    pub const PAGE_SIZE: usize = 10_000;
+
+    //Bounds are datetimes formatted like DATETIME_FORMAT
+    pub fn new(session_id: i64, sensor_ids: Vec<i64>, from: Option<&str>, to: Option<&str>) -> Result<Self> {
+        let query = ExportQuery {
+            session_id,
+            sensor_ids,
+            from: Self::parse_bound(from, "from")?,
+            to: Self::parse_bound(to, "to")?,
+            derived: true,
+        };
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from >= to {
+                return Err(Error::Validation(String::from("'from' must be before 'to'")));
+            }
+        }
+        Ok(query)
+    }
+
+    pub fn from_request(session_id: i64, request: &HttpRequest) -> Result<Self> {
+        let sensor_ids = match request.parameter("sensor") {
+            Some(sensors) => sensors
+                .split(',')
+                .map(|sensor_id| {
+                    sensor_id.trim().parse::<i64>().map_err(|e| {
+                        Error::Validation(format!("Invalid sensor id '{sensor_id}' in 'sensor': {e}"))
+                    })
+                })
+                .collect::<Result<Vec<i64>>>()?,
+            None => Vec::new(),
+        };
+        Ok(ExportQuery {
+            derived: Deriver::requested(request)?,
+            ..Self::new(session_id, sensor_ids, request.parameter("from"), request.parameter("to"))?
+        })
+    }
+
+    //Datapoints of the session matching the query a page at a time, oldest first
+    pub fn pages<'a>(&self, database: &'a dyn Database, page_size: usize) -> Result<ExportPages<'a>> {
+        database.get_session(self.session_id)?;
+        let session_sensors = database.get_session_sensors(self.session_id)?;
+        if let Some(sensor_id) = self
+            .sensor_ids
+            .iter()
+            .find(|sensor_id| !session_sensors.iter().any(|session_sensor| session_sensor.get_sensor_id() == *sensor_id))
+        {
+            return Err(Error::NotFound(format!("Sensor {sensor_id} in session {} not found", self.session_id)));
+        }
+        let sensor_types: HashMap<i64, String> = database
+            .get_sensors()?
+            .into_iter()
+            .map(|sensor| (*sensor.get_id(), sensor.get_sensor_type().to_string()))
+            .collect();
+        let sensors = session_sensors
+            .iter()
+            .filter(|session_sensor| self.sensor_ids.is_empty() || self.sensor_ids.contains(session_sensor.get_sensor_id()))
+            .map(|session_sensor| {
+                let sensor_id = *session_sensor.get_sensor_id();
+                (*session_sensor.get_id(), (sensor_id, sensor_types.get(&sensor_id).cloned().unwrap_or_default()))
+            })
+            .collect();
+
+        Ok(ExportPages {
+            database,
+            session_id: self.session_id,
+            from: self.from.map(|from| from.format(Self::DATETIME_FORMAT).to_string()),
+            to: self.to.map(|to| to.format(Self::DATETIME_FORMAT).to_string()),
+            sensors,
+            page_size: page_size.max(1),
+            deriver: self.derived.then(|| Deriver::new(database)),
+            after: None,
+            done: false,
+        })
+    }
+
+    //Exports a session in the `format` of the request, csv when none is given
+    pub fn response(database: &Arc<dyn Database>, session_id: i64, request: &HttpRequest) -> HttpResponse {
+        let query = match Self::from_request(session_id, request) {
+            Ok(query) => query,
+            Err(e) => return HttpResponse::from_error(&e),
+        };
+        match request.parameter("format").unwrap_or("csv") {
+            "csv" => match CsvOptions::from_request(request) {
+                Ok(options) => CsvExport::response(Arc::clone(database), query, options),
+                Err(e) => HttpResponse::from_error(&e),
+            },
+            #[cfg(feature = "parquet")]
+            format @ ("parquet" | "arrow") => {
+                let format = match format {
+                    "parquet" => ColumnarFormat::Parquet,
+                    _ => ColumnarFormat::Arrow,
+                };
+                match ColumnarOptions::from_request(format, request) {
+                    Ok(options) => ColumnarExport::response(Arc::clone(database), query, options),
+                    Err(e) => HttpResponse::from_error(&e),
+                }
+            }
+            #[cfg(not(feature = "parquet"))]
+            "parquet" | "arrow" => HttpResponse::from_error(&Self::columnar_unavailable()),
+            format => match TrackFormat::from_name(format) {
+                Some(format) => match TrackOptions::from_request(format, request) {
+                    Ok(options) => TrackExport::response(database.as_ref(), &query, options),
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                None => HttpResponse::bad_request(&format!(
+                    "Unknown export format '{format}', expected one of [csv, parquet, arrow, gpx, kml, geojson]"
+                )),
+            },
+        }
+    }
+
+    //Writes the query to a file in the format of its extension, .csv, .parquet, .arrows, .gpx, .kml or .geojson
+    pub fn export_to_file(&self, database: &dyn Database, path: &Path) -> Result<()> {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+        let writer = || {
+            File::create(path)
+                .map(BufWriter::new)
+                .map_err(|e| Error::Io(format!("Failed to create {path:?}: {e}")))
+        };
+        match extension.as_deref() {
+            Some("csv") => CsvExport::new(CsvOptions::default(), database, self)?.write(database, self, writer()?),
+            #[cfg(feature = "parquet")]
+            Some(extension @ ("parquet" | "arrow" | "arrows")) => {
+                let format = match extension {
+                    "parquet" => ColumnarFormat::Parquet,
+                    _ => ColumnarFormat::Arrow,
+                };
+                ColumnarExport::new(ColumnarOptions::new(format), database, self)?.write(database, self, writer()?)
+            }
+            #[cfg(not(feature = "parquet"))]
+            Some("parquet" | "arrow" | "arrows") => Err(Self::columnar_unavailable()),
+            Some(extension @ ("gpx" | "kml" | "geojson")) => {
+                let format = TrackFormat::from_name(extension).unwrap_or(TrackFormat::GeoJson);
+                let track = TrackExport::new(TrackOptions::new(format), database, self)?.write();
+                let mut writer = writer()?;
+                writer.write_all(track.as_bytes())?;
+                Ok(writer.flush()?)
+            }
+            _ => Err(Error::Validation(format!(
+                "Cannot tell the export format of {path:?}, expected a .csv, .parquet, .arrows, .gpx, .kml or .geojson file"
+            ))),
+        }
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn columnar_unavailable() -> Error {
+        Error::Unavailable(String::from(
+            "Parquet and arrow exports require the server to be built with '--features parquet'",
+        ))
+    }
+
+    fn parse_bound(datetime: Option<&str>, key: &str) -> Result<Option<NaiveDateTime>> {
+        datetime
+            .map(|datetime| {
+                NaiveDateTime::parse_from_str(datetime, Self::DATETIME_FORMAT).map_err(|e| {
+                    Error::Validation(format!("Invalid datetime '{datetime}' in '{key}', expected YYYY-MM-DDTHH:MM:SS: {e}"))
+                })
+            })
+            .transpose()
+    }
+}
+
+//Reads the datapoints of an ExportQuery from the database a page at a time
+pub struct ExportPages<'a> {
+    database: &'a dyn Database,
+    session_id: i64,
+    from: Option<String>,
+    to: Option<String>,
+    // sensor id and type of every session sensor exported
+    sensors: HashMap<i64, (i64, String)>,
+    page_size: usize,
+    deriver: Option<Deriver<'a>>,
+    // last datapoint read, the next page starts after it
+    after: Option<SessionSensorData>,
+    done: bool,
+}
+
+impl Iterator for ExportPages<'_> {
+    type Item = Result<Vec<ExportRow>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let page = match self.database.get_sessions_sensor_data_page(
+                self.session_id,
+                self.from.as_deref(),
+                self.to.as_deref(),
+                self.after.as_ref(),
+                self.page_size,
+            ) {
+                Ok(page) => page,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            self.done = page.len() < self.page_size;
+            self.after = page.last().cloned();
+
+            let mut rows = Vec::with_capacity(page.len());
+            for datapoint in page {
+                // datapoints of sensors that are not exported, or recorded before datapoints had a sensor
+                let Some((sensor_id, sensor_type)) =
+                    datapoint.get_session_sensor_id().and_then(|session_sensor_id| self.sensors.get(&session_sensor_id))
+                else {
+                    continue;
+                };
+                let datetime = match NaiveDateTime::parse_from_str(datapoint.get_datetime(), ExportQuery::DATETIME_FORMAT) {
+                    Ok(datetime) => datetime,
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e.into()));
+                    }
+                };
+                let derived = match self.deriver.as_mut().map(|deriver| deriver.derive_blob(sensor_type, datapoint.get_blob())) {
+                    Some(Ok(Some(derived))) => derived.values,
+                    Some(Err(e)) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                    Some(Ok(None)) | None => Map::new(),
+                };
+                rows.push(ExportRow {
+                    sensor_id: *sensor_id,
+                    sensor_type: sensor_type.clone(),
+                    datetime,
+                    datapoint,
+                    derived,
+                });
+            }
+            if !rows.is_empty() {
+                return Some(Ok(rows));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,10 @@
+#[cfg(feature = "parquet")]
+pub mod columnar_export;
+pub mod csv_export;
+pub mod export_query;
+pub mod track_export;
+
+pub use self::export_query::ExportQuery;
+
+#[cfg(test)]
+mod test_export;
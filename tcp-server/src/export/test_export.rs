@@ -0,0 +1,286 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::error::Error;
+    #[cfg(feature = "parquet")]
+    use crate::export::columnar_export::{ColumnType, ColumnarExport, ColumnarFormat, ColumnarOptions};
+    use crate::export::csv_export::{flatten_values, CsvExport, CsvOptions};
+    use crate::export::track_export::{simplify, TrackExport, TrackFormat, TrackOptions, TrackPoint};
+    use crate::export::ExportQuery;
+    use crate::http::{HttpHeader, HttpPath, HttpRequest};
+    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+
+    /* Helpers */
+
+    // Session 1 records an accelerometer (sensor 1) and a gps (sensor 2), each with a datapoint a minute from 12:00 to 12:02
+    fn init_database() -> MemoryDatabase {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string())).unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        let blobs = [
+            ("Acceleration", json!({ "accel": { "x": 0.5, "y": -1 }, "label": "walk, fast" })),
+            ("GPS", json!({ "lat": 44.56, "lon": -123.28, "fix": null })),
+        ];
+        for (sensor_type, blob) in blobs {
+            let sensor = db.insert_sensor(&Sensor::new(0, sensor_type.to_string())).unwrap();
+            let session_sensor = db.insert_session_sensor(&SessionSensor::new(0, 1, *sensor.get_id())).unwrap();
+            let datapoints: Vec<SessionSensorData> = (0..3)
+                .map(|minute| {
+                    SessionSensorData::new(
+                        Some(1),
+                        Some(*session_sensor.get_id()),
+                        format!("2025-01-01T12:{minute:02}:00"),
+                        blob.clone(),
+                    )
+                })
+                .collect();
+            db.batch_session_sensor_data(&datapoints).unwrap();
+        }
+        db
+    }
+
+    fn request(parameters: &[(&str, &str)]) -> HttpRequest {
+        HttpRequest::get(
+            HttpPath::Export(String::from("/session/1")),
+            Some(parameters.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()),
+            HttpHeader::new(),
+            None,
+        )
+    }
+
+    #[cfg(feature = "parquet")]
+    fn columnar(db: &MemoryDatabase, parameters: &[(&str, &str)], format: ColumnarFormat) -> crate::Result<Vec<u8>> {
+        let request = request(parameters);
+        let query = ExportQuery::from_request(1, &request)?;
+        let mut bytes = Vec::new();
+        ColumnarExport::new(ColumnarOptions::from_request(format, &request)?, db, &query)?.write(db, &query, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn export(db: &MemoryDatabase, parameters: &[(&str, &str)]) -> crate::Result<String> {
+        let request = request(parameters);
+        let query = ExportQuery::from_request(1, &request)?;
+        let mut csv = Vec::new();
+        CsvExport::new(CsvOptions::from_request(&request)?, db, &query)?.write(db, &query, &mut csv)?;
+        Ok(String::from_utf8(csv).unwrap())
+    }
+
+    fn track(db: &MemoryDatabase, parameters: &[(&str, &str)], format: TrackFormat) -> crate::Result<TrackExport> {
+        let request = request(parameters);
+        let query = ExportQuery::from_request(1, &request)?;
+        TrackExport::new(TrackOptions::from_request(format, &request)?, db, &query)
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_flatten() {
+        let fields = flatten_values(&json!({ "accel": { "x": 1, "y": [2, 3] }, "label": "a", "none": null, "ok": true }));
+        let expected = vec![
+            ("accel.x", json!(1)),
+            ("accel.y.0", json!(2)),
+            ("accel.y.1", json!(3)),
+            ("label", json!("a")),
+            ("none", Value::Null),
+            ("ok", json!(true)),
+        ];
+        assert_eq!(fields.iter().map(|(key, value)| (key.as_str(), value.clone())).collect::<Vec<_>>(), expected);
+        assert_eq!(flatten_values(&Value::from(4.5)).get("value"), Some(&json!(4.5)));
+    }
+
+    #[test]
+    fn test_export_infers_columns() {
+        let db = init_database();
+        let csv = export(&db, &[]).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        // A row per datapoint of either sensor, columns missing from a blob are left empty
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "datetime,sensor_id,sensor_type,accel.x,accel.y,fix,label,lat,lon");
+        assert_eq!(lines[1], "2025-01-01T12:00:00+00:00,1,Acceleration,0.5,-1,,\"walk, fast\",,");
+        assert_eq!(lines[2], "2025-01-01T12:00:00+00:00,2,GPS,,,,,44.56,-123.28");
+    }
+
+    #[test]
+    fn test_export_options() {
+        let db = init_database();
+        let csv = export(
+            &db,
+            &[
+                ("sensor", "1"),
+                ("from", "2025-01-01T12:01:00"),
+                ("to", "2025-01-01T12:02:00"),
+                ("delimiter", ";"),
+                ("header", "false"),
+                ("timezone", "America/Los_Angeles"),
+                ("datetime_format", "%Y-%m-%d %H:%M"),
+                ("columns", "label,accel.x,missing"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(csv, "2025-01-01 04:01;1;Acceleration;walk, fast;0.5;\n");
+
+        assert!(matches!(export(&db, &[("delimiter", "ab")]), Err(Error::Validation(_))));
+        assert!(matches!(export(&db, &[("timezone", "Mars/Olympus")]), Err(Error::Validation(_))));
+        assert!(matches!(export(&db, &[("datetime_format", "%Q")]), Err(Error::Validation(_))));
+        assert!(matches!(export(&db, &[("from", "yesterday")]), Err(Error::Validation(_))));
+        assert!(matches!(export(&db, &[("sensor", "3")]), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_export_to_file_extension() {
+        let db = init_database();
+        let query = ExportQuery::new(1, Vec::new(), None, None).unwrap();
+        let path = std::env::temp_dir().join(format!("tcp_server_export_{}.txt", std::process::id()));
+        assert!(matches!(query.export_to_file(&db, &path), Err(Error::Validation(_))));
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_export_row_groups() {
+        use arrow_schema::{DataType, TimeUnit};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let db = init_database();
+        let parquet = columnar(&db, &[("row_group_size", "4"), ("compression", "snappy")], ColumnarFormat::Parquet).unwrap();
+        let path = std::env::temp_dir().join(format!("tcp_server_export_{}.parquet", std::process::id()));
+        std::fs::write(&path, parquet).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Columns are typed from their values, fix is only ever null and left out
+        let schema = reader.schema().clone();
+        let types: Vec<(&str, &DataType)> =
+            schema.fields().iter().map(|field| (field.name().as_str(), field.data_type())).collect();
+        assert_eq!(
+            types,
+            vec![
+                ("datetime", &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))),
+                ("sensor_id", &DataType::Int64),
+                ("sensor_type", &DataType::Utf8),
+                ("accel.x", &DataType::Float64),
+                ("accel.y", &DataType::Int64),
+                ("label", &DataType::Utf8),
+                ("lat", &DataType::Float64),
+                ("lon", &DataType::Float64),
+            ]
+        );
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let rows: usize = reader.build().unwrap().map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 6);
+
+        assert_eq!(ColumnType::Int64.merge(ColumnType::Float64), ColumnType::Float64);
+        assert_eq!(ColumnType::Boolean.merge(ColumnType::Int64), ColumnType::Utf8);
+        assert!(matches!(
+            columnar(&db, &[("compression", "snappy")], ColumnarFormat::Arrow),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(columnar(&db, &[("row_group_size", "0")], ColumnarFormat::Parquet), Err(Error::Validation(_))));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_arrow_export() {
+        use arrow_array::{Array, Float64Array, TimestampMicrosecondArray};
+        use arrow_ipc::reader::StreamReader;
+
+        let db = init_database();
+        let arrow = columnar(&db, &[("sensor", "2"), ("from", "2025-01-01T12:01:00")], ColumnarFormat::Arrow).unwrap();
+        let batches: Vec<_> =
+            StreamReader::try_new(std::io::Cursor::new(arrow), None).unwrap().map(|batch| batch.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+
+        let datetimes = batch.column(0).as_any().downcast_ref::<TimestampMicrosecondArray>().unwrap();
+        assert_eq!(datetimes.value(0), 1_735_732_860_000_000);
+        let lat = batch.column_by_name("lat").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(lat.value(1), 44.56);
+        // only the gps was exported, so the accelerometer columns are never inferred
+        assert!(batch.column_by_name("accel.x").is_none());
+    }
+
+    #[test]
+    fn test_track_export() {
+        let db = init_database();
+
+        // The accelerometer has no position, so only the gps is written
+        let gpx = track(&db, &[("bbox", "true")], TrackFormat::Gpx).unwrap().write();
+        assert_eq!(gpx.matches("<trk>").count(), 1);
+        assert!(gpx.contains("<name>GPS 2</name>"));
+        assert!(gpx.contains(r#"<bounds minlat="44.56" minlon="-123.28" maxlat="44.56" maxlon="-123.28"/>"#));
+        assert!(gpx.contains(r#"<trkpt lat="44.56" lon="-123.28"><time>2025-01-01T12:01:00Z</time></trkpt>"#));
+
+        let geojson: Value = serde_json::from_str(&track(&db, &[("bbox", "true")], TrackFormat::GeoJson).unwrap().write()).unwrap();
+        assert_eq!(geojson["type"], "FeatureCollection");
+        assert_eq!(geojson["bbox"], json!([-123.28, 44.56, -123.28, 44.56]));
+        assert_eq!(geojson["features"][0]["properties"]["sensor_id"], 2);
+        assert_eq!(geojson["features"][0]["geometry"]["coordinates"][2], json!([-123.28, 44.56]));
+
+        let kml = track(&db, &[], TrackFormat::Kml).unwrap().write();
+        assert!(kml.contains("<coordinates>-123.28,44.56 -123.28,44.56 -123.28,44.56</coordinates>"));
+        assert!(!kml.contains("<Region>"));
+
+        assert!(matches!(track(&db, &[("geometry", "linestring")], TrackFormat::Gpx), Err(Error::Validation(_))));
+        assert!(matches!(track(&db, &[("simplify", "-1")], TrackFormat::Gpx), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn test_track_export_mapping() {
+        let db = init_database();
+        // Positions nested in the blob with their own fix time, recorded out of order
+        let datapoints: Vec<SessionSensorData> = [(3, 44.5, 1735732802), (4, 44.4, 1735732801), (5, 91.0, 1735732803)]
+            .into_iter()
+            .map(|(minute, lat, time)| {
+                SessionSensorData::new(
+                    Some(1),
+                    Some(2),
+                    format!("2025-01-01T12:{minute:02}:00"),
+                    json!({ "gps": { "position": [lat, -123.0], "height": "71.5" }, "fix_time": time }),
+                )
+            })
+            .collect();
+        db.batch_session_sensor_data(&datapoints).unwrap();
+
+        let parameters = [
+            ("lat", "gps.position.0"),
+            ("lon", "gps.position.1"),
+            ("ele", "gps.height"),
+            ("time", "fix_time"),
+            ("geometry", "linestring"),
+        ];
+        let export = track(&db, &parameters, TrackFormat::GeoJson).unwrap();
+        // Out of range latitudes are left out, and the points are ordered by the mapped time
+        assert_eq!(export.tracks().len(), 1);
+        let geojson: Value = serde_json::from_str(&export.write()).unwrap();
+        assert_eq!(geojson, json!({ "type": "LineString", "coordinates": [[-123.0, 44.4, 71.5], [-123.0, 44.5, 71.5]] }));
+    }
+
+    #[test]
+    fn test_track_simplify() {
+        let time = chrono::NaiveDateTime::parse_from_str("2025-01-01T12:00:00", ExportQuery::DATETIME_FORMAT).unwrap();
+        // A straight line north with a 5 meter wobble, and a 100 meter turn east at the end
+        let points: Vec<TrackPoint> = [(0.0, 0.0), (0.001, 0.00004), (0.002, 0.0), (0.003, 0.0), (0.003, 0.0009)]
+            .into_iter()
+            .map(|(latitude, longitude)| TrackPoint { latitude, longitude, elevation: None, time })
+            .collect();
+
+        let simplified = simplify(&points, 10.0);
+        let kept: Vec<(f64, f64)> = simplified.iter().map(|point| (point.latitude, point.longitude)).collect();
+        assert_eq!(kept, vec![(0.0, 0.0), (0.003, 0.0), (0.003, 0.0009)]);
+        assert_eq!(simplify(&points, 1.0).len(), 5);
+        assert_eq!(simplify(&points[..2], 1000.0).len(), 2);
+    }
+}
@@ -0,0 +1,486 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, NaiveDateTime};
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpRequest, HttpResponse, HttpStatus};
+
+use super::export_query::{ExportQuery, ExportRow};
+
+type Result<T> = crate::Result<T>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackFormat {
+    Gpx,
+    Kml,
+    GeoJson,
+}
+
+impl TrackFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gpx" => Some(TrackFormat::Gpx),
+            "kml" => Some(TrackFormat::Kml),
+            "geojson" => Some(TrackFormat::GeoJson),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TrackFormat::Gpx => "gpx",
+            TrackFormat::Kml => "kml",
+            TrackFormat::GeoJson => "geojson",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TrackFormat::Gpx => "application/gpx+xml",
+            TrackFormat::Kml => "application/vnd.google-earth.kml+xml",
+            TrackFormat::GeoJson => "application/geo+json",
+        }
+    }
+}
+
+//Which blob keys hold a position and how tracks are written, read from the query string
+//Keys are dotted like the csv columns, so {"gps": {"lat": 1}} is read with lat=gps.lat
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackOptions {
+    pub format: TrackFormat,
+    pub latitude: String,
+    pub longitude: String,
+    // tracks have no elevation when the key is missing from a blob
+    pub elevation: String,
+    // the datetime of the datapoint when unset
+    pub time: Option<String>,
+    // greatest distance in meters a simplified track strays from the recorded one
+    pub simplify: Option<f64>,
+    pub bounds: bool,
+    // a single geojson LineString instead of a FeatureCollection
+    pub linestring: bool,
+}
+
+impl TrackOptions {
+    pub fn new(format: TrackFormat) -> Self {
+        TrackOptions {
+            format,
+            latitude: String::from("lat"),
+            longitude: String::from("lon"),
+            elevation: String::from("alt"),
+            time: None,
+            simplify: None,
+            bounds: false,
+            linestring: false,
+        }
+    }
+
+    pub fn from_request(format: TrackFormat, request: &HttpRequest) -> Result<Self> {
+        let mut options = TrackOptions::new(format);
+        if let Some(latitude) = request.parameter("lat") {
+            options.latitude = latitude.to_string();
+        }
+        if let Some(longitude) = request.parameter("lon") {
+            options.longitude = longitude.to_string();
+        }
+        if let Some(elevation) = request.parameter("ele") {
+            options.elevation = elevation.to_string();
+        }
+        options.time = request.parameter("time").map(String::from);
+        if let Some(simplify) = request.parameter("simplify") {
+            options.simplify = match simplify.parse::<f64>() {
+                Ok(tolerance) if tolerance.is_finite() && tolerance >= 0.0 => Some(tolerance),
+                _ => {
+                    return Err(Error::Validation(format!(
+                        "Invalid simplify '{simplify}', expected a distance in meters"
+                    )))
+                }
+            };
+        }
+        if let Some(bounds) = request.parameter("bbox") {
+            options.bounds = bounds
+                .parse::<bool>()
+                .map_err(|_| Error::Validation(format!("Invalid bbox '{bounds}', expected true or false")))?;
+        }
+        if let Some(geometry) = request.parameter("geometry") {
+            options.linestring = match (geometry, format) {
+                ("collection", TrackFormat::GeoJson) => false,
+                ("linestring", TrackFormat::GeoJson) => true,
+                (geometry, TrackFormat::GeoJson) => {
+                    return Err(Error::Validation(format!(
+                        "Unknown geometry '{geometry}', expected one of [collection, linestring]"
+                    )))
+                }
+                _ => return Err(Error::Validation(String::from("'geometry' only applies to geojson"))),
+            };
+        }
+        Ok(options)
+    }
+
+    // A datapoint's position, none when its latitude or longitude is missing or out of range
+    fn point(&self, row: &ExportRow) -> Option<TrackPoint> {
+        let fields = row.fields();
+        let number = |key: &str| fields.get(key).and_then(|value| match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(text) => text.trim().parse::<f64>().ok(),
+            _ => None,
+        });
+        let latitude = number(&self.latitude).filter(|latitude| (-90.0..=90.0).contains(latitude))?;
+        let longitude = number(&self.longitude).filter(|longitude| (-180.0..=180.0).contains(longitude))?;
+        let time = match &self.time {
+            Some(key) => parse_time(fields.get(key)?)?,
+            None => row.datetime,
+        };
+        Some(TrackPoint {
+            latitude,
+            longitude,
+            elevation: number(&self.elevation).filter(|elevation| elevation.is_finite()),
+            time,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,
+    pub time: NaiveDateTime,
+}
+
+//Positions recorded by one sensor, oldest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub sensor_id: i64,
+    pub sensor_type: String,
+    pub points: Vec<TrackPoint>,
+}
+
+//Smallest box holding every point of the tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+}
+
+impl Bounds {
+    pub fn of(tracks: &[Track]) -> Option<Self> {
+        tracks.iter().flat_map(|track| &track.points).fold(None, |bounds, point| {
+            let bounds = bounds.unwrap_or(Bounds {
+                min_latitude: point.latitude,
+                min_longitude: point.longitude,
+                max_latitude: point.latitude,
+                max_longitude: point.longitude,
+            });
+            Some(Bounds {
+                min_latitude: bounds.min_latitude.min(point.latitude),
+                min_longitude: bounds.min_longitude.min(point.longitude),
+                max_latitude: bounds.max_latitude.max(point.latitude),
+                max_longitude: bounds.max_longitude.max(point.longitude),
+            })
+        })
+    }
+}
+
+//Writes the positions in datapoint blobs as a GPX track, KML placemark or GeoJSON feature per sensor
+//Datapoints without a valid latitude and longitude are left out, and so are sensors without any
+pub struct TrackExport {
+    options: TrackOptions,
+    tracks: Vec<Track>,
+}
+
+impl TrackExport {
+    const TIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.fZ";
+
+    //Every position is read before anything is written, since simplifying and bounding a track needs all of it
+    pub fn new(options: TrackOptions, database: &dyn Database, query: &ExportQuery) -> Result<Self> {
+        let mut tracks: BTreeMap<i64, Track> = BTreeMap::new();
+        for page in query.pages(database, ExportQuery::PAGE_SIZE)? {
+            for row in page? {
+                if let Some(point) = options.point(&row) {
+                    tracks
+                        .entry(row.sensor_id)
+                        .or_insert_with(|| Track {
+                            sensor_id: row.sensor_id,
+                            sensor_type: row.sensor_type.clone(),
+                            points: Vec::new(),
+                        })
+                        .points
+                        .push(point);
+                }
+            }
+        }
+        let mut tracks: Vec<Track> = tracks.into_values().collect();
+        for track in tracks.iter_mut() {
+            // a time read from the blob may not follow the order of the datapoints
+            track.points.sort_by_key(|point| point.time);
+            if let Some(tolerance) = options.simplify {
+                track.points = simplify(&track.points, tolerance);
+            }
+        }
+        if options.linestring && tracks.len() > 1 {
+            return Err(Error::Validation(format!(
+                "A LineString holds a single track but {} sensors have positions, choose one with 'sensor'",
+                tracks.len()
+            )));
+        }
+        Ok(TrackExport { options, tracks })
+    }
+
+    #[allow(unused)]
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn write(&self) -> String {
+        match self.options.format {
+            TrackFormat::Gpx => self.gpx(),
+            TrackFormat::Kml => self.kml(),
+            TrackFormat::GeoJson => self.geojson().to_string(),
+        }
+    }
+
+    pub fn response(database: &dyn Database, query: &ExportQuery, options: TrackOptions) -> HttpResponse {
+        let format = options.format;
+        match TrackExport::new(options, database, query) {
+            Ok(export) => HttpResponse::new(
+                HttpStatus::OK,
+                HttpHeader::default_attachment(
+                    format.content_type(),
+                    &format!("session-{}.{}", query.session_id, format.extension()),
+                ),
+                export.write(),
+            ),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+
+    fn gpx(&self) -> String {
+        let mut gpx = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"tcp-server\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+        );
+        if let Some(bounds) = self.bounds() {
+            let _ = writeln!(
+                gpx,
+                "  <metadata><bounds minlat=\"{}\" minlon=\"{}\" maxlat=\"{}\" maxlon=\"{}\"/></metadata>",
+                bounds.min_latitude, bounds.min_longitude, bounds.max_latitude, bounds.max_longitude
+            );
+        }
+        for track in &self.tracks {
+            let _ = writeln!(gpx, "  <trk>\n    <name>{}</name>\n    <trkseg>", Self::track_name(track));
+            for point in &track.points {
+                let _ = write!(gpx, "      <trkpt lat=\"{}\" lon=\"{}\">", point.latitude, point.longitude);
+                if let Some(elevation) = point.elevation {
+                    let _ = write!(gpx, "<ele>{elevation}</ele>");
+                }
+                let _ = writeln!(gpx, "<time>{}</time></trkpt>", point.time.format(Self::TIME_FORMAT));
+            }
+            gpx.push_str("    </trkseg>\n  </trk>\n");
+        }
+        gpx.push_str("</gpx>\n");
+        gpx
+    }
+
+    // Times are kept with gx:Track, which Google Earth plays back, and a LineString is added for every other viewer
+    fn kml(&self) -> String {
+        let mut kml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\" xmlns:gx=\"http://www.google.com/kml/ext/2.2\">\n<Document>\n",
+        );
+        if let Some(bounds) = self.bounds() {
+            let _ = writeln!(
+                kml,
+                "  <Region><LatLonAltBox><north>{}</north><south>{}</south><east>{}</east><west>{}</west></LatLonAltBox></Region>",
+                bounds.max_latitude, bounds.min_latitude, bounds.max_longitude, bounds.min_longitude
+            );
+        }
+        for track in &self.tracks {
+            let altitude_mode = match track.points.iter().all(|point| point.elevation.is_some()) {
+                true => "absolute",
+                false => "clampToGround",
+            };
+            let _ = writeln!(
+                kml,
+                "  <Placemark>\n    <name>{}</name>\n    <MultiGeometry>\n      <gx:Track><altitudeMode>{altitude_mode}</altitudeMode>",
+                Self::track_name(track)
+            );
+            for point in &track.points {
+                let _ = writeln!(kml, "        <when>{}</when>", point.time.format(Self::TIME_FORMAT));
+            }
+            for point in &track.points {
+                let _ = writeln!(kml, "        <gx:coord>{}</gx:coord>", Self::coordinates(point, " "));
+            }
+            let _ = writeln!(
+                kml,
+                "      </gx:Track>\n      <LineString><altitudeMode>{altitude_mode}</altitudeMode><coordinates>{}</coordinates></LineString>\n    </MultiGeometry>\n  </Placemark>",
+                track
+                    .points
+                    .iter()
+                    .map(|point| Self::coordinates(point, ","))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+        kml.push_str("</Document>\n</kml>\n");
+        kml
+    }
+
+    fn geojson(&self) -> Value {
+        let bbox = self.bounds().map(|bounds| {
+            json!([bounds.min_longitude, bounds.min_latitude, bounds.max_longitude, bounds.max_latitude])
+        });
+        let line_string = |track: &Track| {
+            let mut geometry = json!({
+                "type": "LineString",
+                "coordinates": track
+                    .points
+                    .iter()
+                    .map(|point| match point.elevation {
+                        Some(elevation) => json!([point.longitude, point.latitude, elevation]),
+                        None => json!([point.longitude, point.latitude]),
+                    })
+                    .collect::<Vec<_>>(),
+            });
+            if let Some(bbox) = &bbox {
+                geometry["bbox"] = bbox.clone();
+            }
+            geometry
+        };
+        if self.options.linestring {
+            return match self.tracks.first() {
+                Some(track) => line_string(track),
+                None => json!({ "type": "LineString", "coordinates": [] }),
+            };
+        }
+
+        let features: Vec<Value> = self
+            .tracks
+            .iter()
+            .map(|track| {
+                json!({
+                    "type": "Feature",
+                    "geometry": line_string(track),
+                    "properties": {
+                        "sensor_id": track.sensor_id,
+                        "sensor_type": track.sensor_type,
+                        "times": track
+                            .points
+                            .iter()
+                            .map(|point| point.time.format(Self::TIME_FORMAT).to_string())
+                            .collect::<Vec<_>>(),
+                    },
+                })
+            })
+            .collect();
+        let mut collection = json!({ "type": "FeatureCollection", "features": features });
+        if let Some(bbox) = bbox {
+            collection["bbox"] = bbox;
+        }
+        collection
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        match self.options.bounds {
+            true => Bounds::of(&self.tracks),
+            false => None,
+        }
+    }
+
+    fn track_name(track: &Track) -> String {
+        escape_xml(&format!("{} {}", track.sensor_type, track.sensor_id))
+    }
+
+    fn coordinates(point: &TrackPoint, separator: &str) -> String {
+        match point.elevation {
+            Some(elevation) => format!("{}{separator}{}{separator}{elevation}", point.longitude, point.latitude),
+            None => format!("{}{separator}{}", point.longitude, point.latitude),
+        }
+    }
+}
+
+// Times in a blob are RFC 3339, a UTC datetime like the datapoints', or Unix seconds
+fn parse_time(value: &Value) -> Option<NaiveDateTime> {
+    match value {
+        Value::Number(seconds) => {
+            DateTime::from_timestamp_micros((seconds.as_f64()? * 1e6).round() as i64).map(|time| time.naive_utc())
+        }
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .map(|time| time.naive_utc())
+            .or_else(|_| NaiveDateTime::parse_from_str(text, ExportQuery::DATETIME_FORMAT))
+            .ok(),
+        _ => None,
+    }
+}
+
+//Douglas-Peucker simplification, keeping the points a track needs to stay within tolerance meters of every recorded point
+//Distances are measured on a flat projection around the first point, which is close enough over the length of a recording
+pub fn simplify(points: &[TrackPoint], tolerance: f64) -> Vec<TrackPoint> {
+    const EARTH_RADIUS: f64 = 6_371_008.8;
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let origin_cos = points[0].latitude.to_radians().cos();
+    let projected: Vec<(f64, f64)> = points
+        .iter()
+        .map(|point| {
+            (
+                point.longitude.to_radians() * origin_cos * EARTH_RADIUS,
+                point.latitude.to_radians() * EARTH_RADIUS,
+            )
+        })
+        .collect();
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    // ranges still to simplify, a stack instead of recursion so long tracks cannot overflow it
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|index| (index, segment_distance(projected[index], projected[first], projected[last])))
+            .max_by(|left, right| left.1.total_cmp(&right.1));
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                ranges.push((first, index));
+                ranges.push((index, last));
+            }
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(point, _)| point.clone())
+        .collect()
+}
+
+fn segment_distance(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = dx * dx + dy * dy;
+    let t = match length {
+        0.0 => 0.0,
+        _ => (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length).clamp(0.0, 1.0),
+    };
+    (point.0 - (start.0 + t * dx)).hypot(point.1 - (start.1 + t * dy))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
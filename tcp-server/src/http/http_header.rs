@@ -7,31 +7,57 @@ Unless required by applicable law or agreed to in writing, software distributed
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
 use chrono::Utc;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 //statuses the server uses
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum HttpStatus {
+    SwitchingProtocols = 101,
     OK = 200,
     Created = 201,
+    Accepted = 202,
     NoContent = 204,
     BadRequest = 400,
     Unauthorized = 401,
     Forbidden = 403,
     NotFound = 404,
+    MethodNotAllowed = 405,
+    Conflict = 409,
+    PayloadTooLarge = 413,
+    UnsupportedMediaType = 415,
+    UnprocessableEntity = 422,
+    TooManyRequests = 429,
+    InternalServerError = 500,
+    ServiceUnavailable = 503,
 }
 
 impl HttpStatus {
     pub const fn as_str(&self) -> &'static str {
         match self {
+            HttpStatus::SwitchingProtocols => "101 Switching Protocols",
             HttpStatus::OK => "200 OK",
             HttpStatus::Created => "201 Created",
+            HttpStatus::Accepted => "202 Accepted",
             HttpStatus::NoContent => "204 No Content",
             HttpStatus::BadRequest => "400 Bad Request",
             HttpStatus::Unauthorized => "401 Unauthorized",
             HttpStatus::Forbidden => "403 Forbidden",
             HttpStatus::NotFound => "404 Not Found",
+            HttpStatus::MethodNotAllowed => "405 Method Not Allowed",
+            HttpStatus::Conflict => "409 Conflict",
+            HttpStatus::PayloadTooLarge => "413 Payload Too Large",
+            HttpStatus::UnsupportedMediaType => "415 Unsupported Media Type",
+            HttpStatus::UnprocessableEntity => "422 Unprocessable Entity",
+            HttpStatus::TooManyRequests => "429 Too Many Requests",
+            HttpStatus::InternalServerError => "500 Internal Server Error",
+            HttpStatus::ServiceUnavailable => "503 Service Unavailable",
         }
     }
+
+    pub const fn code(&self) -> u16 {
+        *self as u16
+    }
 }
 
 #[allow(unused)]
@@ -42,6 +68,9 @@ pub enum HttpHeaderType {
     DateTime,
     ContentType,
     ContentLength,
+    ContentDisposition,
+    RequestID,
+    Allow,
     // CORS Access Control (Ac) headers
     // Server
     AcAllowOrigin,
@@ -55,6 +84,15 @@ pub enum HttpHeaderType {
     Origin,
     AcRequestMethod,
     AcRequestHeaders,
+    // WebSocket upgrade
+    Upgrade,
+    Connection,
+    SecWebSocketKey,
+    SecWebSocketVersion,
+    SecWebSocketAccept,
+    // Server-sent events
+    CacheControl,
+    LastEventID,
 }
 
 impl HttpHeaderType {
@@ -66,6 +104,9 @@ impl HttpHeaderType {
             HttpHeaderType::DateTime => "datetime",
             HttpHeaderType::ContentType => "content-type",
             HttpHeaderType::ContentLength => "content-length",
+            HttpHeaderType::ContentDisposition => "content-disposition",
+            HttpHeaderType::RequestID => "x-request-id",
+            HttpHeaderType::Allow => "allow",
             HttpHeaderType::AcAllowOrigin => "access-control-allow-origin",
             HttpHeaderType::AcAllowMethods => "access-control-allow-methods",
             HttpHeaderType::AcAllowHeaders => "access-control-allow-headers",
@@ -76,6 +117,13 @@ impl HttpHeaderType {
             HttpHeaderType::Origin => "origin",
             HttpHeaderType::AcRequestMethod => "access-control-request-method",
             HttpHeaderType::AcRequestHeaders => "access-control-request-headers",
+            HttpHeaderType::Upgrade => "upgrade",
+            HttpHeaderType::Connection => "connection",
+            HttpHeaderType::SecWebSocketKey => "sec-websocket-key",
+            HttpHeaderType::SecWebSocketVersion => "sec-websocket-version",
+            HttpHeaderType::SecWebSocketAccept => "sec-websocket-accept",
+            HttpHeaderType::CacheControl => "cache-control",
+            HttpHeaderType::LastEventID => "last-event-id",
         }
     }
 }
@@ -142,6 +190,15 @@ impl HttpHeader {
         header.build()
     }
 
+    pub fn default_problem_json() -> Self {
+        let mut header = HttpHeader::new();
+        header.default().with(vec![(
+            HttpHeaderType::ContentType.as_str(),
+            "application/problem+json",
+        )]);
+        header.build()
+    }
+
     pub fn default_html() -> Self {
         let mut header = HttpHeader::new();
         header.default().with(vec![(
@@ -151,6 +208,30 @@ impl HttpHeader {
         header.build()
     }
 
+    pub fn default_event_stream() -> Self {
+        let mut header = HttpHeader::new();
+        header.default().with(vec![
+            (HttpHeaderType::ContentType.as_str(), "text/event-stream"),
+            (HttpHeaderType::CacheControl.as_str(), "no-cache"),
+            (HttpHeaderType::Connection.as_str(), "keep-alive"),
+        ]);
+        header.build()
+    }
+
+    //Body downloaded as a file named file_name, the connection is closed once it is written
+    pub fn default_attachment(content_type: &str, file_name: &str) -> Self {
+        let mut header = HttpHeader::new();
+        header.default().with(vec![
+            (HttpHeaderType::ContentType.as_str(), content_type),
+            (
+                HttpHeaderType::ContentDisposition.as_str(),
+                &format!("attachment; filename=\"{file_name}\""),
+            ),
+            (HttpHeaderType::Connection.as_str(), "close"),
+        ]);
+        header.build()
+    }
+
     pub fn default_options() -> Self {
         let mut header = HttpHeader::new();
         header.default().with(vec![
@@ -161,9 +242,10 @@ impl HttpHeader {
             (
                 HttpHeaderType::AcAllowHeaders.as_str(),
                 &format!(
-                    "{}, {}",
+                    "{}, {}, {}",
                     HttpHeaderType::ContentType.as_str(),
-                    HttpHeaderType::SessionID.as_str()
+                    HttpHeaderType::SessionID.as_str(),
+                    HttpHeaderType::RequestID.as_str()
                 ),
             ),
             (HttpHeaderType::AcMaxAge.as_str(), Self::AC_MAX_AGE),
@@ -171,14 +253,6 @@ impl HttpHeader {
         header.build()
     }
 
-    pub fn to_string(&self) -> String {
-        self.headers
-            .iter()
-            .map(|(k, v)| format!("{}: {}", k, v))
-            .collect::<Vec<String>>()
-            .join("\r\n")
-    }
-
     pub fn insert(&mut self, key: String, value: String) -> Option<String> {
         self.headers.insert(key, value)
     }
@@ -200,3 +274,17 @@ impl HttpHeader {
         }
     }
 }
+
+impl fmt::Display for HttpHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.headers
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<String>>()
+                .join("\r\n")
+        )
+    }
+}
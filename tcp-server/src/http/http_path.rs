@@ -44,7 +44,17 @@ impl_http_path!(HttpPath {
     (Sensor, HttpPath::SENSOR_ENDPOINT),
     (Session, HttpPath::SESSION_ENDPOINT),
     (SessionSensor, HttpPath::SESSION_SENSOR_ENDPOINT),
-    (SessionSensorData, HttpPath::SESSION_SENSOR_DATA_ENDPOINT)
+    (SessionSensorData, HttpPath::SESSION_SENSOR_DATA_ENDPOINT),
+    (Telemetry, HttpPath::TELEMETRY_ENDPOINT),
+    (Live, HttpPath::LIVE_ENDPOINT),
+    (Retention, HttpPath::RETENTION_ENDPOINT),
+    (Backup, HttpPath::BACKUP_ENDPOINT),
+    (Export, HttpPath::EXPORT_ENDPOINT),
+    (Import, HttpPath::IMPORT_ENDPOINT),
+    (SensorSchema, HttpPath::SENSOR_SCHEMA_ENDPOINT),
+    (Quarantine, HttpPath::QUARANTINE_ENDPOINT),
+    (Alert, HttpPath::ALERT_ENDPOINT),
+    (DerivedChannel, HttpPath::DERIVED_CHANNEL_ENDPOINT)
 });
 
 #[derive(Debug, Clone)]
@@ -57,6 +67,16 @@ pub enum HttpPath {
     Session(String),
     SessionSensor(String),
     SessionSensorData(String),
+    Telemetry(String),
+    Live(String),
+    Retention(String),
+    Backup(String),
+    Export(String),
+    Import(String),
+    SensorSchema(String),
+    Quarantine(String),
+    Alert(String),
+    DerivedChannel(String),
 }
 
 impl HttpPath {
@@ -68,6 +88,16 @@ impl HttpPath {
     const SESSION_ENDPOINT: &str = "/sessions";
     const SESSION_SENSOR_ENDPOINT: &str = "/sessions-sensors";
     const SESSION_SENSOR_DATA_ENDPOINT: &str = "/sessions-sensors-data";
+    const TELEMETRY_ENDPOINT: &str = "/telemetry";
+    const LIVE_ENDPOINT: &str = "/live";
+    const RETENTION_ENDPOINT: &str = "/retention";
+    const BACKUP_ENDPOINT: &str = "/backups";
+    const EXPORT_ENDPOINT: &str = "/export";
+    const IMPORT_ENDPOINT: &str = "/import";
+    const SENSOR_SCHEMA_ENDPOINT: &str = "/sensor-schemas";
+    const QUARANTINE_ENDPOINT: &str = "/quarantine";
+    const ALERT_ENDPOINT: &str = "/alerts";
+    const DERIVED_CHANNEL_ENDPOINT: &str = "/derived-channels";
 
     pub fn from_string(path: String) -> HttpPath {
         let (base, subpath) = path[1..]
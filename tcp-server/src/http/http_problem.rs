@@ -0,0 +1,73 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use serde_json::{json, Value};
+
+use crate::error::Error;
+
+use super::HttpStatus;
+
+//Body of an error response following RFC 7807, the request fields are filled in once the response is routed
+pub struct HttpProblem {
+    error: Error,
+    instance: Option<String>,
+    request_id: Option<String>,
+    // extension member listing the individual errors behind the problem
+    errors: Option<Value>,
+}
+
+impl HttpProblem {
+    // problem types are relative so they resolve against whichever host served them
+    pub const TYPE_PREFIX: &'static str = "/problems/";
+
+    pub fn new(error: Error) -> Self {
+        HttpProblem {
+            error,
+            instance: None,
+            request_id: None,
+            errors: None,
+        }
+    }
+
+    pub fn with_errors(mut self, errors: Value) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    pub fn set_request(&mut self, instance: Option<&str>, request_id: &str) {
+        self.instance = instance.map(str::to_string);
+        self.request_id = Some(request_id.to_string());
+    }
+
+    pub fn status(&self) -> HttpStatus {
+        self.error.status()
+    }
+
+    pub fn get_type(&self) -> String {
+        format!("{}{}", Self::TYPE_PREFIX, self.error.kind())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut json = json!({
+            "type": self.get_type(),
+            "title": self.error.title(),
+            "status": self.error.status().code(),
+            "detail": self.error.message(),
+        });
+        if let Some(instance) = &self.instance {
+            json["instance"] = json!(instance);
+        }
+        if let Some(request_id) = &self.request_id {
+            json["request_id"] = json!(request_id);
+        }
+        if let Some(errors) = &self.errors {
+            json["errors"] = errors.clone();
+        }
+        json
+    }
+}
@@ -6,13 +6,16 @@ You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2
 Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
+use chrono::Utc;
 use serde_json::Value;
-use std::{fs, str};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fmt, fs, str};
 use url::form_urlencoded;
 
+use crate::error::Error;
 use crate::HTTP_HEADER_DELIMITER;
 
-use super::{HttpHeader, HttpMethod, HttpPath};
+use super::{HttpHeader, HttpHeaderType, HttpMethod, HttpPath};
 
 #[allow(unused)]
 pub struct HttpRequest {
@@ -24,17 +27,7 @@ pub struct HttpRequest {
 }
 
 impl HttpRequest {
-    #[allow(unused)]
-    pub fn to_string(&self) -> String {
-        format!(
-            "{} {}{} HTTP/1.1\r\n{}\r\n\r\n{:#?}",
-            self.method.as_str(),
-            self.path.to_string(),
-            self.parameters_to_string(),
-            self.headers.to_string(),
-            self.body,
-        )
-    }
+    pub const MAX_REQUEST_ID_LEN: usize = 128;
 
     #[allow(unused)]
     //Returns a String in the format of "?key1=val1&keyN=valN" or "" if parameters is empty
@@ -55,6 +48,45 @@ impl HttpRequest {
             .unwrap_or_default()
     }
 
+    //Returns the value of the first query parameter named key
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.parameters
+            .as_ref()?
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    //Identifies the request in responses and logs, an X-Request-ID sent by the client is kept when it is well formed
+    pub fn request_id(&self) -> String {
+        match self
+            .headers
+            .get(HttpHeaderType::RequestID.as_str())
+            .map(|id| id.trim())
+        {
+            Some(id) if Self::is_valid_request_id(id) => id.to_string(),
+            _ => Self::generate_request_id(),
+        }
+    }
+
+    //Unique for the lifetime of the process, the timestamp keeps ids distinct across restarts
+    pub fn generate_request_id() -> String {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        format!(
+            "{:x}-{:x}",
+            Utc::now().timestamp_millis(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    pub fn is_valid_request_id(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= Self::MAX_REQUEST_ID_LEN
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+    }
+
     #[allow(unused)]
     pub fn to_bytes(&self) -> Vec<u8> {
         self.to_string().into_bytes()
@@ -67,7 +99,7 @@ impl HttpRequest {
             buffer
                 .windows(delimiter.len())
                 .position(|window| window == delimiter)
-                .unwrap_or_else(|| buffer.len()),
+                .unwrap_or(buffer.len()),
         );
 
         //split the header on spaces ' '
@@ -133,8 +165,8 @@ impl HttpRequest {
             .trim_end_matches(&String::from_utf8_lossy(HTTP_HEADER_DELIMITER).to_string())
             .to_string();
 
-        if trim_body.len() == 0 {
-            return Err("Failed to parse request body to utf8 string".to_string());
+        if trim_body.is_empty() {
+            return Err(Error::Parse(String::from("Failed to parse request body to utf8 string")));
         }
 
         //parse buffer to Value object
@@ -143,12 +175,20 @@ impl HttpRequest {
             Err(e) => {
                 #[cfg(debug_assertions)]
                 let _ = fs::write("failed_parse.txt", trim_body);
-                return Err(format!("Failed to parse request body to json value: {e}"));
+                return Err(Error::Parse(format!("Failed to parse request body to json value: {e}")));
             }
         };
         Ok(())
     }
 
+    //Keeps a body that is not json, such as a csv file, as a json string
+    pub fn parse_text_body(&mut self, buffer: &[u8]) -> crate::Result<()> {
+        let text = str::from_utf8(buffer)
+            .map_err(|e| Error::Parse(format!("Failed to parse request body to utf8 string: {e}")))?;
+        self.body = Some(Value::String(text.trim_end_matches('\0').to_string()));
+        Ok(())
+    }
+
     #[allow(unused)]
     pub fn error() -> Self {
         HttpRequest {
@@ -241,3 +281,17 @@ impl HttpRequest {
         }
     }
 }
+
+impl fmt::Display for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}{} HTTP/1.1\r\n{}\r\n\r\n{:#?}",
+            self.method.as_str(),
+            self.path.to_string(),
+            self.parameters_to_string(),
+            self.headers,
+            self.body,
+        )
+    }
+}
@@ -6,16 +6,23 @@ You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2
 Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
-use std::{io::Write, net::TcpStream, sync::Mutex};
+use std::{fmt, io::Write, net::TcpStream, sync::Mutex};
 
-use serde_json::json;
+use crate::error::Error;
 
-use super::{HttpHeader, HttpHeaderType, HttpStatus};
+use super::{HttpHeader, HttpHeaderType, HttpProblem, HttpStatus};
+
+type Result<T> = crate::Result<T>;
+
+//Writes the body of a streamed response, it owns the connection until it returns
+pub type HttpStreamWriter = Box<dyn FnOnce(TcpStream) + Send>;
 
 pub struct HttpResponse {
     pub status: HttpStatus,
     pub headers: Mutex<HttpHeader>,
     pub body: String,
+    pub stream_writer: Option<HttpStreamWriter>,
+    pub problem: Option<HttpProblem>,
 }
 
 impl HttpResponse {
@@ -24,36 +31,48 @@ impl HttpResponse {
             status,
             headers: Mutex::new(header),
             body: body.trim_end_matches('\0').to_string(),
+            stream_writer: None,
+            problem: None,
         }
     }
 
-    pub fn from_vec(body: String) -> HttpResponse {
-        HttpResponse::new(HttpStatus::OK, HttpHeader::default_json(), body)
+    //Response without a length whose body is written over time after the head is sent
+    pub fn streamed(status: HttpStatus, header: HttpHeader, writer: HttpStreamWriter) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: Mutex::new(header),
+            body: String::new(),
+            stream_writer: Some(writer),
+            problem: None,
+        }
     }
 
-    pub fn to_string(&self) -> String {
-        format!(
-            "HTTP/1.1 {}\r\n{}: {}\r\n{}\r\n\r\n{}",
-            self.status.as_str(),
-            HttpHeaderType::ContentLength.as_str(),
-            self.body.len(),
-            self.headers.lock().unwrap().to_string(),
-            self.body,
-        )
+    pub fn from_vec(body: String) -> HttpResponse {
+        HttpResponse::new(HttpStatus::OK, HttpHeader::default_json(), body)
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
         self.to_string().into_bytes()
     }
 
-    pub fn send(&self, mut stream: TcpStream) -> Result<(), String> {
+    pub fn send(mut self, mut stream: TcpStream) -> Result<()> {
+        self.send_to(&mut stream)?;
+        //streamed bodies get their own thread so the server keeps accepting connections
+        if let Some(writer) = self.stream_writer.take() {
+            std::thread::spawn(move || writer(stream));
+        }
+        Ok(())
+    }
+
+    //Sends the response without closing the stream so it can be reused after an upgrade
+    pub fn send_to(&self, stream: &mut impl Write) -> Result<()> {
         let data: &[u8] = &self.to_bytes();
         stream
             .write_all(data)
-            .map_err(|e| format!("Failed to send data (attempted {} bytes): {e}", data.len()))?;
+            .map_err(|e| Error::Io(format!("Failed to send data (attempted {} bytes): {e}", data.len())))?;
         stream
             .flush()
-            .map_err(|e| format!("Failed to flush after sending data: {e}"))?;
+            .map_err(|e| Error::Io(format!("Failed to flush after sending data: {e}")))?;
         Ok(())
     }
 
@@ -66,11 +85,48 @@ impl HttpResponse {
     }
 
     pub fn json_404(resource: &str) -> HttpResponse {
-        HttpResponse::new(
-            HttpStatus::NotFound,
-            HttpHeader::default_json(),
-            json!({"error": format!("{resource} not found")}).to_string(),
-        )
+        HttpResponse::from_error(&Error::not_found(resource))
+    }
+
+    //Response for a failed operation, the status is decided by the kind of error
+    pub fn from_error(error: &Error) -> HttpResponse {
+        HttpResponse::from_problem(HttpProblem::new(error.clone()))
+    }
+
+    //Response for a problem built by the caller, such as one listing the errors behind it
+    pub fn from_problem(problem: HttpProblem) -> HttpResponse {
+        let mut response = HttpResponse::new(
+            problem.status(),
+            HttpHeader::default_problem_json(),
+            problem.to_json().to_string(),
+        );
+        response.problem = Some(problem);
+        response
+    }
+
+    //Response for a method the path does not accept, allowed is listed as in the Allow header
+    pub fn method_not_allowed(allowed: &str) -> HttpResponse {
+        let response = HttpResponse::from_error(&Error::MethodNotAllowed(format!(
+            "Method not allowed, expected one of [{allowed}]"
+        )));
+        response
+            .headers
+            .lock()
+            .unwrap()
+            .insert(HttpHeaderType::Allow.as_str().to_string(), allowed.to_string());
+        response
+    }
+
+    //Tags the response with the request it answers, error bodies also name the path as their instance
+    pub fn set_request(&mut self, instance: Option<&str>, request_id: &str) {
+        self.headers.lock().unwrap().insert(
+            HttpHeaderType::RequestID.as_str().to_string(),
+            request_id.to_string(),
+        );
+        if let Some(problem) = self.problem.as_mut() {
+            problem.set_request(instance, request_id);
+            self.body = problem.to_json().to_string();
+        }
     }
 
     pub fn options_response() -> HttpResponse {
@@ -82,11 +138,7 @@ impl HttpResponse {
     }
 
     pub fn bad_request(error_msg: &str) -> HttpResponse {
-        HttpResponse::new(
-            HttpStatus::BadRequest,
-            HttpHeader::default_json(),
-            json!({"error": error_msg}).to_string(),
-        )
+        HttpResponse::from_error(&Error::Parse(error_msg.to_string()))
     }
 
     pub fn missing_body(msg: Option<&str>) -> HttpResponse {
@@ -102,19 +154,13 @@ impl HttpResponse {
     }
 
     pub fn not_authorized() -> HttpResponse {
-        HttpResponse::new(
-            HttpStatus::Unauthorized,
-            HttpHeader::default_json(),
-            json!({"error": "Invalid authentication credentials."}).to_string(),
-        )
+        HttpResponse::from_error(&Error::Unauthorized(String::from(
+            "Invalid authentication credentials.",
+        )))
     }
 
     pub fn forbidden() -> HttpResponse {
-        HttpResponse::new(
-            HttpStatus::Forbidden,
-            HttpHeader::default_json(),
-            json!({"error": "User not authorized."}).to_string(),
-        )
+        HttpResponse::from_error(&Error::Forbidden(String::from("User not authorized.")))
     }
 
     pub fn no_content() -> HttpResponse {
@@ -125,3 +171,26 @@ impl HttpResponse {
         )
     }
 }
+
+impl fmt::Display for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        //informational and streamed responses must not carry a content length
+        if matches!(self.status, HttpStatus::SwitchingProtocols) || self.stream_writer.is_some() {
+            return write!(
+                f,
+                "HTTP/1.1 {}\r\n{}\r\n\r\n",
+                self.status.as_str(),
+                self.headers.lock().unwrap(),
+            );
+        }
+        write!(
+            f,
+            "HTTP/1.1 {}\r\n{}: {}\r\n{}\r\n\r\n{}",
+            self.status.as_str(),
+            HttpHeaderType::ContentLength.as_str(),
+            self.body.len(),
+            self.headers.lock().unwrap(),
+            self.body,
+        )
+    }
+}
@@ -0,0 +1,245 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha1::{Digest, Sha1};
+
+use crate::error::Error;
+
+use super::{HttpHeader, HttpHeaderType, HttpMethod, HttpRequest, HttpResponse, HttpStatus};
+
+type Result<T> = crate::Result<T>;
+
+//frame opcodes defined by RFC 6455 section 5.2
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebSocketOpcode {
+    Continuation = 0x0,
+    Text = 0x1,
+    Binary = 0x2,
+    Close = 0x8,
+    Ping = 0x9,
+    Pong = 0xA,
+}
+
+impl WebSocketOpcode {
+    pub fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0x0 => Ok(Self::Continuation),
+            0x1 => Ok(Self::Text),
+            0x2 => Ok(Self::Binary),
+            0x8 => Ok(Self::Close),
+            0x9 => Ok(Self::Ping),
+            0xA => Ok(Self::Pong),
+            _ => Err(Error::Parse(format!("Unknown websocket opcode: {byte:#x}"))),
+        }
+    }
+
+    pub fn is_control(&self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+//A single websocket frame, payloads are always stored unmasked
+pub struct WebSocketFrame {
+    pub fin: bool,
+    pub opcode: WebSocketOpcode,
+    pub payload: Vec<u8>,
+}
+
+impl WebSocketFrame {
+    pub fn new(opcode: WebSocketOpcode, payload: Vec<u8>) -> Self {
+        WebSocketFrame {
+            fin: true,
+            opcode,
+            payload,
+        }
+    }
+
+    pub fn text(text: &str) -> Self {
+        Self::new(WebSocketOpcode::Text, text.as_bytes().to_vec())
+    }
+
+    pub fn ping(payload: &[u8]) -> Self {
+        Self::new(WebSocketOpcode::Ping, payload.to_vec())
+    }
+
+    pub fn pong(payload: &[u8]) -> Self {
+        Self::new(WebSocketOpcode::Pong, payload.to_vec())
+    }
+
+    //Close frame with a status code from RFC 6455 section 7.4
+    pub fn close(code: u16, reason: &str) -> Self {
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        Self::new(WebSocketOpcode::Close, payload)
+    }
+
+    //Reads one frame from a client, client frames must be masked
+    pub fn read_from(stream: &mut impl Read, max_payload_len: usize) -> Result<Self> {
+        let mut header = [0u8; 2];
+        stream
+            .read_exact(&mut header)
+            .map_err(|e| Error::Io(format!("Failed to read websocket frame header: {e}")))?;
+
+        let fin = header[0] & 0x80 != 0;
+        if header[0] & 0x70 != 0 {
+            return Err(Error::Parse(String::from("Websocket frame uses reserved bits without an extension")));
+        }
+        let opcode = WebSocketOpcode::from_byte(header[0] & 0x0F)?;
+        if header[1] & 0x80 == 0 {
+            return Err(Error::Parse(String::from("Websocket frame from client is not masked")));
+        }
+
+        let payload_len = match header[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                stream
+                    .read_exact(&mut len)
+                    .map_err(|e| Error::Io(format!("Failed to read websocket frame length: {e}")))?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                stream
+                    .read_exact(&mut len)
+                    .map_err(|e| Error::Io(format!("Failed to read websocket frame length: {e}")))?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+
+        if opcode.is_control() && (payload_len > 125 || !fin) {
+            return Err(Error::Parse(String::from("Websocket control frame is fragmented or too large")));
+        }
+        if payload_len > max_payload_len as u64 {
+            return Err(Error::Validation(format!(
+                "Websocket frame payload of {payload_len} bytes exceeds the {max_payload_len} byte limit"
+            )));
+        }
+
+        let mut mask = [0u8; 4];
+        stream
+            .read_exact(&mut mask)
+            .map_err(|e| Error::Io(format!("Failed to read websocket frame mask: {e}")))?;
+
+        let mut payload = vec![0u8; payload_len as usize];
+        stream
+            .read_exact(&mut payload)
+            .map_err(|e| Error::Io(format!("Failed to read websocket frame payload: {e}")))?;
+        payload
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, byte)| *byte ^= mask[i % 4]);
+
+        Ok(WebSocketFrame {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    //Serializes the frame as the server would send it, server frames are never masked
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.payload.len() + 10);
+        bytes.push(if self.fin { 0x80 } else { 0x00 } | self.opcode as u8);
+        match self.payload.len() {
+            len if len < 126 => bytes.push(len as u8),
+            len if len <= u16::MAX as usize => {
+                bytes.push(126);
+                bytes.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                bytes.push(127);
+                bytes.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    pub fn write_to(&self, stream: &mut impl Write) -> Result<()> {
+        stream
+            .write_all(&self.to_bytes())
+            .map_err(|e| Error::Io(format!("Failed to send websocket frame: {e}")))?;
+        stream
+            .flush()
+            .map_err(|e| Error::Io(format!("Failed to flush after sending websocket frame: {e}")))
+    }
+}
+
+pub struct WebSocket;
+
+impl WebSocket {
+    // GUID appended to the client key, RFC 6455 section 1.3
+    pub const ACCEPT_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    pub const VERSION: &'static str = "13";
+
+    //Returns true if the request asks to upgrade the connection to a websocket
+    pub fn is_upgrade(request: &HttpRequest) -> bool {
+        matches!(request.method, HttpMethod::Get)
+            && request
+                .headers
+                .get(HttpHeaderType::Upgrade.as_str())
+                .is_some_and(|upgrade| upgrade.trim().eq_ignore_ascii_case("websocket"))
+            && request
+                .headers
+                .get(HttpHeaderType::Connection.as_str())
+                .is_some_and(|connection| {
+                    connection
+                        .split(',')
+                        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+                })
+    }
+
+    pub fn accept_key(key: &str) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(key.trim().as_bytes());
+        hasher.update(Self::ACCEPT_GUID.as_bytes());
+        STANDARD.encode(hasher.finalize())
+    }
+
+    //Validates the opening handshake and builds the 101 response that completes it
+    pub fn handshake(request: &HttpRequest) -> Result<HttpResponse> {
+        match request.headers.get(HttpHeaderType::SecWebSocketVersion.as_str()) {
+            Some(version) if version.trim() == Self::VERSION => {}
+            Some(version) => {
+                return Err(Error::Validation(format!(
+                    "Unsupported websocket version: expected {}, recieved '{}'",
+                    Self::VERSION,
+                    version.trim()
+                )))
+            }
+            None => return Err(Error::Validation(String::from("Missing websocket version header"))),
+        }
+
+        let key = match request.headers.get(HttpHeaderType::SecWebSocketKey.as_str()) {
+            Some(key) => match STANDARD.decode(key.trim()) {
+                Ok(decoded) if decoded.len() == 16 => key.trim(),
+                _ => return Err(Error::Validation(format!("Invalid websocket key: '{}'", key.trim()))),
+            },
+            None => return Err(Error::Validation(String::from("Missing websocket key header"))),
+        };
+
+        let mut header = HttpHeader::new();
+        header.default().with(vec![
+            (HttpHeaderType::Upgrade.as_str(), "websocket"),
+            (HttpHeaderType::Connection.as_str(), "Upgrade"),
+            (
+                HttpHeaderType::SecWebSocketAccept.as_str(),
+                &Self::accept_key(key),
+            ),
+        ]);
+        Ok(HttpResponse::new(
+            HttpStatus::SwitchingProtocols,
+            header.build(),
+            String::new(),
+        ))
+    }
+}
@@ -1,11 +1,18 @@
 pub mod http_header;
 pub mod http_method;
 pub mod http_path;
+pub mod http_problem;
 pub mod http_request;
 pub mod http_response;
+pub mod http_websocket;
 
 pub use self::http_header::{HttpHeader, HttpHeaderType, HttpStatus};
 pub use self::http_method::HttpMethod;
 pub use self::http_path::HttpPath;
+pub use self::http_problem::HttpProblem;
 pub use self::http_request::HttpRequest;
 pub use self::http_response::HttpResponse;
+pub use self::http_websocket::{WebSocket, WebSocketFrame, WebSocketOpcode};
+
+#[cfg(test)]
+mod test_http;
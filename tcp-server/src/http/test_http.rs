@@ -0,0 +1,81 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::error::Error;
+    use crate::http::http_header::HttpHeaderType;
+    use crate::http::{HttpRequest, HttpResponse, HttpStatus};
+
+    /* Helpers */
+
+    fn header(response: &HttpResponse, header: HttpHeaderType) -> Option<String> {
+        response.headers.lock().unwrap().get(header.as_str()).cloned()
+    }
+
+    fn body_json(response: &HttpResponse) -> Value {
+        serde_json::from_str(&response.body).expect("Response body is not json")
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_problem_response() {
+        let mut response = HttpResponse::from_error(&Error::not_found("Session 42"));
+        assert_eq!(response.status, HttpStatus::NotFound);
+        assert_eq!(
+            header(&response, HttpHeaderType::ContentType).as_deref(),
+            Some("application/problem+json")
+        );
+        assert_eq!(
+            body_json(&response),
+            json!({
+                "type": "/problems/not-found",
+                "title": "Resource not found",
+                "status": 404,
+                "detail": "Session 42 not found",
+            })
+        );
+
+        // The request fields are added once the response is routed
+        response.set_request(Some("/sessions/id/42"), "abc-1");
+        let body = body_json(&response);
+        assert_eq!(body["instance"], "/sessions/id/42");
+        assert_eq!(body["request_id"], "abc-1");
+        assert_eq!(header(&response, HttpHeaderType::RequestID).as_deref(), Some("abc-1"));
+
+        let response = HttpResponse::method_not_allowed("GET, OPTIONS");
+        assert_eq!(response.status, HttpStatus::MethodNotAllowed);
+        assert_eq!(body_json(&response)["type"], "/problems/method-not-allowed");
+        assert_eq!(header(&response, HttpHeaderType::Allow).as_deref(), Some("GET, OPTIONS"));
+
+        // Successful responses keep their body
+        let mut response = HttpResponse::from_vec(json!({"ok": true}).to_string());
+        response.set_request(Some("/sensors"), "abc-2");
+        assert_eq!(body_json(&response), json!({"ok": true}));
+    }
+
+    #[test]
+    fn test_request_id() {
+        let request = HttpRequest::from_request_bytes(
+            b"GET /sensors HTTP/1.1\r\nHost: localhost\r\nX-Request-ID: client-id.1\r\n\r\n",
+        );
+        assert_eq!(request.request_id(), "client-id.1");
+
+        // Ids that could corrupt logs or headers are replaced
+        let request = HttpRequest::from_request_bytes(
+            b"GET /sensors HTTP/1.1\r\nHost: localhost\r\nX-Request-ID: bad id\"\r\n\r\n",
+        );
+        let generated = request.request_id();
+        assert_ne!(generated, "bad id\"");
+        assert!(HttpRequest::is_valid_request_id(&generated));
+        assert_ne!(generated, request.request_id());
+    }
+}
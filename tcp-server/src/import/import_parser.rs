@@ -0,0 +1,441 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use serde_json::{json, Map, Value};
+
+use crate::error::Error;
+use crate::export::ExportQuery;
+use crate::http::{HttpHeaderType, HttpRequest};
+
+type Result<T> = crate::Result<T>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    // a header row naming the columns, then a row per datapoint
+    Csv,
+    // a json object per line
+    Ndjson,
+}
+
+impl ImportFormat {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ImportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ImportFormat::Ndjson),
+            name => Err(Error::Validation(format!("Unknown import format '{name}', expected one of [csv, ndjson]"))),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension() {
+            Some(extension) => Self::from_name(&extension.to_string_lossy()),
+            None => Err(Error::Validation(format!(
+                "Cannot tell the import format of {path:?}, expected a .csv or .ndjson file"
+            ))),
+        }
+    }
+
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase().as_str() {
+            "text/csv" => Some(ImportFormat::Csv),
+            "application/x-ndjson" | "application/ndjson" | "application/jsonl" => Some(ImportFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+//Unit of datetimes written as a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EpochUnit {
+    Seconds,
+    Milliseconds,
+}
+
+//How the lines of an import file are read, from the query string or the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportOptions {
+    pub format: ImportFormat,
+    // timezone of datetimes written without an offset
+    pub timezone: Tz,
+    pub epoch: EpochUnit,
+    pub delimiter: u8,
+    // sensor of lines without a sensor_id, the session's only sensor when unset
+    pub sensor: Option<i64>,
+    // sensor ids of the labels a file uses in its sensor_id column
+    pub sensors: HashMap<String, i64>,
+    // every line is checked but nothing is written
+    pub dry_run: bool,
+}
+
+impl ImportOptions {
+    // columns and fields that describe the datapoint instead of being part of its blob
+    pub const DATETIME_KEY: &'static str = "datetime";
+    pub const SENSOR_KEY: &'static str = "sensor_id";
+    pub const IGNORED_KEYS: [&'static str; 1] = ["sensor_type"];
+
+    pub fn new(format: ImportFormat) -> Self {
+        ImportOptions {
+            format,
+            timezone: Tz::UTC,
+            epoch: EpochUnit::Seconds,
+            delimiter: b',',
+            sensor: None,
+            sensors: HashMap::new(),
+            dry_run: false,
+        }
+    }
+
+    //The format is read from `format`, then the content type of the body, and defaults to csv
+    pub fn from_request(request: &HttpRequest) -> Result<Self> {
+        let format = match request.parameter("format") {
+            Some(format) => ImportFormat::from_name(format)?,
+            None => request
+                .headers
+                .get(HttpHeaderType::ContentType.as_str())
+                .and_then(|content_type| ImportFormat::from_content_type(content_type))
+                .unwrap_or(ImportFormat::Csv),
+        };
+        let mut options = ImportOptions::new(format);
+        if let Some(timezone) = request.parameter("timezone") {
+            options.timezone = timezone.parse::<Tz>().map_err(|_| {
+                Error::Validation(format!("Unknown timezone '{timezone}', expected a name such as UTC or Europe/Berlin"))
+            })?;
+        }
+        if let Some(epoch) = request.parameter("epoch") {
+            options.epoch = match epoch {
+                "s" => EpochUnit::Seconds,
+                "ms" => EpochUnit::Milliseconds,
+                epoch => return Err(Error::Validation(format!("Unknown epoch '{epoch}', expected one of [s, ms]"))),
+            };
+        }
+        if let Some(delimiter) = request.parameter("delimiter") {
+            options.delimiter = match delimiter {
+                "tab" | "\t" => b'\t',
+                delimiter if delimiter.len() == 1 && delimiter != "\"" => delimiter.as_bytes()[0],
+                delimiter => {
+                    return Err(Error::Validation(format!(
+                        "Invalid delimiter '{delimiter}', expected a single character other than '\"' or \"tab\""
+                    )))
+                }
+            };
+        }
+        if let Some(sensor) = request.parameter("sensor") {
+            options.sensor = Some(
+                sensor
+                    .parse::<i64>()
+                    .map_err(|e| Error::Validation(format!("Invalid sensor id '{sensor}' in 'sensor': {e}")))?,
+            );
+        }
+        if let Some(sensors) = request.parameter("sensors") {
+            options.sensors = Self::parse_sensors(sensors)?;
+        }
+        if let Some(dry_run) = request.parameter("dry_run") {
+            options.dry_run = dry_run
+                .parse::<bool>()
+                .map_err(|_| Error::Validation(format!("Invalid dry_run '{dry_run}', expected true or false")))?;
+        }
+        Ok(options)
+    }
+
+    //Parses a comma separated list of label:sensor_id pairs
+    pub fn parse_sensors(sensors: &str) -> Result<HashMap<String, i64>> {
+        sensors
+            .split(',')
+            .map(|pair| match pair.split_once(':') {
+                Some((label, sensor_id)) => match sensor_id.trim().parse::<i64>() {
+                    Ok(sensor_id) => Ok((label.trim().to_string(), sensor_id)),
+                    Err(e) => Err(Error::Validation(format!("Invalid sensor id '{sensor_id}' in 'sensors': {e}"))),
+                },
+                None => Err(Error::Validation(format!(
+                    "Invalid sensor mapping '{pair}' in 'sensors', expected label:sensor_id"
+                ))),
+            })
+            .collect()
+    }
+
+    //Reads the lines of a file, the csv header is read and checked before the first line is returned
+    pub fn lines<R: Read>(&self, reader: R) -> Result<ImportLines<'_, R>> {
+        let source = match self.format {
+            ImportFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .delimiter(self.delimiter)
+                    .flexible(true)
+                    .from_reader(reader);
+                let headers: Vec<String> = reader
+                    .headers()
+                    .map_err(|e| Error::Validation(format!("Failed to read the csv header: {e}")))?
+                    .iter()
+                    .map(|header| header.trim().to_string())
+                    .collect();
+                if !headers.iter().any(|header| header == Self::DATETIME_KEY) {
+                    return Err(Error::Validation(format!(
+                        "The csv header has no '{}' column",
+                        Self::DATETIME_KEY
+                    )));
+                }
+                Source::Csv { reader, headers }
+            }
+            ImportFormat::Ndjson => Source::Ndjson {
+                lines: BufReader::new(reader).lines(),
+                line: 0,
+            },
+        };
+        Ok(ImportLines {
+            options: self,
+            source,
+            done: false,
+        })
+    }
+
+    //Datetimes are stored in UTC like the rest of the datapoints, with the fraction of a second kept
+    //Accepts RFC 3339, YYYY-MM-DDTHH:MM:SS[.f] or YYYY-MM-DD HH:MM:SS[.f] in the timezone option, or a Unix timestamp
+    pub fn normalize_datetime(&self, value: &Value) -> std::result::Result<String, String> {
+        let epoch = match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(text) => text.trim().parse::<f64>().ok(),
+            value => return Err(format!("Invalid datetime {value}, expected a string or a number")),
+        };
+        let datetime = match (epoch, value) {
+            (Some(epoch), _) => {
+                let micros = match self.epoch {
+                    EpochUnit::Seconds => epoch * 1e6,
+                    EpochUnit::Milliseconds => epoch * 1e3,
+                };
+                DateTime::from_timestamp_micros(micros.round() as i64)
+                    .map(|datetime| datetime.naive_utc())
+                    .ok_or_else(|| format!("Unix timestamp {epoch} is out of range"))?
+            }
+            (None, Value::String(text)) => self.parse_datetime(text.trim())?,
+            (None, value) => return Err(format!("Invalid datetime {value}")),
+        };
+        Ok(datetime.format(ExportQuery::DATETIME_FORMAT).to_string())
+    }
+
+    fn parse_datetime(&self, text: &str) -> std::result::Result<NaiveDateTime, String> {
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+            return Ok(datetime.naive_utc());
+        }
+        let local = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            .ok_or_else(|| format!("Invalid datetime '{text}', expected RFC 3339, YYYY-MM-DDTHH:MM:SS or a Unix timestamp"))?;
+        // a time skipped by a daylight saving change does not exist, one repeated by it is taken the first time
+        self.timezone
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|datetime| datetime.naive_utc())
+            .ok_or_else(|| format!("Datetime '{text}' does not exist in {}", self.timezone))
+    }
+
+    fn csv_line(&self, headers: &[String], record: &csv::StringRecord, line: u64) -> std::result::Result<ImportRecord, String> {
+        if record.len() != headers.len() {
+            return Err(format!("Expected {} fields like the header, found {}", headers.len(), record.len()));
+        }
+        let mut datetime = None;
+        let mut sensor = None;
+        let mut blob = Map::new();
+        for (header, field) in headers.iter().zip(record.iter()) {
+            match header.as_str() {
+                Self::DATETIME_KEY => datetime = Some(Value::from(field)),
+                Self::SENSOR_KEY => sensor = Some(field.trim()).filter(|sensor| !sensor.is_empty()).map(String::from),
+                header if Self::IGNORED_KEYS.contains(&header) => {}
+                // columns left empty are missing from that datapoint, as in the csv export
+                _ if field.is_empty() => {}
+                header => insert_dotted(&mut blob, header, csv_value(field))?,
+            }
+        }
+        let datetime = datetime.ok_or_else(|| format!("Missing '{}'", Self::DATETIME_KEY))?;
+        self.record(line, &datetime, sensor, arrays_from_indexes(Value::Object(blob)))
+    }
+
+    fn ndjson_line(&self, text: &str, line: u64) -> std::result::Result<ImportRecord, String> {
+        let mut fields = match serde_json::from_str::<Value>(text) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(_) => return Err(String::from("Expected a json object")),
+            Err(e) => return Err(format!("Invalid json: {e}")),
+        };
+        let datetime = fields
+            .remove(Self::DATETIME_KEY)
+            .ok_or_else(|| format!("Missing '{}'", Self::DATETIME_KEY))?;
+        let sensor = match fields.remove(Self::SENSOR_KEY) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(sensor)) => Some(sensor),
+            Some(Value::Number(sensor)) => Some(sensor.to_string()),
+            Some(sensor) => return Err(format!("Invalid '{}' {sensor}", Self::SENSOR_KEY)),
+        };
+        Self::IGNORED_KEYS.iter().for_each(|key| {
+            fields.remove(*key);
+        });
+        // a line is either a datapoint with its blob under data_blob, or the blob's fields themselves
+        let blob = match fields.remove("data_blob") {
+            Some(blob) if fields.is_empty() => blob,
+            Some(_) => return Err(String::from("Expected either 'data_blob' or the blob's fields, not both")),
+            None => Value::Object(fields),
+        };
+        self.record(line, &datetime, sensor, blob)
+    }
+
+    fn record(&self, line: u64, datetime: &Value, sensor: Option<String>, blob: Value) -> std::result::Result<ImportRecord, String> {
+        match &blob {
+            Value::Object(fields) if !fields.is_empty() => Ok(ImportRecord {
+                line,
+                datetime: self.normalize_datetime(datetime)?,
+                sensor,
+                blob,
+            }),
+            Value::Object(_) => Err(String::from("The datapoint has no data")),
+            _ => Err(String::from("Expected 'data_blob' to be a json object")),
+        }
+    }
+}
+
+//A line of an import file, its sensor is resolved against the session when it is imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRecord {
+    pub line: u64,
+    // normalized to UTC
+    pub datetime: String,
+    // sensor_id as written in the file, a sensor id or a label of the sensor mapping
+    pub sensor: Option<String>,
+    pub blob: Value,
+}
+
+//A line that could not be imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+    pub line: u64,
+    pub message: String,
+}
+
+impl ImportError {
+    pub fn to_json(&self) -> Value {
+        json!({ "line": self.line, "error": self.message })
+    }
+}
+
+enum Source<R: Read> {
+    Csv {
+        reader: csv::Reader<R>,
+        headers: Vec<String>,
+    },
+    Ndjson {
+        lines: Lines<BufReader<R>>,
+        line: u64,
+    },
+}
+
+//Reads an import file a line at a time, blank lines are skipped
+pub struct ImportLines<'a, R: Read> {
+    options: &'a ImportOptions,
+    source: Source<R>,
+    done: bool,
+}
+
+impl<R: Read> Iterator for ImportLines<'_, R> {
+    type Item = std::result::Result<ImportRecord, ImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match &mut self.source {
+            Source::Csv { reader, headers } => {
+                let mut record = csv::StringRecord::new();
+                match reader.read_record(&mut record) {
+                    Ok(true) => {
+                        let line = record.position().map(|position| position.line()).unwrap_or_default();
+                        Some(
+                            self.options
+                                .csv_line(headers, &record, line)
+                                .map_err(|message| ImportError { line, message }),
+                        )
+                    }
+                    Ok(false) => None,
+                    Err(e) => {
+                        let line = e.position().map(|position| position.line()).unwrap_or_default();
+                        // the reader cannot continue past a failed read
+                        self.done = matches!(e.kind(), csv::ErrorKind::Io(_));
+                        Some(Err(ImportError { line, message: format!("Failed to read csv: {e}") }))
+                    }
+                }
+            }
+            Source::Ndjson { lines, line } => loop {
+                *line += 1;
+                match lines.next()? {
+                    Ok(text) if text.trim().is_empty() => continue,
+                    Ok(text) => {
+                        break Some(
+                            self.options
+                                .ndjson_line(&text, *line)
+                                .map_err(|message| ImportError { line: *line, message }),
+                        )
+                    }
+                    Err(e) => {
+                        self.done = true;
+                        break Some(Err(ImportError { line: *line, message: format!("Failed to read line: {e}") }));
+                    }
+                }
+            },
+        }
+    }
+}
+
+// Values read from csv are typed the way the csv export writes them
+fn csv_value(field: &str) -> Value {
+    if let Ok(boolean) = field.parse::<bool>() {
+        return Value::Bool(boolean);
+    }
+    if let Ok(integer) = field.parse::<i64>() {
+        return Value::from(integer);
+    }
+    match field.parse::<f64>() {
+        Ok(float) if float.is_finite() => Value::from(float),
+        _ => Value::from(field),
+    }
+}
+
+// The dotted column accel.x is stored as {"accel": {"x": ...}}
+fn insert_dotted(blob: &mut Map<String, Value>, column: &str, value: Value) -> std::result::Result<(), String> {
+    let conflict = || format!("Column '{column}' conflicts with another column of the same name");
+    match column.split_once('.') {
+        None => match blob.insert(column.to_string(), value) {
+            None => Ok(()),
+            Some(_) => Err(conflict()),
+        },
+        Some((name, rest)) => match blob.entry(name).or_insert_with(|| Value::Object(Map::new())) {
+            Value::Object(nested) => insert_dotted(nested, rest, value).map_err(|_| conflict()),
+            _ => Err(conflict()),
+        },
+    }
+}
+
+// Objects keyed 0 to n, which is how the csv export writes arrays, are turned back into arrays
+fn arrays_from_indexes(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let is_array = !fields.is_empty()
+                && (0..fields.len()).all(|index| fields.contains_key(&index.to_string()));
+            let mut fields: Vec<(String, Value)> =
+                fields.into_iter().map(|(key, value)| (key, arrays_from_indexes(value))).collect();
+            match is_array {
+                true => {
+                    fields.sort_by_key(|(key, _)| key.parse::<usize>().unwrap_or_default());
+                    Value::Array(fields.into_iter().map(|(_, value)| value).collect())
+                }
+                false => Value::Object(fields.into_iter().collect()),
+            }
+        }
+        value => value,
+    }
+}
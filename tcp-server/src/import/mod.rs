@@ -0,0 +1,8 @@
+pub mod import_parser;
+pub mod session_import;
+
+pub use self::import_parser::{ImportFormat, ImportOptions};
+pub use self::session_import::SessionImport;
+
+#[cfg(test)]
+mod test_import;
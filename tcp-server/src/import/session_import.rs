@@ -0,0 +1,204 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+use std::io::Read;
+
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpRequest, HttpResponse, HttpStatus};
+use crate::models::SessionSensorData;
+use crate::validation::SchemaRegistry;
+
+use super::import_parser::{ImportError, ImportOptions, ImportRecord};
+
+type Result<T> = crate::Result<T>;
+
+//Outcome of an import, every line read is either imported, a duplicate, quarantined, or failed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    // lines holding a datapoint, the csv header and blank lines are not counted
+    pub lines: usize,
+    pub imported: usize,
+    // datapoints already stored, or repeated earlier in the file
+    pub duplicates: usize,
+    // datapoints set aside because the schema of their sensor type quarantines blobs not matching it
+    pub quarantined: usize,
+    pub failed: usize,
+    // the first MAX_ERRORS failed lines
+    pub errors: Vec<ImportError>,
+}
+
+impl ImportReport {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "lines": self.lines,
+            "imported": self.imported,
+            "duplicates": self.duplicates,
+            "quarantined": self.quarantined,
+            "failed": self.failed,
+            "errors": self.errors.iter().map(ImportError::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    fn fail(&mut self, error: ImportError) {
+        self.failed += 1;
+        if self.errors.len() < SessionImport::MAX_ERRORS {
+            self.errors.push(error);
+        }
+    }
+}
+
+//Backfills a session from a csv or ndjson file, such as one recorded offline to an SD card
+//Lines that fail, or whose blobs are rejected by the schema of their sensor type, are reported and skipped
+//The rest are written a batch per transaction without the ones already stored
+pub struct SessionImport<'a> {
+    database: &'a dyn Database,
+    session_id: i64,
+    options: ImportOptions,
+    // session sensor id of every sensor attached to the session
+    session_sensors: HashMap<i64, i64>,
+    // loaded once, so the schemas are those of when the import started
+    schemas: SchemaRegistry,
+}
+
+impl<'a> SessionImport<'a> {
+    pub const BATCH_SIZE: usize = 500;
+    pub const MAX_ERRORS: usize = 1_000;
+
+    //The session and the sensors named by the options are checked before anything is read
+    pub fn new(database: &'a dyn Database, session_id: i64, options: ImportOptions) -> Result<Self> {
+        database.get_session(session_id)?;
+        let session_sensors: HashMap<i64, i64> = database
+            .get_session_sensors(session_id)?
+            .iter()
+            .map(|session_sensor| (*session_sensor.get_sensor_id(), *session_sensor.get_id()))
+            .collect();
+        if let Some(sensor_id) = options
+            .sensor
+            .iter()
+            .chain(options.sensors.values())
+            .find(|sensor_id| !session_sensors.contains_key(sensor_id))
+        {
+            return Err(Error::NotFound(format!("Sensor {sensor_id} in session {session_id} not found")));
+        }
+        Ok(SessionImport {
+            database,
+            session_id,
+            options,
+            session_sensors,
+            schemas: SchemaRegistry::new(),
+        })
+    }
+
+    //Reads every line of the file, progress is called with the report so far after each batch is written
+    pub fn run(&self, reader: impl Read, mut progress: impl FnMut(&ImportReport)) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut batch = Vec::with_capacity(Self::BATCH_SIZE);
+        for line in self.options.lines(reader)? {
+            report.lines += 1;
+            match line.and_then(|record| Ok((record.line, self.datapoint(record)?))) {
+                Ok(datapoint) => batch.push(datapoint),
+                Err(error) => report.fail(error),
+            }
+            if batch.len() == Self::BATCH_SIZE {
+                self.write(&mut batch, &mut report)?;
+                progress(&report);
+            }
+        }
+        if !batch.is_empty() {
+            self.write(&mut batch, &mut report)?;
+            progress(&report);
+        }
+        Ok(report)
+    }
+
+    //Imports the csv or ndjson body of a request, answering with the report
+    pub fn response(database: &dyn Database, session_id: i64, request: &HttpRequest) -> HttpResponse {
+        let body = match &request.body {
+            Some(Value::String(body)) => body,
+            _ => return HttpResponse::missing_body(Some(" Requires the csv or ndjson file to import")),
+        };
+        let import = match ImportOptions::from_request(request)
+            .and_then(|options| SessionImport::new(database, session_id, options))
+        {
+            Ok(import) => import,
+            Err(e) => return HttpResponse::from_error(&e),
+        };
+        match import.run(body.as_bytes(), |_| {}) {
+            Ok(report) => HttpResponse::new(HttpStatus::OK, HttpHeader::default_json(), report.to_json().to_string()),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+
+    fn datapoint(&self, record: ImportRecord) -> std::result::Result<SessionSensorData, ImportError> {
+        let error = |message: String| ImportError {
+            line: record.line,
+            message,
+        };
+        let sensor_id = match &record.sensor {
+            Some(sensor) => match self.options.sensors.get(sensor) {
+                Some(sensor_id) => *sensor_id,
+                None => sensor.parse::<i64>().map_err(|_| {
+                    error(format!("Unknown sensor '{sensor}', expected a sensor id or a label of 'sensors'"))
+                })?,
+            },
+            None => match (self.options.sensor, self.session_sensors.keys().collect::<Vec<_>>().as_slice()) {
+                (Some(sensor_id), _) => sensor_id,
+                (None, [sensor_id]) => **sensor_id,
+                (None, []) => return Err(error(format!("Session {} has no sensors attached", self.session_id))),
+                (None, _) => {
+                    return Err(error(format!(
+                        "Session {} has several sensors, the line needs a '{}' or the import a 'sensor'",
+                        self.session_id,
+                        ImportOptions::SENSOR_KEY
+                    )))
+                }
+            },
+        };
+        let session_sensor_id = self
+            .session_sensors
+            .get(&sensor_id)
+            .ok_or_else(|| error(format!("Sensor {sensor_id} is not attached to session {}", self.session_id)))?;
+        Ok(SessionSensorData::new(
+            Some(self.session_id),
+            Some(*session_sensor_id),
+            record.datetime,
+            record.blob,
+        ))
+    }
+
+    // Batches hold the line of every datapoint, so the ones rejected by a schema can be reported
+    fn write(&self, batch: &mut Vec<(u64, SessionSensorData)>, report: &mut ImportReport) -> Result<()> {
+        let (lines, datapoints): (Vec<u64>, Vec<SessionSensorData>) = batch.drain(..).unzip();
+        let check = self.schemas.check(self.database, datapoints)?;
+        for violation in check.rejected() {
+            report.fail(ImportError {
+                line: lines[violation.index],
+                message: format!(
+                    "Blob does not match the schema of sensor type '{}': {}",
+                    violation.sensor_type,
+                    violation.errors.join("; ")
+                ),
+            });
+        }
+        let (imported, quarantined) = match self.options.dry_run {
+            true => (check.accepted.len(), check.quarantined().count()),
+            false => (
+                self.database.import_session_sensor_data(&check.accepted)?.len(),
+                check.quarantine(self.database)?,
+            ),
+        };
+        report.imported += imported;
+        report.duplicates += check.accepted.len() - imported;
+        report.quarantined += quarantined;
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::error::Error;
+    use crate::export::csv_export::{CsvExport, CsvOptions};
+    use crate::export::ExportQuery;
+    use crate::import::import_parser::{EpochUnit, ImportFormat, ImportOptions};
+    use crate::import::session_import::{ImportReport, SessionImport};
+    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+
+    /* Helpers */
+
+    // Sessions 1 and 2 both record an accelerometer (sensor 1) and a gps (sensor 2)
+    fn init_database() -> MemoryDatabase {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string())).unwrap();
+        for sensor_type in ["Acceleration", "GPS"] {
+            db.insert_sensor(&Sensor::new(0, sensor_type.to_string())).unwrap();
+        }
+        for session_id in [1, 2] {
+            db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+            for sensor_id in [1, 2] {
+                db.insert_session_sensor(&SessionSensor::new(0, session_id, sensor_id)).unwrap();
+            }
+        }
+        db
+    }
+
+    fn import(db: &MemoryDatabase, session_id: i64, options: ImportOptions, file: &str) -> crate::Result<ImportReport> {
+        SessionImport::new(db, session_id, options)?.run(file.as_bytes(), |_| {})
+    }
+
+    // Datetime and blob of every datapoint of a session, oldest first
+    fn stored(db: &MemoryDatabase, session_id: i64) -> Vec<(String, Value)> {
+        let mut datapoints: Vec<(String, Value)> = db
+            .get_sessions_sensor_data(session_id)
+            .unwrap()
+            .iter()
+            .map(|data| (data.get_datetime().to_string(), data.get_blob().clone()))
+            .collect();
+        datapoints.sort_by(|left, right| left.0.cmp(&right.0));
+        datapoints
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_normalize_datetime() {
+        let mut options = ImportOptions::new(ImportFormat::Csv);
+        let normalize = |options: &ImportOptions, value: Value| options.normalize_datetime(&value);
+
+        assert_eq!(normalize(&options, json!("2025-01-01T12:00:00")).unwrap(), "2025-01-01T12:00:00");
+        assert_eq!(normalize(&options, json!("2025-01-01 12:00:00.250")).unwrap(), "2025-01-01T12:00:00.250");
+        assert_eq!(normalize(&options, json!("2025-01-01T13:00:00+01:00")).unwrap(), "2025-01-01T12:00:00");
+        assert_eq!(normalize(&options, json!(1735732800)).unwrap(), "2025-01-01T12:00:00");
+        assert_eq!(normalize(&options, json!("1735732800.5")).unwrap(), "2025-01-01T12:00:00.500");
+
+        options.epoch = EpochUnit::Milliseconds;
+        assert_eq!(normalize(&options, json!(1735732800123_i64)).unwrap(), "2025-01-01T12:00:00.123");
+
+        // Datetimes without an offset are read in the timezone of the import
+        options.timezone = "America/Los_Angeles".parse().unwrap();
+        assert_eq!(normalize(&options, json!("2025-01-01T04:00:00")).unwrap(), "2025-01-01T12:00:00");
+        assert_eq!(normalize(&options, json!("2025-01-01T13:00:00Z")).unwrap(), "2025-01-01T13:00:00");
+        assert!(normalize(&options, json!("2025-03-09T02:30:00")).is_err());
+        assert!(normalize(&options, json!("yesterday")).is_err());
+        assert!(normalize(&options, json!(true)).is_err());
+    }
+
+    #[test]
+    fn test_import_csv() {
+        let db = init_database();
+        db.batch_session_sensor_data(&[SessionSensorData::new(
+            Some(1),
+            Some(1),
+            String::from("2025-01-01T12:00:00"),
+            json!({ "x": 0 }),
+        )])
+        .unwrap();
+
+        let mut options = ImportOptions::new(ImportFormat::Csv);
+        options.sensors = ImportOptions::parse_sensors("imu:1,gps:2").unwrap();
+        let file = "\
+datetime,sensor_id,x,accel.y.0,accel.y.1,label
+2025-01-01T12:00:00,imu,1,,,
+2025-01-01T12:00:01,imu,2,3,4.5,\"walk, fast\"
+2025-01-01T12:00:01,imu,5,,,
+2025-01-01T12:00:02,gps,,,,true
+2025-01-01T12:00:03,3,6,,,
+2025-01-01T12:00:04,imu,7,,
+not a datetime,imu,8,,,
+2025-01-01T12:00:05,,9,,,
+";
+        let report = import(&db, 1, options, file).unwrap();
+        assert_eq!((report.lines, report.imported, report.duplicates, report.failed), (8, 2, 2, 4));
+        let failed: Vec<u64> = report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(failed, vec![6, 7, 8, 9]);
+
+        // The stored datapoint is kept, and the dotted columns are nested again
+        assert_eq!(
+            stored(&db, 1),
+            vec![
+                (String::from("2025-01-01T12:00:00"), json!({ "x": 0 })),
+                (String::from("2025-01-01T12:00:01"), json!({ "x": 2, "accel": { "y": [3, 4.5] }, "label": "walk, fast" })),
+                (String::from("2025-01-01T12:00:02"), json!({ "label": true })),
+            ]
+        );
+
+        // Sensors of the mapping are checked before anything is read, and so is the header
+        let mut options = ImportOptions::new(ImportFormat::Csv);
+        options.sensor = Some(3);
+        assert!(matches!(import(&db, 1, options, file), Err(Error::NotFound(_))));
+        let options = ImportOptions::new(ImportFormat::Csv);
+        assert!(matches!(import(&db, 1, options, "time,x\n1,2\n"), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn test_import_ndjson() {
+        let db = init_database();
+        let mut options = ImportOptions::new(ImportFormat::Ndjson);
+        options.dry_run = true;
+        let file = r#"{"datetime": "2025-01-01T12:00:00", "sensor_id": 1, "data_blob": {"x": 1}}
+
+{"datetime": "2025-01-01T12:00:01", "sensor_id": "2", "sensor_type": "GPS", "lat": 44.56}
+{"datetime": "2025-01-01T12:00:02", "x": 2}
+{"datetime": "2025-01-01T12:00:03", "sensor_id": 1}
+["2025-01-01T12:00:04", 1]
+{"datetime": "2025-01-01T12:00:05", "sensor_id": 1, "data_blob": {"x": 3}, "y": 4}
+"#;
+        let report = import(&db, 1, options.clone(), file).unwrap();
+        assert_eq!((report.lines, report.imported, report.failed), (6, 2, 4));
+        assert!(report.errors[0].message.contains("several sensors"));
+        let failed: Vec<u64> = report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(failed, vec![4, 5, 6, 7]);
+        // A dry run writes nothing
+        assert!(stored(&db, 1).is_empty());
+
+        options.dry_run = false;
+        options.sensor = Some(1);
+        let report = import(&db, 1, options, file).unwrap();
+        assert_eq!((report.imported, report.failed), (3, 3));
+        assert_eq!(stored(&db, 1)[1], (String::from("2025-01-01T12:00:01"), json!({ "lat": 44.56 })));
+    }
+
+    #[test]
+    fn test_import_exported_csv() {
+        let db = init_database();
+        let datapoints: Vec<SessionSensorData> = (0..3)
+            .map(|second| {
+                SessionSensorData::new(
+                    Some(1),
+                    Some(1 + second % 2),
+                    format!("2025-01-01T12:00:0{second}.125"),
+                    json!({ "accel": { "x": second, "y": [0.5, -1] }, "label": "walk" }),
+                )
+            })
+            .collect();
+        db.batch_session_sensor_data(&datapoints).unwrap();
+
+        // A session exported as csv imports into another session as the same datapoints
+        let query = ExportQuery::new(1, Vec::new(), None, None).unwrap();
+        let mut csv = Vec::new();
+        CsvExport::new(CsvOptions::default(), &db, &query).unwrap().write(&db, &query, &mut csv).unwrap();
+        let report = import(&db, 2, ImportOptions::new(ImportFormat::Csv), &String::from_utf8(csv).unwrap()).unwrap();
+        assert_eq!((report.imported, report.failed), (3, 0));
+        assert_eq!(stored(&db, 2), stored(&db, 1));
+    }
+
+    #[test]
+    fn test_csv_errors() {
+        // Malformed csv is the client's fault, failing to read or write it is not
+        let mut reader = csv::ReaderBuilder::new().from_reader("datetime,x\n2025-01-01T12:00:00\n".as_bytes());
+        let malformed = reader.records().next().unwrap().unwrap_err();
+        assert!(matches!(Error::from(malformed), Error::Validation(message) if message.starts_with("Invalid csv: ")));
+
+        let closed = csv::Error::from(std::io::Error::other("connection closed"));
+        assert!(matches!(Error::from(closed), Error::Io(message) if message.starts_with("Csv I/O failed: ")));
+    }
+}
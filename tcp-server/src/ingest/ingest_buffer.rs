@@ -0,0 +1,287 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::alerts::AlertEngine;
+use crate::data::Database;
+use crate::error::Error;
+use crate::live::LiveHub;
+use crate::models::SessionSensorData;
+
+type Result<T> = crate::Result<T>;
+
+//Settings of the ingestion buffer
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    // queued datapoints that trigger a flush
+    pub batch_size: usize,
+    // longest time a datapoint stays queued before it is flushed
+    pub flush_interval: Duration,
+    // most datapoints queued at once, further writes wait for room
+    pub capacity: usize,
+    // how long a write waits for room before it is refused
+    pub enqueue_timeout: Duration,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        IngestConfig {
+            batch_size: 500,
+            flush_interval: Duration::from_millis(50),
+            capacity: 10_000,
+            enqueue_timeout: Duration::from_millis(5_000),
+        }
+    }
+}
+
+//Outcome of the flush a write went out in, for writers waiting on a durable commit
+struct Commit {
+    result: Mutex<Option<Result<()>>>,
+    done: Condvar,
+}
+
+impl Commit {
+    fn new() -> Self {
+        Commit {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        }
+    }
+
+    fn wait(&self) -> Result<()> {
+        let mut result = self.result.lock().map_err(|_| Self::poisoned())?;
+        loop {
+            match result.as_ref() {
+                Some(result) => return result.clone(),
+                None => result = self.done.wait(result).map_err(|_| Self::poisoned())?,
+            }
+        }
+    }
+
+    fn complete(&self, outcome: Result<()>) {
+        if let Ok(mut result) = self.result.lock() {
+            *result = Some(outcome);
+            self.done.notify_all();
+        }
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Ingestion commit was poisoned by a panic"))
+    }
+}
+
+//The datapoints of a single request, written together or not at all
+struct PendingWrite {
+    datapoints: Vec<SessionSensorData>,
+    commit: Option<Arc<Commit>>,
+}
+
+impl PendingWrite {
+    fn complete(&self, outcome: Result<()>) {
+        if let Some(commit) = &self.commit {
+            commit.complete(outcome);
+        }
+    }
+}
+
+#[derive(Default)]
+struct IngestQueue {
+    writes: Vec<PendingWrite>,
+    // datapoints across every queued write
+    len: usize,
+    oldest: Option<Instant>,
+    shutdown: bool,
+}
+
+//Write-behind buffer that coalesces datapoints from many requests into one transaction per flush
+//A flush happens once a batch worth of datapoints is queued or the oldest has waited the flush interval
+pub struct IngestBuffer {
+    queue: Mutex<IngestQueue>,
+    // woken when a write is queued or the buffer shuts down
+    queued: Condvar,
+    // woken when a flush takes the queued writes and makes room
+    drained: Condvar,
+    config: IngestConfig,
+    flusher: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl IngestBuffer {
+    //Starts the thread that flushes the buffer into the database
+    //Written datapoints are published to live subscribers and evaluated against the alert rules
+    pub fn start(
+        database: Arc<dyn Database>,
+        hub: Arc<LiveHub>,
+        alerts: Arc<AlertEngine>,
+        config: IngestConfig,
+    ) -> Arc<Self> {
+        let buffer = Arc::new(IngestBuffer {
+            queue: Mutex::new(IngestQueue::default()),
+            queued: Condvar::new(),
+            drained: Condvar::new(),
+            config: IngestConfig {
+                batch_size: config.batch_size.max(1),
+                capacity: config.capacity.max(1),
+                ..config
+            },
+            flusher: Mutex::new(None),
+        });
+
+        let flusher = Arc::clone(&buffer);
+        let handle = std::thread::spawn(move || flusher.run(database.as_ref(), &hub, &alerts));
+        if let Ok(mut slot) = buffer.flusher.lock() {
+            *slot = Some(handle);
+        }
+        buffer
+    }
+
+    //Queues the datapoints of one request, waiting for room while the buffer is full
+    //When wait_for_commit is set this returns once the datapoints are durably written, with the write's own result
+    pub fn enqueue(&self, datapoints: Vec<SessionSensorData>, wait_for_commit: bool) -> Result<()> {
+        if datapoints.is_empty() {
+            return Ok(());
+        }
+
+        let deadline = Instant::now() + self.config.enqueue_timeout;
+        let mut queue = self.lock()?;
+        // a write larger than the whole buffer is let in once the buffer is empty
+        while !queue.shutdown && queue.len > 0 && queue.len + datapoints.len() > self.config.capacity {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Unavailable(format!(
+                    "Ingestion buffer is full with {} queued datapoints, retry later",
+                    queue.len
+                )));
+            }
+            queue = self.drained.wait_timeout(queue, remaining).map_err(|_| Self::poisoned())?.0;
+        }
+        if queue.shutdown {
+            return Err(Error::Unavailable(String::from("Ingestion buffer is shutting down")));
+        }
+
+        let commit = wait_for_commit.then(|| Arc::new(Commit::new()));
+        queue.len += datapoints.len();
+        queue.oldest.get_or_insert_with(Instant::now);
+        queue.writes.push(PendingWrite {
+            datapoints,
+            commit: commit.clone(),
+        });
+        self.queued.notify_one();
+        drop(queue);
+
+        match commit {
+            Some(commit) => commit.wait(),
+            None => Ok(()),
+        }
+    }
+
+    //Stops accepting writes and returns once everything queued has been flushed
+    pub fn shutdown(&self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.shutdown = true;
+            self.queued.notify_all();
+            self.drained.notify_all();
+        }
+
+        let handle = self.flusher.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(handle) = handle {
+            if handle.join().is_err() {
+                eprintln!("Ingestion flusher panicked before the buffer was flushed");
+            }
+        }
+    }
+
+    fn run(&self, database: &dyn Database, hub: &LiveHub, alerts: &AlertEngine) {
+        loop {
+            let (writes, shutdown) = match self.next_flush() {
+                Ok(next) => next,
+                Err(e) => {
+                    eprintln!("Ingestion flusher stopped: {e}");
+                    return;
+                }
+            };
+            if !writes.is_empty() {
+                Self::flush(database, hub, alerts, &writes);
+            }
+            if shutdown {
+                return;
+            }
+        }
+    }
+
+    //Waits until a flush is due and takes every queued write
+    fn next_flush(&self) -> Result<(Vec<PendingWrite>, bool)> {
+        let mut queue = self.lock()?;
+        while !queue.shutdown && queue.len < self.config.batch_size {
+            queue = match queue.oldest {
+                Some(oldest) => {
+                    let remaining = (oldest + self.config.flush_interval).saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        break;
+                    }
+                    self.queued.wait_timeout(queue, remaining).map_err(|_| Self::poisoned())?.0
+                }
+                None => self.queued.wait(queue).map_err(|_| Self::poisoned())?,
+            };
+        }
+
+        queue.len = 0;
+        queue.oldest = None;
+        let writes = std::mem::take(&mut queue.writes);
+        self.drained.notify_all();
+        Ok((writes, queue.shutdown))
+    }
+
+    //Writes every write in one transaction, when that fails each write is retried alone so a bad one only fails itself
+    fn flush(database: &dyn Database, hub: &LiveHub, alerts: &AlertEngine, writes: &[PendingWrite]) {
+        let datapoints: Vec<SessionSensorData> = writes
+            .iter()
+            .flat_map(|write| write.datapoints.iter().cloned())
+            .collect();
+        match database.batch_session_sensor_data(&datapoints) {
+            Ok(inserted) => {
+                hub.publish_all(&inserted);
+                alerts.evaluate(database, &inserted);
+                writes.iter().for_each(|write| write.complete(Ok(())));
+            }
+            Err(_) if writes.len() > 1 => {
+                for write in writes {
+                    write.complete(Self::write(database, hub, alerts, write));
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to write {} queued datapoints: {e}", datapoints.len());
+                writes.iter().for_each(|write| write.complete(Err(e.clone())));
+            }
+        }
+    }
+
+    fn write(database: &dyn Database, hub: &LiveHub, alerts: &AlertEngine, write: &PendingWrite) -> Result<()> {
+        match database.batch_session_sensor_data(&write.datapoints) {
+            Ok(inserted) => {
+                hub.publish_all(&inserted);
+                alerts.evaluate(database, &inserted);
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Failed to write {} queued datapoints: {e}", write.datapoints.len());
+                Err(e)
+            }
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, IngestQueue>> {
+        self.queue.lock().map_err(|_| Self::poisoned())
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Ingestion buffer was poisoned by a panic"))
+    }
+}
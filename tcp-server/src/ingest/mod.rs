@@ -0,0 +1,6 @@
+pub mod ingest_buffer;
+
+pub use self::ingest_buffer::{IngestBuffer, IngestConfig};
+
+#[cfg(test)]
+mod test_ingest;
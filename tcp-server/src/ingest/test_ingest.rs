@@ -0,0 +1,189 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#![cfg(feature = "sql")]
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    use crate::alerts::AlertEngine;
+    use crate::data::{Database, SqliteDatabase};
+    use crate::error::Error;
+    use crate::http::HttpStatus;
+    use crate::ingest::{IngestBuffer, IngestConfig};
+    use crate::live::LiveHub;
+    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+
+    /* Helpers */
+
+    const SESSION_SENSOR_ID: i64 = 1;
+
+    // In memory database with one session recording from one sensor
+    fn init_database() -> Arc<SqliteDatabase> {
+        let conn = Connection::open_in_memory().expect("Failed to open connection in memory");
+        let db = SqliteDatabase::from_connection(conn).expect("Failed to create test db");
+        db.migrate().expect("Failed to initialize schema");
+        Arc::new(db)
+    }
+
+    fn add_test_recording(db: &SqliteDatabase) {
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .expect("Failed to insert test user");
+        db.insert_session(&Session::new(1, "user1".to_string()))
+            .expect("Failed to insert test session");
+        db.insert_sensor(&Sensor::new(1, "Acceleration".to_string()))
+            .expect("Failed to insert test sensor");
+        db.insert_session_sensor(&SessionSensor::new(SESSION_SENSOR_ID, 1, 1))
+            .expect("Failed to insert test session sensor");
+    }
+
+    fn start(db: &Arc<SqliteDatabase>, config: IngestConfig) -> Arc<IngestBuffer> {
+        IngestBuffer::start(
+            Arc::clone(db) as Arc<dyn Database>,
+            Arc::new(LiveHub::new(16)),
+            Arc::new(AlertEngine::new(None)),
+            config,
+        )
+    }
+
+    fn datapoints(first: usize, count: usize) -> Vec<SessionSensorData> {
+        (first..first + count)
+            .map(|n| {
+                SessionSensorData::new(
+                    Some(1),
+                    Some(SESSION_SENSOR_ID),
+                    format!("2025-01-01T12:00:{:02}.{:03}", n / 1_000, n % 1_000),
+                    json!({ "value": n }),
+                )
+            })
+            .collect()
+    }
+
+    fn stored(db: &SqliteDatabase) -> usize {
+        db.get_session_sensor_data(SESSION_SENSOR_ID).expect("Failed to read datapoints").len()
+    }
+
+    // Polls until the expected datapoints are stored or the timeout passes
+    fn wait_for_stored(db: &SqliteDatabase, expected: usize, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if stored(db) == expected {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        stored(db) == expected
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_flush_on_batch_size() {
+        let db = init_database();
+        add_test_recording(&db);
+        let ingest = start(&db, IngestConfig {
+            batch_size: 10,
+            flush_interval: Duration::from_secs(3_600),
+            ..Default::default()
+        });
+
+        ingest.enqueue(datapoints(0, 4), false).unwrap();
+        ingest.enqueue(datapoints(4, 4), false).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(stored(&db), 0, "Flushed before a batch was queued");
+
+        ingest.enqueue(datapoints(8, 2), false).unwrap();
+        assert!(wait_for_stored(&db, 10, Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_flush_on_interval() {
+        let db = init_database();
+        add_test_recording(&db);
+        let ingest = start(&db, IngestConfig {
+            batch_size: 1_000,
+            flush_interval: Duration::from_millis(20),
+            ..Default::default()
+        });
+
+        ingest.enqueue(datapoints(0, 3), false).unwrap();
+        assert!(wait_for_stored(&db, 3, Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_wait_for_commit() {
+        let db = init_database();
+        add_test_recording(&db);
+        let ingest = start(&db, IngestConfig {
+            batch_size: 1_000,
+            flush_interval: Duration::from_millis(20),
+            ..Default::default()
+        });
+
+        ingest.enqueue(datapoints(0, 5), true).expect("Commit failed");
+        assert_eq!(stored(&db), 5);
+    }
+
+    #[test]
+    fn test_failed_write_fails_alone() {
+        let db = init_database();
+        add_test_recording(&db);
+        db.batch_session_sensor_data(&datapoints(0, 1)).unwrap();
+        let ingest = start(&db, IngestConfig {
+            batch_size: 1_000,
+            flush_interval: Duration::from_millis(100),
+            ..Default::default()
+        });
+
+        // Both writes go out in the same flush, the second repeats a stored datapoint
+        ingest.enqueue(datapoints(1, 5), false).unwrap();
+        let result = ingest.enqueue(datapoints(0, 1), true);
+
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        assert_eq!(stored(&db), 6);
+    }
+
+    #[test]
+    fn test_backpressure() {
+        let db = init_database();
+        add_test_recording(&db);
+        let ingest = start(&db, IngestConfig {
+            batch_size: 1_000,
+            flush_interval: Duration::from_secs(3_600),
+            capacity: 10,
+            enqueue_timeout: Duration::from_millis(50),
+        });
+
+        ingest.enqueue(datapoints(0, 10), false).unwrap();
+        let result = ingest.enqueue(datapoints(10, 1), false);
+        assert!(matches!(result, Err(Error::Unavailable(_))));
+        assert_eq!(result.unwrap_err().status(), HttpStatus::ServiceUnavailable);
+    }
+
+    #[test]
+    fn test_shutdown_flushes() {
+        let db = init_database();
+        add_test_recording(&db);
+        let ingest = start(&db, IngestConfig {
+            batch_size: 1_000,
+            flush_interval: Duration::from_secs(3_600),
+            ..Default::default()
+        });
+
+        ingest.enqueue(datapoints(0, 7), false).unwrap();
+        ingest.shutdown();
+        assert_eq!(stored(&db), 7);
+
+        // Nothing is accepted once the flusher has stopped
+        assert!(matches!(ingest.enqueue(datapoints(7, 1), false), Err(Error::Unavailable(_))));
+    }
+}
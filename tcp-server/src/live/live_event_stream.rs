@@ -0,0 +1,249 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashSet;
+use std::io::Write;
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpResponse, HttpStatus};
+use crate::models::SessionSensorData;
+
+use super::live_hub::LiveEvent;
+use super::{LiveHub, LiveMessage, LiveSubscription, LiveTopic};
+
+type Result<T> = crate::Result<T>;
+
+//Streams a session's datapoints as server-sent events for clients that cannot use websockets
+pub struct LiveEventStream {
+    stream: TcpStream,
+    subscription: LiveSubscription,
+    session_id: i64,
+    // datapoints already sent from the database, live copies of them are skipped
+    sent: HashSet<(Option<i64>, String)>,
+}
+
+impl LiveEventStream {
+    // comment lines are sent this often so proxies keep the connection open
+    pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+    pub const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+    // how long clients wait before reconnecting
+    pub const RETRY_MS: u64 = 3_000;
+    // datapoints read at a time while catching a reconnecting client up
+    const BACKLOG_PAGE_SIZE: usize = 1_000;
+
+    //Builds the streamed response, datapoints after the Last-Event-ID are sent a page at a time before live ones
+    pub fn response(
+        database: &Arc<dyn Database>,
+        hub: &Arc<LiveHub>,
+        session_id: i64,
+        last_event_id: Option<&str>,
+    ) -> HttpResponse {
+        if let Err(e) = database.get_session(session_id) {
+            return HttpResponse::from_error(&e);
+        }
+
+        let after = match last_event_id.map(str::trim) {
+            Some(last_event_id) if !last_event_id.is_empty() => match Self::resume_after(session_id, last_event_id) {
+                Ok(after) => Some(after),
+                Err(Error::Validation(message)) => {
+                    return HttpResponse::bad_request(&format!("Invalid Last-Event-ID: {message}"))
+                }
+                Err(e) => return HttpResponse::from_error(&e),
+            },
+            _ => None,
+        };
+
+        //subscribe before reading the backlog so nothing is missed in between
+        let subscription = match hub.subscribe() {
+            Ok(subscription) => subscription,
+            Err(e) => return HttpResponse::from_error(&e),
+        };
+        if let Err(e) = subscription.subscribe(&[LiveTopic::Session(session_id)]) {
+            return HttpResponse::from_error(&e);
+        }
+
+        let database = Arc::clone(database);
+        HttpResponse::streamed(
+            HttpStatus::OK,
+            HttpHeader::default_event_stream(),
+            Box::new(move |stream| {
+                let backlog = after.map(|after| Self::backlog(database.as_ref(), session_id, after));
+                LiveEventStream {
+                    stream,
+                    subscription,
+                    session_id,
+                    sent: HashSet::new(),
+                }
+                .serve(backlog)
+            }),
+        )
+    }
+
+    //Every datapoint of the session after the given one, a page at a time ordered by datetime then session sensor
+    pub fn backlog(database: &dyn Database, session_id: i64, after: SessionSensorData) -> BacklogPages<'_> {
+        BacklogPages {
+            database,
+            session_id,
+            after,
+            done: false,
+        }
+    }
+
+    //The datapoint a Last-Event-ID resumes after
+    //A bare datetime, the event id of older servers, resumes after every datapoint recorded at that time
+    pub fn resume_after(session_id: i64, last_event_id: &str) -> Result<SessionSensorData> {
+        let (datetime, session_sensor_id) = match last_event_id.rsplit_once('/') {
+            Some((datetime, session_sensor_id)) => (
+                datetime,
+                session_sensor_id.parse::<i64>().map_err(|e| {
+                    Error::Validation(format!("Invalid session sensor id '{session_sensor_id}': {e}"))
+                })?,
+            ),
+            None => (last_event_id, i64::MAX),
+        };
+        if Self::parse_datetime(datetime).is_none() {
+            return Err(Error::Validation(format!(
+                "Invalid datetime '{datetime}', expected YYYY-MM-DDTHH:MM:SS"
+            )));
+        }
+
+        Ok(SessionSensorData::new(
+            Some(session_id),
+            Some(session_sensor_id),
+            datetime.to_string(),
+            Value::Null,
+        ))
+    }
+
+    //The datetime and session sensor of a datapoint, which is unique within its session and orders it
+    pub fn event_id(datapoint: &SessionSensorData) -> String {
+        format!(
+            "{}/{}",
+            datapoint.get_datetime(),
+            datapoint.get_session_sensor_id().unwrap_or(0)
+        )
+    }
+
+    fn serve(mut self, backlog: Option<BacklogPages>) {
+        if let Err(e) = self.stream.set_write_timeout(Some(Self::WRITE_TIMEOUT)) {
+            eprintln!("Failed to set event stream write timeout: {e}");
+        }
+        if let Err(e) = self.run(backlog) {
+            eprintln!("Event stream closed: {e}");
+        }
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn run(&mut self, backlog: Option<BacklogPages>) -> Result<()> {
+        self.write(&format!("retry: {}\n\n", Self::RETRY_MS))?;
+        //a failed read closes the stream, the client reconnects from the last event it received
+        for page in backlog.into_iter().flatten() {
+            let mut page = page?;
+            page.sort_by_key(|datapoint| Self::parse_datetime(datapoint.get_datetime()));
+            for datapoint in page {
+                self.sent.insert(Self::sent_key(&datapoint));
+                self.send_event(&LiveEvent {
+                    session_id: self.session_id,
+                    session_sensor_id: *datapoint.get_session_sensor_id(),
+                    datapoint,
+                })?;
+            }
+        }
+
+        loop {
+            match self.subscription.recv_timeout(Self::KEEPALIVE_INTERVAL) {
+                Ok(LiveMessage::Event(event)) => {
+                    //the client reconnects with its Last-Event-ID and reads what it missed from the database
+                    let dropped = self.subscription.take_dropped();
+                    if dropped > 0 {
+                        let data = json!({"type": "lagged", "dropped": dropped});
+                        self.write(&format!("event: lagged\ndata: {data}\n\n"))?;
+                        return Err(Error::Io(format!("Client fell behind by {dropped} events")));
+                    }
+                    if !self.sent.contains(&Self::sent_key(&event.datapoint)) {
+                        self.send_event(&event)?;
+                    }
+                }
+                Ok(LiveMessage::Frame(_)) => {}
+                Ok(LiveMessage::Closed) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) => self.write(": keepalive\n\n")?,
+            }
+        }
+    }
+
+    fn sent_key(datapoint: &SessionSensorData) -> (Option<i64>, String) {
+        (*datapoint.get_session_sensor_id(), datapoint.get_datetime().to_string())
+    }
+
+    fn send_event(&mut self, event: &LiveEvent) -> Result<()> {
+        self.write(&format!(
+            "id: {}\nevent: datapoint\ndata: {}\n\n",
+            Self::event_id(&event.datapoint),
+            event.to_json()
+        ))
+    }
+
+    fn write(&mut self, data: &str) -> Result<()> {
+        self.stream
+            .write_all(data.as_bytes())
+            .map_err(|e| Error::Io(format!("Failed to send event: {e}")))?;
+        self.stream
+            .flush()
+            .map_err(|e| Error::Io(format!("Failed to flush after sending event: {e}")))
+    }
+
+    fn parse_datetime(datetime: &str) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f").ok()
+    }
+}
+
+//Reads a reconnecting client's missed datapoints a page at a time so they are never all held in memory
+pub struct BacklogPages<'a> {
+    database: &'a dyn Database,
+    session_id: i64,
+    // last datapoint read, the next page starts after it
+    after: SessionSensorData,
+    done: bool,
+}
+
+impl Iterator for BacklogPages<'_> {
+    type Item = Result<Vec<SessionSensorData>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let page = match self.database.get_sessions_sensor_data_page(
+            self.session_id,
+            None,
+            None,
+            Some(&self.after),
+            LiveEventStream::BACKLOG_PAGE_SIZE,
+        ) {
+            Ok(page) => page,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        self.done = page.len() < LiveEventStream::BACKLOG_PAGE_SIZE;
+        match page.last() {
+            Some(last) => self.after = last.clone(),
+            None => return None,
+        }
+        Some(Ok(page))
+    }
+}
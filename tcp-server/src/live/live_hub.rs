@@ -0,0 +1,250 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::error::Error;
+use crate::http::WebSocketFrame;
+use crate::models::SessionSensorData;
+
+//What a subscriber can listen to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiveTopic {
+    Session(i64),
+    SessionSensor(i64),
+}
+
+//A datapoint that was just ingested
+pub struct LiveEvent {
+    pub session_id: i64,
+    pub session_sensor_id: Option<i64>,
+    pub datapoint: SessionSensorData,
+}
+
+impl LiveEvent {
+    pub fn matches(&self, topics: &HashSet<LiveTopic>) -> bool {
+        topics.contains(&LiveTopic::Session(self.session_id))
+            || self
+                .session_sensor_id
+                .is_some_and(|id| topics.contains(&LiveTopic::SessionSensor(id)))
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": "datapoint",
+            "session_id": self.session_id,
+            "session_sensor_id": self.session_sensor_id,
+            "datetime": self.datapoint.get_datetime(),
+            "data_blob": self.datapoint.get_blob(),
+        })
+    }
+}
+
+//Everything a subscriber's connection thread can be woken up by
+pub enum LiveMessage {
+    Event(Arc<LiveEvent>),
+    Frame(WebSocketFrame),
+    Closed,
+}
+
+struct Subscriber {
+    sender: SyncSender<LiveMessage>,
+    topics: HashSet<LiveTopic>,
+    dropped: Arc<AtomicU64>,
+}
+
+//Publish/subscribe hub that fans ingested datapoints out to live connections
+pub struct LiveHub {
+    subscribers: Mutex<HashMap<u64, Subscriber>>,
+    next_id: AtomicU64,
+    capacity: usize,
+}
+
+impl LiveHub {
+    // most topics a single subscriber may listen to
+    pub const MAX_TOPICS: usize = 256;
+
+    pub fn new(capacity: usize) -> Self {
+        LiveHub {
+            subscribers: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn subscribe(self: &Arc<Self>) -> crate::Result<LiveSubscription> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.lock()?.insert(
+            id,
+            Subscriber {
+                sender: sender.clone(),
+                topics: HashSet::new(),
+                dropped: Arc::clone(&dropped),
+            },
+        );
+        Ok(LiveSubscription {
+            id,
+            hub: Arc::clone(self),
+            sender,
+            receiver,
+            dropped,
+        })
+    }
+
+    #[allow(unused)]
+    pub fn subscriber_count(&self) -> crate::Result<usize> {
+        Ok(self.lock()?.len())
+    }
+
+    //Sends a datapoint to every matching subscriber without ever blocking ingestion
+    #[allow(unused)]
+    pub fn publish(&self, datapoint: &SessionSensorData) {
+        self.publish_all(std::slice::from_ref(datapoint));
+    }
+
+    //Publishing runs on the ingest path, so a failure is logged instead of failing the write
+    pub fn publish_all(&self, datapoints: &[SessionSensorData]) {
+        if let Err(e) = self.try_publish_all(datapoints) {
+            eprintln!(
+                "Failed to publish {} datapoints to live subscribers: {e}",
+                datapoints.len()
+            );
+        }
+    }
+
+    fn try_publish_all(&self, datapoints: &[SessionSensorData]) -> crate::Result<()> {
+        let mut subscribers = self.lock()?;
+        if subscribers.is_empty() {
+            return Ok(());
+        }
+        datapoints
+            .iter()
+            .for_each(|datapoint| Self::send(&mut subscribers, datapoint));
+        Ok(())
+    }
+
+    fn send(subscribers: &mut HashMap<u64, Subscriber>, datapoint: &SessionSensorData) {
+        let session_id = match datapoint.get_id() {
+            Some(session_id) => *session_id,
+            None => return,
+        };
+
+        let event = Arc::new(LiveEvent {
+            session_id,
+            session_sensor_id: *datapoint.get_session_sensor_id(),
+            datapoint: datapoint.clone(),
+        });
+
+        subscribers.retain(|_, subscriber| {
+            if !event.matches(&subscriber.topics) {
+                return true;
+            }
+            match subscriber.sender.try_send(LiveMessage::Event(Arc::clone(&event))) {
+                Ok(_) => true,
+                // slow consumer, drop the event and let it know how far behind it is
+                Err(TrySendError::Full(_)) => {
+                    subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+
+    fn update_topics(&self, id: u64, update: impl FnOnce(&mut HashSet<LiveTopic>)) -> crate::Result<Vec<LiveTopic>> {
+        Ok(match self.lock()?.get_mut(&id) {
+            Some(subscriber) => {
+                update(&mut subscriber.topics);
+                subscriber.topics.iter().copied().collect()
+            }
+            None => Vec::new(),
+        })
+    }
+
+    fn remove(&self, id: u64) {
+        match self.lock() {
+            Ok(mut subscribers) => {
+                subscribers.remove(&id);
+            }
+            Err(e) => eprintln!("Failed to remove live subscriber {id}: {e}"),
+        }
+    }
+
+    fn lock(&self) -> crate::Result<MutexGuard<'_, HashMap<u64, Subscriber>>> {
+        self.subscribers.lock().map_err(|_| Self::poisoned())
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Live subscribers were poisoned by a panic"))
+    }
+}
+
+//A single subscriber's handle, the subscriber is removed from the hub when this is dropped
+pub struct LiveSubscription {
+    id: u64,
+    hub: Arc<LiveHub>,
+    sender: SyncSender<LiveMessage>,
+    receiver: Receiver<LiveMessage>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LiveSubscription {
+    //Adds topics and returns the full set the subscriber is listening to
+    pub fn subscribe(&self, topics: &[LiveTopic]) -> crate::Result<Vec<LiveTopic>> {
+        let mut result = Ok(());
+        let current = self.hub.update_topics(self.id, |current| {
+            let new_topics = topics.iter().filter(|topic| !current.contains(topic)).count();
+            if current.len() + new_topics > LiveHub::MAX_TOPICS {
+                result = Err(Error::Validation(format!(
+                    "Subscription limit of {} topics exceeded",
+                    LiveHub::MAX_TOPICS
+                )));
+            } else {
+                current.extend(topics.iter().copied());
+            }
+        })?;
+        result.map(|_| current)
+    }
+
+    //Removes topics and returns the full set the subscriber is still listening to
+    pub fn unsubscribe(&self, topics: &[LiveTopic]) -> crate::Result<Vec<LiveTopic>> {
+        self.hub.update_topics(self.id, |current| {
+            topics.iter().for_each(|topic| {
+                current.remove(topic);
+            })
+        })
+    }
+
+    //Sender for other threads of the same connection to wake it with messages
+    pub fn sender(&self) -> SyncSender<LiveMessage> {
+        self.sender.clone()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<LiveMessage, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    //Returns how many events were dropped since the last call
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+impl Drop for LiveSubscription {
+    fn drop(&mut self) {
+        self.hub.remove(self.id);
+    }
+}
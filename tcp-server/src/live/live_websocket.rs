@@ -0,0 +1,238 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{RecvTimeoutError, SyncSender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::Error;
+use crate::http::{WebSocketFrame, WebSocketOpcode};
+
+use super::{LiveHub, LiveMessage, LiveSubscription, LiveTopic};
+
+type Result<T> = crate::Result<T>;
+
+//Subscription command sent by the client as a text frame
+#[derive(Deserialize)]
+struct LiveCommand {
+    action: String,
+    #[serde(default)]
+    sessions: Vec<i64>,
+    #[serde(default)]
+    session_sensors: Vec<i64>,
+}
+
+impl LiveCommand {
+    fn topics(&self) -> Vec<LiveTopic> {
+        self.sessions
+            .iter()
+            .map(|id| LiveTopic::Session(*id))
+            .chain(
+                self.session_sensors
+                    .iter()
+                    .map(|id| LiveTopic::SessionSensor(*id)),
+            )
+            .collect()
+    }
+}
+
+//Serves a websocket connection after the handshake completed
+pub struct LiveWebSocket {
+    stream: TcpStream,
+    subscription: LiveSubscription,
+    last_seen: Instant,
+    last_ping: Instant,
+}
+
+impl LiveWebSocket {
+    pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+    // a client that answers nothing for this long is considered gone
+    pub const IDLE_TIMEOUT: Duration = Duration::from_secs(75);
+    // a client that cannot take a frame for this long is disconnected
+    pub const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const MAX_MESSAGE_LEN: usize = 65_536;
+
+    //Serves the connection until either side closes it, blocking the calling thread
+    pub fn serve(stream: TcpStream, hub: &Arc<LiveHub>) {
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Failed to clone websocket stream: {e}");
+                return;
+            }
+        };
+        if let Err(e) = stream.set_write_timeout(Some(Self::WRITE_TIMEOUT)) {
+            eprintln!("Failed to set websocket write timeout: {e}");
+        }
+
+        let subscription = match hub.subscribe() {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                eprintln!("Failed to subscribe websocket: {e}");
+                let _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+        };
+        let sender = subscription.sender();
+        std::thread::spawn(move || Self::read_frames(reader, sender));
+
+        let mut websocket = LiveWebSocket {
+            stream,
+            subscription,
+            last_seen: Instant::now(),
+            last_ping: Instant::now(),
+        };
+        if let Err(e) = websocket.run() {
+            eprintln!("Websocket connection closed: {e}");
+        }
+        let _ = websocket.stream.shutdown(Shutdown::Both);
+    }
+
+    fn run(&mut self) -> Result<()> {
+        loop {
+            match self.subscription.recv_timeout(Self::PING_INTERVAL) {
+                Ok(LiveMessage::Event(event)) => {
+                    let dropped = self.subscription.take_dropped();
+                    if dropped > 0 {
+                        self.send_json(json!({"type": "lagged", "dropped": dropped}))?;
+                    }
+                    self.send_json(event.to_json())?;
+                }
+                Ok(LiveMessage::Frame(frame)) => {
+                    self.last_seen = Instant::now();
+                    if !self.handle_frame(frame)? {
+                        return Ok(());
+                    }
+                }
+                Ok(LiveMessage::Closed) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            if self.last_ping.elapsed() >= Self::PING_INTERVAL {
+                if self.last_seen.elapsed() >= Self::IDLE_TIMEOUT {
+                    let _ = WebSocketFrame::close(1001, "keepalive timeout").write_to(&mut self.stream);
+                    return Err(Error::Io(String::from("Client stopped answering pings")));
+                }
+                WebSocketFrame::ping(b"keepalive").write_to(&mut self.stream)?;
+                self.last_ping = Instant::now();
+            }
+        }
+    }
+
+    //Returns false once the connection should be closed
+    fn handle_frame(&mut self, frame: WebSocketFrame) -> Result<bool> {
+        match frame.opcode {
+            WebSocketOpcode::Text => {
+                let reply = match String::from_utf8(frame.payload) {
+                    Ok(text) => self.handle_command(&text),
+                    Err(_) => {
+                        WebSocketFrame::close(1007, "invalid utf-8").write_to(&mut self.stream)?;
+                        return Ok(false);
+                    }
+                };
+                self.send_json(reply)?;
+            }
+            WebSocketOpcode::Binary | WebSocketOpcode::Continuation => {
+                self.send_json(json!({"type": "error", "error": "Only text messages are supported."}))?;
+            }
+            WebSocketOpcode::Ping => WebSocketFrame::pong(&frame.payload).write_to(&mut self.stream)?,
+            WebSocketOpcode::Pong => {}
+            WebSocketOpcode::Close => {
+                // echo the status code back, RFC 6455 section 5.5.1
+                let code = frame
+                    .payload
+                    .get(0..2)
+                    .map(|code| u16::from_be_bytes([code[0], code[1]]))
+                    .unwrap_or(1000);
+                let _ = WebSocketFrame::close(code, "").write_to(&mut self.stream);
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn handle_command(&self, text: &str) -> serde_json::Value {
+        let command = match serde_json::from_str::<LiveCommand>(text) {
+            Ok(command) => command,
+            Err(e) => return json!({"type": "error", "error": format!("Invalid command: {e}")}),
+        };
+
+        let topics = match command.action.as_str() {
+            "subscribe" => match self.subscription.subscribe(&command.topics()) {
+                Ok(topics) => topics,
+                Err(e) => return json!({"type": "error", "error": e.message()}),
+            },
+            "unsubscribe" => match self.subscription.unsubscribe(&command.topics()) {
+                Ok(topics) => topics,
+                Err(e) => return json!({"type": "error", "error": e.message()}),
+            },
+            action => {
+                return json!({
+                    "type": "error",
+                    "error": format!("Unknown action '{action}', expected one of [subscribe, unsubscribe]")
+                })
+            }
+        };
+
+        let (mut sessions, mut session_sensors) = (Vec::new(), Vec::new());
+        topics.iter().for_each(|topic| match topic {
+            LiveTopic::Session(id) => sessions.push(*id),
+            LiveTopic::SessionSensor(id) => session_sensors.push(*id),
+        });
+        sessions.sort_unstable();
+        session_sensors.sort_unstable();
+        json!({"type": "subscribed", "sessions": sessions, "session_sensors": session_sensors})
+    }
+
+    fn send_json(&mut self, value: serde_json::Value) -> Result<()> {
+        WebSocketFrame::text(&value.to_string()).write_to(&mut self.stream)
+    }
+
+    //Reads frames on a separate thread and hands complete messages to the connection thread
+    fn read_frames(mut reader: TcpStream, sender: SyncSender<LiveMessage>) {
+        let mut message: Option<WebSocketFrame> = None;
+        while let Ok(frame) = WebSocketFrame::read_from(&mut reader, Self::MAX_MESSAGE_LEN) {
+            let complete = match frame.opcode {
+                _ if frame.opcode.is_control() => frame,
+                WebSocketOpcode::Continuation => match message.as_mut() {
+                    Some(partial) => {
+                        partial.payload.extend_from_slice(&frame.payload);
+                        if partial.payload.len() > Self::MAX_MESSAGE_LEN {
+                            break;
+                        }
+                        if !frame.fin {
+                            continue;
+                        }
+                        match message.take() {
+                            Some(mut partial) => {
+                                partial.fin = true;
+                                partial
+                            }
+                            None => break,
+                        }
+                    }
+                    None => break,
+                },
+                _ if !frame.fin => {
+                    message = Some(frame);
+                    continue;
+                }
+                _ => frame,
+            };
+
+            if sender.send(LiveMessage::Frame(complete)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(LiveMessage::Closed);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod live_event_stream;
+pub mod live_hub;
+pub mod live_websocket;
+
+pub use self::live_event_stream::LiveEventStream;
+pub use self::live_hub::{LiveHub, LiveMessage, LiveSubscription, LiveTopic};
+pub use self::live_websocket::LiveWebSocket;
+
+#[cfg(test)]
+mod test_live;
@@ -0,0 +1,252 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::error::Error;
+    use crate::http::http_header::HttpHeaderType;
+    use crate::http::{HttpHeader, HttpResponse, HttpStatus, WebSocket, WebSocketFrame, WebSocketOpcode};
+    use crate::live::{LiveEventStream, LiveHub, LiveMessage, LiveTopic};
+    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, User};
+
+    /* Helpers */
+
+    fn datapoint(session_id: i64, session_sensor_id: Option<i64>, datetime: &str) -> SessionSensorData {
+        SessionSensorData::new(
+            Some(session_id),
+            session_sensor_id,
+            datetime.to_string(),
+            json!({"value": 1}),
+        )
+    }
+
+    fn recv_event(message: LiveMessage) -> (i64, Option<i64>, String) {
+        match message {
+            LiveMessage::Event(event) => (
+                event.session_id,
+                event.session_sensor_id,
+                event.datapoint.get_datetime().to_string(),
+            ),
+            _ => panic!("Expected an event message"),
+        }
+    }
+
+    // masks a server style frame the way a client would send it
+    fn client_bytes(frame: &WebSocketFrame, mask: [u8; 4]) -> Vec<u8> {
+        let mut bytes = frame.to_bytes();
+        let header_len = bytes.len() - frame.payload.len();
+        bytes[1] |= 0x80;
+        let mut masked = bytes[..header_len].to_vec();
+        masked.extend_from_slice(&mask);
+        masked.extend(
+            frame
+                .payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+        masked
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_accept_key() {
+        // example handshake from RFC 6455 section 1.3
+        assert_eq!(
+            WebSocket::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        for len in [0, 5, 125, 126, 70_000] {
+            let frame = WebSocketFrame::new(WebSocketOpcode::Text, vec![b'a'; len]);
+            let bytes = client_bytes(&frame, [1, 2, 3, 4]);
+
+            let parsed = WebSocketFrame::read_from(&mut bytes.as_slice(), 100_000)
+                .expect("Failed to read frame");
+            assert!(parsed.fin);
+            assert_eq!(parsed.opcode, WebSocketOpcode::Text);
+            assert_eq!(parsed.payload, frame.payload);
+        }
+    }
+
+    #[test]
+    fn test_frame_rejects_unmasked() {
+        let bytes = WebSocketFrame::text("hello").to_bytes();
+        assert!(WebSocketFrame::read_from(&mut bytes.as_slice(), 1_024).is_err());
+    }
+
+    #[test]
+    fn test_frame_rejects_oversized() {
+        let frame = WebSocketFrame::text("hello world");
+        let bytes = client_bytes(&frame, [9, 9, 9, 9]);
+        assert!(WebSocketFrame::read_from(&mut bytes.as_slice(), 4).is_err());
+    }
+
+    #[test]
+    fn test_hub_routes_by_topic() {
+        let hub = Arc::new(LiveHub::new(16));
+        let session_subscriber = hub.subscribe().expect("Failed to add subscriber");
+        let sensor_subscriber = hub.subscribe().expect("Failed to add subscriber");
+        session_subscriber
+            .subscribe(&[LiveTopic::Session(1)])
+            .expect("Failed to subscribe");
+        sensor_subscriber
+            .subscribe(&[LiveTopic::SessionSensor(7)])
+            .expect("Failed to subscribe");
+
+        hub.publish(&datapoint(1, None, "first"));
+        hub.publish(&datapoint(2, Some(7), "second"));
+        hub.publish(&datapoint(3, None, "third"));
+
+        let timeout = Duration::from_millis(10);
+        let received = session_subscriber.recv_timeout(timeout).expect("Missing event");
+        assert_eq!(recv_event(received), (1, None, String::from("first")));
+        assert!(session_subscriber.recv_timeout(timeout).is_err());
+
+        let received = sensor_subscriber.recv_timeout(timeout).expect("Missing event");
+        assert_eq!(recv_event(received), (2, Some(7), String::from("second")));
+        assert!(sensor_subscriber.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    fn test_hub_unsubscribe() {
+        let hub = Arc::new(LiveHub::new(16));
+        let subscriber = hub.subscribe().expect("Failed to add subscriber");
+        subscriber
+            .subscribe(&[LiveTopic::Session(1), LiveTopic::Session(2)])
+            .expect("Failed to subscribe");
+
+        let remaining = subscriber
+            .unsubscribe(&[LiveTopic::Session(1)])
+            .expect("Failed to unsubscribe");
+        assert_eq!(remaining, vec![LiveTopic::Session(2)]);
+
+        hub.publish(&datapoint(1, None, "first"));
+        assert!(subscriber.recv_timeout(Duration::from_millis(10)).is_err());
+    }
+
+    #[test]
+    fn test_hub_backpressure_drops_and_counts() {
+        let hub = Arc::new(LiveHub::new(2));
+        let subscriber = hub.subscribe().expect("Failed to add subscriber");
+        subscriber
+            .subscribe(&[LiveTopic::Session(1)])
+            .expect("Failed to subscribe");
+
+        (0..5).for_each(|i| hub.publish(&datapoint(1, None, &i.to_string())));
+
+        assert_eq!(subscriber.take_dropped(), 3);
+        assert_eq!(subscriber.take_dropped(), 0);
+    }
+
+    #[test]
+    fn test_hub_topic_limit() {
+        let hub = Arc::new(LiveHub::new(2));
+        let subscriber = hub.subscribe().expect("Failed to add subscriber");
+        let topics = (0..=LiveHub::MAX_TOPICS as i64)
+            .map(LiveTopic::Session)
+            .collect::<Vec<_>>();
+        assert!(subscriber.subscribe(&topics).is_err());
+    }
+
+    #[test]
+    fn test_hub_removes_dropped_subscription() {
+        let hub = Arc::new(LiveHub::new(2));
+        let subscriber = hub.subscribe().expect("Failed to add subscriber");
+        assert_eq!(hub.subscriber_count().unwrap(), 1);
+        drop(subscriber);
+        assert_eq!(hub.subscriber_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_streamed_response_head() {
+        let response = HttpResponse::streamed(
+            HttpStatus::OK,
+            HttpHeader::default_event_stream(),
+            Box::new(|_| {}),
+        );
+        let head = response.to_string();
+        assert!(head.ends_with("\r\n\r\n"));
+        assert!(head.contains("text/event-stream"));
+        assert!(!head.contains(HttpHeaderType::ContentLength.as_str()));
+    }
+    #[test]
+    fn test_event_stream_resumes_after_event_id() {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        for sensor_type in ["Acceleration", "GPS"] {
+            db.insert_sensor(&Sensor::new(0, sensor_type.to_string())).unwrap();
+        }
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 2)).unwrap();
+        // both sensors record at the second datetime
+        db.batch_session_sensor_data(&[
+            datapoint(1, Some(1), "2025-01-01T12:00:00"),
+            datapoint(1, Some(1), "2025-01-01T12:00:01"),
+            datapoint(1, Some(2), "2025-01-01T12:00:01"),
+            datapoint(1, Some(2), "2025-01-01T12:00:02"),
+        ])
+        .unwrap();
+        let event_ids = |last_event_id: &str| -> Vec<String> {
+            let after = LiveEventStream::resume_after(1, last_event_id).expect("Failed to parse event id");
+            LiveEventStream::backlog(&db, 1, after)
+                .flat_map(|page| page.expect("Failed to read backlog page"))
+                .map(|datapoint| LiveEventStream::event_id(&datapoint))
+                .collect()
+        };
+
+        assert_eq!(
+            event_ids("2025-01-01T12:00:01/1"),
+            vec!["2025-01-01T12:00:01/2", "2025-01-01T12:00:02/2"]
+        );
+        assert_eq!(event_ids("2025-01-01T12:00:01/2"), vec!["2025-01-01T12:00:02/2"]);
+        // a bare datetime resumes after everything recorded at it
+        assert_eq!(event_ids("2025-01-01T12:00:01"), vec!["2025-01-01T12:00:02/2"]);
+        assert!(matches!(
+            LiveEventStream::resume_after(1, "2025-01-01T12:00:01/x"),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            LiveEventStream::resume_after(1, "yesterday/1"),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_event_stream_backlog_is_paged() {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        db.insert_sensor(&Sensor::new(0, "Acceleration".to_string())).unwrap();
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        let datapoints: Vec<SessionSensorData> = (0..2_500)
+            .map(|i| datapoint(1, Some(1), &format!("2025-01-01T12:00:00.{i:06}")))
+            .collect();
+        db.batch_session_sensor_data(&datapoints).unwrap();
+
+        let after = LiveEventStream::resume_after(1, "2025-01-01T12:00:00.000000/1").expect("Failed to parse event id");
+        let pages: Vec<usize> = LiveEventStream::backlog(&db, 1, after)
+            .map(|page| page.expect("Failed to read backlog page").len())
+            .collect();
+        assert_eq!(pages, vec![1_000, 1_000, 499]);
+    }
+}
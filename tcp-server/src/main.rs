@@ -12,7 +12,7 @@
 //! - [x] Response generation from files
 //! - [x] Route handling with respect to method, path, and body
 //! - [ ] Request query string parsed
-//! - [ ] Multithread with pooling
+//! - [x] Multithread with pooling
 //! - [x] Database interface
 //!
 /*
@@ -23,32 +23,153 @@ You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2
 Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
+#[cfg(any(
+    all(feature = "sql", feature = "postgres"),
+    all(feature = "sql", feature = "memory"),
+    all(feature = "postgres", feature = "memory")
+))]
+compile_error!("Features 'sql', 'postgres' and 'memory' select different databases, enable only one of them");
+
+mod alerts;
+mod calibration;
 mod data;
+mod derived;
+mod error;
+mod export;
 mod http;
+mod import;
+mod ingest;
+mod live;
 mod models;
+mod retention;
+mod udp;
+mod validation;
 
 use std::fs;
 use std::io::Read;
 use std::net::{TcpListener, TcpStream};
+use std::panic::AssertUnwindSafe;
 use std::str;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
+use alerts::{AlertConfig, AlertEngine, WebhookSender};
+use calibration::Calibrator;
+use data::sqlite_backup::SqliteBackup;
 use data::Database;
 use http::http_header::HttpHeaderType;
-use http::{HttpHeader, HttpMethod, HttpPath, HttpRequest, HttpResponse, HttpStatus};
-use models::{BaseModel, Sensor, Session, SessionSensor, SessionSensorData, User};
+use http::{HttpHeader, HttpMethod, HttpPath, HttpRequest, HttpResponse, HttpStatus, WebSocket};
+use ingest::{IngestBuffer, IngestConfig};
+use live::{LiveEventStream, LiveHub, LiveWebSocket};
+use models::{
+    BaseModel, DerivedChannel, QuarantinedSensorData, Sensor, SensorCalibration, SensorSchema, Session, SessionSensor,
+    SessionSensorData, SessionSensorRollup, SessionSummary, User,
+};
+use retention::{RetentionConfig, RetentionJob, RetentionPolicy};
 use serde::Deserialize;
 use serde_json::json;
+use udp::{UdpListener, UdpShutdown, UdpStats};
+
+use error::Error;
+use export::ExportQuery;
+use import::{ImportFormat, ImportOptions, SessionImport};
+use validation::SchemaRegistry;
 
-//Result generalization, could replace String with custom error enum
-type Result<T> = core::result::Result<T, String>;
+//Result generalization over the crate's error type
+type Result<T> = core::result::Result<T, Error>;
 
 const HTTP_HEADER_DELIMITER: &[u8] = b"\r\n\r\n";
+// largest request body read into memory, larger requests are refused before reading it
+const MAX_BODY_SIZE: usize = 16 * 1_048_576;
 
 #[derive(Deserialize)]
 struct Config {
     #[cfg(feature = "sql")]
     database_file: String,
+    #[cfg(feature = "postgres")]
+    database_url: String,
+    #[cfg(feature = "postgres")]
+    #[serde(default)]
+    database_timescale: bool,
+    #[cfg(any(feature = "sql", feature = "postgres"))]
+    #[serde(default = "Config::default_database_pool_size")]
+    database_pool_size: usize,
+    #[cfg(feature = "sql")]
+    #[serde(default = "Config::default_database_busy_timeout_ms")]
+    database_busy_timeout_ms: u64,
+    #[cfg(feature = "sql")]
+    #[serde(default = "Config::default_database_synchronous")]
+    database_synchronous: String,
     local_addr: String,
+    udp_addr: Option<String>,
+    #[serde(default)]
+    udp_secret: String,
+    #[serde(default = "Config::default_udp_batch_size")]
+    udp_batch_size: usize,
+    #[serde(default = "Config::default_udp_flush_ms")]
+    udp_flush_ms: u64,
+    #[serde(default = "Config::default_live_queue_size")]
+    live_queue_size: usize,
+    #[serde(default = "Config::default_http_workers")]
+    http_workers: usize,
+    #[serde(default = "Config::default_ingest_batch_size")]
+    ingest_batch_size: usize,
+    #[serde(default = "Config::default_ingest_flush_ms")]
+    ingest_flush_ms: u64,
+    #[serde(default = "Config::default_ingest_queue_size")]
+    ingest_queue_size: usize,
+    #[serde(default)]
+    retention: RetentionConfig,
+    #[serde(default)]
+    alerts: AlertConfig,
+    #[cfg(feature = "sql")]
+    #[serde(default)]
+    backup: data::BackupConfig,
+}
+
+impl Config {
+    fn default_udp_batch_size() -> usize {
+        500
+    }
+
+    fn default_udp_flush_ms() -> u64 {
+        250
+    }
+
+    fn default_live_queue_size() -> usize {
+        1024
+    }
+
+    fn default_http_workers() -> usize {
+        4
+    }
+
+    fn default_ingest_batch_size() -> usize {
+        500
+    }
+
+    fn default_ingest_flush_ms() -> u64 {
+        50
+    }
+
+    fn default_ingest_queue_size() -> usize {
+        10_000
+    }
+
+    #[cfg(any(feature = "sql", feature = "postgres"))]
+    fn default_database_pool_size() -> usize {
+        4
+    }
+
+    #[cfg(feature = "sql")]
+    fn default_database_busy_timeout_ms() -> u64 {
+        5_000
+    }
+
+    #[cfg(feature = "sql")]
+    fn default_database_synchronous() -> String {
+        String::from("normal")
+    }
 }
 
 #[allow(unused)]
@@ -84,7 +205,35 @@ fn main() {
         }
         Err(e) => panic!("Failed to get current directory: {e}"),
     };
-    let listener = match init_server(Address::IPv4(config.local_addr)) {
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        if let Err(error) = migrate_command(&config, std::env::args().nth(2).as_deref()) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(command @ ("backup" | "restore")) = std::env::args().nth(1).as_deref() {
+        if let Err(error) = backup_command(&config, command, std::env::args().nth(2).as_deref()) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if std::env::args().nth(1).as_deref() == Some("import") {
+        if let Err(error) = import_command(&config, &std::env::args().skip(2).collect::<Vec<String>>()) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if std::env::args().nth(1).as_deref() == Some("export") {
+        if let Err(error) = export_command(&config, &std::env::args().skip(2).collect::<Vec<String>>()) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let listener = match init_server(Address::IPv4(config.local_addr.clone())) {
         Ok((tcp_listener, address)) => {
             println!("Server listening on '{}'", address.to_string());
             tcp_listener
@@ -95,34 +244,323 @@ fn main() {
         }
     };
 
-    #[cfg(not(feature = "sql"))]
-    let database = data::MockDatabase::new();
+    let database = match open_database(&config) {
+        Ok(db) => db,
+        Err(error) => {
+            println!("Failed to establish database connection. Error: {error}");
+            return;
+        }
+    };
+    #[cfg(any(feature = "sql", feature = "postgres"))]
+    database.init();
+    // shared by the http workers and the udp listener, the pool decides which connection serves each call
+    let database: Arc<dyn Database> = Arc::new(database);
 
-    #[cfg(feature = "sql")]
-    let database = {
-        match std::env::current_dir() {
-            Ok(mut path) => {
-                path.push("src");
-                path.push(&config.database_file);
-                match path.to_str() {
-                    Some(path_str) => match data::SqliteDatabase::new(path_str) {
-                        Ok(db) => {
-                            db.init();
-                            db
-                        }
-                        Err(error) => {
-                            println!("Failed to establish database connection. Error: {error}");
-                            return;
-                        }
-                    },
-                    None => panic!("Failed to convert database path to a string."),
-                }
+    let hub = Arc::new(LiveHub::new(config.live_queue_size));
+    let schemas = Arc::new(SchemaRegistry::new());
+    let udp_stats = Arc::new(Mutex::new(UdpStats::new()));
+    let alerts = match WebhookSender::start(Arc::clone(&database), config.alerts.clone()) {
+        Ok(webhooks) => Arc::new(AlertEngine::new(webhooks)),
+        Err(error) => {
+            eprintln!("Failed to start alert delivery: {error}");
+            return;
+        }
+    };
+    let udp = match &config.udp_addr {
+        Some(udp_addr) => match init_udp_listener(
+            &config,
+            udp_addr,
+            Arc::clone(&database),
+            Arc::clone(&udp_stats),
+            Arc::clone(&hub),
+            Arc::clone(&schemas),
+            Arc::clone(&alerts),
+        ) {
+            Ok(udp) => Some(udp),
+            Err(error) => {
+                eprintln!("{error}");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    RetentionJob::new(config.retention.clone()).start(Arc::clone(&database));
+    let backups = open_backups(&config);
+
+    let ingest = IngestBuffer::start(
+        Arc::clone(&database),
+        Arc::clone(&hub),
+        Arc::clone(&alerts),
+        IngestConfig {
+            batch_size: config.ingest_batch_size,
+            flush_interval: Duration::from_millis(config.ingest_flush_ms),
+            capacity: config.ingest_queue_size,
+            ..Default::default()
+        },
+    );
+    // queued and udp buffered datapoints are written before the process exits on ctrl-c or a termination signal
+    let shutdown_ingest = Arc::clone(&ingest);
+    if let Err(error) = ctrlc::set_handler(move || {
+        println!("Shutting down, flushing queued datapoints");
+        if let Some(udp) = &udp {
+            udp.shutdown();
+        }
+        shutdown_ingest.shutdown();
+        std::process::exit(0);
+    }) {
+        eprintln!("Failed to install shutdown handler, queued datapoints may be lost on exit. Error: {error}");
+    }
+
+    wait_for_connections(
+        ServerState {
+            database: &database,
+            udp_stats: &udp_stats,
+            hub: &hub,
+            ingest: &ingest,
+            schemas: &schemas,
+            alerts: &alerts,
+            retention: &config.retention.policy,
+            backups: backups.as_deref(),
+        },
+        listener,
+        config.http_workers,
+    );
+}
+
+#[cfg(not(any(feature = "sql", feature = "postgres", feature = "memory")))]
+fn open_database(_config: &Config) -> Result<data::MockDatabase> {
+    Ok(data::MockDatabase::new())
+}
+
+// Starts out empty on every run, nothing is kept once the server stops
+#[cfg(feature = "memory")]
+fn open_database(_config: &Config) -> Result<data::MemoryDatabase> {
+    Ok(data::MemoryDatabase::new())
+}
+
+#[cfg(feature = "sql")]
+fn open_database(config: &Config) -> Result<data::SqliteDatabase> {
+    match database_path(config).to_str() {
+        Some(path_str) => data::SqliteDatabase::new(
+            path_str,
+            &data::SqlitePoolConfig {
+                readers: config.database_pool_size,
+                busy_timeout: Duration::from_millis(config.database_busy_timeout_ms),
+                synchronous: data::SqliteSynchronous::from_str(&config.database_synchronous)?,
+                ..Default::default()
+            },
+        ),
+        None => panic!("Failed to convert database path to a string."),
+    }
+}
+
+//Path of the SQLite database file, next to config.toml
+#[cfg(feature = "sql")]
+fn database_path(config: &Config) -> std::path::PathBuf {
+    match std::env::current_dir() {
+        Ok(mut path) => {
+            path.push("src");
+            path.push(&config.database_file);
+            path
+        }
+        Err(e) => panic!("Failed to get current directory: {e}"),
+    }
+}
+
+// Only the SQLite database file is backed up
+#[cfg(not(feature = "sql"))]
+fn open_backups(_config: &Config) -> Option<Arc<SqliteBackup>> {
+    None
+}
+
+//Backs up the database on the configured schedule, the same backups are taken on request
+#[cfg(feature = "sql")]
+fn open_backups(config: &Config) -> Option<Arc<SqliteBackup>> {
+    let backups = Arc::new(SqliteBackup::new(database_path(config), &config.backup));
+    if let Some(minutes) = config.backup.interval_minutes {
+        Arc::clone(&backups).start(Duration::from_secs(minutes.max(1) * 60));
+    }
+    Some(backups)
+}
+
+#[cfg(feature = "postgres")]
+fn open_database(config: &Config) -> Result<data::PostgresDatabase> {
+    data::PostgresDatabase::new(
+        &config.database_url,
+        &data::PostgresConfig {
+            connections: config.database_pool_size,
+            timescale: config.database_timescale,
+            ..Default::default()
+        },
+    )
+}
+
+#[cfg(not(any(feature = "sql", feature = "postgres")))]
+fn migrate_command(_config: &Config, _command: Option<&str>) -> Result<()> {
+    Err(Error::Validation(String::from(
+        "Migrations require the crate to be built with '--features sql' or '--features postgres'",
+    )))
+}
+
+//Handles `migrate [status|dry-run|up]`, status is the default
+#[cfg(any(feature = "sql", feature = "postgres"))]
+fn migrate_command(config: &Config, command: Option<&str>) -> Result<()> {
+    let database = open_database(config)?;
+    match command.unwrap_or("status") {
+        "status" => {
+            for status in database.migration_status()? {
+                println!("{status}");
             }
-            Err(e) => panic!("Failed to get current directory: {e}"),
+        }
+        "dry-run" => match database.dry_run_migrations()? {
+            pending if pending.is_empty() => println!("Database is up to date"),
+            pending => println!("Would apply migrations {pending:?}"),
+        },
+        "up" => match database.migrate()? {
+            applied if applied.is_empty() => println!("Database is up to date"),
+            applied => println!("Applied migrations {applied:?}"),
+        },
+        command => {
+            return Err(Error::Validation(format!(
+                "Unknown migrate command '{command}', expected one of [status, dry-run, up]"
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sql"))]
+fn backup_command(_config: &Config, _command: &str, _file: Option<&str>) -> Result<()> {
+    Err(Error::Validation(String::from(
+        "Backups require the crate to be built with '--features sql'",
+    )))
+}
+
+//Handles `backup` and `restore <file>`, restoring must be done while the server is stopped
+#[cfg(feature = "sql")]
+fn backup_command(config: &Config, command: &str, file: Option<&str>) -> Result<()> {
+    let backups = SqliteBackup::new(database_path(config), &config.backup);
+    match (command, file) {
+        ("backup", _) => {
+            let backup = backups.create()?;
+            println!("Backed up database to {:?}", backup.path);
+        }
+        ("restore", Some(file)) => {
+            let restored = backups.restore(std::path::Path::new(file))?;
+            println!(
+                "Restored {file} at schema version {}, the replaced database was kept as {:?}",
+                restored.schema_version,
+                restored.path.with_extension("pre-restore")
+            );
+        }
+        _ => {
+            return Err(Error::Validation(String::from(
+                "Expected the backup file to restore, as in 'restore <file>'",
+            )))
+        }
+    }
+    Ok(())
+}
+
+//Handles `export <session_id> <file> [from] [to]`, the format follows the extension of the file
+fn export_command(config: &Config, args: &[String]) -> Result<()> {
+    let (session_id, file) = match args {
+        [session_id, file, ..] => (session_id.parse::<i64>()?, std::path::Path::new(file)),
+        _ => {
+            return Err(Error::Validation(String::from(
+                "Expected the session and file to export, as in 'export <session_id> <file> [from] [to]'",
+            )))
+        }
+    };
+    let query = ExportQuery::new(session_id, Vec::new(), args.get(2).map(String::as_str), args.get(3).map(String::as_str))?;
+    let database = open_database(config)?;
+    #[cfg(any(feature = "sql", feature = "postgres"))]
+    database.init();
+    query.export_to_file(&database, file)?;
+    println!("Exported session {session_id} to {file:?}");
+    Ok(())
+}
+
+//Handles `import <session_id> <file> [sensors] [timezone]`, the format follows the extension of the file
+//sensors is the sensor id of every line, or label:sensor_id pairs for the labels of the file's sensor_id column
+fn import_command(config: &Config, args: &[String]) -> Result<()> {
+    let (session_id, file) = match args {
+        [session_id, file, ..] => (session_id.parse::<i64>()?, std::path::Path::new(file)),
+        _ => {
+            return Err(Error::Validation(String::from(
+                "Expected the session and file to import, as in 'import <session_id> <file> [sensors] [timezone]'",
+            )))
         }
     };
+    let mut options = ImportOptions::new(ImportFormat::from_path(file)?);
+    match args.get(2) {
+        Some(sensors) if sensors.contains(':') => options.sensors = ImportOptions::parse_sensors(sensors)?,
+        Some(sensor) => options.sensor = Some(sensor.parse::<i64>()?),
+        None => {}
+    }
+    if let Some(timezone) = args.get(3) {
+        options.timezone = timezone
+            .parse()
+            .map_err(|_| Error::Validation(format!("Unknown timezone '{timezone}', expected a name such as UTC or Europe/Berlin")))?;
+    }
+    let reader = fs::File::open(file).map_err(|e| Error::Io(format!("Failed to open {file:?}: {e}")))?;
 
-    wait_for_connections(&database, listener);
+    let database = open_database(config)?;
+    #[cfg(any(feature = "sql", feature = "postgres"))]
+    database.init();
+    let import = SessionImport::new(&database, session_id, options)?;
+    let mut reported = 0;
+    let report = import.run(std::io::BufReader::new(reader), |report| {
+        for error in &report.errors[reported..] {
+            eprintln!("Line {}: {}", error.line, error.message);
+        }
+        reported = report.errors.len();
+        println!(
+            "Read {} lines, {} imported, {} duplicates, {} quarantined, {} failed",
+            report.lines, report.imported, report.duplicates, report.quarantined, report.failed
+        );
+    })?;
+    // lines that failed after the last batch was written
+    for error in &report.errors[reported..] {
+        eprintln!("Line {}: {}", error.line, error.message);
+    }
+    println!(
+        "Imported {} of {} lines of {file:?} into session {session_id}, {} duplicates skipped, {} quarantined, {} failed",
+        report.imported, report.lines, report.duplicates, report.quarantined, report.failed
+    );
+    Ok(())
+}
+
+//Binds the udp listener and moves it to its own thread, returning what stops it
+fn init_udp_listener(
+    config: &Config,
+    udp_addr: &str,
+    database: Arc<dyn Database>,
+    udp_stats: Arc<Mutex<UdpStats>>,
+    hub: Arc<LiveHub>,
+    schemas: Arc<SchemaRegistry>,
+    alerts: Arc<AlertEngine>,
+) -> Result<UdpShutdown> {
+    if config.udp_secret.is_empty() {
+        return Err(Error::Validation(String::from(
+            "Failed to start udp listener: 'udp_secret' must be set when 'udp_addr' is configured",
+        )));
+    }
+
+    let udp_listener = UdpListener::bind(
+        udp_addr,
+        &config.udp_secret,
+        config.udp_batch_size,
+        Duration::from_millis(config.udp_flush_ms),
+        udp_stats,
+        hub,
+        schemas,
+    )?
+    .with_alerts(alerts);
+
+    println!("Udp listener listening on '{}'", udp_listener.local_addr()?);
+    Ok(udp_listener.start(database))
 }
 
 //Returns a tcp listener on success or error string on failure
@@ -130,23 +568,80 @@ fn init_server(address: Address) -> Result<(TcpListener, Address)> {
     let addr_str = address.to_string();
     Ok((
         TcpListener::bind(addr_str).map_err(|error| {
-            format!("Failed to bind server at address {addr_str}, Error: {error}")
+            Error::Io(format!("Failed to bind server at address {addr_str}, Error: {error}"))
         })?,
         address,
     ))
 }
 
-//Forever wait for connections on the listener
-fn wait_for_connections(database: &dyn Database, listener: TcpListener) {
-    listener
-        .incoming()
-        .for_each(|stream_result| match stream_result {
-            Ok(stream) => handle_connection(database, stream),
-            Err(error) => eprintln!("Error occured when establishing connection. Error: {error}"),
-        });
+//Everything the http workers share while the server runs
+#[derive(Clone, Copy)]
+struct ServerState<'a> {
+    database: &'a Arc<dyn Database>,
+    udp_stats: &'a Mutex<UdpStats>,
+    hub: &'a Arc<LiveHub>,
+    ingest: &'a IngestBuffer,
+    schemas: &'a SchemaRegistry,
+    alerts: &'a AlertEngine,
+    retention: &'a RetentionPolicy,
+    // only the SQLite database is backed up
+    backups: Option<&'a SqliteBackup>,
+}
+
+//Forever wait for connections on the listener, each connection is handled by the next free worker
+fn wait_for_connections(state: ServerState, listener: TcpListener, workers: usize) {
+    let (sender, receiver) = mpsc::channel::<TcpStream>();
+    let receiver = Mutex::new(receiver);
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| loop {
+                // the lock is released before handling so other workers can take the next stream
+                let stream = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+                match stream {
+                    // a panic while handling one request must not take the worker down with it
+                    Ok(stream) => {
+                        if std::panic::catch_unwind(AssertUnwindSafe(|| {
+                            handle_connection(state, stream)
+                        }))
+                        .is_err()
+                        {
+                            eprintln!("Worker recovered from a panic while handling a connection");
+                        }
+                    }
+                    Err(_) => return,
+                }
+            });
+        }
+
+        listener
+            .incoming()
+            .for_each(|stream_result| match stream_result {
+                Ok(stream) => {
+                    if sender.send(stream).is_err() {
+                        eprintln!("Failed to hand connection to a worker, all workers have stopped");
+                    }
+                }
+                Err(error) => eprintln!("Error occured when establishing connection. Error: {error}"),
+            });
+        drop(sender);
+    });
 }
 
-fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
+fn handle_connection(state: ServerState, mut stream: TcpStream) {
+    let ServerState {
+        database: shared_database,
+        udp_stats,
+        hub,
+        ingest,
+        schemas,
+        alerts,
+        retention,
+        backups,
+    } = state;
+    let database = shared_database.as_ref();
     // allocate buffer to hold request
     let mut buffer = vec![0; 1_024]; //1_500_000
     let mut total_bytes = 0;
@@ -159,7 +654,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
             Ok(0) => {
                 tries -= 1;
                 if tries <= 0 {
-                    break Err(format!("Connection closed after {tries} attempts."));
+                    break Err(Error::Io(format!("Connection closed after {tries} attempts.")));
                 }
                 std::thread::sleep(std::time::Duration::from_millis(500));
             }
@@ -180,14 +675,14 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                     if let HttpMethod::Options = parsed_request.method {
                         match parsed_request.headers.get(HttpHeaderType::Origin.as_str()) {
                             Some(origin) if HttpHeader::AC_ORIGINS.contains(&origin.as_str()) => {},
-                            Some(invalid_origin) => break Err(format!(
+                            Some(invalid_origin) => break Err(Error::Validation(format!(
                                     "Request origin invalid: expected one of [{}], recieved '{invalid_origin}'",
                                     HttpHeader::AC_ORIGINS.join(", ")
-                                )),
-                            None => break Err(format!(
+                                ))),
+                            None => break Err(Error::Validation(format!(
                                 "Request origin invalid: expected one of [{}], recieved None",
                                 HttpHeader::AC_ORIGINS.join(", ")
-                            )),
+                            ))),
                         }
                     }
 
@@ -198,10 +693,10 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                         Some(length) => match length.trim().parse::<usize>() {
                             Ok(num) => num,
                             Err(e) => {
-                                break Err(format!(
+                                break Err(Error::Parse(format!(
                                     "Failed to parse '{}' header to usize: {e}",
                                     HttpHeaderType::ContentLength.as_str()
-                                ))
+                                )))
                             }
                         },
                         None => {
@@ -213,7 +708,30 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                         }
                     };
 
+                    if body_size > MAX_BODY_SIZE {
+                        break Err(Error::PayloadTooLarge(format!(
+                            "Request body of {body_size} bytes exceeds the limit of {MAX_BODY_SIZE} bytes"
+                        )));
+                    }
+                    // imported files are kept as text, every other body is json
+                    let is_import = matches!(parsed_request.path, HttpPath::Import(_));
                     if body_size > 0 {
+                        match parsed_request.headers.get(HttpHeaderType::ContentType.as_str()) {
+                            Some(content_type) if is_import && !is_import_media_type(content_type) => {
+                                break Err(Error::UnsupportedMediaType(format!(
+                                    "Unsupported content type '{}', expected text/csv or application/x-ndjson",
+                                    content_type.trim()
+                                )))
+                            }
+                            Some(content_type) if !is_import && !is_json_media_type(content_type) => {
+                                break Err(Error::UnsupportedMediaType(format!(
+                                    "Unsupported content type '{}', expected application/json",
+                                    content_type.trim()
+                                )))
+                            }
+                            _ => {}
+                        }
+
                         let body_recieved = total_bytes - delim_index;
                         let request_len = total_bytes - body_recieved + body_size;
 
@@ -229,9 +747,12 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                         total_bytes += body_size - body_recieved;
 
                         // add body into request struct
-                        if let Err(e) = parsed_request
-                            .parse_body(&buffer[delim_index..(delim_index + body_size)])
-                        {
+                        let body = &buffer[delim_index..(delim_index + body_size)];
+                        let parsed = match is_import {
+                            true => parsed_request.parse_text_body(body),
+                            false => parsed_request.parse_body(body),
+                        };
+                        if let Err(e) = parsed {
                             eprintln!("Failed to parse request body: {e}");
                         }
                     }
@@ -246,11 +767,24 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
     };
     println!("{total_bytes} total bytes read\n");
 
-    let (origin, response) = match request_option {
-        Err(e) => (
-            String::new(),
-            HttpResponse::bad_request(&format!("Failed to parse request on the server: {e}")),
+    let (request_id, instance) = match &request_option {
+        Ok(request) => (
+            request.request_id(),
+            Some(request.path.to_string() + &request.parameters_to_string()),
         ),
+        Err(_) => (HttpRequest::generate_request_id(), None),
+    };
+
+    //websocket connections outlive the request so they are moved to their own thread
+    if let Ok(request) = &request_option {
+        if matches!(request.path, HttpPath::Live(_)) && WebSocket::is_upgrade(request) {
+            upgrade_connection(request, &request_id, hub, stream);
+            return;
+        }
+    }
+
+    let (origin, mut response) = match request_option {
+        Err(e) => (String::new(), HttpResponse::from_error(&e)),
         Ok(request) => {
             //construct response from possible pathways
             let gen_view =
@@ -259,7 +793,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
             (
                 match request.headers.get(HttpHeaderType::Origin.as_str()) {
                     Some(origin) => origin.to_string(),
-                    None => HttpHeader::AC_ORIGINS.get(0).unwrap_or(&"").to_string(),
+                    None => HttpHeader::AC_ORIGINS.first().unwrap_or(&"").to_string(),
                 },
                 match request.path.clone() {
                     HttpPath::Index(_subpath) => gen_view("index.html"),
@@ -276,7 +810,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                         ),
                                         Err(_) => HttpResponse::not_authorized(),
                                     },
-                                    Err(msg) => HttpResponse::invalid_body(Some(&msg)),
+                                    Err(e) => HttpResponse::invalid_body(Some(e.message())),
                                 },
                                 None => HttpResponse::missing_body(Some(User::REQUIRED_VALUES)),
                             },
@@ -304,7 +838,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             },
                             _ => HttpResponse::json_404(&request.path.to_string()),
                         },
-                        _ => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("POST"),
                     },
                     HttpPath::User(subpath) => match request.method {
                         HttpMethod::Get => match HttpPath::subsection(&subpath, 0) {
@@ -318,7 +852,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                             Ok(users) => HttpResponse::from_vec(
                                                 json!({"users": users.iter().map(|user| user.get_username()).collect::<Vec<_>>()}).to_string()
                                             ),
-                                            Err(_) => HttpResponse::bad_request("Failed to fetch users from database.")
+                                            Err(e) => HttpResponse::from_error(&e)
                                         }
                                         }
                                     }
@@ -335,17 +869,20 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             },
                             Some(username) => match database.get_user(username) {
                                 Ok(user) => user.to_ok_response(),
-                                Err(_) => HttpResponse::json_404(&request.path.to_string()),
+                                Err(e) => HttpResponse::from_error(&e),
                             },
                         },
                         HttpMethod::Post => User::insert_model(database, request.body),
                         HttpMethod::Patch => User::update_model(database, &subpath, request.body),
                         HttpMethod::Delete => User::delete_model(database, &subpath),
                         HttpMethod::Options => HttpResponse::options_response(),
-                        HttpMethod::Error => HttpResponse::json_404(&request.path.to_string()),
+                        HttpMethod::Error => HttpResponse::method_not_allowed(HttpHeader::AC_ALLOWED_METHODS),
                     },
                     HttpPath::Sensor(subpath) => {
                         match request.method {
+                            method if HttpPath::subsection(&subpath, 1) == Some("calibrations") => {
+                                SensorCalibration::calibrations_model(database, method, &subpath, request.body)
+                            }
                             HttpMethod::Get => {
                                 match HttpPath::subsection(&subpath, 0) {
                                     None => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
@@ -355,11 +892,8 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                                     HttpResponse::forbidden()
                                                 } else {
                                                     match database.get_sensors() {
-                                                        Ok(sensors) => HttpResponse::from_vec(json!({"sensors": sensors.iter().map(|sensor| json!({
-                                                            "id": sensor.get_id(),
-                                                            "type": sensor.get_sensor_type()
-                                                        })).collect::<Vec<_>>()}).to_string()),
-                                                        Err(_) => HttpResponse::bad_request("failed to fetch sensors from database."),
+                                                        Ok(sensors) => HttpResponse::from_vec(json!({"sensors": sensors.iter().map(Sensor::to_json).collect::<Vec<_>>()}).to_string()),
+                                                        Err(e) => HttpResponse::from_error(&e),
                                                     }
                                                 }
                                             }
@@ -370,7 +904,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                     Some(sensor_id) => match sensor_id.parse::<i64>() {
                                     Ok(sensor_id) => match database.get_sensor(sensor_id) {
                                             Ok(sensor) => sensor.to_ok_response(),
-                                            Err(_) => HttpResponse::json_404(&request.path.to_string()),
+                                            Err(e) => HttpResponse::from_error(&e),
                                     },
                                     Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
                                     },
@@ -380,7 +914,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             HttpMethod::Patch => Sensor::update_model(database, &subpath, request.body),
                             HttpMethod::Delete => Sensor::delete_model(database, &subpath),
                             HttpMethod::Options => HttpResponse::options_response(),
-                            HttpMethod::Error => HttpResponse::json_404(&request.path.to_string()),
+                            HttpMethod::Error => HttpResponse::method_not_allowed(HttpHeader::AC_ALLOWED_METHODS),
                         }
                     }
                     HttpPath::Session(subpath) => {
@@ -393,11 +927,8 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                                 HttpResponse::forbidden()
                                             } else {
                                                 match database.get_all_sessions() {
-                                            Ok(sessions) => HttpResponse::from_vec(json!({"sessions": sessions.iter().map(|session| json!({
-                                                "session_id": session.get_id(),
-                                                "username": session.get_username()
-                                            })).collect::<Vec<_>>()}).to_string()),
-                                            Err(_) => HttpResponse::bad_request("failed to fetch sessions from the database."),
+                                            Ok(sessions) => HttpResponse::from_vec(json!({"sessions": sessions.iter().map(Session::to_json).collect::<Vec<_>>()}).to_string()),
+                                            Err(e) => HttpResponse::from_error(&e),
                                         }
                                             }
                                         }
@@ -406,17 +937,12 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                     None => HttpResponse::not_authorized(),
                                 },
                                 Some("user") => match HttpPath::subsection(&subpath, 1) {
-                                    Some(username) => match database.get_user_sessions(&username) {
+                                    Some(username) => match database.get_user_sessions(username) {
                                         Ok(sessions) => HttpResponse::from_vec(
-                                            json!({"sessions": sessions.iter().map(|session| json!({
-                                            "session_id": session.get_id(),
-                                            "username": session.get_username()
-                                        })).collect::<Vec<_>>()})
-                                            .to_string(),
-                                        ),
-                                        Err(_) => HttpResponse::bad_request(
-                                            "failed to fetch user sessions from the database.",
+                                            json!({"sessions": sessions.iter().map(Session::to_json).collect::<Vec<_>>()})
+                                                .to_string(),
                                         ),
+                                        Err(e) => HttpResponse::from_error(&e),
                                     },
                                     None => HttpResponse::json_404(&request.path.to_string()),
                                 },
@@ -424,21 +950,32 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                     Some(session_id) => match session_id.parse::<i64>() {
                                     Ok(session_id) => match database.get_session(session_id) {
                                             Ok(session) => session.to_ok_response(),
-                                            Err(_) => HttpResponse::bad_request(
-                                                "failed to fetch session from the database.",
-                                            ),
+                                            Err(e) => HttpResponse::from_error(&e),
                                     },
                                     Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
                                     },
                                     None => HttpResponse::json_404(&request.path.to_string()),
                                 },
+                                Some("active") => match (HttpPath::subsection(&subpath, 1), HttpPath::subsection(&subpath, 2)) {
+                                    (Some("sensor"), Some(sensor_id)) => match sensor_id.parse::<i64>() {
+                                        Ok(sensor_id) => Session::active_response(database, sensor_id),
+                                        Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                                    },
+                                    _ => HttpResponse::json_404(&request.path.to_string()),
+                                },
+                                Some(_) if HttpPath::subsection(&subpath, 1) == Some("summary") => {
+                                    SessionSummary::summary_model(database, &subpath, &request)
+                                }
                                 _ => HttpResponse::json_404(&request.path.to_string()),
                             },
-                            HttpMethod::Post => Session::insert_model(database, request.body),
+                            HttpMethod::Post => match HttpPath::subsection(&subpath, 0) {
+                                None => Session::insert_model(database, request.body),
+                                Some(_) => Session::transition_model(database, &subpath),
+                            },
                             HttpMethod::Patch => Session::update_model(database, &subpath, request.body),
                             HttpMethod::Delete => Session::delete_model(database, &subpath),
                             HttpMethod::Options => HttpResponse::options_response(),
-                            HttpMethod::Error => HttpResponse::json_404(&request.path.to_string()),
+                            HttpMethod::Error => HttpResponse::method_not_allowed(HttpHeader::AC_ALLOWED_METHODS),
                         }
                     }
                     HttpPath::SessionSensor(subpath) => match request.method {
@@ -457,9 +994,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                                         "sensor_id": session_sensor.get_sensor_id(),
                                                     })
                                                 }).collect::<Vec<_>>()}).to_string()),
-                                                Err(_) => HttpResponse::bad_request(
-                                                    "failed to fetch sessions sensors from the database.",
-                                                ),
+                                                Err(e) => HttpResponse::from_error(&e),
                                             }
                                         }
                                     }
@@ -477,9 +1012,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                                 "sensor_id": session_sensor.get_sensor_id(),
                                             })
                                         }).collect::<Vec<_>>()}).to_string()),
-                                        Err(_) => HttpResponse::bad_request(
-                                            "failed to fetch session sensors from the database.",
-                                        ),
+                                        Err(e) => HttpResponse::from_error(&e),
                                     },
                                 Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
                             }
@@ -489,9 +1022,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                 Some(session_sensor_id) => match session_sensor_id.parse::<i64>() {
                                 Ok(session_sensor_id) => match database.get_session_sensor(session_sensor_id) {
                                         Ok(session_sensor) => session_sensor.to_ok_response(),
-                                        Err(_) => HttpResponse::bad_request(
-                                            "failed to fetch session sensor from the database.",
-                                        ),
+                                        Err(e) => HttpResponse::from_error(&e),
                                 },
                                 Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
                                 },
@@ -503,7 +1034,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                         HttpMethod::Patch => SessionSensor::update_model(database, &subpath, request.body),
                         HttpMethod::Delete => SessionSensor::delete_model(database, &subpath),
                         HttpMethod::Options => HttpResponse::options_response(),
-                        HttpMethod::Error => HttpResponse::json_404(&request.path.to_string()),
+                        HttpMethod::Error => HttpResponse::method_not_allowed(HttpHeader::AC_ALLOWED_METHODS),
                     },
                     HttpPath::SessionSensorData(subpath) => match request.method {
                         HttpMethod::Get => match HttpPath::subsection(&subpath, 0) {
@@ -514,14 +1045,8 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                                             HttpResponse::forbidden()
                                         } else {
                                             match database.get_sessions_sensors_data() {
-                                            Ok(sessions_sensors_data) => HttpResponse::from_vec(json!({ "datapoints": sessions_sensors_data.iter().map(|session_sensor_data| {
-                                                json!({
-                                                    "id": session_sensor_data.get_id(),
-                                                    "datetime": session_sensor_data.get_datetime(),
-                                                    "data_blob": session_sensor_data.get_blob(),
-                                                })
-                                            }).collect::<Vec<_>>()}).to_string()),
-                                            Err(_) => HttpResponse::bad_request("failed to fetch session sensor data from the database."),
+                                            Ok(sessions_sensors_data) => Calibrator::list_response(database, &sessions_sensors_data, &request),
+                                            Err(e) => HttpResponse::from_error(&e),
                                         }
                                         }
                                     }
@@ -532,33 +1057,27 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             Some("session") => match HttpPath::subsection(&subpath, 1) {
                                 Some(session_id) => match session_id.parse::<i64>() {
                                 Ok(session_id) => match HttpPath::subsection(&subpath, 2) {
+                                    Some("sensor") => match HttpPath::subsection(&subpath, 3).map(str::parse::<i64>) {
+                                        Some(Ok(sensor_id)) => match database.get_session_sensors(session_id) {
+                                            Ok(session_sensors) => match session_sensors.iter().find(|session_sensor| *session_sensor.get_sensor_id() == sensor_id) {
+                                                Some(session_sensor) => match database.get_session_sensor_data(*session_sensor.get_id()) {
+                                                    Ok(session_sensor_data) => Calibrator::list_response(database, &session_sensor_data, &request),
+                                                    Err(e) => HttpResponse::from_error(&e),
+                                                },
+                                                None => HttpResponse::json_404(&format!("Sensor {sensor_id} in session {session_id}")),
+                                            },
+                                            Err(e) => HttpResponse::from_error(&e),
+                                        },
+                                        Some(Err(e)) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                                        None => HttpResponse::json_404(&request.path.to_string()),
+                                    },
                                     Some(datetime) => match database.get_sessions_sensor_data_after(session_id, datetime) {
-                                        Ok(session_sensor_data) => HttpResponse::from_vec(json!({
-                                            "datapoints": session_sensor_data.iter().map(|session_sensor_data| {
-                                                json!({
-                                                    "id": session_sensor_data.get_id(),
-                                                    "datetime": session_sensor_data.get_datetime(),
-                                                    "data_blob": session_sensor_data.get_blob(),
-                                                })
-                                            }).collect::<Vec<_>>()
-                                        }).to_string()),
-                                        Err(_) => HttpResponse::json_404(&request.path.to_string()),
+                                        Ok(session_sensor_data) => Calibrator::list_response(database, &session_sensor_data, &request),
+                                        Err(e) => HttpResponse::from_error(&e),
                                     },
                                     None => match database.get_sessions_sensor_data(session_id) {
-                                        Ok(sessions_sensor_data) => {
-                                            //TODO: Remove this later as it temporary supports the data flow while waiting for Pi_Transmit
-                                            if session_id == 1 {
-                                                database.temp_session_id_solution();
-                                            }
-                                            HttpResponse::from_vec(json!({ "datapoints": sessions_sensor_data.iter().map(|session_sensor_data| {
-                                                    json!({
-                                                        "id": session_sensor_data.get_id(),
-                                                        "datetime": session_sensor_data.get_datetime(),
-                                                        "data_blob": session_sensor_data.get_blob(),
-                                                    })
-                                                }).collect::<Vec<_>>()}).to_string())
-                                        },
-                                            Err(_) => HttpResponse::json_404(&request.path.to_string()),
+                                        Ok(sessions_sensor_data) => Calibrator::list_response(database, &sessions_sensor_data, &request),
+                                        Err(e) => HttpResponse::from_error(&e),
                                     },
                                 },
                                 Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
@@ -568,14 +1087,8 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             Some("id") => match HttpPath::subsection(&subpath, 1) {
                                 Some(session_sensor_id) => match session_sensor_id.parse::<i64>() {
                                 Ok(session_sensor_id) => match database.get_session_sensor_data(session_sensor_id) {
-                                        Ok(session_sensor_data) => HttpResponse::from_vec(json!({ "datapoints": session_sensor_data.iter().map(|session_sensor_data| {
-                                            json!({
-                                                "id": session_sensor_data.get_id(),
-                                                "datetime": session_sensor_data.get_datetime(),
-                                                "data_blob": session_sensor_data.get_blob(),
-                                            })
-                                        }).collect::<Vec<_>>()}).to_string()),
-                                        Err(_) => HttpResponse::json_404(&request.path.to_string()),
+                                        Ok(session_sensor_data) => Calibrator::list_response(database, &session_sensor_data, &request),
+                                        Err(e) => HttpResponse::from_error(&e),
                                 },
                                 Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
                                 },
@@ -583,9 +1096,9 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             },
                             Some(session_sensor_id) => match session_sensor_id.parse::<i64>() {
                             Ok(session_sensor_id) => match HttpPath::subsection(&subpath, 1) {
-                                    Some(datetime) => match database.get_session_sensor_datapoint(session_sensor_id, &datetime) {
-                                        Ok(datapoint) => datapoint.to_ok_response(),
-                                        Err(_) => HttpResponse::json_404(&request.path.to_string()),
+                                    Some(datetime) => match database.get_session_sensor_datapoint(session_sensor_id, datetime) {
+                                        Ok(datapoint) => Calibrator::datapoint_response(database, &datapoint, &request),
+                                        Err(e) => HttpResponse::from_error(&e),
                                     },
                                     None => HttpResponse::json_404(&request.path.to_string()),
                             },
@@ -593,14 +1106,271 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
                             },
                         },
                         HttpMethod::Post => match subpath.as_str() {
-                            "" => SessionSensorData::insert_model(database, request.body),
-                            "/batch" => SessionSensorData::try_batch_model(database, request.body),
+                            "" => SessionSensorData::try_insert_model(database, ingest, schemas, request.parameter("wait") == Some("commit"), request.body),
+                            "/batch" => SessionSensorData::try_batch_model(database, ingest, schemas, request.parameter("wait") == Some("commit"), request.body),
                             _ => HttpResponse::json_404(&request.path.to_string()),
                         },
                         HttpMethod::Patch => SessionSensorData::update_model(database, &subpath, request.body),
                         HttpMethod::Delete => SessionSensorData::delete_model(database, &subpath),
                         HttpMethod::Options => HttpResponse::options_response(),
-                        HttpMethod::Error => HttpResponse::json_404(&request.path.to_string()),
+                        HttpMethod::Error => HttpResponse::method_not_allowed(HttpHeader::AC_ALLOWED_METHODS),
+                    },
+                    HttpPath::Telemetry(subpath) => match request.method {
+                        HttpMethod::Get => match HttpPath::subsection(&subpath, 0) {
+                            None => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                                Some(session_id) => match database.get_session_user(&session_id) {
+                                    Ok(user) => {
+                                        if !database.is_admin(&user) {
+                                            HttpResponse::forbidden()
+                                        } else {
+                                            match udp_stats.lock() {
+                                                Ok(stats) => HttpResponse::from_vec(stats.to_json().to_string()),
+                                                Err(_) => HttpResponse::from_error(&UdpStats::poisoned()),
+                                            }
+                                        }
+                                    }
+                                    Err(_) => HttpResponse::not_authorized(),
+                                },
+                                None => HttpResponse::not_authorized(),
+                            },
+                            Some("session") => match HttpPath::subsection(&subpath, 1) {
+                                Some(session_id) => match session_id.parse::<i64>() {
+                                    Ok(session_id) => match udp_stats.lock().map(|stats| stats.session_json(session_id)) {
+                                        Ok(Some(session_stats)) => HttpResponse::from_vec(session_stats.to_string()),
+                                        Ok(None) => HttpResponse::json_404(&request.path.to_string()),
+                                        Err(_) => HttpResponse::from_error(&UdpStats::poisoned()),
+                                    },
+                                    Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                                },
+                                None => HttpResponse::json_404(&request.path.to_string()),
+                            },
+                            _ => HttpResponse::json_404(&request.path.to_string()),
+                        },
+                        HttpMethod::Options => HttpResponse::options_response(),
+                        _ => HttpResponse::method_not_allowed("GET, OPTIONS"),
+                    },
+                    HttpPath::Retention(subpath) => match (request.method, HttpPath::subsection(&subpath, 0)) {
+                        (HttpMethod::Get | HttpMethod::Post, Some("preview")) => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                            Some(session_id) => match database.get_session_user(&session_id) {
+                                Ok(user) => {
+                                    if !database.is_admin(&user) {
+                                        HttpResponse::forbidden()
+                                    } else {
+                                        // a posted policy is previewed in place of the configured one
+                                        match request.body {
+                                            Some(json) => match serde_json::from_value::<RetentionPolicy>(json) {
+                                                Ok(policy) => policy.preview_response(database),
+                                                Err(e) => HttpResponse::invalid_body(Some(&e.to_string())),
+                                            },
+                                            None => retention.preview_response(database),
+                                        }
+                                    }
+                                }
+                                Err(_) => HttpResponse::not_authorized(),
+                            },
+                            None => HttpResponse::not_authorized(),
+                        },
+                        (HttpMethod::Get, Some("rollups")) => match HttpPath::subsection(&subpath, 1) {
+                            Some(session_sensor_id) => match session_sensor_id.parse::<i64>() {
+                                Ok(session_sensor_id) => match database.get_session_sensor_rollups(session_sensor_id) {
+                                    Ok(rollups) => HttpResponse::from_vec(
+                                        json!({"rollups": rollups.iter().map(SessionSensorRollup::to_json).collect::<Vec<_>>()}).to_string()
+                                    ),
+                                    Err(e) => HttpResponse::from_error(&e),
+                                },
+                                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                            },
+                            None => HttpResponse::json_404(&request.path.to_string()),
+                        },
+                        (HttpMethod::Options, _) => HttpResponse::options_response(),
+                        (HttpMethod::Get | HttpMethod::Post, _) => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("GET, POST, OPTIONS"),
+                    },
+                    HttpPath::Backup(subpath) => match (request.method, HttpPath::subsection(&subpath, 0)) {
+                        (method @ (HttpMethod::Get | HttpMethod::Post), None) => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                            Some(session_id) => match database.get_session_user(&session_id) {
+                                Ok(user) => {
+                                    if !database.is_admin(&user) {
+                                        HttpResponse::forbidden()
+                                    } else {
+                                        match (backups, method) {
+                                            (None, _) => HttpResponse::from_error(&Error::Unavailable(String::from(
+                                                "Backups require the server to be built with '--features sql'",
+                                            ))),
+                                            (Some(backups), HttpMethod::Post) => match backups.create() {
+                                                Ok(backup) => HttpResponse::new(
+                                                    HttpStatus::Created,
+                                                    HttpHeader::default_json(),
+                                                    backup.to_json().to_string(),
+                                                ),
+                                                Err(e) => HttpResponse::from_error(&e),
+                                            },
+                                            (Some(backups), _) => match backups.list() {
+                                                Ok(list) => HttpResponse::from_vec(
+                                                    json!({"backups": list.iter().map(|backup| backup.to_json()).collect::<Vec<_>>()}).to_string()
+                                                ),
+                                                Err(e) => HttpResponse::from_error(&e),
+                                            },
+                                        }
+                                    }
+                                }
+                                Err(_) => HttpResponse::not_authorized(),
+                            },
+                            None => HttpResponse::not_authorized(),
+                        },
+                        (HttpMethod::Options, _) => HttpResponse::options_response(),
+                        (HttpMethod::Get | HttpMethod::Post, _) => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("GET, POST, OPTIONS"),
+                    },
+                    HttpPath::Export(subpath) => match (&request.method, HttpPath::subsection(&subpath, 0)) {
+                        (HttpMethod::Get, Some("session")) => match HttpPath::subsection(&subpath, 1) {
+                            Some(session_id) => match session_id.parse::<i64>() {
+                                Ok(session_id) => ExportQuery::response(shared_database, session_id, &request),
+                                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                            },
+                            None => HttpResponse::json_404(&request.path.to_string()),
+                        },
+                        (HttpMethod::Options, _) => HttpResponse::options_response(),
+                        (HttpMethod::Get, _) => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("GET, OPTIONS"),
+                    },
+                    HttpPath::Import(subpath) => match (&request.method, HttpPath::subsection(&subpath, 0)) {
+                        (HttpMethod::Post, Some("session")) => match HttpPath::subsection(&subpath, 1) {
+                            Some(session_id) => match session_id.parse::<i64>() {
+                                Ok(session_id) => SessionImport::response(database, session_id, &request),
+                                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                            },
+                            None => HttpResponse::json_404(&request.path.to_string()),
+                        },
+                        (HttpMethod::Options, _) => HttpResponse::options_response(),
+                        (HttpMethod::Post, _) => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("POST, OPTIONS"),
+                    },
+                    HttpPath::SensorSchema(subpath) => match (request.method, HttpPath::subsection(&subpath, 0)) {
+                        // devices may read the schema their blobs are checked against
+                        (HttpMethod::Get, Some(sensor_type)) => match database.get_sensor_schema(sensor_type) {
+                            Ok(schema) => HttpResponse::from_vec(schema.to_json().to_string()),
+                            Err(e) => HttpResponse::from_error(&e),
+                        },
+                        (method @ (HttpMethod::Get | HttpMethod::Post), None) | (method @ HttpMethod::Delete, Some(_)) => {
+                            match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                                Some(session_id) => match database.get_session_user(&session_id) {
+                                    Ok(user) => {
+                                        if !database.is_admin(&user) {
+                                            HttpResponse::forbidden()
+                                        } else {
+                                            match (method, HttpPath::subsection(&subpath, 0)) {
+                                                (HttpMethod::Post, _) => match request.body {
+                                                    Some(json) => match serde_json::from_value::<SensorSchema>(json) {
+                                                        Ok(schema) => match schemas.put(database, schema) {
+                                                            Ok(schema) => HttpResponse::from_vec(schema.to_json().to_string()),
+                                                            Err(e) => HttpResponse::from_error(&e),
+                                                        },
+                                                        Err(e) => HttpResponse::invalid_body(Some(&e.to_string())),
+                                                    },
+                                                    None => HttpResponse::missing_body(Some(" Requires a sensor_type and a schema")),
+                                                },
+                                                (HttpMethod::Delete, Some(sensor_type)) => match schemas.remove(database, sensor_type) {
+                                                    Ok(()) => HttpResponse::no_content(),
+                                                    Err(e) => HttpResponse::from_error(&e),
+                                                },
+                                                _ => match database.get_sensor_schemas() {
+                                                    Ok(list) => HttpResponse::from_vec(
+                                                        json!({"sensor_schemas": list.iter().map(SensorSchema::to_json).collect::<Vec<_>>()}).to_string()
+                                                    ),
+                                                    Err(e) => HttpResponse::from_error(&e),
+                                                },
+                                            }
+                                        }
+                                    }
+                                    Err(_) => HttpResponse::not_authorized(),
+                                },
+                                None => HttpResponse::not_authorized(),
+                            }
+                        }
+                        (HttpMethod::Options, _) => HttpResponse::options_response(),
+                        (HttpMethod::Delete, None) => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("GET, POST, DELETE, OPTIONS"),
+                    },
+                    HttpPath::Alert(subpath) => match request.method {
+                        HttpMethod::Options => HttpResponse::options_response(),
+                        method => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                            Some(cookie) => match database.get_session_user(&cookie) {
+                                Ok(user) if database.is_admin(&user) => alerts.alerts_model(database, method, &subpath, request.body),
+                                Ok(_) => HttpResponse::forbidden(),
+                                Err(_) => HttpResponse::not_authorized(),
+                            },
+                            None => HttpResponse::not_authorized(),
+                        },
+                    },
+                    HttpPath::DerivedChannel(subpath) => match request.method {
+                        HttpMethod::Options => HttpResponse::options_response(),
+                        method => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                            Some(cookie) => match database.get_session_user(&cookie) {
+                                // every user may read the channels and try expressions out, only admins change them
+                                Ok(user) => match (&method, HttpPath::subsection(&subpath, 0)) {
+                                    (HttpMethod::Get, _) | (HttpMethod::Post, Some("evaluate")) => DerivedChannel::derived_channels_model(database, method, &subpath, request.body),
+                                    _ if database.is_admin(&user) => DerivedChannel::derived_channels_model(database, method, &subpath, request.body),
+                                    _ => HttpResponse::forbidden(),
+                                },
+                                Err(_) => HttpResponse::not_authorized(),
+                            },
+                            None => HttpResponse::not_authorized(),
+                        },
+                    },
+                    HttpPath::Quarantine(subpath) => match (request.method, HttpPath::subsection(&subpath, 0)) {
+                        (method @ (HttpMethod::Get | HttpMethod::Delete), Some("session")) => match HttpPath::subsection(&subpath, 1) {
+                            Some(session_id) => match session_id.parse::<i64>() {
+                                Ok(session_id) => match request.headers.get_cookie(HttpHeaderType::SessionID.as_str()) {
+                                    Some(cookie) => match database.get_session_user(&cookie) {
+                                        Ok(user) => {
+                                            if !database.is_admin(&user) {
+                                                HttpResponse::forbidden()
+                                            } else if matches!(method, HttpMethod::Delete) {
+                                                match database.delete_quarantined_sensor_data(session_id) {
+                                                    Ok(_) => HttpResponse::no_content(),
+                                                    Err(e) => HttpResponse::from_error(&e),
+                                                }
+                                            } else {
+                                                match database.get_quarantined_sensor_data(session_id) {
+                                                    Ok(list) => HttpResponse::from_vec(
+                                                        json!({"quarantined": list.iter().map(QuarantinedSensorData::to_json).collect::<Vec<_>>()}).to_string()
+                                                    ),
+                                                    Err(e) => HttpResponse::from_error(&e),
+                                                }
+                                            }
+                                        }
+                                        Err(_) => HttpResponse::not_authorized(),
+                                    },
+                                    None => HttpResponse::not_authorized(),
+                                },
+                                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                            },
+                            None => HttpResponse::json_404(&request.path.to_string()),
+                        },
+                        (HttpMethod::Options, _) => HttpResponse::options_response(),
+                        (HttpMethod::Get | HttpMethod::Delete, _) => HttpResponse::json_404(&request.path.to_string()),
+                        _ => HttpResponse::method_not_allowed("GET, DELETE, OPTIONS"),
+                    },
+                    HttpPath::Live(subpath) => match request.method {
+                        HttpMethod::Get => match HttpPath::subsection(&subpath, 0) {
+                            None => HttpResponse::bad_request("Expected a websocket upgrade request."),
+                            Some("session") => match HttpPath::subsection(&subpath, 1) {
+                                Some(session_id) => match session_id.parse::<i64>() {
+                                    Ok(session_id) => LiveEventStream::response(
+                                        shared_database,
+                                        hub,
+                                        session_id,
+                                        request.headers.get(HttpHeaderType::LastEventID.as_str()).map(String::as_str),
+                                    ),
+                                    Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+                                },
+                                None => HttpResponse::json_404(&request.path.to_string()),
+                            },
+                            _ => HttpResponse::json_404(&request.path.to_string()),
+                        },
+                        HttpMethod::Options => HttpResponse::options_response(),
+                        _ => HttpResponse::method_not_allowed("GET, OPTIONS"),
                     },
                 }
             )
@@ -612,6 +1382,7 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
         .lock()
         .unwrap()
         .insert(HttpHeaderType::AcAllowOrigin.as_str().to_owned(), origin);
+    response.set_request(instance.as_deref(), &request_id);
 
     //send generated response //TODO: add stream identifier for error message
     if let Err(error) = response.send(stream) {
@@ -619,6 +1390,43 @@ fn handle_connection(database: &dyn Database, mut stream: TcpStream) {
     }
 }
 
+//Completes the websocket handshake and serves the connection on a new thread
+fn upgrade_connection(request: &HttpRequest, request_id: &str, hub: &Arc<LiveHub>, mut stream: TcpStream) {
+    let response = match WebSocket::handshake(request) {
+        Ok(response) => response,
+        Err(e) => {
+            let mut response = HttpResponse::from_error(&e);
+            response.set_request(Some(&request.path.to_string()), request_id);
+            if let Err(error) = response.send(stream) {
+                eprintln!("Failed to send response to stream. Error: {error}")
+            }
+            return;
+        }
+    };
+
+    if let Err(error) = response.send_to(&mut stream) {
+        eprintln!("Failed to send websocket handshake to stream. Error: {error}");
+        return;
+    }
+
+    let hub = Arc::clone(hub);
+    std::thread::spawn(move || LiveWebSocket::serve(stream, &hub));
+}
+
+//Accepts application/json and structured +json types, parameters such as charset are ignored
+fn is_json_media_type(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    media_type == "application/json" || (media_type.starts_with("application/") && media_type.ends_with("+json"))
+}
+
+fn is_import_media_type(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    matches!(
+        media_type.as_str(),
+        "text/csv" | "text/plain" | "application/x-ndjson" | "application/ndjson" | "application/jsonl"
+    )
+}
+
 fn generate_html_response(path: String) -> HttpResponse {
     //read content file
     let (status, body) = match fs::read_to_string(&path) {
@@ -633,5 +1441,7 @@ fn generate_html_response(path: String) -> HttpResponse {
         status,
         headers: std::sync::Mutex::new(HttpHeader::default_html()),
         body,
+        stream_writer: None,
+        problem: None,
     }
 }
@@ -0,0 +1,388 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+
+//How the value of a field is compared to the threshold of a rule
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AlertComparator {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AtLeast,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    AtMost,
+}
+
+impl AlertComparator {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            AlertComparator::Above => ">",
+            AlertComparator::AtLeast => ">=",
+            AlertComparator::Below => "<",
+            AlertComparator::AtMost => "<=",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            ">" => Ok(AlertComparator::Above),
+            ">=" => Ok(AlertComparator::AtLeast),
+            "<" => Ok(AlertComparator::Below),
+            "<=" => Ok(AlertComparator::AtMost),
+            _ => Err(Error::Validation(format!(
+                "Unknown alert comparator '{name}', expected one of [>, >=, <, <=]"
+            ))),
+        }
+    }
+
+    pub fn compare(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            AlertComparator::Above => value > threshold,
+            AlertComparator::AtLeast => value >= threshold,
+            AlertComparator::Below => value < threshold,
+            AlertComparator::AtMost => value <= threshold,
+        }
+    }
+
+    // Whether the rule watches for values rising past the threshold
+    const fn is_upper_limit(&self) -> bool {
+        matches!(self, AlertComparator::Above | AlertComparator::AtLeast)
+    }
+}
+
+//Limit on a numeric field of the blobs recorded by a sensor, or by every sensor of a session
+//It triggers once the field has breached the threshold for duration_seconds of datapoint time,
+//and resolves once the field is back past the threshold by the hysteresis
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    #[serde(default)]
+    id: i64,
+    // exactly one of sensor_id and session_id is set
+    #[serde(default)]
+    sensor_id: Option<i64>,
+    #[serde(default)]
+    session_id: Option<i64>,
+    #[serde(default)]
+    name: String,
+    // top level field of the data blob
+    field: String,
+    comparator: AlertComparator,
+    threshold: f64,
+    #[serde(default)]
+    duration_seconds: f64,
+    #[serde(default)]
+    hysteresis: f64,
+}
+
+impl AlertRule {
+    pub fn new(
+        id: i64,
+        sensor_id: Option<i64>,
+        session_id: Option<i64>,
+        name: String,
+        field: String,
+        comparator: AlertComparator,
+        threshold: f64,
+    ) -> Self {
+        AlertRule {
+            id,
+            sensor_id,
+            session_id,
+            name,
+            field,
+            comparator,
+            threshold,
+            duration_seconds: 0.0,
+            hysteresis: 0.0,
+        }
+    }
+
+    pub fn with_timing(mut self, duration_seconds: f64, hysteresis: f64) -> Self {
+        self.duration_seconds = duration_seconds;
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn with_id(mut self, id: i64) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn get_id(&self) -> &i64 {
+        &self.id
+    }
+
+    pub fn get_sensor_id(&self) -> Option<i64> {
+        self.sensor_id
+    }
+
+    pub fn get_session_id(&self) -> Option<i64> {
+        self.session_id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn get_comparator(&self) -> &AlertComparator {
+        &self.comparator
+    }
+
+    pub fn get_threshold(&self) -> &f64 {
+        &self.threshold
+    }
+
+    pub fn get_duration_seconds(&self) -> &f64 {
+        &self.duration_seconds
+    }
+
+    pub fn get_hysteresis(&self) -> &f64 {
+        &self.hysteresis
+    }
+
+    pub fn validate(self) -> Result<Self> {
+        if self.sensor_id.is_some() == self.session_id.is_some() {
+            return Err(Error::Validation(String::from(
+                "An alert rule requires exactly one of \"sensor_id\" and \"session_id\"",
+            )));
+        }
+        if self.field.is_empty() {
+            return Err(Error::Validation(String::from("An alert rule requires a \"field\"")));
+        }
+        if !self.threshold.is_finite() {
+            return Err(Error::Validation(String::from(
+                "An alert rule requires a finite \"threshold\"",
+            )));
+        }
+        for (name, value) in [
+            ("duration_seconds", self.duration_seconds),
+            ("hysteresis", self.hysteresis),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(Error::Validation(format!(
+                    "The \"{name}\" of an alert rule must not be negative"
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    //Whether the rule watches datapoints of the session recorded by the sensor
+    pub fn applies_to(&self, session_id: i64, sensor_id: i64) -> bool {
+        self.session_id == Some(session_id) || self.sensor_id == Some(sensor_id)
+    }
+
+    //The value of the watched field, none when the blob has no numeric field of that name
+    pub fn value_of(&self, blob: &Value) -> Option<f64> {
+        blob.get(&self.field).and_then(Value::as_f64)
+    }
+
+    pub fn breaches(&self, value: f64) -> bool {
+        self.comparator.compare(value, self.threshold)
+    }
+
+    //Whether a value ends a triggered alert, values within the hysteresis of the threshold keep it triggered
+    pub fn clears(&self, value: f64) -> bool {
+        match self.comparator.is_upper_limit() {
+            true => !self.breaches(value + self.hysteresis),
+            false => !self.breaches(value - self.hysteresis),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "sensor_id": self.sensor_id,
+            "session_id": self.session_id,
+            "name": self.name,
+            "field": self.field,
+            "comparator": self.comparator,
+            "threshold": self.threshold,
+            "duration_seconds": self.duration_seconds,
+            "hysteresis": self.hysteresis,
+        })
+    }
+}
+
+//Whether an event starts or ends an alert
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Triggered,
+    Resolved,
+}
+
+impl AlertState {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Triggered => "triggered",
+            AlertState::Resolved => "resolved",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "triggered" => Ok(AlertState::Triggered),
+            "resolved" => Ok(AlertState::Resolved),
+            _ => Err(Error::Validation(format!(
+                "Unknown alert state '{name}', expected one of [triggered, resolved]"
+            ))),
+        }
+    }
+}
+
+//How far an event got in being posted to the webhooks
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertDelivery {
+    #[default]
+    Pending,
+    // every webhook accepted the event
+    Delivered,
+    // a webhook still refused the event after every retry
+    Failed,
+    // no webhooks are configured
+    Skipped,
+}
+
+impl AlertDelivery {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            AlertDelivery::Pending => "pending",
+            AlertDelivery::Delivered => "delivered",
+            AlertDelivery::Failed => "failed",
+            AlertDelivery::Skipped => "skipped",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "pending" => Ok(AlertDelivery::Pending),
+            "delivered" => Ok(AlertDelivery::Delivered),
+            "failed" => Ok(AlertDelivery::Failed),
+            "skipped" => Ok(AlertDelivery::Skipped),
+            _ => Err(Error::Validation(format!(
+                "Unknown alert delivery '{name}', expected one of [pending, delivered, failed, skipped]"
+            ))),
+        }
+    }
+}
+
+//A rule triggering or resolving on a datapoint of a session sensor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    id: i64,
+    rule_id: i64,
+    session_id: i64,
+    session_sensor_id: i64,
+    state: AlertState,
+    // the value of the rule's field in the datapoint
+    value: f64,
+    // when the datapoint was recorded
+    datetime: String,
+    delivery: AlertDelivery,
+    // posts made to the webhooks
+    attempts: i64,
+}
+
+impl AlertEvent {
+    pub fn new(
+        id: i64,
+        rule_id: i64,
+        session_id: i64,
+        session_sensor_id: i64,
+        state: AlertState,
+        value: f64,
+        datetime: String,
+    ) -> Self {
+        AlertEvent {
+            id,
+            rule_id,
+            session_id,
+            session_sensor_id,
+            state,
+            value,
+            datetime,
+            delivery: AlertDelivery::Pending,
+            attempts: 0,
+        }
+    }
+
+    pub fn with_delivery(mut self, delivery: AlertDelivery, attempts: i64) -> Self {
+        self.delivery = delivery;
+        self.attempts = attempts;
+        self
+    }
+
+    pub fn with_id(mut self, id: i64) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn get_id(&self) -> &i64 {
+        &self.id
+    }
+
+    pub fn get_rule_id(&self) -> &i64 {
+        &self.rule_id
+    }
+
+    pub fn get_session_id(&self) -> &i64 {
+        &self.session_id
+    }
+
+    pub fn get_session_sensor_id(&self) -> &i64 {
+        &self.session_sensor_id
+    }
+
+    pub fn get_state(&self) -> &AlertState {
+        &self.state
+    }
+
+    pub fn get_value(&self) -> &f64 {
+        &self.value
+    }
+
+    pub fn get_datetime(&self) -> &str {
+        &self.datetime
+    }
+
+    pub fn get_delivery(&self) -> &AlertDelivery {
+        &self.delivery
+    }
+
+    pub fn get_attempts(&self) -> &i64 {
+        &self.attempts
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "rule_id": self.rule_id,
+            "session_id": self.session_id,
+            "session_sensor_id": self.session_sensor_id,
+            "state": self.state,
+            "value": self.value,
+            "datetime": self.datetime,
+            "delivery": self.delivery,
+            "attempts": self.attempts,
+        })
+    }
+}
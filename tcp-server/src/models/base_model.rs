@@ -10,6 +10,7 @@ use serde::de::DeserializeOwned;
 
 use crate::{
     data::Database,
+    error::Error,
     http::{HttpHeader, HttpResponse, HttpStatus},
 };
 
@@ -18,7 +19,6 @@ type Result<T> = crate::Result<T>;
 pub trait BaseModel {
     const TYPE_NAME: &'static str;
     const REQUIRED_VALUES: &'static str;
-    #[allow(unused)]
     fn is_valid(&self) -> bool;
     fn public_json(&self) -> String;
     #[allow(unused)]
@@ -64,11 +64,11 @@ pub trait BaseModel {
             Some(json) => match Self::from_json(json) {
                 Ok(model) => match (Self::insert_interface())(database, model) {
                     Ok(new_model) => new_model.to_created_response(),
-                    Err(_) => HttpResponse::bad_request(&Self::create_error_msg()),
+                    Err(e) => HttpResponse::from_error(&e),
                 },
-                Err(msg) => HttpResponse::invalid_body(Some(&msg)),
+                Err(e) => HttpResponse::invalid_body(Some(e.message())),
             },
-            None => HttpResponse::missing_body(Some(&Self::REQUIRED_VALUES)),
+            None => HttpResponse::missing_body(Some(Self::REQUIRED_VALUES)),
         }
     }
 
@@ -85,12 +85,12 @@ pub trait BaseModel {
                 Ok(update_model) => {
                     match (Self::update_interface())(database, subpath, update_model) {
                         Ok(updated_model) => updated_model.to_ok_response(),
-                        Err(_) => HttpResponse::json_404(&Self::TYPE_NAME),
+                        Err(e) => HttpResponse::from_error(&e),
                     }
                 }
-                Err(_) => HttpResponse::bad_request(&Self::create_error_msg()),
+                Err(e) => HttpResponse::invalid_body(Some(e.message())),
             },
-            None => HttpResponse::missing_body(Some(&Self::REQUIRED_VALUES)),
+            None => HttpResponse::missing_body(Some(Self::REQUIRED_VALUES)),
         }
     }
 
@@ -100,7 +100,7 @@ pub trait BaseModel {
     {
         match (Self::delete_interface())(database, subpath) {
             Ok(_) => HttpResponse::no_content(),
-            Err(_) => HttpResponse::json_404(&Self::TYPE_NAME),
+            Err(e) => HttpResponse::from_error(&e),
         }
     }
 
@@ -110,11 +110,11 @@ pub trait BaseModel {
     {
         match serde_json::from_value::<Self>(json) {
             Ok(model) => Ok(model),
-            Err(_) => Err(format!(
+            Err(_) => Err(Error::Parse(format!(
                 "failed to parse to {}.{}",
                 Self::TYPE_NAME,
                 Self::create_error_msg()
-            )),
+            ))),
         }
     }
 }
@@ -0,0 +1,152 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::derived::Expression;
+use crate::error::Error;
+use crate::http::{HttpHeader, HttpMethod, HttpPath, HttpResponse, HttpStatus};
+
+type Result<T> = crate::Result<T>;
+
+//A virtual channel of a sensor type, computed from the data blob by an expression whenever datapoints are read
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DerivedChannel {
+    sensor_type: String,
+    // unique per sensor type
+    name: String,
+    expression: String,
+    #[serde(default)]
+    unit: String,
+}
+
+impl DerivedChannel {
+    pub fn new(sensor_type: String, name: String, expression: String, unit: String) -> Self {
+        DerivedChannel {
+            sensor_type,
+            name,
+            expression,
+            unit,
+        }
+    }
+
+    pub fn get_sensor_type(&self) -> &str {
+        &self.sensor_type
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn get_unit(&self) -> &str {
+        &self.unit
+    }
+
+    //Parses the expression of the channel, failing when it or the channel's name is not valid
+    pub fn compile(&self) -> Result<Expression> {
+        if self.sensor_type.is_empty() {
+            return Err(Error::Validation(String::from(
+                "A derived channel requires a \"sensor_type\"",
+            )));
+        }
+        let mut chars = self.name.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(Error::Validation(format!(
+                "Invalid derived channel name '{}', expected letters, digits and underscores",
+                self.name
+            )));
+        }
+        Expression::parse(&self.expression)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "sensor_type": self.sensor_type,
+            "name": self.name,
+            "expression": self.expression,
+            "unit": self.unit,
+        })
+    }
+
+    //Handles /derived-channels, listing them all or those of a sensor type at /{sensor_type},
+    //deleting one at /{sensor_type}/{name}, and trying an expression out against a blob at /evaluate
+    pub fn derived_channels_model(
+        database: &dyn Database,
+        method: HttpMethod,
+        subpath: &str,
+        body: Option<Value>,
+    ) -> HttpResponse {
+        match (
+            method,
+            HttpPath::subsection(subpath, 0),
+            HttpPath::subsection(subpath, 1),
+        ) {
+            (HttpMethod::Get, sensor_type, None) => match database.get_derived_channels() {
+                Ok(channels) => HttpResponse::from_vec(
+                    json!({"derived_channels": channels
+                        .iter()
+                        .filter(|channel| sensor_type.is_none_or(|sensor_type| channel.sensor_type == sensor_type))
+                        .map(Self::to_json)
+                        .collect::<Vec<_>>()})
+                    .to_string(),
+                ),
+                Err(e) => HttpResponse::from_error(&e),
+            },
+            (HttpMethod::Post, None, None) => match body.map(serde_json::from_value::<DerivedChannel>) {
+                Some(Ok(channel)) => match channel
+                    .compile()
+                    .and_then(|_| database.insert_derived_channel(&channel))
+                {
+                    Ok(channel) => HttpResponse::new(
+                        HttpStatus::Created,
+                        HttpHeader::default_json(),
+                        channel.to_json().to_string(),
+                    ),
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                Some(Err(e)) => HttpResponse::invalid_body(Some(&e.to_string())),
+                None => HttpResponse::missing_body(Some(
+                    " Requires values \"sensor_type\": string, \"name\": string, \"expression\": string",
+                )),
+            },
+            (HttpMethod::Post, Some("evaluate"), None) => {
+                let Some(body) = body else {
+                    return HttpResponse::missing_body(Some(
+                        " Requires values \"expression\": string, \"data_blob\": object",
+                    ));
+                };
+                let Some(expression) = body.get("expression").and_then(Value::as_str) else {
+                    return HttpResponse::invalid_body(Some("\"expression\" must be a string"));
+                };
+                match Expression::parse(expression)
+                    .and_then(|expression| expression.evaluate(body.get("data_blob").unwrap_or(&Value::Null)))
+                {
+                    Ok(value) => HttpResponse::from_vec(json!({ "value": value }).to_string()),
+                    Err(e) => HttpResponse::from_error(&e),
+                }
+            }
+            (HttpMethod::Delete, Some(sensor_type), Some(name)) => {
+                match database.delete_derived_channel(sensor_type, name) {
+                    Ok(()) => HttpResponse::no_content(),
+                    Err(e) => HttpResponse::from_error(&e),
+                }
+            }
+            (HttpMethod::Options, _, _) => HttpResponse::options_response(),
+            (HttpMethod::Get | HttpMethod::Post | HttpMethod::Delete, _, _) => HttpResponse::json_404(subpath),
+            _ => HttpResponse::method_not_allowed("GET, POST, DELETE, OPTIONS"),
+        }
+    }
+}
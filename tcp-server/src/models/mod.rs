@@ -1,13 +1,28 @@
+pub mod alert_model;
 pub mod base_model;
+pub mod derived_channel_model;
+pub mod sensor_calibration_model;
 pub mod sensor_model;
+pub mod sensor_schema_model;
 pub mod session_model;
 pub mod session_sensor_data_model;
 pub mod session_sensor_model;
+pub mod session_sensor_rollup_model;
+pub mod session_summary_model;
 pub mod user_model;
 
+pub use self::alert_model::{AlertComparator, AlertDelivery, AlertEvent, AlertRule, AlertState};
 pub use self::base_model::BaseModel;
+pub use self::derived_channel_model::DerivedChannel;
+pub use self::sensor_calibration_model::{CalibrationMethod, SensorCalibration};
 pub use self::sensor_model::Sensor;
-pub use self::session_model::Session;
+pub use self::sensor_schema_model::{QuarantinedSensorData, SchemaPolicy, SensorSchema};
+pub use self::session_model::{Session, SessionStatus};
 pub use self::session_sensor_data_model::SessionSensorData;
 pub use self::session_sensor_model::SessionSensor;
+pub use self::session_sensor_rollup_model::SessionSensorRollup;
+pub use self::session_summary_model::{FieldSummary, SensorSummary, SessionSummary, SummaryGap};
 pub use self::user_model::User;
+
+#[cfg(test)]
+mod test_models;
@@ -0,0 +1,320 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    data::Database,
+    error::Error,
+    http::{HttpHeader, HttpMethod, HttpPath, HttpResponse, HttpStatus},
+};
+
+use super::Sensor;
+
+type Result<T> = crate::Result<T>;
+
+//How a calibration turns the raw value of a channel into its calibrated value
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CalibrationMethod {
+    // coefficients of a polynomial in the raw value, lowest power first
+    #[default]
+    Polynomial,
+    // an offset and a gain, the polynomial of degree one
+    Linear,
+    // pairs of raw and calibrated values, interpolated between and clamped beyond the first and last
+    Table,
+}
+
+impl CalibrationMethod {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            CalibrationMethod::Polynomial => "polynomial",
+            CalibrationMethod::Linear => "linear",
+            CalibrationMethod::Table => "table",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "polynomial" => Ok(CalibrationMethod::Polynomial),
+            "linear" => Ok(CalibrationMethod::Linear),
+            "table" => Ok(CalibrationMethod::Table),
+            _ => Err(Error::Validation(format!(
+                "Unknown calibration method '{name}', expected one of [polynomial, linear, table]"
+            ))),
+        }
+    }
+}
+
+//Calibration of one channel of a sensor, valid from valid_from until valid_to
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SensorCalibration {
+    #[serde(default)]
+    id: i64,
+    // taken from the path the calibration is posted to
+    #[serde(default)]
+    sensor_id: i64,
+    channel: String,
+    #[serde(default)]
+    method: CalibrationMethod,
+    #[serde(default)]
+    coefficients: Vec<f64>,
+    // raw and calibrated value pairs of a table calibration, in increasing order of raw value
+    #[serde(default)]
+    table: Vec<(f64, f64)>,
+    valid_from: String,
+    // open ended when missing
+    #[serde(default)]
+    valid_to: Option<String>,
+}
+
+impl SensorCalibration {
+    pub const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.f";
+
+    pub fn new(
+        id: i64,
+        sensor_id: i64,
+        channel: String,
+        coefficients: Vec<f64>,
+        valid_from: String,
+        valid_to: Option<String>,
+    ) -> Self {
+        SensorCalibration {
+            id,
+            sensor_id,
+            channel,
+            coefficients,
+            valid_from,
+            valid_to,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_table(mut self, method: CalibrationMethod, table: Vec<(f64, f64)>) -> Self {
+        self.method = method;
+        self.table = table;
+        self
+    }
+
+    pub fn with_ids(mut self, id: i64, sensor_id: i64) -> Self {
+        self.id = id;
+        self.sensor_id = sensor_id;
+        self
+    }
+
+    pub fn get_id(&self) -> &i64 {
+        &self.id
+    }
+
+    pub fn get_sensor_id(&self) -> &i64 {
+        &self.sensor_id
+    }
+
+    pub fn get_channel(&self) -> &str {
+        &self.channel
+    }
+
+    pub fn get_method(&self) -> &CalibrationMethod {
+        &self.method
+    }
+
+    pub fn get_coefficients(&self) -> &Vec<f64> {
+        &self.coefficients
+    }
+
+    pub fn get_table(&self) -> &Vec<(f64, f64)> {
+        &self.table
+    }
+
+    pub fn get_valid_from(&self) -> &str {
+        &self.valid_from
+    }
+
+    pub fn get_valid_to(&self) -> Option<&str> {
+        self.valid_to.as_deref()
+    }
+
+    //Checks the calibration against the sensor it is for, normalizing its datetimes so they compare as strings
+    pub fn validate(mut self, sensor: &Sensor) -> Result<Self> {
+        if self.channel.is_empty() {
+            return Err(Error::Validation(String::from("A calibration requires a \"channel\"")));
+        }
+        if !sensor.get_channels().is_empty() && sensor.get_channel(&self.channel).is_none() {
+            return Err(Error::Validation(format!(
+                "Sensor {} has no channel '{}'",
+                sensor.get_id(),
+                self.channel
+            )));
+        }
+        let finite = |values: &[f64]| values.iter().all(|value| value.is_finite());
+        match self.method {
+            CalibrationMethod::Polynomial if self.coefficients.is_empty() || !finite(&self.coefficients) => {
+                return Err(Error::Validation(String::from(
+                    "A polynomial calibration requires finite \"coefficients\", lowest power first",
+                )))
+            }
+            CalibrationMethod::Linear if self.coefficients.len() != 2 || !finite(&self.coefficients) => {
+                return Err(Error::Validation(String::from(
+                    "A linear calibration requires the \"coefficients\" [offset, gain]",
+                )))
+            }
+            CalibrationMethod::Table
+                if self.table.len() < 2
+                    || !finite(&self.table.iter().flat_map(|(raw, value)| [*raw, *value]).collect::<Vec<_>>())
+                    || self.table.windows(2).any(|pair| pair[0].0 >= pair[1].0) =>
+            {
+                return Err(Error::Validation(String::from(
+                    "A table calibration requires a \"table\" of at least two [raw, value] pairs in increasing order of raw value",
+                )))
+            }
+            _ => {}
+        }
+        self.valid_from = Self::normalize_datetime(&self.valid_from)?;
+        self.valid_to = self.valid_to.as_deref().map(Self::normalize_datetime).transpose()?;
+        if self.valid_to.as_ref().is_some_and(|valid_to| *valid_to <= self.valid_from) {
+            return Err(Error::Validation(String::from("A calibration must end after it starts")));
+        }
+        self.sensor_id = *sensor.get_id();
+        Ok(self)
+    }
+
+    //The open ended calibrations of the channel this one replaces, ended where this one starts
+    //Fails when it overlaps a calibration of its channel that cannot be ended
+    pub fn superseded(&self, existing: &[SensorCalibration]) -> Result<Vec<SensorCalibration>> {
+        let mut superseded = Vec::new();
+        for calibration in existing.iter().filter(|calibration| self.overlaps(calibration)) {
+            if calibration.valid_to.is_some() || calibration.valid_from >= self.valid_from {
+                return Err(Error::Conflict(format!(
+                    "Calibration {} of channel '{}' of sensor {} is already valid from {}",
+                    calibration.id, self.channel, self.sensor_id, calibration.valid_from
+                )));
+            }
+            let mut ended = calibration.clone();
+            ended.valid_to = Some(self.valid_from.clone());
+            superseded.push(ended);
+        }
+        Ok(superseded)
+    }
+
+    //Whether the calibration applies to a datapoint recorded at the datetime
+    pub fn is_valid_at(&self, datetime: &str) -> bool {
+        self.valid_from.as_str() <= datetime && self.valid_to.as_deref().is_none_or(|valid_to| datetime < valid_to)
+    }
+
+    //The calibrated value of a raw value
+    pub fn apply(&self, raw: f64) -> f64 {
+        match self.method {
+            CalibrationMethod::Polynomial | CalibrationMethod::Linear => {
+                self.coefficients.iter().rev().fold(0.0, |value, coefficient| value * raw + coefficient)
+            }
+            CalibrationMethod::Table => {
+                let (Some(first), Some(last)) = (self.table.first(), self.table.last()) else {
+                    return raw;
+                };
+                if raw <= first.0 {
+                    return first.1;
+                }
+                if raw >= last.0 {
+                    return last.1;
+                }
+                let upper = self.table.partition_point(|(point, _)| *point < raw);
+                let ((raw_low, low), (raw_high, high)) = (self.table[upper - 1], self.table[upper]);
+                low + (high - low) * (raw - raw_low) / (raw_high - raw_low)
+            }
+        }
+    }
+
+    //Whether both calibrations are of the same channel and valid at some common datetime
+    fn overlaps(&self, other: &SensorCalibration) -> bool {
+        let starts_before_end = |calibration: &SensorCalibration, end: Option<&str>| {
+            end.is_none_or(|end| calibration.get_valid_from() < end)
+        };
+        self.sensor_id == other.sensor_id
+            && self.channel == other.channel
+            && starts_before_end(self, other.get_valid_to())
+            && starts_before_end(other, self.get_valid_to())
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "sensor_id": self.sensor_id,
+            "channel": self.channel,
+            "method": self.method,
+            "coefficients": self.coefficients,
+            "table": self.table,
+            "valid_from": self.valid_from,
+            "valid_to": self.valid_to,
+        })
+    }
+
+    //Handles /sensors/{id}/calibrations, listing or adding the calibrations of a sensor and deleting one by id
+    pub fn calibrations_model(
+        database: &dyn Database,
+        method: HttpMethod,
+        subpath: &str,
+        body: Option<Value>,
+    ) -> HttpResponse {
+        let sensor_id = match HttpPath::subsection(subpath, 0).map(str::parse::<i64>) {
+            Some(Ok(sensor_id)) => sensor_id,
+            Some(Err(e)) => return HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+            None => return HttpResponse::json_404(subpath),
+        };
+        match (method, HttpPath::subsection(subpath, 2)) {
+            (HttpMethod::Get, None) => match database
+                .get_sensor(sensor_id)
+                .and_then(|_| database.get_sensor_calibrations(sensor_id))
+            {
+                Ok(calibrations) => HttpResponse::from_vec(
+                    json!({"calibrations": calibrations.iter().map(Self::to_json).collect::<Vec<_>>()}).to_string(),
+                ),
+                Err(e) => HttpResponse::from_error(&e),
+            },
+            (HttpMethod::Post, None) => match body.map(serde_json::from_value::<SensorCalibration>) {
+                Some(Ok(calibration)) => match database
+                    .get_sensor(sensor_id)
+                    .and_then(|sensor| calibration.validate(&sensor))
+                    .and_then(|calibration| database.insert_sensor_calibration(&calibration))
+                {
+                    Ok(calibration) => HttpResponse::new(
+                        HttpStatus::Created,
+                        HttpHeader::default_json(),
+                        calibration.to_json().to_string(),
+                    ),
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                Some(Err(e)) => HttpResponse::invalid_body(Some(&e.to_string())),
+                None => HttpResponse::missing_body(Some(
+                    " Requires values \"channel\": string, \"coefficients\": [f64] or \"table\": [[f64, f64]], \"valid_from\": string",
+                )),
+            },
+            (HttpMethod::Delete, Some(calibration_id)) => match calibration_id.parse::<i64>() {
+                Ok(calibration_id) => match database.delete_sensor_calibration(sensor_id, calibration_id) {
+                    Ok(()) => HttpResponse::no_content(),
+                    Err(e) => HttpResponse::from_error(&e),
+                },
+                Err(e) => HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+            },
+            (HttpMethod::Options, _) => HttpResponse::options_response(),
+            (HttpMethod::Get | HttpMethod::Post | HttpMethod::Delete, _) => HttpResponse::json_404(subpath),
+            _ => HttpResponse::method_not_allowed("GET, POST, DELETE, OPTIONS"),
+        }
+    }
+
+    fn normalize_datetime(datetime: &str) -> Result<String> {
+        match NaiveDateTime::parse_from_str(datetime, Self::DATETIME_FORMAT) {
+            Ok(datetime) => Ok(datetime.format(Self::DATETIME_FORMAT).to_string()),
+            Err(_) => Err(Error::Validation(format!(
+                "Invalid calibration datetime '{datetime}', expected YYYY-MM-DDTHH:MM:SS"
+            ))),
+        }
+    }
+}
@@ -7,34 +7,154 @@ Unless required by applicable law or agreed to in writing, software distributed
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::{data::Database, http::HttpPath};
+use crate::{data::Database, error::Error, http::HttpPath};
 
 type Result<T> = crate::Result<T>;
 
 use super::base_model::BaseModel;
-#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+
+//Kind of value a sensor channel holds in the data blob
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelType {
+    #[default]
+    Number,
+    Integer,
+    Boolean,
+    String,
+}
+
+//A field of the data blob a sensor emits, with its unit and the range of values it can take
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SensorChannel {
+    // key of the field in the data blob
+    pub name: String,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub data_type: ChannelType,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 pub struct Sensor {
     #[serde(default)]
     id: i64,
     #[serde(rename = "type")]
     sensor_type: String,
+    #[serde(default)]
+    manufacturer: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    serial_number: String,
+    #[serde(default)]
+    firmware: String,
+    #[serde(default)]
+    channels: Vec<SensorChannel>,
+    // nominal samples per second
+    #[serde(default)]
+    sample_rate: Option<f64>,
 }
 
 impl Sensor {
     pub fn new(id: i64, sensor_type: String) -> Self {
-        Sensor { id, sensor_type }
+        Sensor {
+            id,
+            sensor_type,
+            ..Default::default()
+        }
     }
     #[allow(unused)]
     pub fn empty() -> Self {
         Self::new(-1, String::new())
     }
+
+    pub fn with_metadata(mut self, manufacturer: String, model: String, serial_number: String, firmware: String) -> Self {
+        self.manufacturer = manufacturer;
+        self.model = model;
+        self.serial_number = serial_number;
+        self.firmware = firmware;
+        self
+    }
+
+    pub fn with_channels(mut self, channels: Vec<SensorChannel>, sample_rate: Option<f64>) -> Self {
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        self
+    }
+
     pub fn get_id(&self) -> &i64 {
         &self.id
     }
     pub fn get_sensor_type(&self) -> &str {
         &self.sensor_type
     }
+    pub fn get_manufacturer(&self) -> &str {
+        &self.manufacturer
+    }
+    pub fn get_model(&self) -> &str {
+        &self.model
+    }
+    pub fn get_serial_number(&self) -> &str {
+        &self.serial_number
+    }
+    pub fn get_firmware(&self) -> &str {
+        &self.firmware
+    }
+    pub fn get_channels(&self) -> &Vec<SensorChannel> {
+        &self.channels
+    }
+    pub fn get_channel(&self, name: &str) -> Option<&SensorChannel> {
+        self.channels.iter().find(|channel| channel.name == name)
+    }
+    pub fn get_sample_rate(&self) -> Option<f64> {
+        self.sample_rate
+    }
+
+    //Checks the catalog values a client sent, channels need distinct names and sensible ranges
+    pub fn validate(&self) -> Result<()> {
+        if let Some(sample_rate) = self.sample_rate.filter(|rate| !rate.is_finite() || *rate <= 0.0) {
+            return Err(Error::Validation(format!(
+                "Sample rate must be a positive number of samples per second, not {sample_rate}"
+            )));
+        }
+        for (index, channel) in self.channels.iter().enumerate() {
+            if channel.name.is_empty() {
+                return Err(Error::Validation(String::from("Every channel requires a \"name\"")));
+            }
+            if self.channels[..index].iter().any(|other| other.name == channel.name) {
+                return Err(Error::Validation(format!("Channel '{}' is defined more than once", channel.name)));
+            }
+            if let (Some(min), Some(max)) = (channel.min, channel.max) {
+                if min > max {
+                    return Err(Error::Validation(format!(
+                        "Channel '{}' has a min of {min} above its max of {max}",
+                        channel.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "type": self.sensor_type,
+            "manufacturer": self.manufacturer,
+            "model": self.model,
+            "serial_number": self.serial_number,
+            "firmware": self.firmware,
+            "channels": self.channels,
+            "sample_rate": self.sample_rate,
+        })
+    }
 }
 
 impl BaseModel for Sensor {
@@ -46,27 +166,48 @@ impl BaseModel for Sensor {
     }
 
     fn public_json(&self) -> String {
-        format!(
-            "{{\"id\":\"{}\", \"type\":\"{}\"}}",
-            self.get_id(),
-            self.get_sensor_type()
-        )
+        let mut json = self.to_json();
+        //single sensor responses have always sent the id as a string
+        json["id"] = json!(self.id.to_string());
+        json.to_string()
     }
 
     fn fill_from(&mut self, other: &Self) {
-        if self.id == -1 {
-            self.id = other.get_id().clone()
+        // an id missing from the request deserializes to 0
+        if self.id == 0 {
+            self.id = *other.get_id()
         }
         if self.sensor_type.is_empty() {
             self.sensor_type = other.get_sensor_type().to_string()
         }
+        if self.manufacturer.is_empty() {
+            self.manufacturer = other.get_manufacturer().to_string()
+        }
+        if self.model.is_empty() {
+            self.model = other.get_model().to_string()
+        }
+        if self.serial_number.is_empty() {
+            self.serial_number = other.get_serial_number().to_string()
+        }
+        if self.firmware.is_empty() {
+            self.firmware = other.get_firmware().to_string()
+        }
+        if self.channels.is_empty() {
+            self.channels = other.get_channels().clone()
+        }
+        if self.sample_rate.is_none() {
+            self.sample_rate = other.get_sample_rate()
+        }
     }
 
     fn insert_interface() -> impl FnOnce(&dyn Database, Self) -> Result<Self>
     where
         Self: Sized,
     {
-        |database: &dyn Database, sensor: Self| -> Result<Self> { database.insert_sensor(&sensor) }
+        |database: &dyn Database, sensor: Self| -> Result<Self> {
+            sensor.validate()?;
+            database.insert_sensor(&sensor)
+        }
     }
 
     fn update_interface() -> impl FnOnce(&dyn Database, &str, Self) -> Result<Self>
@@ -74,12 +215,15 @@ impl BaseModel for Sensor {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str, updated_sensor: Self| -> Result<Self> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(id) => match id.parse::<i64>() {
-                    Ok(id) => database.update_sensor(id, &updated_sensor),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                    Ok(id) => {
+                        updated_sensor.validate()?;
+                        database.update_sensor(id, &updated_sensor)
+                    }
+                    Err(e) => Err(Error::from(e)),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -89,12 +233,12 @@ impl BaseModel for Sensor {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str| -> Result<()> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(id) => match id.parse::<i64>() {
                     Ok(id) => database.delete_sensor(id),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                    Err(e) => Err(Error::from(e)),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -0,0 +1,177 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use chrono::Utc;
+use jsonschema::Validator;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::Error;
+
+use super::SessionSensorData;
+
+type Result<T> = crate::Result<T>;
+
+//What happens to a datapoint whose blob does not match its sensor type's schema
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaPolicy {
+    // the datapoint is refused and the errors are returned to the device
+    #[default]
+    Reject,
+    // the datapoint is set aside with its errors for an admin to review, the rest of the request is accepted
+    Quarantine,
+}
+
+impl SchemaPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchemaPolicy::Reject => "reject",
+            SchemaPolicy::Quarantine => "quarantine",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "reject" => Ok(SchemaPolicy::Reject),
+            "quarantine" => Ok(SchemaPolicy::Quarantine),
+            _ => Err(Error::Validation(format!(
+                "Unknown schema policy '{name}', expected one of [reject, quarantine]"
+            ))),
+        }
+    }
+}
+
+//JSON Schema every data blob recorded by a sensor of the type must match, managed by admins
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SensorSchema {
+    sensor_type: String,
+    schema: Value,
+    #[serde(default)]
+    policy: SchemaPolicy,
+}
+
+impl SensorSchema {
+    pub fn new(sensor_type: String, schema: Value, policy: SchemaPolicy) -> Self {
+        SensorSchema {
+            sensor_type,
+            schema,
+            policy,
+        }
+    }
+
+    pub fn get_sensor_type(&self) -> &str {
+        &self.sensor_type
+    }
+
+    pub fn get_schema(&self) -> &Value {
+        &self.schema
+    }
+
+    pub fn get_policy(&self) -> &SchemaPolicy {
+        &self.policy
+    }
+
+    //Builds the validator of the schema, failing when it is not a valid JSON Schema
+    pub fn compile(&self) -> Result<Validator> {
+        if self.sensor_type.is_empty() {
+            return Err(Error::Validation(String::from("A sensor schema requires a \"sensor_type\"")));
+        }
+        jsonschema::validator_for(&self.schema).map_err(|e| {
+            Error::Validation(format!("Invalid JSON Schema for sensor type '{}': {e}", self.sensor_type))
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "sensor_type": self.sensor_type,
+            "schema": self.schema,
+            "policy": self.policy,
+        })
+    }
+}
+
+//A datapoint set aside because its blob did not match the schema of its sensor type
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QuarantinedSensorData {
+    session_id: i64,
+    session_sensor_id: i64,
+    datetime: String,
+    data_blob: Value,
+    errors: Vec<String>,
+    quarantined_at: String,
+}
+
+impl QuarantinedSensorData {
+    pub const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+
+    pub fn new(
+        session_id: i64,
+        session_sensor_id: i64,
+        datetime: String,
+        data_blob: Value,
+        errors: Vec<String>,
+        quarantined_at: String,
+    ) -> Self {
+        QuarantinedSensorData {
+            session_id,
+            session_sensor_id,
+            datetime,
+            data_blob,
+            errors,
+            quarantined_at,
+        }
+    }
+
+    //Quarantines an attached datapoint now
+    pub fn from_datapoint(datapoint: &SessionSensorData, errors: Vec<String>) -> Self {
+        Self::new(
+            datapoint.get_id().unwrap_or_default(),
+            datapoint.get_session_sensor_id().unwrap_or_default(),
+            datapoint.get_datetime().to_string(),
+            datapoint.get_blob().clone(),
+            errors,
+            Utc::now().naive_utc().format(Self::DATETIME_FORMAT).to_string(),
+        )
+    }
+
+    pub fn get_session_id(&self) -> &i64 {
+        &self.session_id
+    }
+
+    pub fn get_session_sensor_id(&self) -> &i64 {
+        &self.session_sensor_id
+    }
+
+    pub fn get_datetime(&self) -> &str {
+        &self.datetime
+    }
+
+    pub fn get_blob(&self) -> &Value {
+        &self.data_blob
+    }
+
+    pub fn get_errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    pub fn get_quarantined_at(&self) -> &str {
+        &self.quarantined_at
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "session_id": self.session_id,
+            "session_sensor_id": self.session_sensor_id,
+            "datetime": self.datetime,
+            "data_blob": self.data_blob,
+            "errors": self.errors,
+            "quarantined_at": self.quarantined_at,
+        })
+    }
+}
@@ -6,28 +6,117 @@ You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2
 Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::{data::Database, http::HttpPath};
+use crate::{
+    data::Database,
+    error::Error,
+    http::{HttpPath, HttpResponse},
+};
 
 type Result<T> = crate::Result<T>;
 
 use super::base_model::BaseModel;
+
+//Where a session is in its recording lifecycle
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    #[default]
+    Pending,
+    Recording,
+    Stopped,
+    Archived,
+}
+
+impl SessionStatus {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Pending => "pending",
+            SessionStatus::Recording => "recording",
+            SessionStatus::Stopped => "stopped",
+            SessionStatus::Archived => "archived",
+        }
+    }
+
+    pub fn from_str(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(SessionStatus::Pending),
+            "recording" => Ok(SessionStatus::Recording),
+            "stopped" => Ok(SessionStatus::Stopped),
+            "archived" => Ok(SessionStatus::Archived),
+            _ => Err(Error::Validation(format!("Unknown session status: {status}"))),
+        }
+    }
+
+    //A session records once, pending -> recording -> stopped, and can be archived when it isn't recording
+    pub fn can_transition_to(&self, next: SessionStatus) -> bool {
+        matches!(
+            (self, next),
+            (SessionStatus::Pending, SessionStatus::Recording)
+                | (SessionStatus::Recording, SessionStatus::Stopped)
+                | (SessionStatus::Pending, SessionStatus::Archived)
+                | (SessionStatus::Stopped, SessionStatus::Archived)
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct Session {
     #[serde(default)]
     id: i64,
     username: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    // lifecycle values are only changed through the start, stop, and archive endpoints
+    #[serde(skip_deserializing)]
+    status: SessionStatus,
+    #[serde(skip_deserializing)]
+    start_time: Option<String>,
+    #[serde(skip_deserializing)]
+    end_time: Option<String>,
 }
 
 impl Session {
+    pub const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.6f";
+
     pub fn new(id: i64, username: String) -> Self {
-        Session { id, username }
+        Session {
+            id,
+            username,
+            ..Default::default()
+        }
     }
     #[allow(unused)]
     pub fn empty() -> Self {
         Self::new(-1, String::new())
     }
+
+    pub fn with_metadata(mut self, name: String, description: String, tags: Vec<String>) -> Self {
+        self.name = name;
+        self.description = description;
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_lifecycle(
+        mut self,
+        status: SessionStatus,
+        start_time: Option<String>,
+        end_time: Option<String>,
+    ) -> Self {
+        self.status = status;
+        self.start_time = start_time;
+        self.end_time = end_time;
+        self
+    }
+
     pub fn get_id(&self) -> &i64 {
         &self.id
     }
@@ -35,6 +124,108 @@ impl Session {
     pub fn get_username(&self) -> &str {
         &self.username
     }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn get_tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    pub fn get_status(&self) -> &SessionStatus {
+        &self.status
+    }
+
+    pub fn get_start_time(&self) -> Option<&str> {
+        self.start_time.as_deref()
+    }
+
+    pub fn get_end_time(&self) -> Option<&str> {
+        self.end_time.as_deref()
+    }
+
+    //Moves the session to the next status, stamping the start or end time
+    pub fn transition(&mut self, next: SessionStatus) -> Result<()> {
+        if !self.status.can_transition_to(next) {
+            return Err(Error::Conflict(format!(
+                "Session {} cannot go from {} to {}",
+                self.id,
+                self.status.as_str(),
+                next.as_str()
+            )));
+        }
+        match next {
+            SessionStatus::Recording => self.start_time = Some(Self::now()),
+            SessionStatus::Stopped => self.end_time = Some(Self::now()),
+            _ => {}
+        }
+        self.status = next;
+        Ok(())
+    }
+
+    pub fn now() -> String {
+        Utc::now().naive_utc().format(Self::DATETIME_FORMAT).to_string()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "session_id": self.id,
+            "username": self.username,
+            "name": self.name,
+            "description": self.description,
+            "tags": self.tags,
+            "status": self.status,
+            "start_time": self.start_time,
+            "end_time": self.end_time,
+        })
+    }
+
+    //Handles the start, stop, and archive actions of /sessions/{id}/{action}
+    pub fn transition_model(database: &dyn Database, subpath: &str) -> HttpResponse {
+        let status = match HttpPath::subsection(subpath, 1) {
+            Some("start") => SessionStatus::Recording,
+            Some("stop") => SessionStatus::Stopped,
+            Some("archive") => SessionStatus::Archived,
+            action => {
+                return HttpResponse::bad_request(&format!(
+                    "Unknown session action '{}', expected one of [start, stop, archive]",
+                    action.unwrap_or_default()
+                ))
+            }
+        };
+        let session_id = match HttpPath::subsection(subpath, 0).map(str::parse::<i64>) {
+            Some(Ok(session_id)) => session_id,
+            Some(Err(e)) => return HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+            None => return HttpResponse::json_404(Self::TYPE_NAME),
+        };
+        match database.set_session_status(session_id, status) {
+            Ok(session) => session.to_ok_response(),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+
+    //Tells a device which session its sensor is recording to, along with its session sensor id
+    pub fn active_response(database: &dyn Database, sensor_id: i64) -> HttpResponse {
+        match database.get_active_session(sensor_id) {
+            Ok(session) => {
+                let session_sensor_id = database
+                    .get_session_sensors(*session.get_id())
+                    .unwrap_or_default()
+                    .iter()
+                    .find(|session_sensor| *session_sensor.get_sensor_id() == sensor_id)
+                    .map(|session_sensor| *session_sensor.get_id());
+                let mut json = session.to_json();
+                json["session_sensor_id"] = json!(session_sensor_id);
+                HttpResponse::from_vec(json.to_string())
+            }
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
 }
 
 impl BaseModel for Session {
@@ -46,19 +237,34 @@ impl BaseModel for Session {
     }
 
     fn public_json(&self) -> String {
-        format!(
-            "{{\"session_id\":\"{}\", \"username\":\"{}\"}}",
-            self.id, self.username
-        )
+        let mut json = self.to_json();
+        //single session responses have always sent the id as a string
+        json["session_id"] = json!(self.id.to_string());
+        json.to_string()
     }
 
     fn fill_from(&mut self, other: &Self) {
-        if self.id == -1 {
-            self.id = other.get_id().clone()
+        // an id missing from the request deserializes to 0
+        if self.id == 0 {
+            self.id = *other.get_id()
         }
         if self.username.is_empty() {
             self.username = other.get_username().to_string()
         }
+        if self.name.is_empty() {
+            self.name = other.get_name().to_string()
+        }
+        if self.description.is_empty() {
+            self.description = other.get_description().to_string()
+        }
+        if self.tags.is_empty() {
+            self.tags = other.get_tags().clone()
+        }
+        if self.start_time.is_none() && self.end_time.is_none() {
+            self.status = *other.get_status();
+            self.start_time = other.start_time.clone();
+            self.end_time = other.end_time.clone();
+        }
     }
 
     fn insert_interface() -> impl FnOnce(&dyn Database, Self) -> Result<Self>
@@ -75,12 +281,12 @@ impl BaseModel for Session {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str, updated_session: Self| -> Result<Self> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(id) => match id.parse::<i64>() {
                     Ok(id) => database.update_session(id, &updated_session),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                    Err(e) => Err(Error::from(e)),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -90,12 +296,12 @@ impl BaseModel for Session {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str| -> Result<()> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(id) => match id.parse::<i64>() {
                     Ok(id) => database.delete_session(id),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                    Err(e) => Err(Error::from(e)),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -6,29 +6,43 @@ You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2
 Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
 WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
 */
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use crate::{
     data::Database,
-    http::{HttpPath, HttpResponse},
+    error::Error,
+    http::{HttpHeader, HttpPath, HttpResponse, HttpStatus},
+    ingest::IngestBuffer,
+    validation::{SchemaCheck, SchemaRegistry, SchemaViolation},
 };
 
 type Result<T> = crate::Result<T>;
 
-use super::base_model::BaseModel;
+use super::{base_model::BaseModel, SessionSensor};
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct SessionSensorData {
     #[serde(default)]
     id: Option<i64>,
+    #[serde(default)]
+    session_sensor_id: Option<i64>,
     datetime: String,
     data_blob: Value,
 }
 
 impl SessionSensorData {
-    pub fn new(id: Option<i64>, datetime: String, data_blob: Value) -> Self {
+    pub fn new(
+        id: Option<i64>,
+        session_sensor_id: Option<i64>,
+        datetime: String,
+        data_blob: Value,
+    ) -> Self {
         SessionSensorData {
             id,
+            session_sensor_id,
             datetime,
             data_blob,
         }
@@ -36,13 +50,17 @@ impl SessionSensorData {
 
     #[allow(unused)]
     pub fn empty() -> Self {
-        Self::new(None, String::new(), Value::Null)
+        Self::new(None, None, String::new(), Value::Null)
     }
 
     pub fn get_id(&self) -> &Option<i64> {
         &self.id
     }
 
+    pub fn get_session_sensor_id(&self) -> &Option<i64> {
+        &self.session_sensor_id
+    }
+
     pub fn get_datetime(&self) -> &str {
         &self.datetime
     }
@@ -51,11 +69,114 @@ impl SessionSensorData {
         &self.data_blob
     }
 
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "session_sensor_id": self.session_sensor_id,
+            "datetime": self.datetime,
+            "data_blob": self.data_blob,
+        })
+    }
+
+    //Checks every datapoint has a session id, a datetime and an object blob, whatever the schema of its sensor
+    pub fn validate(datapoints: &[Self]) -> Result<()> {
+        match datapoints.iter().position(|datapoint| !datapoint.is_valid()) {
+            Some(index) => Err(Error::Validation(format!(
+                "Invalid {} at index {index}, expected an \"id\", a \"datetime\" and an object \"data_blob\"",
+                Self::TYPE_NAME
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    //Checks every datapoint's sensor is attached to its session
+    //A datapoint without a session sensor is given its session's only sensor, sessions with several must be told which
+    pub fn attach_session_sensors(database: &dyn Database, datapoints: &mut [Self]) -> Result<()> {
+        let mut session_sensors: HashMap<i64, Vec<SessionSensor>> = HashMap::new();
+        for datapoint in datapoints.iter_mut() {
+            let session_id = datapoint
+                .id
+                .ok_or(Error::Validation(String::from("Datapoint is missing its session id")))?;
+            let attached = match session_sensors.entry(session_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(database.get_session_sensors(session_id)?),
+            };
+
+            datapoint.session_sensor_id = match datapoint.session_sensor_id {
+                Some(session_sensor_id) => match attached
+                    .iter()
+                    .any(|session_sensor| *session_sensor.get_id() == session_sensor_id)
+                {
+                    true => Some(session_sensor_id),
+                    false => {
+                        return Err(Error::Validation(format!(
+                            "Session sensor {session_sensor_id} is not attached to session {session_id}"
+                        )))
+                    }
+                },
+                None => match attached.as_slice() {
+                    [session_sensor] => Some(*session_sensor.get_id()),
+                    [] => return Err(Error::Validation(format!("Session {session_id} has no sensors attached"))),
+                    _ => {
+                        return Err(Error::Validation(format!(
+                            "Session {session_id} has several sensors, \"session_sensor_id\" is required"
+                        )))
+                    }
+                },
+            };
+        }
+        Ok(())
+    }
+
+    //Queues a single datapoint for the ingestion buffer, which publishes it to live subscribers once written
+    //Answers 202 Accepted once queued, or 201 Created once committed when wait_for_commit is set
+    //A blob not matching its sensor type's schema is answered 422, or 202 listing it when the schema quarantines it
+    pub fn try_insert_model(
+        database: &dyn Database,
+        ingest: &IngestBuffer,
+        schemas: &SchemaRegistry,
+        wait_for_commit: bool,
+        body: Option<serde_json::Value>,
+    ) -> HttpResponse {
+        match body {
+            Some(json) => match SessionSensorData::from_json(json) {
+                Ok(mut model) => {
+                    let check = match Self::validate(std::slice::from_ref(&model))
+                        .and_then(|_| Self::attach_session_sensors(database, std::slice::from_mut(&mut model)))
+                        .and_then(|_| schemas.check(database, vec![model.clone()]))
+                    {
+                        Ok(check) => check,
+                        Err(e) => return HttpResponse::from_error(&e),
+                    };
+                    if check.is_rejected() {
+                        return check.rejection_response();
+                    }
+                    if !check.violations.is_empty() {
+                        return Self::queued_response(database, ingest, check, wait_for_commit);
+                    }
+                    match ingest.enqueue(vec![model.clone()], wait_for_commit) {
+                        Ok(_) if wait_for_commit => model.to_created_response(),
+                        Ok(_) => HttpResponse::new(HttpStatus::Accepted, HttpHeader::default_json(), model.public_json()),
+                        Err(e) => HttpResponse::from_error(&e),
+                    }
+                }
+                Err(e) => HttpResponse::invalid_body(Some(e.message())),
+            },
+            None => HttpResponse::missing_body(Some(Self::REQUIRED_VALUES)),
+        }
+    }
+
+    //Queues a batch of datapoints, answering 202 Accepted once queued or 204 No Content once committed
+    //The batch is refused with 422 when any blob is rejected by its schema, quarantined blobs are listed in the answer
     pub fn try_batch_model(
         database: &dyn Database,
+        ingest: &IngestBuffer,
+        schemas: &SchemaRegistry,
+        wait_for_commit: bool,
         body: Option<serde_json::Value>,
     ) -> crate::http::HttpResponse {
-        let msg = Some(" Requires the values \"datapoints\": array [ { \"id\": i64, \"datetime\": string, \"data_blob\": string }, ... ]");
+        let required = " Requires the values \"datapoints\": array [ { \"id\": i64, \"session_sensor_id\": i64, \"datetime\": string, \"data_blob\": object }, ... ]";
+        let msg = Some(required);
         match body {
             Some(json) => match json.get("datapoints") {
                 Some(json_value_array) => match json_value_array.as_array() {
@@ -64,9 +185,13 @@ impl SessionSensorData {
                         .map(|json_value| SessionSensorData::from_json(json_value.clone()))
                         .collect::<std::result::Result<Vec<_>, _>>()
                     {
-                        Ok(data) => match database.batch_session_sensor_data(&data) {
-                            Ok(_) => HttpResponse::no_content(),
-                            Err(_) => HttpResponse::bad_request(msg.unwrap()),
+                        Ok(mut data) => match Self::validate(&data)
+                            .and_then(|_| Self::attach_session_sensors(database, &mut data))
+                            .and_then(|_| schemas.check(database, data))
+                        {
+                            Ok(check) if check.is_rejected() => check.rejection_response(),
+                            Ok(check) => Self::queued_response(database, ingest, check, wait_for_commit),
+                            Err(e) => HttpResponse::from_error(&e),
                         },
                         Err(_) => HttpResponse::invalid_body(msg),
                     },
@@ -77,29 +202,53 @@ impl SessionSensorData {
             None => HttpResponse::missing_body(msg),
         }
     }
+
+    // Quarantines and queues the datapoints of a check without rejections
+    fn queued_response(database: &dyn Database, ingest: &IngestBuffer, check: SchemaCheck, wait_for_commit: bool) -> HttpResponse {
+        let quarantined: Vec<Value> = check.quarantined().map(SchemaViolation::to_json).collect();
+        let queued = check.accepted.len();
+        match check
+            .quarantine(database)
+            .and_then(|_| ingest.enqueue(check.accepted, wait_for_commit))
+        {
+            Ok(_) if wait_for_commit && quarantined.is_empty() => HttpResponse::no_content(),
+            Ok(_) if quarantined.is_empty() => HttpResponse::new(
+                HttpStatus::Accepted,
+                HttpHeader::default_json(),
+                json!({ "queued": queued }).to_string(),
+            ),
+            Ok(_) => HttpResponse::new(
+                match wait_for_commit {
+                    true => HttpStatus::OK,
+                    false => HttpStatus::Accepted,
+                },
+                HttpHeader::default_json(),
+                json!({ "queued": queued, "quarantined": quarantined }).to_string(),
+            ),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
 }
 
 impl BaseModel for SessionSensorData {
     const TYPE_NAME: &'static str = "session sensor data";
     const REQUIRED_VALUES: &'static str =
-        " Requires values \"datetime\": string and \"data_blob\": string";
+        " Requires values \"id\": i64, \"datetime\": string, and \"data_blob\": object, with \"session_sensor_id\": i64 when the session has several sensors";
 
     fn is_valid(&self) -> bool {
         self.id.is_some() && !self.datetime.is_empty() && self.data_blob.is_object()
     }
 
     fn public_json(&self) -> String {
-        json!({
-            "id": self.id,
-            "datetime": self.datetime,
-            "data_blob": self.data_blob
-        })
-        .to_string()
+        self.to_json().to_string()
     }
 
     fn fill_from(&mut self, other: &Self) {
         if self.id.is_none() {
-            self.id = other.get_id().clone()
+            self.id = *other.get_id()
+        }
+        if self.session_sensor_id.is_none() {
+            self.session_sensor_id = *other.get_session_sensor_id()
         }
         if self.datetime.is_empty() {
             self.datetime = other.get_datetime().to_string()
@@ -126,19 +275,19 @@ impl BaseModel for SessionSensorData {
          subpath: &str,
          updated_session_sensor_datapoint: Self|
          -> Result<Self> {
-            match HttpPath::subsection(&subpath, 0) {
-                Some(id) => match HttpPath::subsection(&subpath, 1) {
-                    Some(datetime) => match id.parse::<i64>() {
-                        Ok(id) => database.update_session_sensor_datapoint(
-                            id,
+            match HttpPath::subsection(subpath, 0) {
+                Some(session_sensor_id) => match HttpPath::subsection(subpath, 1) {
+                    Some(datetime) => match session_sensor_id.parse::<i64>() {
+                        Ok(session_sensor_id) => database.update_session_sensor_datapoint(
+                            session_sensor_id,
                             datetime,
                             &updated_session_sensor_datapoint,
                         ),
-                        Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                        Err(e) => Err(Error::from(e)),
                     },
-                    None => Err(format!("Missing identifier in path: {subpath}")),
+                    None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -148,15 +297,15 @@ impl BaseModel for SessionSensorData {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str| -> Result<()> {
-            match HttpPath::subsection(&subpath, 0) {
-                Some(id) => match HttpPath::subsection(&subpath, 1) {
-                    Some(datetime) => match id.parse::<i64>() {
-                    Ok(id) => database.delete_session_sensor_datapoint(id, datetime),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+            match HttpPath::subsection(subpath, 0) {
+                Some(session_sensor_id) => match HttpPath::subsection(subpath, 1) {
+                    Some(datetime) => match session_sensor_id.parse::<i64>() {
+                    Ok(session_sensor_id) => database.delete_session_sensor_datapoint(session_sensor_id, datetime),
+                    Err(e) => Err(Error::from(e)),
                 },
-                    None => Err(format!("Missing identifier in path: {subpath}")),
+                    None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
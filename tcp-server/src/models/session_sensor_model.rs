@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 
 type Result<T> = crate::Result<T>;
 
-use crate::{data::Database, http::HttpPath};
+use crate::{data::Database, error::Error, http::HttpPath};
 
 use super::base_model::BaseModel;
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -65,14 +65,15 @@ impl BaseModel for SessionSensor {
     }
 
     fn fill_from(&mut self, other: &Self) {
-        if self.id == -1 {
-            self.id = other.get_id().clone()
+        // an id missing from the request deserializes to 0
+        if self.id == 0 {
+            self.id = *other.get_id()
         }
         if self.session_id == -1 {
-            self.session_id = other.session_id.clone()
+            self.session_id = other.session_id
         }
         if self.sensor_id == -1 {
-            self.sensor_id = other.sensor_id.clone()
+            self.sensor_id = other.sensor_id
         }
     }
 
@@ -90,12 +91,12 @@ impl BaseModel for SessionSensor {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str, updated_session_sensor: Self| -> Result<Self> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(id) => match id.parse::<i64>() {
                     Ok(id) => database.update_session_sensor(id, &updated_session_sensor),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                    Err(e) => Err(Error::from(e)),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -105,12 +106,12 @@ impl BaseModel for SessionSensor {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str| -> Result<()> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(id) => match id.parse::<i64>() {
                     Ok(id) => database.delete_session_sensor(id),
-                    Err(e) => Err(format!("Failed to parse id to i64: {e}")),
+                    Err(e) => Err(Error::from(e)),
                 },
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -0,0 +1,114 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+type Result<T> = crate::Result<T>;
+
+//Summary of the datapoints a session sensor recorded in one interval, kept once the raw datapoints are pruned
+//The blob holds the count, min, max, and mean of every numeric field of the datapoints' blobs
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SessionSensorRollup {
+    session_sensor_id: i64,
+    // start of the interval
+    bucket: String,
+    interval_seconds: i64,
+    count: i64,
+    data_blob: Value,
+}
+
+impl SessionSensorRollup {
+    pub const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+
+    pub fn new(session_sensor_id: i64, bucket: String, interval_seconds: i64, count: i64, data_blob: Value) -> Self {
+        SessionSensorRollup {
+            session_sensor_id,
+            bucket,
+            interval_seconds,
+            count,
+            data_blob,
+        }
+    }
+
+    //An empty rollup of the interval a datetime falls in
+    pub fn for_datetime(session_sensor_id: i64, datetime: &str, interval_seconds: i64) -> Result<Self> {
+        let timestamp = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f")?
+            .and_utc()
+            .timestamp();
+        let bucket = DateTime::from_timestamp(timestamp - timestamp.rem_euclid(interval_seconds.max(1)), 0)
+            .unwrap_or_default()
+            .naive_utc()
+            .format(Self::DATETIME_FORMAT)
+            .to_string();
+        Ok(Self::new(session_sensor_id, bucket, interval_seconds, 0, json!({})))
+    }
+
+    pub fn get_session_sensor_id(&self) -> &i64 {
+        &self.session_sensor_id
+    }
+
+    pub fn get_bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    pub fn get_interval_seconds(&self) -> &i64 {
+        &self.interval_seconds
+    }
+
+    pub fn get_count(&self) -> &i64 {
+        &self.count
+    }
+
+    pub fn get_blob(&self) -> &Value {
+        &self.data_blob
+    }
+
+    //Counts a datapoint's blob into the rollup, fields that are not numbers are only counted as a datapoint
+    pub fn add(&mut self, blob: &Value) {
+        self.count += 1;
+        let Some(fields) = blob.as_object() else {
+            return;
+        };
+        if !self.data_blob.is_object() {
+            self.data_blob = Value::Object(Map::new());
+        }
+        let Some(summary) = self.data_blob.as_object_mut() else {
+            return;
+        };
+        for (field, value) in fields {
+            let Some(value) = value.as_f64() else {
+                continue;
+            };
+            let stats = summary
+                .entry(field.clone())
+                .or_insert_with(|| json!({ "count": 0, "min": value, "max": value, "mean": 0.0 }));
+            let count = stats["count"].as_i64().unwrap_or(0);
+            let mean = stats["mean"].as_f64().unwrap_or(0.0);
+            let min = stats["min"].as_f64().unwrap_or(value).min(value);
+            let max = stats["max"].as_f64().unwrap_or(value).max(value);
+            *stats = json!({
+                "count": count + 1,
+                "min": min,
+                "max": max,
+                "mean": mean + (value - mean) / (count + 1) as f64,
+            });
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "session_sensor_id": self.session_sensor_id,
+            "bucket": self.bucket,
+            "interval_seconds": self.interval_seconds,
+            "count": self.count,
+            "data_blob": self.data_blob,
+        })
+    }
+}
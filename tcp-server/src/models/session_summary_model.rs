@@ -0,0 +1,191 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    data::Database,
+    error::Error,
+    http::{HttpPath, HttpRequest, HttpResponse},
+};
+
+use super::SessionStatus;
+
+type Result<T> = crate::Result<T>;
+
+//Statistics of one numeric field of the blobs a session sensor recorded
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct FieldSummary {
+    pub count: i64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    // sample standard deviation, 0 for a single value
+    pub stddev: f64,
+}
+
+//Two consecutive datapoints of a session sensor recorded further apart than the gap threshold
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SummaryGap {
+    pub session_sensor_id: Option<i64>,
+    pub from: String,
+    pub to: String,
+    pub seconds: f64,
+}
+
+//The datapoints one session sensor recorded, datapoints from before sensors were attached have no session sensor
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SensorSummary {
+    pub session_sensor_id: Option<i64>,
+    pub count: i64,
+    pub first: String,
+    pub last: String,
+    // datapoints per second between the first and the last
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+    #[serde(default)]
+    pub gap_count: usize,
+    // the first MAX_GAPS gaps
+    #[serde(default)]
+    pub gaps: Vec<SummaryGap>,
+    // numeric fields of the top level of the blobs, by name
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldSummary>,
+}
+
+//Overview of a session's datapoints, so it can be shown without downloading them
+//Stopped and archived sessions no longer record, so their summaries are cached until their datapoints change
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    session_id: i64,
+    // shortest time between consecutive datapoints of a sensor counted as a gap
+    gap_seconds: f64,
+    count: i64,
+    first: Option<String>,
+    last: Option<String>,
+    duration_seconds: Option<f64>,
+    sensors: Vec<SensorSummary>,
+}
+
+impl SessionSummary {
+    pub const DEFAULT_GAP_SECONDS: f64 = 60.0;
+    pub const MAX_GAPS: usize = 100;
+
+    //Completes the sensor summaries with their gaps and sample rates and totals them
+    pub fn new(session_id: i64, gap_seconds: f64, mut sensors: Vec<SensorSummary>, gaps: Vec<SummaryGap>) -> Self {
+        for gap in gaps {
+            if let Some(sensor) = sensors
+                .iter_mut()
+                .find(|sensor| sensor.session_sensor_id == gap.session_sensor_id)
+            {
+                sensor.gap_count += 1;
+                if sensor.gaps.len() < Self::MAX_GAPS {
+                    sensor.gaps.push(gap);
+                }
+            }
+        }
+        for sensor in sensors.iter_mut() {
+            sensor.sample_rate = Self::seconds_between(&sensor.first, &sensor.last)
+                .filter(|seconds| *seconds > 0.0)
+                .map(|seconds| (sensor.count - 1) as f64 / seconds);
+        }
+        let first = sensors.iter().map(|sensor| sensor.first.clone()).min();
+        let last = sensors.iter().map(|sensor| sensor.last.clone()).max();
+        SessionSummary {
+            session_id,
+            gap_seconds,
+            count: sensors.iter().map(|sensor| sensor.count).sum(),
+            duration_seconds: first
+                .as_deref()
+                .zip(last.as_deref())
+                .and_then(|(first, last)| Self::seconds_between(first, last)),
+            first,
+            last,
+            sensors,
+        }
+    }
+
+    pub fn get_session_id(&self) -> &i64 {
+        &self.session_id
+    }
+
+    pub fn get_gap_seconds(&self) -> &f64 {
+        &self.gap_seconds
+    }
+
+    //Summarizes a session, taking the summary of a stopped or archived session from the cache when it holds one
+    pub fn summarize(database: &dyn Database, session_id: i64, gap_seconds: f64) -> Result<Self> {
+        let finished = matches!(
+            database.get_session(session_id)?.get_status(),
+            SessionStatus::Stopped | SessionStatus::Archived
+        );
+        if finished {
+            if let Some(summary) = database.get_cached_session_summary(session_id, gap_seconds)? {
+                return Ok(summary);
+            }
+        }
+
+        let summary = Self::new(
+            session_id,
+            gap_seconds,
+            database.get_session_sensor_summaries(session_id)?,
+            database.get_session_sensor_gaps(session_id, gap_seconds)?,
+        );
+        if finished {
+            database.cache_session_summary(&summary)?;
+        }
+        Ok(summary)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "session_id": self.session_id,
+            "gap_seconds": self.gap_seconds,
+            "count": self.count,
+            "first": self.first,
+            "last": self.last,
+            "duration_seconds": self.duration_seconds,
+            "sensors": self.sensors,
+        })
+    }
+
+    //Handles GET /sessions/{id}/summary, gaps are counted from ?gap=seconds or DEFAULT_GAP_SECONDS
+    pub fn summary_model(database: &dyn Database, subpath: &str, request: &HttpRequest) -> HttpResponse {
+        let session_id = match HttpPath::subsection(subpath, 0).map(str::parse::<i64>) {
+            Some(Ok(session_id)) => session_id,
+            Some(Err(e)) => return HttpResponse::bad_request(&format!("Failed to parse id to i64: {e}")),
+            None => return HttpResponse::json_404(subpath),
+        };
+        let gap_seconds = match request.parameter("gap") {
+            Some(gap) => match gap.parse::<f64>() {
+                Ok(gap_seconds) if gap_seconds.is_finite() && gap_seconds > 0.0 => gap_seconds,
+                _ => {
+                    return HttpResponse::from_error(&Error::Validation(format!(
+                        "Invalid gap '{gap}', expected a positive number of seconds"
+                    )))
+                }
+            },
+            None => Self::DEFAULT_GAP_SECONDS,
+        };
+
+        match Self::summarize(database, session_id, gap_seconds) {
+            Ok(summary) => HttpResponse::from_vec(summary.to_json().to_string()),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+
+    pub fn seconds_between(from: &str, to: &str) -> Option<f64> {
+        let parse = |datetime: &str| NaiveDateTime::parse_from_str(datetime, "%Y-%m-%dT%H:%M:%S%.f").ok();
+        let elapsed = parse(to)? - parse(from)?;
+        Some(elapsed.num_microseconds()? as f64 / 1_000_000.0)
+    }
+}
@@ -0,0 +1,178 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::{Database, MockDatabase};
+    use crate::error::Error;
+    use crate::models::{
+        BaseModel, Sensor, SensorCalibration, Session, SessionSensor, SessionSensorData, SessionStatus, SessionSummary,
+        User,
+    };
+
+    #[test]
+    fn test_session_lifecycle() {
+        let mut session = Session::new(1, String::from("TestUser"));
+        assert_eq!(*session.get_status(), SessionStatus::Pending);
+
+        session.transition(SessionStatus::Recording).expect("Failed to start session");
+        assert!(session.get_start_time().is_some());
+        assert!(session.get_end_time().is_none());
+
+        session.transition(SessionStatus::Stopped).expect("Failed to stop session");
+        assert!(session.get_end_time().is_some());
+
+        session.transition(SessionStatus::Archived).expect("Failed to archive session");
+        assert_eq!(*session.get_status(), SessionStatus::Archived);
+    }
+
+    #[test]
+    fn test_session_invalid_transitions() {
+        let mut session = Session::new(1, String::from("TestUser"));
+        assert!(session.transition(SessionStatus::Stopped).is_err());
+
+        session.transition(SessionStatus::Recording).expect("Failed to start session");
+        assert!(session.transition(SessionStatus::Recording).is_err());
+        assert!(session.transition(SessionStatus::Archived).is_err());
+
+        session.transition(SessionStatus::Stopped).expect("Failed to stop session");
+        assert!(session.transition(SessionStatus::Recording).is_err());
+    }
+
+    #[test]
+    fn test_session_lifecycle_not_deserialized() {
+        let session = Session::from_json(json!({
+            "username": "TestUser",
+            "name": "Morning run",
+            "tags": ["outdoor"],
+            "status": "recording",
+            "start_time": "2025-01-01T00:00:00"
+        }))
+        .expect("Failed to parse session");
+
+        assert_eq!(session.get_name(), "Morning run");
+        assert_eq!(session.get_tags(), &vec![String::from("outdoor")]);
+        assert_eq!(*session.get_status(), SessionStatus::Pending);
+        assert!(session.get_start_time().is_none());
+    }
+
+    #[test]
+    fn test_session_fill_from_missing_id() {
+        let mut session = Session::from_json(json!({"username": "TestUser", "name": "Renamed"}))
+            .expect("Failed to parse session");
+        session.fill_from(&Session::new(7, String::from("TestUser")));
+
+        assert_eq!(*session.get_id(), 7);
+        assert_eq!(session.get_name(), "Renamed");
+    }
+
+    #[test]
+    fn test_datapoint_session_sensor_validation() {
+        let database = MockDatabase::new();
+        let datapoint = |session_sensor_id| {
+            SessionSensorData::new(Some(1), session_sensor_id, String::from("2025-01-01T00:00:00"), json!({}))
+        };
+
+        let mut attached = [datapoint(Some(2))];
+        SessionSensorData::attach_session_sensors(&database, &mut attached).expect("Failed to attach datapoint");
+        assert_eq!(*attached[0].get_session_sensor_id(), Some(2));
+
+        //the mock session has several sensors so one must be named
+        assert!(SessionSensorData::attach_session_sensors(&database, &mut [datapoint(None)]).is_err());
+        assert!(SessionSensorData::attach_session_sensors(&database, &mut [datapoint(Some(99))]).is_err());
+    }
+
+    #[test]
+    fn test_sensor_catalog_validation() {
+        let sensor = Sensor::from_json(json!({
+            "type": "Acceleration",
+            "manufacturer": "Bosch",
+            "channels": [
+                { "name": "x", "unit": "m/s^2", "min": -160, "max": 160 },
+                { "name": "label", "data_type": "string" }
+            ],
+            "sample_rate": 100
+        }))
+        .expect("Failed to parse sensor");
+        assert!(sensor.validate().is_ok());
+        assert_eq!(sensor.get_channel("x").and_then(|channel| channel.max), Some(160.0));
+        assert_eq!(sensor.to_json()["channels"][1]["data_type"], json!("string"));
+
+        let invalid = |json| Sensor::from_json(json).unwrap().validate();
+        assert!(invalid(json!({ "type": "GPS", "sample_rate": 0 })).is_err());
+        assert!(invalid(json!({ "type": "GPS", "channels": [{ "name": "lat" }, { "name": "lat" }] })).is_err());
+        assert!(invalid(json!({ "type": "GPS", "channels": [{ "name": "lat", "min": 90, "max": -90 }] })).is_err());
+        assert!(Sensor::from_json(json!({ "type": "GPS", "channels": [{ "name": "lat", "data_type": "complex" }] })).is_err());
+    }
+
+    #[test]
+    fn test_sensor_calibration_periods() {
+        let sensor = Sensor::from_json(json!({ "type": "Thermometer", "channels": [{ "name": "celsius" }] })).unwrap();
+        let calibration = |valid_from: &str, valid_to: Option<&str>| {
+            SensorCalibration::new(
+                0,
+                0,
+                String::from("celsius"),
+                vec![-40.0, 0.125],
+                valid_from.to_string(),
+                valid_to.map(str::to_string),
+            )
+            .validate(&sensor)
+        };
+        let first = calibration("2025-01-01T00:00:00.000", None).unwrap().with_ids(1, 0);
+        assert_eq!(first.get_valid_from(), "2025-01-01T00:00:00");
+
+        // An open ended calibration is ended by the next one, a closed one cannot be overlapped
+        let second = calibration("2025-02-01T00:00:00", Some("2025-03-01T00:00:00")).unwrap();
+        let superseded = second.superseded(std::slice::from_ref(&first)).unwrap();
+        assert_eq!(superseded[0].get_valid_to(), Some("2025-02-01T00:00:00"));
+        let second = second.with_ids(2, 0);
+        let overlapping = calibration("2025-02-15T00:00:00", None).unwrap();
+        assert!(matches!(overlapping.superseded(&[superseded[0].clone(), second.clone()]), Err(Error::Conflict(_))));
+        let later = calibration("2025-03-01T00:00:00", None).unwrap();
+        assert!(later.superseded(&[superseded[0].clone(), second]).unwrap().is_empty());
+
+        assert!(calibration("2025-01-02T00:00:00", Some("2025-01-01T00:00:00")).is_err());
+        assert!(calibration("yesterday", None).is_err());
+        let other_channel =
+            SensorCalibration::new(0, 0, String::from("kelvin"), vec![1.0], String::from("2025-01-01T00:00:00"), None);
+        assert!(matches!(other_channel.validate(&sensor), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new(String::from("user1"), String::from("hunter2"))).unwrap();
+        db.insert_session(&Session::new(0, String::from("user1"))).unwrap();
+        db.insert_sensor(&Sensor::new(0, String::from("Thermometer"))).unwrap();
+        db.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        db.set_session_status(1, SessionStatus::Recording).unwrap();
+        for second in 0..5 {
+            let datetime = format!("2025-01-01T00:00:{:02}", second * 2);
+            db.insert_session_sensor_data(&SessionSensorData::new(Some(1), Some(1), datetime, json!({ "celsius": second })))
+                .unwrap();
+        }
+
+        let summary = SessionSummary::summarize(&db, 1, 60.0).unwrap().to_json();
+        assert_eq!((&summary["count"], &summary["duration_seconds"]), (&json!(5), &json!(8.0)));
+        assert_eq!(summary["sensors"][0]["sample_rate"], json!(0.5));
+        assert_eq!(summary["sensors"][0]["fields"]["celsius"]["mean"], json!(2.0));
+
+        // Only the summaries of sessions that stopped recording are cached
+        assert_eq!(db.get_cached_session_summary(1, 60.0).unwrap(), None);
+        db.set_session_status(1, SessionStatus::Stopped).unwrap();
+        let stopped = SessionSummary::summarize(&db, 1, 1.5).unwrap();
+        assert_eq!(db.get_cached_session_summary(1, 1.5).unwrap(), Some(stopped.clone()));
+        assert_eq!(stopped.to_json()["sensors"][0]["gap_count"], json!(4));
+
+        assert!(matches!(SessionSummary::summarize(&db, 2, 60.0), Err(Error::NotFound(_))));
+    }
+}
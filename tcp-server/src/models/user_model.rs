@@ -8,7 +8,7 @@ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
 */
 use serde::{Deserialize, Serialize};
 
-use crate::{data::Database, http::HttpPath};
+use crate::{data::Database, error::Error, http::HttpPath};
 
 type Result<T> = crate::Result<T>;
 
@@ -76,9 +76,9 @@ impl BaseModel for User {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str, updated_user: Self| -> Result<Self> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(username) => database.update_user(username, &updated_user),
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
@@ -88,9 +88,9 @@ impl BaseModel for User {
         Self: Sized,
     {
         |database: &dyn Database, subpath: &str| -> Result<()> {
-            match HttpPath::subsection(&subpath, 0) {
+            match HttpPath::subsection(subpath, 0) {
                 Some(username) => database.delete_user(username),
-                None => Err(format!("Missing identifier in path: {subpath}")),
+                None => Err(Error::Validation(format!("Missing identifier in path: {subpath}"))),
             }
         }
     }
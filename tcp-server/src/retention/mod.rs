@@ -0,0 +1,8 @@
+pub mod retention_job;
+pub mod retention_policy;
+
+pub use self::retention_job::{RetentionConfig, RetentionJob};
+pub use self::retention_policy::RetentionPolicy;
+
+#[cfg(test)]
+mod test_retention;
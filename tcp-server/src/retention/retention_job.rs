@@ -0,0 +1,149 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Deserialize;
+
+use crate::data::Database;
+use crate::models::SessionSensorRollup;
+
+use super::retention_policy::PruneTarget;
+use super::RetentionPolicy;
+
+type Result<T> = crate::Result<T>;
+
+//Settings of the retention job along with the policy it enforces
+#[derive(Deserialize, Debug, Clone)]
+pub struct RetentionConfig {
+    #[serde(flatten)]
+    pub policy: RetentionPolicy,
+    // minutes between runs of the job
+    #[serde(default = "RetentionConfig::default_interval_minutes")]
+    pub interval_minutes: u64,
+    // datapoints pruned per transaction
+    #[serde(default = "RetentionConfig::default_batch_size")]
+    pub batch_size: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            policy: RetentionPolicy::default(),
+            interval_minutes: Self::default_interval_minutes(),
+            batch_size: Self::default_batch_size(),
+        }
+    }
+}
+
+impl RetentionConfig {
+    fn default_interval_minutes() -> u64 {
+        60
+    }
+
+    fn default_batch_size() -> usize {
+        500
+    }
+}
+
+//What a run of the job pruned
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PruneReport {
+    pub datapoints: usize,
+    pub rollups: usize,
+}
+
+//Background job that enforces a RetentionPolicy, pruning in transactions of at most batch_size datapoints
+//so ingestion is never held up for long
+pub struct RetentionJob {
+    config: RetentionConfig,
+}
+
+impl RetentionJob {
+    pub fn new(config: RetentionConfig) -> Self {
+        RetentionJob { config }
+    }
+
+    //Runs the job every interval on its own thread, nothing is started when the policy keeps everything
+    pub fn start(self, database: Arc<dyn Database>) -> Option<JoinHandle<()>> {
+        if !self.config.policy.prunes() {
+            return None;
+        }
+        let interval = Duration::from_secs(self.config.interval_minutes.max(1) * 60);
+        Some(std::thread::spawn(move || loop {
+            match self.run(database.as_ref(), Utc::now().naive_utc()) {
+                Ok(report) if report.datapoints > 0 => println!(
+                    "Retention pruned {} datapoints into {} rollups",
+                    report.datapoints, report.rollups
+                ),
+                Ok(_) => {}
+                Err(error) => eprintln!("Retention run failed, it is retried next interval. Error: {error}"),
+            }
+            std::thread::sleep(interval);
+        }))
+    }
+
+    //Prunes everything the policy allows as of now
+    pub fn run(&self, database: &dyn Database, now: NaiveDateTime) -> Result<PruneReport> {
+        let mut report = PruneReport::default();
+        for target in self.config.policy.plan(database, now)? {
+            let pruned = self.prune(database, &target)?;
+            report.datapoints += pruned.datapoints;
+            report.rollups += pruned.rollups;
+        }
+        Ok(report)
+    }
+
+    fn prune(&self, database: &dyn Database, target: &PruneTarget) -> Result<PruneReport> {
+        let batch_size = self.config.batch_size.max(1);
+        let rollup_seconds = self.config.policy.rollup_seconds();
+        // rollups left by earlier runs are added to rather than replaced
+        let mut rollups: HashMap<String, SessionSensorRollup> = match rollup_seconds {
+            Some(seconds) => database
+                .get_session_sensor_rollups(target.session_sensor_id)?
+                .into_iter()
+                .filter(|rollup| *rollup.get_interval_seconds() == seconds)
+                .map(|rollup| (rollup.get_bucket().to_string(), rollup))
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        let mut report = PruneReport::default();
+        loop {
+            let batch = database.get_session_sensor_data_before(target.session_sensor_id, &target.before, batch_size)?;
+            if batch.is_empty() {
+                break;
+            }
+
+            let mut buckets = BTreeSet::new();
+            if let Some(seconds) = rollup_seconds {
+                for data in &batch {
+                    let empty = SessionSensorRollup::for_datetime(target.session_sensor_id, data.get_datetime(), seconds)?;
+                    let bucket = empty.get_bucket().to_string();
+                    rollups.entry(bucket.clone()).or_insert(empty).add(data.get_blob());
+                    buckets.insert(bucket);
+                }
+            }
+            let changed: Vec<SessionSensorRollup> = buckets.iter().map(|bucket| rollups[bucket].clone()).collect();
+            let datetimes: Vec<String> = batch.iter().map(|data| data.get_datetime().to_string()).collect();
+
+            let pruned = database.prune_session_sensor_data(target.session_sensor_id, &datetimes, &changed)?;
+            report.datapoints += pruned;
+            report.rollups += changed.len();
+            // stops rather than spinning if the datapoints were removed by someone else in between
+            if pruned == 0 || batch.len() < batch_size {
+                break;
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,175 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::http::HttpResponse;
+use crate::models::{SessionSensorRollup, SessionStatus};
+
+type Result<T> = crate::Result<T>;
+
+// A period is written as a number of days or "forever"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PeriodValue {
+    Days(u64),
+    Keyword(String),
+}
+
+//How long raw datapoints are kept
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(try_from = "PeriodValue")]
+pub enum RetentionPeriod {
+    Days(u64),
+    #[default]
+    Forever,
+}
+
+impl TryFrom<PeriodValue> for RetentionPeriod {
+    type Error = String;
+
+    fn try_from(value: PeriodValue) -> core::result::Result<Self, Self::Error> {
+        match value {
+            PeriodValue::Days(days) => Ok(RetentionPeriod::Days(days)),
+            PeriodValue::Keyword(keyword) if keyword == "forever" => Ok(RetentionPeriod::Forever),
+            PeriodValue::Keyword(keyword) => Err(format!(
+                "Unknown retention period '{keyword}', expected a number of days or \"forever\""
+            )),
+        }
+    }
+}
+
+//Rules deciding which raw datapoints are pruned, the [retention] table of config.toml
+#[derive(Deserialize, Debug, Clone)]
+pub struct RetentionPolicy {
+    // how long raw datapoints of every sensor are kept
+    #[serde(default)]
+    pub raw_days: RetentionPeriod,
+    // sensor types kept for a period of their own
+    #[serde(default)]
+    pub sensor_types: HashMap<String, RetentionPeriod>,
+    // archived sessions are kept forever
+    #[serde(default = "RetentionPolicy::default_keep_archived")]
+    pub keep_archived: bool,
+    // length of the rollups kept of pruned datapoints, none are kept when unset
+    #[serde(default)]
+    pub rollup_minutes: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            raw_days: RetentionPeriod::Forever,
+            sensor_types: HashMap::new(),
+            keep_archived: Self::default_keep_archived(),
+            rollup_minutes: None,
+        }
+    }
+}
+
+//Datapoints of a session sensor the policy prunes
+#[derive(Debug, Clone, PartialEq)]
+pub struct PruneTarget {
+    pub session_id: i64,
+    pub session_sensor_id: i64,
+    pub sensor_type: String,
+    // datapoints recorded before this are pruned
+    pub before: String,
+    pub datapoints: usize,
+}
+
+impl PruneTarget {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "session_id": self.session_id,
+            "session_sensor_id": self.session_sensor_id,
+            "sensor_type": self.sensor_type,
+            "before": self.before,
+            "datapoints": self.datapoints,
+        })
+    }
+}
+
+impl RetentionPolicy {
+    fn default_keep_archived() -> bool {
+        true
+    }
+
+    pub fn period(&self, sensor_type: &str) -> RetentionPeriod {
+        self.sensor_types.get(sensor_type).copied().unwrap_or(self.raw_days)
+    }
+
+    //Whether the policy could ever prune a datapoint
+    pub fn prunes(&self) -> bool {
+        self.raw_days != RetentionPeriod::Forever
+            || self.sensor_types.values().any(|period| *period != RetentionPeriod::Forever)
+    }
+
+    pub fn rollup_seconds(&self) -> Option<i64> {
+        self.rollup_minutes
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| minutes as i64 * 60)
+    }
+
+    //Every session sensor with datapoints older than the policy keeps as of now, without changing anything
+    pub fn plan(&self, database: &dyn Database, now: NaiveDateTime) -> Result<Vec<PruneTarget>> {
+        let sensor_types: HashMap<i64, String> = database
+            .get_sensors()?
+            .into_iter()
+            .map(|sensor| (*sensor.get_id(), sensor.get_sensor_type().to_string()))
+            .collect();
+
+        let mut targets = Vec::new();
+        for session in database.get_all_sessions()? {
+            if self.keep_archived && *session.get_status() == SessionStatus::Archived {
+                continue;
+            }
+            for session_sensor in database.get_session_sensors(*session.get_id())? {
+                let sensor_type = sensor_types.get(session_sensor.get_sensor_id()).cloned().unwrap_or_default();
+                let RetentionPeriod::Days(days) = self.period(&sensor_type) else {
+                    continue;
+                };
+                let before = (now - TimeDelta::days(days.min(i32::MAX as u64) as i64))
+                    .format(SessionSensorRollup::DATETIME_FORMAT)
+                    .to_string();
+                let datapoints = database.count_session_sensor_data_before(*session_sensor.get_id(), &before)?;
+                if datapoints > 0 {
+                    targets.push(PruneTarget {
+                        session_id: *session.get_id(),
+                        session_sensor_id: *session_sensor.get_id(),
+                        sensor_type,
+                        before,
+                        datapoints,
+                    });
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    //Lists what the policy would prune right now
+    pub fn preview_response(&self, database: &dyn Database) -> HttpResponse {
+        match self.plan(database, Utc::now().naive_utc()) {
+            Ok(targets) => HttpResponse::from_vec(
+                json!({
+                    "datapoints": targets.iter().map(|target| target.datapoints).sum::<usize>(),
+                    "rollup_minutes": self.rollup_minutes,
+                    "targets": targets.iter().map(PruneTarget::to_json).collect::<Vec<_>>(),
+                })
+                .to_string(),
+            ),
+            Err(e) => HttpResponse::from_error(&e),
+        }
+    }
+}
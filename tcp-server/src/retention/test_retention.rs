@@ -0,0 +1,163 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::models::{Sensor, Session, SessionSensor, SessionSensorData, SessionStatus, User};
+    use crate::retention::retention_job::PruneReport;
+    use crate::retention::retention_policy::RetentionPeriod;
+    use crate::retention::{RetentionConfig, RetentionJob, RetentionPolicy};
+
+    /* Helpers */
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2025-03-01T00:00:00", "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    // Two sessions of user1, each recording from an accelerometer (session sensors 1 and 3) and a gps (2 and 4)
+    // Every session sensor has a datapoint each minute from 00:00 to 00:09 on 2025-01-01, and one on 2025-02-28
+    fn init_database() -> MemoryDatabase {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string())).unwrap();
+        db.insert_sensor(&Sensor::new(0, "Acceleration".to_string())).unwrap();
+        db.insert_sensor(&Sensor::new(0, "GPS".to_string())).unwrap();
+        for session_id in 1..=2 {
+            db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+            for sensor_id in 1..=2 {
+                let session_sensor = db.insert_session_sensor(&SessionSensor::new(0, session_id, sensor_id)).unwrap();
+                let mut datapoints: Vec<SessionSensorData> = (0..10)
+                    .map(|minute| datapoint(&session_sensor, &format!("2025-01-01T00:{minute:02}:30"), minute))
+                    .collect();
+                datapoints.push(datapoint(&session_sensor, "2025-02-28T00:00:00", 100));
+                db.batch_session_sensor_data(&datapoints).unwrap();
+            }
+        }
+        db
+    }
+
+    fn datapoint(session_sensor: &SessionSensor, datetime: &str, value: i64) -> SessionSensorData {
+        SessionSensorData::new(
+            Some(*session_sensor.get_session_id()),
+            Some(*session_sensor.get_id()),
+            datetime.to_string(),
+            json!({ "value": value, "label": "sample" }),
+        )
+    }
+
+    fn policy(raw_days: RetentionPeriod) -> RetentionPolicy {
+        RetentionPolicy {
+            raw_days,
+            ..Default::default()
+        }
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_policy_from_toml() {
+        let config: RetentionConfig = toml::from_str(
+            r#"
+raw_days = 30
+keep_archived = false
+rollup_minutes = 60
+batch_size = 100
+sensor_types = { GPS = "forever", Acceleration = 7 }
+"#,
+        )
+        .expect("Failed to parse retention config");
+
+        assert_eq!(config.policy.raw_days, RetentionPeriod::Days(30));
+        assert_eq!(config.policy.period("GPS"), RetentionPeriod::Forever);
+        assert_eq!(config.policy.period("Acceleration"), RetentionPeriod::Days(7));
+        assert_eq!(config.policy.period("Heart Rate"), RetentionPeriod::Days(30));
+        assert!(!config.policy.keep_archived);
+        assert_eq!(config.policy.rollup_seconds(), Some(3_600));
+        assert_eq!(config.batch_size, 100);
+        assert_eq!(config.interval_minutes, 60);
+
+        // Nothing configured keeps everything
+        let config: RetentionConfig = toml::from_str("").unwrap();
+        assert!(!config.policy.prunes());
+        assert!(toml::from_str::<RetentionConfig>("raw_days = \"never\"").is_err());
+    }
+
+    #[test]
+    fn test_plan_follows_overrides_and_archived_sessions() {
+        let db = init_database();
+        db.set_session_status(2, SessionStatus::Archived).unwrap();
+
+        let mut policy = policy(RetentionPeriod::Days(30));
+        policy.sensor_types.insert("GPS".to_string(), RetentionPeriod::Forever);
+
+        // Only the accelerometer of the session that is not archived, the datapoint from yesterday is kept
+        let targets = policy.plan(&db, now()).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].session_sensor_id, 1);
+        assert_eq!(targets[0].before, "2025-01-30T00:00:00");
+        assert_eq!(targets[0].datapoints, 10);
+
+        policy.keep_archived = false;
+        let targets = policy.plan(&db, now()).unwrap();
+        assert_eq!(targets.iter().map(|target| target.session_sensor_id).collect::<Vec<_>>(), vec![1, 3]);
+
+        // A preview changes nothing
+        assert_eq!(db.get_session_sensor_data(1).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn test_run_prunes_in_batches() {
+        let db = init_database();
+        let job = RetentionJob::new(RetentionConfig {
+            policy: policy(RetentionPeriod::Days(30)),
+            batch_size: 3,
+            ..Default::default()
+        });
+
+        let report = job.run(&db, now()).unwrap();
+        assert_eq!(report, PruneReport { datapoints: 40, rollups: 0 });
+        for session_sensor_id in 1..=4 {
+            let remaining = db.get_session_sensor_data(session_sensor_id).unwrap();
+            assert_eq!(remaining.len(), 1);
+            assert_eq!(remaining[0].get_datetime(), "2025-02-28T00:00:00");
+            assert!(db.get_session_sensor_rollups(session_sensor_id).unwrap().is_empty());
+        }
+
+        // A second run has nothing left to prune
+        assert_eq!(job.run(&db, now()).unwrap(), PruneReport::default());
+    }
+
+    #[test]
+    fn test_run_keeps_rollups() {
+        let db = init_database();
+        let mut policy = policy(RetentionPeriod::Days(30));
+        policy.rollup_minutes = Some(5);
+        // batches of 4 split both five minute buckets, their rollups must still hold every datapoint
+        let job = RetentionJob::new(RetentionConfig {
+            policy,
+            batch_size: 4,
+            ..Default::default()
+        });
+        job.run(&db, now()).unwrap();
+
+        let rollups = db.get_session_sensor_rollups(1).unwrap();
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(rollups[0].get_bucket(), "2025-01-01T00:00:00");
+        assert_eq!(rollups[1].get_bucket(), "2025-01-01T00:05:00");
+        assert_eq!(*rollups[0].get_interval_seconds(), 300);
+        assert_eq!(*rollups[0].get_count(), 5);
+        assert_eq!(
+            rollups[1].get_blob(),
+            &json!({ "value": { "count": 5, "min": 5.0, "max": 9.0, "mean": 7.0 } })
+        );
+    }
+}
@@ -0,0 +1,10 @@
+pub mod udp_datagram;
+pub mod udp_listener;
+pub mod udp_stats;
+
+pub use self::udp_datagram::UdpDatagram;
+pub use self::udp_listener::{UdpListener, UdpShutdown};
+pub use self::udp_stats::UdpStats;
+
+#[cfg(test)]
+mod test_udp;
@@ -0,0 +1,231 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::live::LiveHub;
+    use crate::models::{Sensor, Session, SessionSensor, User};
+    use crate::udp::udp_stats::{DeviceStats, SequenceResult};
+    use crate::udp::{UdpDatagram, UdpListener, UdpStats};
+    use crate::validation::SchemaRegistry;
+
+    const SECRET: &[u8] = b"test_secret";
+
+    /* Helpers */
+
+    fn test_datagram(sequence: u32) -> UdpDatagram {
+        UdpDatagram::new(
+            7,
+            sequence,
+            1_735_732_800_123_456,
+            json!({"x": 1.5, "y": -2, "z": 0}),
+        )
+    }
+
+    // The datetime of the nth datagram a device sends
+    fn datetime(n: usize) -> String {
+        format!("2025-01-01T12:00:00.{n:06}")
+    }
+
+    // Records the sequences as sent one after another
+    fn record_all(device: &mut DeviceStats, sequences: &[u32]) -> Vec<SequenceResult> {
+        sequences
+            .iter()
+            .enumerate()
+            .map(|(n, sequence)| device.record(*sequence, &datetime(n)))
+            .collect()
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_datagram_round_trip() {
+        let datagram = test_datagram(42);
+        let bytes = datagram.to_bytes(SECRET).expect("Failed to serialize datagram");
+
+        let parsed = UdpDatagram::from_bytes(&bytes, SECRET).expect("Failed to parse datagram");
+        assert_eq!(parsed.device_id, datagram.device_id);
+        assert_eq!(parsed.sequence, datagram.sequence);
+        assert_eq!(parsed.timestamp, datagram.timestamp);
+        assert_eq!(parsed.payload, datagram.payload);
+        assert_eq!(
+            parsed.get_datetime().expect("Failed to format datetime"),
+            "2025-01-01T12:00:00.123456"
+        );
+    }
+
+    #[test]
+    fn test_datagram_rejects_wrong_secret() {
+        let bytes = test_datagram(1).to_bytes(SECRET).expect("Failed to serialize datagram");
+        assert!(UdpDatagram::verify(&bytes, b"other_secret").is_err());
+    }
+
+    #[test]
+    fn test_datagram_rejects_tampering() {
+        let mut bytes = test_datagram(1).to_bytes(SECRET).expect("Failed to serialize datagram");
+        // flip a bit in the sequence number
+        bytes[10] ^= 1;
+        assert!(UdpDatagram::verify(&bytes, SECRET).is_err());
+    }
+
+    #[test]
+    fn test_datagram_rejects_short_input() {
+        assert!(UdpDatagram::from_bytes(b"DA", SECRET).is_err());
+    }
+
+    #[test]
+    fn test_datagram_rejects_non_object_payload() {
+        let datagram = UdpDatagram::new(1, 1, 0, json!([1, 2, 3]));
+        let bytes = datagram.to_bytes(SECRET).expect("Failed to serialize datagram");
+        assert!(UdpDatagram::verify(&bytes, SECRET).is_ok());
+        assert!(UdpDatagram::from_bytes(&bytes, SECRET).is_err());
+    }
+
+    #[test]
+    fn test_stats_in_order() {
+        let mut device = DeviceStats::default();
+        let results = record_all(&mut device, &[1, 2, 3, 4]);
+
+        assert!(results.iter().all(|result| *result == SequenceResult::Accepted));
+        assert_eq!(device.lost(), 0);
+        assert_eq!(device.to_json()["gaps"], 0);
+    }
+
+    #[test]
+    fn test_stats_gap_and_loss() {
+        let mut device = DeviceStats::default();
+        record_all(&mut device, &[1, 2, 5, 6, 10]);
+
+        assert_eq!(device.lost(), 5);
+        assert_eq!(device.to_json()["gaps"], 2);
+        assert_eq!(device.to_json()["expected"], 10);
+    }
+
+    #[test]
+    fn test_stats_reordered_fills_gap() {
+        let mut device = DeviceStats::default();
+        let results = record_all(&mut device, &[1, 3, 2]);
+
+        assert_eq!(results[2], SequenceResult::Accepted);
+        assert_eq!(device.lost(), 0);
+        assert_eq!(device.to_json()["reordered"], 1);
+    }
+
+    #[test]
+    fn test_stats_duplicates() {
+        let mut device = DeviceStats::default();
+        let results = record_all(&mut device, &[1, 2, 2, 1]);
+
+        assert_eq!(results[2], SequenceResult::Duplicate);
+        assert_eq!(results[3], SequenceResult::Duplicate);
+        assert_eq!(device.to_json()["received"], 2);
+    }
+
+    #[test]
+    fn test_stats_device_restart() {
+        let mut device = DeviceStats::default();
+        let sequences: Vec<u32> = (1000..=1010).chain(0..=5).collect();
+        let results = record_all(&mut device, &sequences);
+
+        assert!(results.iter().all(|result| *result == SequenceResult::Accepted));
+        assert_eq!(device.lost(), 0);
+        assert_eq!(device.to_json()["resets"], 1);
+        assert_eq!(device.to_json()["highest_sequence"], 5);
+    }
+
+    #[test]
+    fn test_stats_replay_rejected() {
+        let mut device = DeviceStats::default();
+        let sequences: Vec<u32> = (0..=200).collect();
+        record_all(&mut device, &sequences);
+
+        // the datagram of sequence 100 sent again, with the timestamp it was signed with
+        assert_eq!(device.record(100, &datetime(100)), SequenceResult::Replayed);
+        assert_eq!(device.to_json()["replays"], 1);
+        assert_eq!(device.to_json()["resets"], 0);
+        assert_eq!(device.to_json()["received"], 201);
+        assert_eq!(device.to_json()["highest_sequence"], 200);
+    }
+
+    #[test]
+    fn test_stats_sequence_wraps() {
+        let mut device = DeviceStats::default();
+        record_all(&mut device, &[u32::MAX - 1, u32::MAX, 0, 1]);
+
+        assert_eq!(device.lost(), 0);
+        assert_eq!(device.to_json()["highest_sequence"], 1);
+    }
+
+    #[test]
+    fn test_stats_per_session() {
+        let mut stats = UdpStats::new();
+        stats.record(1, 10, 1, "datetime");
+        stats.record(1, 10, 3, "datetime");
+        stats.record(1, 11, 1, "datetime");
+        stats.record(2, 12, 1, "datetime");
+
+        let session = stats.session_json(1).expect("Missing session stats");
+        assert_eq!(session["received"], 3);
+        assert_eq!(session["lost"], 1);
+        assert_eq!(session["devices"].as_array().map(|devices| devices.len()), Some(2));
+        assert!(stats.session_json(3).is_none());
+    }
+    #[test]
+    fn test_shutdown_writes_buffer() {
+        let database = MemoryDatabase::new();
+        database
+            .insert_user(&User::new("user1".to_string(), "hunter2".to_string()))
+            .unwrap();
+        database.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        database.insert_sensor(&Sensor::new(0, "Acceleration".to_string())).unwrap();
+        database.insert_session_sensor(&SessionSensor::new(0, 1, 1)).unwrap();
+        let database: Arc<dyn Database> = Arc::new(database);
+
+        // the flush interval is never reached, so only the shutdown writes the datapoint
+        let stats = Arc::new(Mutex::new(UdpStats::new()));
+        let listener = UdpListener::bind(
+            "127.0.0.1:0",
+            "test_secret",
+            100,
+            Duration::from_secs(60),
+            Arc::clone(&stats),
+            Arc::new(LiveHub::new(4)),
+            Arc::new(SchemaRegistry::new()),
+        )
+        .expect("Failed to bind udp listener");
+        let address = listener.local_addr().expect("Failed to read udp listener address");
+        let udp = listener.start(Arc::clone(&database));
+
+        let bytes = UdpDatagram::new(1, 1, 1_735_732_800_000_000, json!({"x": 1}))
+            .to_bytes(SECRET)
+            .expect("Failed to serialize datagram");
+        UdpSocket::bind("127.0.0.1:0")
+            .and_then(|socket| socket.send_to(&bytes, address))
+            .expect("Failed to send datagram");
+        let started = Instant::now();
+        while stats.lock().unwrap().datagrams == 0 && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(database.get_sessions_sensor_data(1).unwrap().is_empty());
+
+        udp.shutdown();
+        let stored = database.get_sessions_sensor_data(1).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].get_datetime(), "2025-01-01T12:00:00.000000");
+        assert_eq!(stats.lock().unwrap().rows_written, 1);
+    }
+}
@@ -0,0 +1,151 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use chrono::DateTime;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::error::Error;
+
+type Result<T> = crate::Result<T>;
+type HmacSha256 = Hmac<Sha256>;
+
+// Layout of a telemetry datagram, all integers are big endian
+//
+// | offset | size | field                                          |
+// |--------|------|------------------------------------------------|
+// | 0      | 2    | magic bytes "DA"                               |
+// | 2      | 1    | format version                                 |
+// | 3      | 4    | device id (u32), the session sensor id         |
+// | 7      | 4    | sequence number (u32), wraps around            |
+// | 11     | 8    | timestamp (i64), microseconds since unix epoch |
+// | 19     | 2    | payload length (u16)                           |
+// | 21     | n    | payload, compact json object                   |
+// | 21 + n | 16   | truncated HMAC-SHA256 of all preceding bytes   |
+pub struct UdpDatagram {
+    pub device_id: u32,
+    pub sequence: u32,
+    pub timestamp: i64,
+    pub payload: Value,
+}
+
+impl UdpDatagram {
+    pub const MAGIC: &'static [u8; 2] = b"DA";
+    pub const VERSION: u8 = 1;
+    pub const HEADER_LEN: usize = 21;
+    pub const TAG_LEN: usize = 16;
+    pub const DATETIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%.6f";
+
+    pub fn new(device_id: u32, sequence: u32, timestamp: i64, payload: Value) -> Self {
+        UdpDatagram {
+            device_id,
+            sequence,
+            timestamp,
+            payload,
+        }
+    }
+
+    //Parses and authenticates a raw datagram
+    #[allow(unused)]
+    pub fn from_bytes(bytes: &[u8], secret: &[u8]) -> Result<Self> {
+        Self::from_signed_bytes(Self::verify(bytes, secret)?)
+    }
+
+    //Checks the tag of a raw datagram and returns the signed bytes it covers
+    pub fn verify<'a>(bytes: &'a [u8], secret: &[u8]) -> Result<&'a [u8]> {
+        if bytes.len() < Self::HEADER_LEN + Self::TAG_LEN {
+            return Err(Error::Parse(format!(
+                "Datagram too short: expected at least {} bytes, recieved {}",
+                Self::HEADER_LEN + Self::TAG_LEN,
+                bytes.len()
+            )));
+        }
+
+        let (signed, tag) = bytes.split_at(bytes.len() - Self::TAG_LEN);
+        let mut mac = Self::mac(secret)?;
+        mac.update(signed);
+        mac.verify_truncated_left(tag)
+            .map_err(|_| Error::Unauthorized(String::from("Datagram failed authentication")))?;
+        Ok(signed)
+    }
+
+    //Parses the signed portion of a datagram that has already been verified
+    pub fn from_signed_bytes(signed: &[u8]) -> Result<Self> {
+        if signed.len() < Self::HEADER_LEN {
+            return Err(Error::Parse(format!(
+                "Datagram header too short: expected {} bytes, recieved {}",
+                Self::HEADER_LEN,
+                signed.len()
+            )));
+        }
+        if &signed[0..2] != Self::MAGIC {
+            return Err(Error::Parse(String::from("Datagram missing magic bytes")));
+        }
+        if signed[2] != Self::VERSION {
+            return Err(Error::Validation(format!(
+                "Unsupported datagram version: expected {}, recieved {}",
+                Self::VERSION,
+                signed[2]
+            )));
+        }
+
+        let device_id = u32::from_be_bytes(signed[3..7].try_into().unwrap_or_default());
+        let sequence = u32::from_be_bytes(signed[7..11].try_into().unwrap_or_default());
+        let timestamp = i64::from_be_bytes(signed[11..19].try_into().unwrap_or_default());
+        let payload_len = u16::from_be_bytes(signed[19..21].try_into().unwrap_or_default()) as usize;
+
+        if signed.len() - Self::HEADER_LEN != payload_len {
+            return Err(Error::Parse(format!(
+                "Datagram payload length mismatch: header declares {payload_len} bytes, recieved {}",
+                signed.len() - Self::HEADER_LEN
+            )));
+        }
+
+        let payload = serde_json::from_slice::<Value>(&signed[Self::HEADER_LEN..])
+            .map_err(|e| Error::Parse(format!("Failed to parse datagram payload to json value: {e}")))?;
+        if !payload.is_object() {
+            return Err(Error::Validation(String::from("Datagram payload must be a json object")));
+        }
+
+        Ok(UdpDatagram::new(device_id, sequence, timestamp, payload))
+    }
+
+    //Serializes and signs the datagram, used by tests and device simulators
+    #[allow(unused)]
+    pub fn to_bytes(&self, secret: &[u8]) -> Result<Vec<u8>> {
+        let payload = self.payload.to_string().into_bytes();
+        let payload_len = u16::try_from(payload.len())
+            .map_err(|_| Error::Validation(format!("Datagram payload too large: {} bytes", payload.len())))?;
+
+        let mut bytes = Vec::with_capacity(Self::HEADER_LEN + payload.len() + Self::TAG_LEN);
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.push(Self::VERSION);
+        bytes.extend_from_slice(&self.device_id.to_be_bytes());
+        bytes.extend_from_slice(&self.sequence.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&payload_len.to_be_bytes());
+        bytes.extend_from_slice(&payload);
+
+        let mut mac = Self::mac(secret)?;
+        mac.update(&bytes);
+        bytes.extend_from_slice(&mac.finalize().into_bytes()[..Self::TAG_LEN]);
+        Ok(bytes)
+    }
+
+    //Returns the timestamp in the same format the http api uses for datetimes
+    pub fn get_datetime(&self) -> Result<String> {
+        DateTime::from_timestamp_micros(self.timestamp)
+            .map(|datetime| datetime.naive_utc().format(Self::DATETIME_FORMAT).to_string())
+            .ok_or(Error::Validation(format!("Datagram timestamp out of range: {}", self.timestamp)))
+    }
+
+    fn mac(secret: &[u8]) -> Result<HmacSha256> {
+        HmacSha256::new_from_slice(secret).map_err(|e| Error::Validation(format!("Invalid udp secret: {e}")))
+    }
+}
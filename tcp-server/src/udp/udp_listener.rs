@@ -0,0 +1,309 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::alerts::AlertEngine;
+use crate::data::Database;
+use crate::error::Error;
+use crate::live::LiveHub;
+use crate::models::{QuarantinedSensorData, SchemaPolicy, SessionSensorData};
+use crate::validation::SchemaRegistry;
+
+use super::udp_stats::SequenceResult;
+use super::{UdpDatagram, UdpStats};
+
+type Result<T> = crate::Result<T>;
+
+//Receives telemetry datagrams and writes them to the database in batches
+pub struct UdpListener {
+    socket: UdpSocket,
+    secret: Vec<u8>,
+    batch_size: usize,
+    flush_interval: Duration,
+    stats: Arc<Mutex<UdpStats>>,
+    hub: Arc<LiveHub>,
+    schemas: Arc<SchemaRegistry>,
+    alerts: Option<Arc<AlertEngine>>,
+    // device id to session id and when it was looked up
+    devices: HashMap<u32, (i64, Instant)>,
+    buffer: Vec<SessionSensorData>,
+    // datapoints whose blobs the schema of their sensor type quarantines, written along with the buffer
+    quarantine: Vec<QuarantinedSensorData>,
+    last_flush: Instant,
+    stopping: Arc<AtomicBool>,
+}
+
+//Stops a started udp listener, the datapoints it has buffered are written first
+pub struct UdpShutdown {
+    stopping: Arc<AtomicBool>,
+    listener: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl UdpShutdown {
+    //Returns once the listener has stopped and written its buffer
+    pub fn shutdown(&self) {
+        self.stopping.store(true, Ordering::Release);
+
+        let handle = self.listener.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(handle) = handle {
+            if handle.join().is_err() {
+                eprintln!("Udp listener panicked before its buffer was written");
+            }
+        }
+    }
+}
+
+impl UdpListener {
+    // largest datagram that fits in a single udp packet
+    const MAX_DATAGRAM_LEN: usize = 65_507;
+    // longest a receive waits, so a shutdown is noticed even when the flush interval is long
+    const RECEIVE_TIMEOUT: Duration = Duration::from_millis(250);
+    // how long the session of a device is cached before it is looked up again
+    const DEVICE_TTL: Duration = Duration::from_secs(60);
+
+    pub fn bind(
+        address: &str,
+        secret: &str,
+        batch_size: usize,
+        flush_interval: Duration,
+        stats: Arc<Mutex<UdpStats>>,
+        hub: Arc<LiveHub>,
+        schemas: Arc<SchemaRegistry>,
+    ) -> Result<Self> {
+        let socket = UdpSocket::bind(address).map_err(|error| {
+            Error::Io(format!("Failed to bind udp listener at address {address}, Error: {error}"))
+        })?;
+        socket
+            .set_read_timeout(Some(flush_interval.min(Self::RECEIVE_TIMEOUT)))
+            .map_err(|e| Error::Io(format!("Failed to set udp read timeout: {e}")))?;
+
+        Ok(UdpListener {
+            socket,
+            secret: secret.as_bytes().to_vec(),
+            batch_size: batch_size.max(1),
+            flush_interval,
+            stats,
+            hub,
+            schemas,
+            alerts: None,
+            devices: HashMap::new(),
+            buffer: Vec::with_capacity(batch_size),
+            quarantine: Vec::new(),
+            last_flush: Instant::now(),
+            stopping: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket
+            .local_addr()
+            .map_err(|e| Error::Io(format!("Failed to read the udp listener's address: {e}")))
+    }
+
+    //Evaluates written datapoints against the alert rules
+    pub fn with_alerts(mut self, alerts: Arc<AlertEngine>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    //Moves the listener to its own thread, its batches go through the database's writer
+    pub fn start(self, database: Arc<dyn Database>) -> UdpShutdown {
+        let stopping = Arc::clone(&self.stopping);
+        let listener = std::thread::spawn(move || self.run(database.as_ref()));
+        UdpShutdown {
+            stopping,
+            listener: Mutex::new(Some(listener)),
+        }
+    }
+
+    //Receive datagrams until shut down, flushing the buffer when it is full or the flush interval passes
+    fn run(mut self, database: &dyn Database) {
+        let mut buffer = vec![0; Self::MAX_DATAGRAM_LEN];
+        while !self.stopping.load(Ordering::Acquire) {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((n, _)) => self.handle_datagram(database, &buffer[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => eprintln!("Failed to read from the udp socket: {e}"),
+            }
+
+            let pending = !self.buffer.is_empty() || !self.quarantine.is_empty();
+            if self.buffer.len() >= self.batch_size
+                || (pending && self.last_flush.elapsed() >= self.flush_interval)
+            {
+                self.flush(database);
+            }
+        }
+        self.flush(database);
+    }
+
+    fn handle_datagram(&mut self, database: &dyn Database, bytes: &[u8]) {
+        self.count(|stats| stats.datagrams += 1);
+
+        let signed = match UdpDatagram::verify(bytes, &self.secret) {
+            Ok(signed) => signed,
+            Err(_) => {
+                self.count(|stats| stats.rejected_auth += 1);
+                return;
+            }
+        };
+
+        let datagram = match UdpDatagram::from_signed_bytes(signed) {
+            Ok(datagram) => datagram,
+            Err(_) => {
+                self.count(|stats| stats.malformed += 1);
+                return;
+            }
+        };
+
+        let datetime = match datagram.get_datetime() {
+            Ok(datetime) => datetime,
+            Err(_) => {
+                self.count(|stats| stats.malformed += 1);
+                return;
+            }
+        };
+
+        let Some(session_id) = self.session_of(database, datagram.device_id) else {
+            self.count(|stats| stats.unknown_device += 1);
+            return;
+        };
+
+        // datagrams are still written when the stats cannot be updated, the database skips repeated ones
+        if let Some(SequenceResult::Duplicate | SequenceResult::Replayed) =
+            self.count(|stats| stats.record(session_id, datagram.device_id, datagram.sequence, &datetime))
+        {
+            return;
+        }
+
+        let datapoint = SessionSensorData::new(
+            Some(session_id),
+            Some(datagram.device_id as i64),
+            datetime,
+            datagram.payload,
+        );
+        // there is no one to report schema errors to, so they are only counted
+        match self.schemas.check(database, vec![datapoint]) {
+            Ok(check) => {
+                let mut rejected = 0;
+                for violation in check.violations {
+                    match violation.policy {
+                        SchemaPolicy::Reject => rejected += 1,
+                        SchemaPolicy::Quarantine => self.quarantine.push(QuarantinedSensorData::from_datapoint(
+                            &violation.datapoint,
+                            violation.errors,
+                        )),
+                    }
+                }
+                if rejected > 0 {
+                    self.count(|stats| stats.schema_rejected += rejected);
+                }
+                self.buffer.extend(check.accepted);
+            }
+            Err(e) => {
+                self.count(|stats| stats.write_errors += 1);
+                eprintln!("Failed to check udp datapoint against its schema: {e}");
+            }
+        }
+    }
+
+    //The session a device records to, looked up again once DEVICE_TTL passes so a device moved to another session follows it
+    fn session_of(&mut self, database: &dyn Database, device_id: u32) -> Option<i64> {
+        if let Some((session_id, cached_at)) = self.devices.get(&device_id) {
+            if cached_at.elapsed() < Self::DEVICE_TTL {
+                return Some(*session_id);
+            }
+        }
+        match database.get_session_sensor(device_id as i64) {
+            Ok(session_sensor) => {
+                let session_id = *session_sensor.get_session_id();
+                self.devices.insert(device_id, (session_id, Instant::now()));
+                Some(session_id)
+            }
+            Err(_) => {
+                self.devices.remove(&device_id);
+                None
+            }
+        }
+    }
+
+    //Updates the stats, which are only locked for as long as the update takes
+    //A panic while they were locked leaves them poisoned, the update is then only logged as lost
+    fn count<T>(&self, update: impl FnOnce(&mut UdpStats) -> T) -> Option<T> {
+        match self.stats.lock().map_err(|_| UdpStats::poisoned()) {
+            Ok(mut stats) => Some(update(&mut stats)),
+            Err(e) => {
+                eprintln!("Failed to update udp stats: {e}");
+                None
+            }
+        }
+    }
+
+    fn flush(&mut self, database: &dyn Database) {
+        if !self.buffer.is_empty() {
+            self.write_buffer(database);
+        }
+        if !self.quarantine.is_empty() {
+            match database.quarantine_session_sensor_data(&self.quarantine) {
+                Ok(_) => {
+                    let quarantined = self.quarantine.len() as u64;
+                    self.count(|stats| stats.quarantined += quarantined);
+                }
+                Err(e) => {
+                    self.count(|stats| stats.write_errors += 1);
+                    eprintln!(
+                        "Failed to quarantine {} udp datapoints: {e}",
+                        self.quarantine.len()
+                    );
+                }
+            }
+            self.quarantine.clear();
+        }
+        self.last_flush = Instant::now();
+    }
+
+    fn write_buffer(&mut self, database: &dyn Database) {
+        let result = database.batch_session_sensor_data(&self.buffer);
+        if result.is_ok() {
+            self.hub.publish_all(&self.buffer);
+            if let Some(alerts) = &self.alerts {
+                alerts.evaluate(database, &self.buffer);
+            }
+        }
+
+        match result {
+            Ok(_) => {
+                let rows = self.buffer.len() as u64;
+                self.count(|stats| {
+                    stats.batches_written += 1;
+                    stats.rows_written += rows;
+                });
+            }
+            Err(e) => {
+                self.count(|stats| stats.write_errors += 1);
+                eprintln!(
+                    "Failed to write batch of {} udp datapoints: {e}",
+                    self.buffer.len()
+                );
+                // the session sensors may have been deleted or moved, so they are looked up again
+                for datapoint in &self.buffer {
+                    if let Some(session_sensor_id) = datapoint.get_session_sensor_id() {
+                        self.devices.remove(&(*session_sensor_id as u32));
+                    }
+                }
+            }
+        }
+        self.buffer.clear();
+    }
+}
@@ -0,0 +1,208 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::error::Error;
+
+//What the stats tracker decided about a datagram's sequence number
+#[derive(Debug, PartialEq)]
+pub enum SequenceResult {
+    Accepted,
+    Duplicate,
+    // far behind the highest sequence without being newer, so a captured datagram sent again
+    Replayed,
+}
+
+//Sequence tracking for a single device, duplicates are detected within a sliding window
+#[derive(Default)]
+pub struct DeviceStats {
+    received: u64,
+    expected: u64,
+    duplicates: u64,
+    reordered: u64,
+    // times the sequence jumped back past the window with a newer timestamp, as it does when a device restarts
+    resets: u64,
+    replays: u64,
+    gaps: u64,
+    highest_sequence: Option<u32>,
+    window: u64,
+    // newest datetime accepted, datagram datetimes have a fixed width so they compare as text
+    last_datetime: String,
+}
+
+impl DeviceStats {
+    // number of sequence numbers behind the highest that are tracked for duplicates
+    pub const WINDOW_SIZE: i64 = 64;
+
+    pub fn record(&mut self, sequence: u32, datetime: &str) -> SequenceResult {
+        let highest = match self.highest_sequence {
+            Some(highest) => highest,
+            None => {
+                self.highest_sequence = Some(sequence);
+                self.window = 1;
+                self.received = 1;
+                self.expected = 1;
+                self.last_datetime = datetime.to_string();
+                return SequenceResult::Accepted;
+            }
+        };
+
+        //wrapping difference so the u32 sequence can roll over
+        let diff = sequence.wrapping_sub(highest) as i32 as i64;
+        if diff > 0 {
+            if diff > 1 {
+                self.gaps += 1;
+            }
+            self.window = if diff >= Self::WINDOW_SIZE {
+                1
+            } else {
+                (self.window << diff) | 1
+            };
+            self.highest_sequence = Some(sequence);
+            self.expected += diff as u64;
+        } else if -diff >= Self::WINDOW_SIZE {
+            //a restarted device counts up from 0 again but keeps its clock, anything older is a replay
+            if datetime <= self.last_datetime.as_str() {
+                self.replays += 1;
+                return SequenceResult::Replayed;
+            }
+            self.resets += 1;
+            self.highest_sequence = Some(sequence);
+            self.window = 1;
+            self.expected += 1;
+        } else {
+            let bit = 1u64 << -diff;
+            if self.window & bit != 0 {
+                self.duplicates += 1;
+                return SequenceResult::Duplicate;
+            }
+            self.window |= bit;
+            self.reordered += 1;
+        }
+
+        self.received += 1;
+        if datetime > self.last_datetime.as_str() {
+            self.last_datetime = datetime.to_string();
+        }
+        SequenceResult::Accepted
+    }
+
+    pub fn lost(&self) -> u64 {
+        self.expected.saturating_sub(self.received)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "received": self.received,
+            "expected": self.expected,
+            "lost": self.lost(),
+            "loss_ratio": if self.expected == 0 { 0.0 } else { self.lost() as f64 / self.expected as f64 },
+            "gaps": self.gaps,
+            "duplicates": self.duplicates,
+            "reordered": self.reordered,
+            "resets": self.resets,
+            "replays": self.replays,
+            "highest_sequence": self.highest_sequence,
+            "last_datetime": self.last_datetime,
+        })
+    }
+}
+
+//Gap and loss statistics for every device sending to a session
+#[derive(Default)]
+pub struct SessionStats {
+    devices: HashMap<u32, DeviceStats>,
+}
+
+impl SessionStats {
+    pub fn to_json(&self, session_id: i64) -> Value {
+        let (received, expected) = self.devices.values().fold((0, 0), |(r, e), device| {
+            (r + device.received, e + device.expected)
+        });
+        let lost = expected - received.min(expected);
+        json!({
+            "session_id": session_id,
+            "received": received,
+            "expected": expected,
+            "lost": lost,
+            "loss_ratio": if expected == 0 { 0.0 } else { lost as f64 / expected as f64 },
+            "gaps": self.devices.values().map(|device| device.gaps).sum::<u64>(),
+            "devices": self.devices.iter().map(|(device_id, device)| {
+                let mut device_json = device.to_json();
+                device_json["device_id"] = json!(device_id);
+                device_json
+            }).collect::<Vec<_>>(),
+        })
+    }
+}
+
+//Listener wide counters along with per session statistics
+#[derive(Default)]
+pub struct UdpStats {
+    pub datagrams: u64,
+    pub rejected_auth: u64,
+    pub malformed: u64,
+    pub unknown_device: u64,
+    // datagrams whose payload the schema of their sensor type rejected
+    pub schema_rejected: u64,
+    pub quarantined: u64,
+    pub rows_written: u64,
+    pub batches_written: u64,
+    pub write_errors: u64,
+    sessions: HashMap<i64, SessionStats>,
+}
+
+impl UdpStats {
+    pub fn new() -> Self {
+        UdpStats::default()
+    }
+
+    pub fn poisoned() -> Error {
+        Error::Storage(String::from("Udp stats were poisoned by a panic"))
+    }
+
+    pub fn record(
+        &mut self,
+        session_id: i64,
+        device_id: u32,
+        sequence: u32,
+        datetime: &str,
+    ) -> SequenceResult {
+        self.sessions
+            .entry(session_id)
+            .or_default()
+            .devices
+            .entry(device_id)
+            .or_default()
+            .record(sequence, datetime)
+    }
+
+    pub fn session_json(&self, session_id: i64) -> Option<Value> {
+        self.sessions
+            .get(&session_id)
+            .map(|session| session.to_json(session_id))
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "datagrams": self.datagrams,
+            "rejected_auth": self.rejected_auth,
+            "malformed": self.malformed,
+            "unknown_device": self.unknown_device,
+            "schema_rejected": self.schema_rejected,
+            "quarantined": self.quarantined,
+            "rows_written": self.rows_written,
+            "batches_written": self.batches_written,
+            "write_errors": self.write_errors,
+            "sessions": self.sessions.iter().map(|(session_id, session)| session.to_json(*session_id)).collect::<Vec<_>>(),
+        })
+    }
+}
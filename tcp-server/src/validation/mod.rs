@@ -0,0 +1,6 @@
+pub mod schema_registry;
+
+pub use self::schema_registry::{SchemaCheck, SchemaRegistry, SchemaViolation};
+
+#[cfg(test)]
+mod test_validation;
@@ -0,0 +1,240 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+
+use jsonschema::Validator;
+use serde_json::{json, Value};
+
+use crate::data::Database;
+use crate::error::Error;
+use crate::http::{HttpProblem, HttpResponse};
+use crate::models::{QuarantinedSensorData, SchemaPolicy, SensorSchema, SessionSensorData};
+
+type Result<T> = crate::Result<T>;
+
+//A datapoint whose blob does not match the schema of its sensor type
+#[derive(Debug, Clone)]
+pub struct SchemaViolation {
+    // position of the datapoint in the checked datapoints
+    pub index: usize,
+    pub datapoint: SessionSensorData,
+    pub sensor_type: String,
+    pub policy: SchemaPolicy,
+    pub errors: Vec<String>,
+}
+
+impl SchemaViolation {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "index": self.index,
+            "session_sensor_id": self.datapoint.get_session_sensor_id(),
+            "datetime": self.datapoint.get_datetime(),
+            "sensor_type": self.sensor_type,
+            "policy": self.policy,
+            "errors": self.errors,
+        })
+    }
+}
+
+//Datapoints sorted by the schemas of their sensor types, in the order they were checked
+#[derive(Debug, Default)]
+pub struct SchemaCheck {
+    // datapoints whose sensor type has no schema or whose blob matches it
+    pub accepted: Vec<SessionSensorData>,
+    pub violations: Vec<SchemaViolation>,
+}
+
+impl SchemaCheck {
+    pub fn rejected(&self) -> impl Iterator<Item = &SchemaViolation> {
+        self.violations
+            .iter()
+            .filter(|violation| violation.policy == SchemaPolicy::Reject)
+    }
+
+    pub fn quarantined(&self) -> impl Iterator<Item = &SchemaViolation> {
+        self.violations
+            .iter()
+            .filter(|violation| violation.policy == SchemaPolicy::Quarantine)
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.rejected().next().is_some()
+    }
+
+    //Sets the quarantined datapoints aside, returning how many were
+    pub fn quarantine(&self, database: &dyn Database) -> Result<usize> {
+        let quarantined: Vec<QuarantinedSensorData> = self
+            .quarantined()
+            .map(|violation| QuarantinedSensorData::from_datapoint(&violation.datapoint, violation.errors.clone()))
+            .collect();
+        if !quarantined.is_empty() {
+            database.quarantine_session_sensor_data(&quarantined)?;
+        }
+        Ok(quarantined.len())
+    }
+
+    //Answers a request holding rejected datapoints, the problem lists the errors of each of them
+    pub fn rejection_response(&self) -> HttpResponse {
+        let rejected: Vec<Value> = self.rejected().map(SchemaViolation::to_json).collect();
+        let error = Error::Validation(format!(
+            "{} datapoint(s) do not match the schema of their sensor type, nothing was written",
+            rejected.len()
+        ));
+        HttpResponse::from_problem(HttpProblem::new(error).with_errors(Value::Array(rejected)))
+    }
+}
+
+struct CompiledSchema {
+    policy: SchemaPolicy,
+    validator: Validator,
+}
+
+#[derive(Default)]
+struct SchemaCache {
+    schemas: HashMap<String, Arc<CompiledSchema>>,
+    // sensor type of every session sensor checked since the schemas were loaded
+    sensor_types: HashMap<i64, String>,
+    loaded_at: Option<Instant>,
+}
+
+//Validates data blobs against the JSON Schema of their sensor type before they are written
+//Compiled schemas are cached and reloaded every REFRESH_INTERVAL, so schemas changed by another server are picked up
+pub struct SchemaRegistry {
+    cache: RwLock<SchemaCache>,
+}
+
+impl SchemaRegistry {
+    pub const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+    // errors reported for a single datapoint
+    pub const MAX_ERRORS: usize = 10;
+
+    pub fn new() -> Self {
+        SchemaRegistry {
+            cache: RwLock::new(SchemaCache::default()),
+        }
+    }
+
+    //Stores the schema of a sensor type once it compiles, it applies to the next datapoint checked
+    pub fn put(&self, database: &dyn Database, schema: SensorSchema) -> Result<SensorSchema> {
+        let validator = schema.compile()?;
+        let schema = database.upsert_sensor_schema(&schema)?;
+        self.cache_mut()?.schemas.insert(
+            schema.get_sensor_type().to_string(),
+            Arc::new(CompiledSchema {
+                policy: *schema.get_policy(),
+                validator,
+            }),
+        );
+        Ok(schema)
+    }
+
+    pub fn remove(&self, database: &dyn Database, sensor_type: &str) -> Result<()> {
+        database.delete_sensor_schema(sensor_type)?;
+        self.cache_mut()?.schemas.remove(sensor_type);
+        Ok(())
+    }
+
+    //Checks every datapoint against the schema of its session sensor's type
+    pub fn check(&self, database: &dyn Database, datapoints: Vec<SessionSensorData>) -> Result<SchemaCheck> {
+        self.refresh(database)?;
+        let mut check = SchemaCheck::default();
+        // without schemas there is no need to look up sensor types
+        if self.cache()?.schemas.is_empty() {
+            check.accepted = datapoints;
+            return Ok(check);
+        }
+
+        for (index, datapoint) in datapoints.into_iter().enumerate() {
+            let schema = match datapoint.get_session_sensor_id() {
+                Some(session_sensor_id) => self.schema_of(database, *session_sensor_id)?,
+                None => None,
+            };
+            let Some((sensor_type, schema)) = schema else {
+                check.accepted.push(datapoint);
+                continue;
+            };
+            let errors: Vec<String> = schema
+                .validator
+                .iter_errors(datapoint.get_blob())
+                .take(Self::MAX_ERRORS)
+                .map(|error| format!("data_blob{}: {error}", error.instance_path))
+                .collect();
+            match errors.is_empty() {
+                true => check.accepted.push(datapoint),
+                false => check.violations.push(SchemaViolation {
+                    index,
+                    datapoint,
+                    sensor_type,
+                    policy: schema.policy,
+                    errors,
+                }),
+            }
+        }
+        Ok(check)
+    }
+
+    fn refresh(&self, database: &dyn Database) -> Result<()> {
+        if self.cache()?.loaded_at.is_some_and(|loaded_at| loaded_at.elapsed() < Self::REFRESH_INTERVAL) {
+            return Ok(());
+        }
+
+        let mut schemas = HashMap::new();
+        for schema in database.get_sensor_schemas()? {
+            // schemas are compiled before they are stored, so only one edited in the database can fail here
+            match schema.compile() {
+                Ok(validator) => {
+                    schemas.insert(
+                        schema.get_sensor_type().to_string(),
+                        Arc::new(CompiledSchema {
+                            policy: *schema.get_policy(),
+                            validator,
+                        }),
+                    );
+                }
+                Err(e) => eprintln!("Skipping the schema of sensor type '{}': {e}", schema.get_sensor_type()),
+            }
+        }
+        *self.cache_mut()? = SchemaCache {
+            schemas,
+            sensor_types: HashMap::new(),
+            loaded_at: Some(Instant::now()),
+        };
+        Ok(())
+    }
+
+    // The type of a session sensor and its schema, none when the type has no schema
+    fn schema_of(&self, database: &dyn Database, session_sensor_id: i64) -> Result<Option<(String, Arc<CompiledSchema>)>> {
+        let cached = self.cache()?.sensor_types.get(&session_sensor_id).cloned();
+        let sensor_type = match cached {
+            Some(sensor_type) => sensor_type,
+            None => {
+                let session_sensor = database.get_session_sensor(session_sensor_id)?;
+                let sensor_type = database.get_sensor(*session_sensor.get_sensor_id())?.get_sensor_type().to_string();
+                self.cache_mut()?.sensor_types.insert(session_sensor_id, sensor_type.clone());
+                sensor_type
+            }
+        };
+        let schema = self.cache()?.schemas.get(&sensor_type).cloned();
+        Ok(schema.map(|schema| (sensor_type, schema)))
+    }
+
+    fn cache(&self) -> Result<RwLockReadGuard<'_, SchemaCache>> {
+        self.cache.read().map_err(|_| Self::poisoned())
+    }
+
+    fn cache_mut(&self) -> Result<RwLockWriteGuard<'_, SchemaCache>> {
+        self.cache.write().map_err(|_| Self::poisoned())
+    }
+
+    fn poisoned() -> Error {
+        Error::Storage(String::from("Sensor schemas were poisoned by a panic"))
+    }
+}
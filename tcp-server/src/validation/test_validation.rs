@@ -0,0 +1,222 @@
+/*
+Copyright 2025 CS 462 Personal Data Acquisition Prototype Group
+    
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with the License.
+You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions and limitations under the License.
+*/
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::{json, Value};
+
+    use crate::alerts::AlertEngine;
+    use crate::data::memory_database::MemoryDatabase;
+    use crate::data::Database;
+    use crate::error::Error;
+    use crate::http::HttpStatus;
+    use crate::import::import_parser::{ImportFormat, ImportOptions};
+    use crate::import::session_import::SessionImport;
+    use crate::ingest::{IngestBuffer, IngestConfig};
+    use crate::live::LiveHub;
+    use crate::models::{SchemaPolicy, Sensor, SensorSchema, Session, SessionSensor, SessionSensorData, User};
+    use crate::validation::SchemaRegistry;
+
+    /* Helpers */
+
+    // Session 1 records an accelerometer (session sensor 1), a gps (2) and a thermometer without a schema (3)
+    fn init_database(registry: &SchemaRegistry) -> MemoryDatabase {
+        let db = MemoryDatabase::new();
+        db.insert_user(&User::new("user1".to_string(), "hunter2".to_string())).unwrap();
+        db.insert_session(&Session::new(0, "user1".to_string())).unwrap();
+        for (sensor_id, sensor_type) in ["Acceleration", "GPS", "Temperature"].iter().enumerate() {
+            db.insert_sensor(&Sensor::new(0, sensor_type.to_string())).unwrap();
+            db.insert_session_sensor(&SessionSensor::new(0, 1, sensor_id as i64 + 1)).unwrap();
+        }
+        let vector = json!({
+            "type": "object",
+            "properties": { "x": { "type": "number" }, "y": { "type": "number" } },
+            "required": ["x", "y"],
+        });
+        registry
+            .put(&db, SensorSchema::new("Acceleration".to_string(), vector, SchemaPolicy::Reject))
+            .unwrap();
+        let position = json!({
+            "type": "object",
+            "properties": { "lat": { "type": "number", "minimum": -90, "maximum": 90 } },
+            "required": ["lat"],
+        });
+        registry
+            .put(&db, SensorSchema::new("GPS".to_string(), position, SchemaPolicy::Quarantine))
+            .unwrap();
+        db
+    }
+
+    fn datapoint(session_sensor_id: i64, second: u32, blob: Value) -> SessionSensorData {
+        SessionSensorData::new(Some(1), Some(session_sensor_id), format!("2025-01-01T12:00:0{second}"), blob)
+    }
+
+    fn start_ingest(db: &Arc<MemoryDatabase>) -> Arc<IngestBuffer> {
+        IngestBuffer::start(
+            Arc::clone(db) as Arc<dyn Database>,
+            Arc::new(LiveHub::new(16)),
+            Arc::new(AlertEngine::new(None)),
+            IngestConfig::default(),
+        )
+    }
+
+    /* Tests */
+
+    #[test]
+    fn test_schema_check() {
+        let registry = SchemaRegistry::new();
+        let db = init_database(&registry);
+        let check = registry
+            .check(
+                &db,
+                vec![
+                    datapoint(1, 0, json!({ "x": 1, "y": 2.5 })),
+                    datapoint(1, 1, json!({ "x": "1" })),
+                    datapoint(2, 2, json!({ "lat": 91 })),
+                    datapoint(2, 3, json!({ "lat": 44.56 })),
+                    datapoint(3, 4, json!("anything")),
+                ],
+            )
+            .unwrap();
+
+        let accepted: Vec<&str> = check.accepted.iter().map(|data| data.get_datetime()).collect();
+        assert_eq!(accepted, vec!["2025-01-01T12:00:00", "2025-01-01T12:00:03", "2025-01-01T12:00:04"]);
+
+        // Every error of a blob is kept, pointing at the member that failed
+        let rejected: Vec<_> = check.rejected().collect();
+        assert_eq!((rejected.len(), rejected[0].index, rejected[0].sensor_type.as_str()), (1, 1, "Acceleration"));
+        assert_eq!(rejected[0].errors.len(), 2);
+        assert!(rejected[0].errors.iter().any(|error| error.starts_with("data_blob/x:")));
+        assert!(rejected[0].errors.iter().any(|error| error.contains("\"y\" is a required property")));
+        let quarantined: Vec<_> = check.quarantined().collect();
+        assert_eq!((quarantined.len(), quarantined[0].index), (1, 2));
+        assert!(quarantined[0].errors[0].starts_with("data_blob/lat:"));
+
+        assert!(check.is_rejected());
+        let response = check.rejection_response();
+        assert!(matches!(response.status, HttpStatus::UnprocessableEntity));
+        let problem: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(problem["errors"].as_array().unwrap().len(), 1);
+        assert_eq!(problem["errors"][0]["session_sensor_id"], json!(1));
+    }
+
+    #[test]
+    fn test_put_schema() {
+        let registry = SchemaRegistry::new();
+        let db = init_database(&registry);
+
+        // Invalid schemas are refused before they are stored
+        let invalid = SensorSchema::new("Temperature".to_string(), json!({ "type": 5 }), SchemaPolicy::Reject);
+        assert!(matches!(registry.put(&db, invalid), Err(Error::Validation(_))));
+        let untyped = SensorSchema::new(String::new(), json!({}), SchemaPolicy::Reject);
+        assert!(matches!(registry.put(&db, untyped), Err(Error::Validation(_))));
+        assert_eq!(db.get_sensor_schemas().unwrap().len(), 2);
+
+        // Changes made through the registry apply to the next check
+        let strict = SensorSchema::new("Temperature".to_string(), json!({ "type": "number" }), SchemaPolicy::Reject);
+        registry.put(&db, strict).unwrap();
+        registry.remove(&db, "Acceleration").unwrap();
+        let check = registry
+            .check(&db, vec![datapoint(1, 0, json!([])), datapoint(3, 1, json!("warm"))])
+            .unwrap();
+        assert_eq!(check.accepted.len(), 1);
+        assert_eq!(check.rejected().next().unwrap().sensor_type, "Temperature");
+        assert!(matches!(registry.remove(&db, "Acceleration"), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let registry = SchemaRegistry::new();
+        let db = init_database(&registry);
+        let check = registry
+            .check(&db, vec![datapoint(2, 0, json!({ "lat": -91 })), datapoint(2, 1, json!({}))])
+            .unwrap();
+        assert!(!check.is_rejected());
+        assert_eq!(check.quarantine(&db).unwrap(), 2);
+
+        // Quarantined datapoints keep their errors and are not stored with the session's data
+        let quarantined = db.get_quarantined_sensor_data(1).unwrap();
+        assert_eq!(quarantined.len(), 2);
+        assert_eq!(quarantined[0].get_blob(), &json!({ "lat": -91 }));
+        assert!(quarantined[1].get_errors()[0].contains("\"lat\" is a required property"));
+        assert!(db.get_sessions_sensor_data(1).unwrap().is_empty());
+
+        assert_eq!(db.delete_quarantined_sensor_data(1).unwrap(), 2);
+        assert!(db.get_quarantined_sensor_data(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_import_with_schemas() {
+        let registry = SchemaRegistry::new();
+        let db = init_database(&registry);
+        let file = r#"{"datetime": "2025-01-01T12:00:00", "sensor_id": 1, "x": 1, "y": 2}
+{"datetime": "2025-01-01T12:00:01", "sensor_id": 1, "x": 1}
+{"datetime": "2025-01-01T12:00:02", "sensor_id": 2, "lat": 100}
+{"datetime": "2025-01-01T12:00:03", "sensor_id": 3, "celsius": 21.5}
+"#;
+        let report = SessionImport::new(&db, 1, ImportOptions::new(ImportFormat::Ndjson))
+            .unwrap()
+            .run(file.as_bytes(), |_| {})
+            .unwrap();
+
+        // Rejected lines fail with the errors of their blob, quarantined ones are set aside
+        assert_eq!((report.imported, report.quarantined, report.failed), (2, 1, 1));
+        assert_eq!(report.errors[0].line, 2);
+        assert!(report.errors[0].message.starts_with("Blob does not match the schema of sensor type 'Acceleration'"));
+        assert_eq!(db.get_sessions_sensor_data(1).unwrap().len(), 2);
+        assert_eq!(db.get_quarantined_sensor_data(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_quarantined_write_waits_for_commit() {
+        let registry = SchemaRegistry::new();
+        let db = Arc::new(init_database(&registry));
+        let ingest = start_ingest(&db);
+        let body = json!({
+            "id": 1,
+            "session_sensor_id": 2,
+            "datetime": "2025-01-01T12:00:00",
+            "data_blob": { "lat": 91 },
+        });
+
+        // A waiting write is answered once everything it queued is committed, not with 202 Accepted
+        let response = SessionSensorData::try_insert_model(db.as_ref(), &ingest, &registry, true, Some(body));
+        assert!(matches!(response.status, HttpStatus::OK));
+        let answer: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(answer["quarantined"].as_array().unwrap().len(), 1);
+        assert_eq!(db.get_quarantined_sensor_data(1).unwrap().len(), 1);
+        ingest.shutdown();
+    }
+
+    #[test]
+    fn test_scalar_blob_refused_without_schema() {
+        let registry = SchemaRegistry::new();
+        let db = Arc::new(init_database(&registry));
+        let ingest = start_ingest(&db);
+        let scalar = |second: u32| {
+            json!({
+                "id": 1,
+                "session_sensor_id": 3,
+                "datetime": format!("2025-01-01T12:00:0{second}"),
+                "data_blob": "warm",
+            })
+        };
+
+        // The thermometer has no schema, but a blob still has to be an object
+        let response = SessionSensorData::try_insert_model(db.as_ref(), &ingest, &registry, true, Some(scalar(0)));
+        assert!(matches!(response.status, HttpStatus::UnprocessableEntity));
+        let batch = json!({ "datapoints": [datapoint(3, 1, json!({ "celsius": 21.5 })).to_json(), scalar(2)] });
+        let response = SessionSensorData::try_batch_model(db.as_ref(), &ingest, &registry, true, Some(batch));
+        assert!(matches!(response.status, HttpStatus::UnprocessableEntity));
+        assert!(response.body.contains("index 1"));
+        assert!(db.get_sessions_sensor_data(1).unwrap().is_empty());
+        ingest.shutdown();
+    }
+}